@@ -0,0 +1,118 @@
+//! Unicode Bidirectional Algorithm (UAX #9), implemented by the unicode-bidi crate.
+//!
+//! The embedding levels are resolved per paragraph (rules P2 to I2, L1 for the paragraph end),
+//! the Screen applies the reordering (rule L2) to each visual line when the line is complete.<br/>
+//! The cells keep their logical offsets: the marks, the mouse and the selections are not affected by the reordering.
+
+use unicode_bidi::BidiInfo;
+use unicode_bidi::Level;
+use unicode_bidi::ParagraphBidiInfo;
+
+pub use unicode_bidi::bidi_class;
+pub use unicode_bidi::BidiClass;
+
+use BidiClass::*;
+
+/// Returns true if a text containing only codepoints of class 'class' needs the algorithm.<br/>
+/// Left-to-right only text is displayed as is.
+pub fn needs_reordering(class: BidiClass) -> bool {
+    matches!(
+        class,
+        R | AL | AN | LRE | LRO | RLE | RLO | PDF | LRI | RLI | FSI | PDI
+    )
+}
+
+/// Returns the mirrored glyph of 'c' (rule L4), displayed in right-to-left runs.
+pub fn mirrored_char(c: char) -> Option<char> {
+    unicode_bidi_mirroring::get_mirrored(c)
+}
+
+/// Returns the embedding level of the paragraph 'text' (rules P2, P3) and the level of each of its codepoints.
+pub fn resolve_levels(text: &str) -> (u8, Vec<u8>) {
+    let info = ParagraphBidiInfo::new(text, None);
+    let para_level = info.paragraph_level.number();
+    if !info.has_rtl() {
+        return (para_level, vec![para_level; text.chars().count()]);
+    }
+
+    let levels = info
+        .reordered_levels_per_char(0..text.len())
+        .into_iter()
+        .map(|l| l.number())
+        .collect();
+    (para_level, levels)
+}
+
+/// Returns the logical indexes of a line in visual order (rule L2).
+pub fn reorder_line(levels: &[u8]) -> Vec<usize> {
+    let levels: Vec<Level> = levels
+        .iter()
+        .map(|l| Level::new(*l).unwrap_or_else(|_| Level::ltr()))
+        .collect();
+    BidiInfo::reorder_visual(&levels)
+}
+
+#[cfg(test)]
+fn visual(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let (_, levels) = resolve_levels(text);
+    reorder_line(&levels).iter().map(|i| chars[*i]).collect()
+}
+
+#[test]
+fn test_bidi_levels() {
+    // hebrew: alef bet gimel
+    assert_eq!(
+        resolve_levels("ab \u{5d0}\u{5d1} 12"),
+        (0, vec![0, 0, 0, 1, 1, 1, 2, 2])
+    );
+    assert_eq!(
+        resolve_levels("\u{5d0} 12 \u{5d1}"),
+        (1, vec![1, 1, 2, 2, 1, 1])
+    );
+
+    // arabic digits after arabic letters (W2)
+    assert_eq!(resolve_levels("\u{627} 12"), (1, vec![1, 1, 2, 2]));
+
+    // left-to-right text
+    assert_eq!(resolve_levels("ab"), (0, vec![0, 0]));
+    assert_eq!(mirrored_char('('), Some(')'));
+    assert_eq!(mirrored_char('a'), None);
+}
+
+#[test]
+fn test_bidi_reorder() {
+    // left-to-right text is not changed
+    assert_eq!(visual("hello world"), "hello world");
+
+    // right-to-left run in a left-to-right paragraph
+    assert_eq!(
+        visual("abc \u{5d0}\u{5d1}\u{5d2} def"),
+        "abc \u{5d2}\u{5d1}\u{5d0} def"
+    );
+
+    // numbers keep their order in a right-to-left paragraph
+    assert_eq!(
+        visual("\u{5d0}\u{5d1} 123 \u{5d2}"),
+        "\u{5d2} 123 \u{5d1}\u{5d0}"
+    );
+
+    // brackets (N0) take the paragraph direction
+    assert_eq!(visual("\u{5d0}(\u{5d1})"), ")\u{5d1}(\u{5d0}");
+
+    // explicit isolate: the english words keep their order
+    assert_eq!(
+        visual("\u{5d0} \u{2066}ab cd\u{2069}"),
+        "\u{2069}ab cd\u{2066} \u{5d0}"
+    );
+
+    // trailing whitespace stays at the paragraph end (L1)
+    assert_eq!(visual("\u{5d0}\u{5d1}  "), "  \u{5d1}\u{5d0}");
+}
+
+#[test]
+fn test_bidi_reorder_line() {
+    assert_eq!(reorder_line(&[0, 0, 1, 1, 0]), vec![0, 1, 3, 2, 4]);
+    assert_eq!(reorder_line(&[1, 2, 2, 1]), vec![3, 1, 2, 0]);
+    assert_eq!(reorder_line(&[]), Vec::<usize>::new());
+}
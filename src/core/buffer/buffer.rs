@@ -221,11 +221,40 @@ pub trait BufferEventCb {
 // PartialEq, Eq
 #[derive(Debug, Clone)]
 pub enum BufferEvent {
-    BufferFullyIndexed { buffer_id: Id },
-    BufferNodeAdded { buffer_id: Id, node_index: usize },
-    BufferNodeChanged { buffer_id: Id, node_index: usize },
-    BufferNodeRemoved { buffer_id: Id, node_index: usize },
-    BufferNodeIndexed { buffer_id: Id, node_index: usize },
+    BufferFullyIndexed {
+        buffer_id: Id,
+    },
+    BufferNodeAdded {
+        buffer_id: Id,
+        node_index: usize,
+    },
+    BufferNodeChanged {
+        buffer_id: Id,
+        node_index: usize,
+    },
+    BufferNodeRemoved {
+        buffer_id: Id,
+        node_index: usize,
+    },
+    BufferNodeIndexed {
+        buffer_id: Id,
+        node_index: usize,
+    },
+    BufferDataInserted {
+        buffer_id: Id,
+        offset: u64,
+        size: usize,
+    },
+    BufferDataRemoved {
+        buffer_id: Id,
+        offset: u64,
+        size: usize,
+    },
+    /// sent by the worker thread when the buffer cannot be saved
+    BufferSyncFailed {
+        buffer_id: Id,
+        error: String,
+    },
 }
 
 fn buffer_event_to_string(evt: &BufferEvent) -> String {
@@ -258,6 +287,15 @@ fn buffer_event_to_string(evt: &BufferEvent) -> String {
         } => {
             format!("NodeIndexed idx: {}", node_index)
         }
+        BufferEvent::BufferDataInserted { offset, size, .. } => {
+            format!("DataInserted offset: {} size: {}", offset, size)
+        }
+        BufferEvent::BufferDataRemoved { offset, size, .. } => {
+            format!("DataRemoved offset: {} size: {}", offset, size)
+        }
+        BufferEvent::BufferSyncFailed { error, .. } => {
+            format!("SyncFailed error: {}", error)
+        }
     }
 }
 
@@ -278,10 +316,14 @@ pub struct Buffer<'a> {
     pub use_buffer_log: bool,
     pub changed: bool,
     pub is_syncing: bool,
+    /// data is still appended by a producer (stdin)
+    pub is_streaming: bool,
     pub abort_indexing: bool,
     pub indexed: bool,
     pub last_tag_time: std::time::Instant,
-    pub subscribers: Vec<RefCell<Box<dyn BufferEventCb>>>,
+    /// the registered callbacks and their ids (see register_subscriber)
+    pub subscribers: Vec<(usize, RefCell<Box<dyn BufferEventCb>>)>,
+    next_subscriber_id: usize,
 }
 
 impl<'a> fmt::Debug for Buffer<'a> {
@@ -332,11 +374,15 @@ impl<'a> Buffer<'a> {
             inner
         };
 
+        let inner = inner.unwrap();
+        // the content of a compressed file is appended by the decompression
+        let is_streaming = inner.compression.is_some();
+
         let buffer = Buffer {
             id,
             kind,
             name: buffer_name.clone(),
-            inner: inner.unwrap(),
+            inner,
             cache: BufferReadCache::new(), // TODO(ceg): have a per view cache or move to View
             buffer_log: BufferLog::new(),
             use_buffer_log,
@@ -345,8 +391,10 @@ impl<'a> Buffer<'a> {
             indexed: false,
             changed,
             is_syncing: false,
+            is_streaming,
             last_tag_time: std::time::Instant::now(),
             subscribers: vec![],
+            next_subscriber_id: 1,
         };
 
         Some(Arc::new(RwLock::new(buffer)))
@@ -394,6 +442,12 @@ impl<'a> Buffer<'a> {
         self.inner.file_name.clone()
     }
 
+    /// Give a file to a buffer without file (save as), the next sync writes it.
+    pub fn set_file_name(&mut self, file_name: &str) {
+        self.inner.file_name = file_name.to_string();
+        self.name = file_name.to_string();
+    }
+
     pub fn metadata(&self) -> Result<std::fs::Metadata> {
         self.inner.metadata()
     }
@@ -410,6 +464,24 @@ impl<'a> Buffer<'a> {
         self.inner.size as u64
     }
 
+    /// Returns the size of the file as it was opened or last saved.
+    pub fn storage_size(&self) -> u64 {
+        self.inner.data.read().storage_size()
+    }
+
+    /// Appends the content of the file as it was opened or last saved to 'data'.<br/>
+    /// Returns the number of read bytes.
+    pub fn read_storage(&self, data: &mut Vec<u8>) -> usize {
+        let file = self.inner.data.read();
+        let size = file.storage_size() as usize;
+
+        let start = data.len();
+        data.resize(start + size, 0);
+        let n = file.read_storage(0, &mut data[start..]);
+        data.truncate(start + n);
+        n
+    }
+
     pub fn nr_changes(&self) -> usize {
         self.inner.nr_changes() as usize
     }
@@ -428,7 +500,7 @@ impl<'a> Buffer<'a> {
             buffer_event_to_string(&evt),
             self.subscribers.len()
         );
-        for s in self.subscribers.iter() {
+        for (_, s) in self.subscribers.iter() {
             s.borrow_mut().cb(self, evt);
         }
     }
@@ -468,11 +540,17 @@ impl<'a> Buffer<'a> {
         }
     }
 
-    // TODO(ceg): return cb slot / unregister slot_mask
+    /// Register 'cb' to receive the buffer events, returns its id for unregister_subscriber.
     pub fn register_subscriber(&mut self, cb: Box<dyn BufferEventCb>) -> usize {
-        let len = 1 + self.subscribers.len();
-        self.subscribers.push(RefCell::new(cb));
-        len
+        let id = self.next_subscriber_id;
+        self.next_subscriber_id += 1;
+        self.subscribers.push((id, RefCell::new(cb)));
+        id
+    }
+
+    /// Remove the callback registered with the id 'id'.
+    pub fn unregister_subscriber(&mut self, id: usize) {
+        self.subscribers.retain(|(sid, _)| *sid != id);
     }
 
     // read ahead
@@ -682,9 +760,44 @@ impl<'a> Buffer<'a> {
             self.notify(&ev);
         }
 
+        if sz > 0 {
+            self.notify(&BufferEvent::BufferDataInserted {
+                buffer_id: self.id,
+                offset,
+                size: sz,
+            });
+        }
+
         sz
     }
 
+    /// Replace the content of the buffer by the content of 'inner'.<br/>
+    /// The buffer log is not updated: the previous content cannot be restored by undo.
+    pub fn replace_inner(&mut self, inner: InnerBuffer<'a>) {
+        self.set_cache(0, 0); // invalidate cache,
+
+        let removed = self.size();
+        self.inner = inner;
+        self.indexed = false;
+
+        if removed > 0 {
+            self.notify(&BufferEvent::BufferDataRemoved {
+                buffer_id: self.id,
+                offset: 0,
+                size: removed,
+            });
+        }
+
+        let size = self.size();
+        if size > 0 {
+            self.notify(&BufferEvent::BufferDataInserted {
+                buffer_id: self.id,
+                offset: 0,
+                size,
+            });
+        }
+    }
+
     pub fn append(&mut self, data: &[u8]) -> usize {
         let sz = self.size() as u64;
         self.insert(sz, data.len(), &data)
@@ -734,6 +847,14 @@ impl<'a> Buffer<'a> {
             self.notify(&ev);
         }
 
+        if nr_bytes_removed > 0 {
+            self.notify(&BufferEvent::BufferDataRemoved {
+                buffer_id: self.id,
+                offset,
+                size: nr_bytes_removed,
+            });
+        }
+
         nr_bytes_removed
     }
 
@@ -786,6 +907,12 @@ impl<'a> Buffer<'a> {
                         self.notify(&ev);
                     }
 
+                    self.notify(&BufferEvent::BufferDataInserted {
+                        buffer_id: self.id,
+                        offset: op.offset,
+                        size: data.len(),
+                    });
+
                     data.len() as u64
                 } else {
                     0
@@ -810,6 +937,12 @@ impl<'a> Buffer<'a> {
                         self.notify(&ev);
                     }
 
+                    self.notify(&BufferEvent::BufferDataRemoved {
+                        buffer_id: self.id,
+                        offset: op.offset,
+                        size: rm,
+                    });
+
                     assert_eq!(rm, data.len());
                     rm
                 } else {
@@ -932,31 +1065,43 @@ impl<'a> Buffer<'a> {
 }
 
 // helper
+use super::compression::new_spill_file_path;
 use std::path::Path;
 
-// TODO(ceg): handle errors
-pub fn sync_to_storage(buffer: &Arc<RwLock<Buffer>>) {
+/// Write the content of the buffer to its file.<br/>
+/// On error the buffer is left unchanged.
+pub fn sync_to_storage(buffer: &Arc<RwLock<Buffer>>) -> Result<()> {
     // read/copy
-    let mut fd = {
+    let (mut fd, tmp_file_name) = {
         let buffer = buffer.read();
 
         if buffer.file_name().is_empty() {
             // TODO(ceg): save as pop up/notification
             dbg_println!("cannot dsave  target filename is empty");
-            return;
+            return Err(std::io::Error::other("no file name"));
         }
 
-        let tmp_file_name = format!("{}{}", buffer.file_name(), ".update"); // TODO(ceg): move to global config
+        // the content is incomplete until the end of the decompression
+        if buffer.inner.compression.is_some() && buffer.is_streaming {
+            return Err(std::io::Error::other("the file is still decompressing"));
+        }
+
+        // the decompressed content of a compressed file goes to a new spill file
+        let tmp_file_name = if buffer.inner.compression.is_some() {
+            new_spill_file_path().to_string_lossy().to_string()
+        } else {
+            format!("{}{}", buffer.file_name(), ".update") // TODO(ceg): move to global config
+        };
 
         let path = Path::new(&tmp_file_name);
         if let Result::Err(_) = std::fs::remove_file(path) {}
 
         let fd = File::create(path);
-        if fd.is_err() {
+        if let Err(e) = fd {
             dbg_println!("cannot save {}", buffer.file_name());
-            return;
+            return Err(e);
         }
-        fd.unwrap()
+        (fd.unwrap(), tmp_file_name)
     };
 
     dbg_println!("SYNC: fd = {:?}", fd);
@@ -980,12 +1125,8 @@ pub fn sync_to_storage(buffer: &Arc<RwLock<Buffer>>) {
                 data.set_len(data.capacity());
             };
 
-            if file.fd.is_none() {
-                // TODO(ceg): "save as" pop up
-                break;
-            }
-
-            let orig_fd = { Some(file.fd.as_ref().unwrap().clone()) };
+            // a buffer without file (save as) only has nodes in memory
+            let orig_fd = file.fd.clone();
 
             if let Some(_n) = node.do_direct_copy(&orig_fd, &mut data) {
                 let nw = fd.write(&data).unwrap();
@@ -1010,14 +1151,28 @@ pub fn sync_to_storage(buffer: &Arc<RwLock<Buffer>>) {
         }
     }
 
+    // the external compressor can be slow: the buffer is not locked while it runs
+    let compression = {
+        let buffer = buffer.read();
+        buffer.inner.compression.map(|c| (c, buffer.file_name()))
+    };
+    if let Some((compression, file_name)) = &compression {
+        // on error the buffer stays changed
+        let res = compression.compress_file(Path::new(&tmp_file_name), Path::new(file_name));
+        if let Err(e) = res {
+            dbg_println!("cannot compress {} : {:?}", file_name, e);
+            let _ = ::std::fs::remove_file(&tmp_file_name);
+            buffer.write().is_syncing = false;
+            return Err(e);
+        }
+    }
+
     // update
     {
         // TODO(ceg): store permission when opening the file, compare to current permission, etc..
 
         let mut buffer = buffer.write();
 
-        let tmp_file_name = format!("{}{}", buffer.file_name(), ".update"); // TODO(ceg): move '.update' to global config
-
         {
             // TODO(ceg): large file warning in save ? disable backup ?
             let _tmp_backup_name = format!("{}{}", buffer.file_name(), "~");
@@ -1040,10 +1195,26 @@ pub fn sync_to_storage(buffer: &Arc<RwLock<Buffer>>) {
             let _ = ::std::fs::set_permissions(&tmp_file_name, perms);
         }
 
-        let _ = ::std::fs::rename(&tmp_file_name, &buffer.file_name());
-
         // reopen file
-        let new_fd = File::open(&buffer.file_name()).unwrap();
+        let new_fd = match compression {
+            Some(_) => {
+                // the decompressed spill file stays the storage of the buffer
+                let new_fd = File::open(&tmp_file_name);
+                let _ = ::std::fs::remove_file(&tmp_file_name);
+                match new_fd {
+                    Ok(new_fd) => new_fd,
+                    Err(e) => {
+                        dbg_println!("cannot reopen {} : {:?}", tmp_file_name, e);
+                        buffer.is_syncing = false;
+                        return Err(e);
+                    }
+                }
+            }
+            None => {
+                let _ = ::std::fs::rename(&tmp_file_name, &buffer.file_name());
+                File::open(&buffer.file_name()).unwrap()
+            }
+        };
 
         // TODO(ceg): handle skip with ReadOnly
         let mapped_file = buffer.inner.data.clone();
@@ -1056,6 +1227,8 @@ pub fn sync_to_storage(buffer: &Arc<RwLock<Buffer>>) {
         buffer.changed = false;
         buffer.is_syncing = false;
     }
+
+    Ok(())
 }
 
 fn update_byte_index_hierarchy(
@@ -1280,12 +1453,11 @@ pub fn build_index(buffer: &Arc<RwLock<Buffer>>) -> bool {
         }
 
         // update node info (idx)
+        let node_index = idx.unwrap();
         {
             let buffer = buffer.read();
             let mut file = buffer.inner.data.write();
 
-            let node_index = idx.unwrap();
-
             // save byte counters
             {
                 let node = &mut file.pool[node_index];
@@ -1298,11 +1470,11 @@ pub fn build_index(buffer: &Arc<RwLock<Buffer>>) -> bool {
         }
 
         // notify subscribers
-        if idx.is_some() {
+        {
             let buffer = buffer.read();
             buffer.notify(&BufferEvent::BufferNodeIndexed {
                 buffer_id: buffer.id,
-                node_index: idx.unwrap(),
+                node_index,
             });
         }
     }
@@ -1797,7 +1969,7 @@ mod tests {
 
                     {
                         let buffer = buffer.as_ref().unwrap();
-                        sync_to_storage(&buffer);
+                        sync_to_storage(&buffer).unwrap();
 
                         // check on disk size
                         match std::fs::metadata(filename) {
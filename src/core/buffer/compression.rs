@@ -0,0 +1,266 @@
+//! Transparent access to compressed files.
+//!
+//! A compressed file is detected by its magic bytes and opened as an empty buffer.<br/>
+//! A background thread decompresses it with the external tool (gzip, zstd, xz) into a spill file,
+//! the spill file is then mapped like a regular file and replaces the content of the buffer.
+//! The spill file is unlinked once opened, its data stays on disk and not in memory.<br/>
+//! On save the new content is recompressed into the original file with the same format.
+
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::io::Result;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use super::buffer::Buffer;
+use super::inner::InnerBuffer;
+use crate::core::stdin_stream::stream_to_buffer;
+
+static SPILL_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Xz => "xz",
+        }
+    }
+
+    /// Returns the compression of data starting with 'header'.
+    pub fn detect(header: &[u8]) -> Option<Compression> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the compression of the file 'path' (None if the file cannot be read).
+    pub fn detect_file(path: &str) -> Option<Compression> {
+        let mut header = [0; 6];
+        let mut f = File::open(path).ok()?;
+        let n = f.read(&mut header).ok()?;
+        Self::detect(&header[..n])
+    }
+
+    /// the filter command: (program, arguments) reading stdin and writing stdout
+    fn command(&self, decompress: bool) -> (&'static str, &'static [&'static str]) {
+        match (self, decompress) {
+            (Compression::Gzip, true) => ("gzip", &["-dc"]),
+            (Compression::Gzip, false) => ("gzip", &["-c"]),
+            (Compression::Zstd, true) => ("zstd", &["-dcq"]),
+            (Compression::Zstd, false) => ("zstd", &["-cq"]),
+            (Compression::Xz, true) => ("xz", &["-dc"]),
+            (Compression::Xz, false) => ("xz", &["-c"]),
+        }
+    }
+
+    /// Run the (de)compression of 'src' into 'dst'.
+    fn filter(&self, decompress: bool, src: &Path, dst: &Path) -> Result<()> {
+        let (program, args) = self.command(decompress);
+        let status = Command::new(program)
+            .args(args)
+            .stdin(File::open(src)?)
+            .stdout(create_private_file(dst)?)
+            .stderr(Stdio::null())
+            .status()?;
+
+        if !status.success() {
+            let _ = fs::remove_file(dst);
+            return Err(std::io::Error::other(format!(
+                "{} failed on {}",
+                program,
+                src.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Decompress 'path' into a new spill file, returns the spill file path.
+    pub fn decompress_to_spill_file(&self, path: &str) -> Result<PathBuf> {
+        let spill = new_spill_file_path();
+        self.filter(true, Path::new(path), &spill)?;
+        Ok(spill)
+    }
+
+    /// Compress 'src' into 'dst', 'dst' is replaced only if the compression succeeds.
+    pub fn compress_file(&self, src: &Path, dst: &Path) -> Result<()> {
+        let mut tmp = dst.as_os_str().to_owned();
+        tmp.push(".update");
+        let tmp = PathBuf::from(tmp);
+
+        self.filter(false, src, &tmp)?;
+        if let Ok(metadata) = fs::metadata(dst) {
+            let _ = fs::set_permissions(&tmp, metadata.permissions());
+        }
+        fs::rename(&tmp, dst)
+    }
+}
+
+/// Decompress the file of 'buffer' in a new thread, the decompressed data replaces the empty content of the buffer.<br/>
+/// The buffer is streaming until the end of the decompression, 'on_done' is then called.<br/>
+/// If the decompression fails the compressed data is loaded as is.
+pub fn decompress_in_background<F: FnOnce() + Send + 'static>(
+    buffer: &Arc<RwLock<Buffer<'static>>>,
+    on_done: F,
+) {
+    let (compression, file_name) = {
+        let b = buffer.read();
+        match b.inner.compression {
+            Some(compression) if b.is_streaming => (compression, b.file_name()),
+            _ => return,
+        }
+    };
+
+    let buffer = Arc::clone(buffer);
+
+    // the external tool blocks: do not use the executor thread
+    std::thread::spawn(move || {
+        let (map_name, compression) = match compression.decompress_to_spill_file(&file_name) {
+            Ok(spill) => (spill.to_string_lossy().to_string(), Some(compression)),
+            Err(_e) => {
+                dbg_println!("cannot decompress '{}' : {:?}", file_name, _e);
+                (file_name.clone(), None)
+            }
+        };
+
+        let typed = {
+            let mut b = buffer.write();
+            let typed = b.changed || b.size() > 0;
+            if !typed {
+                let inner = InnerBuffer::map(b.id, &file_name, &map_name, b.inner.mode());
+                if let Some(mut inner) = inner {
+                    inner.compression = compression;
+                    b.replace_inner(inner);
+                }
+            }
+            b.inner.compression = compression;
+            typed
+        };
+
+        // keep the text typed during the decompression, the data is appended to it
+        if typed {
+            if let Ok(file) = File::open(&map_name) {
+                stream_to_buffer(file, &buffer, |_| true);
+            }
+        }
+
+        // the mapped file keeps the spill file open
+        if compression.is_some() {
+            let _ = fs::remove_file(&map_name);
+        }
+
+        buffer.write().is_streaming = false;
+        on_done();
+    });
+}
+
+/// The decompressed data may be sensitive: the spill files are only readable by the user.
+fn create_private_file(path: &Path) -> Result<File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)
+}
+
+/// Returns a new path for a spill (or spill update) file in the temporary directory.
+pub fn new_spill_file_path() -> PathBuf {
+    let n = SPILL_FILE_COUNT.fetch_add(1, Ordering::SeqCst);
+    std::env::temp_dir().join(format!("unlimited-{}-{}.spill", std::process::id(), n))
+}
+
+#[test]
+fn test_compression_detect() {
+    assert_eq!(
+        Compression::detect(&[0x1f, 0x8b, 8, 0]),
+        Some(Compression::Gzip)
+    );
+    assert_eq!(
+        Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0]),
+        Some(Compression::Zstd)
+    );
+    assert_eq!(Compression::detect(b"\xfd7zXZ\x00"), Some(Compression::Xz));
+    assert_eq!(Compression::detect(b"\xfd7zX"), None);
+    assert_eq!(Compression::detect(b"hello"), None);
+    assert_eq!(Compression::detect(b""), None);
+}
+
+#[test]
+fn test_compression_round_trip() {
+    use super::buffer::BufferBuilder;
+    use super::buffer::BufferKind;
+
+    if Command::new("gzip").arg("--version").output().is_err() {
+        return;
+    }
+
+    let src = new_spill_file_path();
+    let gz = new_spill_file_path();
+    fs::write(&src, "hello\ncompressed\n").unwrap();
+
+    Compression::Gzip.compress_file(&src, &gz).unwrap();
+    let gz_str = gz.to_string_lossy().to_string();
+    assert_eq!(Compression::detect_file(&gz_str), Some(Compression::Gzip));
+
+    let spill = Compression::Gzip.decompress_to_spill_file(&gz_str).unwrap();
+    assert_eq!(fs::read(&spill).unwrap(), b"hello\ncompressed\n");
+
+    // the buffer is empty until the end of the decompression
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name(&gz_str)
+        .file_name(&gz_str)
+        .finalize()
+        .unwrap();
+    assert!(buffer.read().is_streaming);
+    assert_eq!(buffer.read().size(), 0);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    decompress_in_background(&buffer, move || tx.send(()).unwrap());
+    rx.recv().unwrap();
+
+    let b = buffer.read();
+    assert!(!b.is_streaming);
+    assert!(!b.changed);
+    assert_eq!(b.inner.compression, Some(Compression::Gzip));
+    let mut data = vec![];
+    b.read(0, b.size(), &mut data);
+    assert_eq!(data, b"hello\ncompressed\n");
+    drop(b);
+
+    // the new content is recompressed into the original file
+    buffer.write().insert(0, 4, b"new\n");
+    super::buffer::sync_to_storage(&buffer).unwrap();
+    assert!(!buffer.read().changed);
+    let spill2 = Compression::Gzip.decompress_to_spill_file(&gz_str).unwrap();
+    assert_eq!(fs::read(&spill2).unwrap(), b"new\nhello\ncompressed\n");
+
+    for p in [src, gz, spill, spill2] {
+        let _ = fs::remove_file(p);
+    }
+}
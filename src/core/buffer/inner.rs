@@ -1,6 +1,7 @@
 use std::io::Result;
 use std::io::{Error, ErrorKind};
 
+use super::compression::Compression;
 use crate::core::mapped_file::FileHandle;
 use crate::core::mapped_file::MappedFile;
 use crate::core::mapped_file::MappedFileEvent;
@@ -30,6 +31,8 @@ pub struct InnerBuffer<'a> {
     pub size: usize,
     /// the number of changes (since last save TODO)
     pub nr_changes: u64,
+    /// the compression of the file, the mapped data is the decompressed content
+    pub compression: Option<Compression>,
     mode: OpenMode,
     pub data: FileHandle<'a>,
 }
@@ -53,7 +56,19 @@ impl<'a> InnerBuffer<'a> {
             return InnerBuffer::empty(id, mode);
         }
 
-        let file = match MappedFile::new(id, &file_name.to_owned()) {
+        // a compressed file is loaded later, see compression::decompress_in_background()
+        if let Some(compression) = Compression::detect_file(file_name) {
+            let mut inner = InnerBuffer::empty_with_name(id, &file_name.to_owned(), mode)?;
+            inner.compression = Some(compression);
+            return Some(inner);
+        }
+
+        InnerBuffer::map(id, file_name, file_name, mode)
+    }
+
+    /// Creates an `InnerBuffer` named 'file_name' mapping the file 'map_name'.
+    pub fn map(id: Id, file_name: &str, map_name: &str, mode: OpenMode) -> Option<InnerBuffer<'a>> {
+        let file = match MappedFile::new(id, &map_name.to_owned()) {
             Some(file) => file,
             None => {
                 // TODO(ceg): return Result
@@ -72,6 +87,7 @@ impl<'a> InnerBuffer<'a> {
             mode,
             size,
             nr_changes: 0,
+            compression: None,
             data: file,
         })
     }
@@ -92,6 +108,7 @@ impl<'a> InnerBuffer<'a> {
             mode,
             size,
             nr_changes: 0,
+            compression: None,
             data: file,
         })
     }
@@ -112,6 +129,7 @@ impl<'a> InnerBuffer<'a> {
             mode,
             size,
             nr_changes: 0,
+            compression: None,
             data: file,
         })
     }
@@ -1,5 +1,6 @@
 mod buffer;
 mod bufferlog;
+pub mod compression;
 mod inner;
 
 pub use bufferlog::*;
@@ -20,4 +21,6 @@ pub use buffer::get_byte_count_at_offset;
 pub use buffer::get_node_data;
 pub use buffer::sync_to_storage;
 
+pub use compression::Compression;
+
 pub use buffer::Id;
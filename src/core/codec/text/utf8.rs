@@ -3,6 +3,8 @@
 //   Copyright (c) 2008-2010 Bjoern Hoehrmann <bjoern@hoehrmann.de>
 //   See http://bjoern.hoehrmann.de/utf-8/decoder/dfa/ for details.
 
+use std::cell::RefCell;
+
 use super::SyncDirection;
 use super::TextCodec;
 
@@ -13,6 +15,8 @@ const DEBUG: bool = false;
 
 use crate::core::codec::text::u32_to_char;
 
+use unicode_segmentation::GraphemeCursor;
+
 #[rustfmt::skip]
 static UTF8D: &[u8] = &[
   // The first part of the table maps bytes to character classes that
@@ -334,6 +338,82 @@ fn utf8_decode_byte_checked(
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Upper bound of the number of codepoints of a grapheme cluster.<br/>
+/// Longer sequences (zalgo text, ...) are split to keep the lookups bounded.
+pub const MAX_GRAPHEME_CLUSTER_CODEPOINTS: usize = 32;
+
+thread_local! {
+    /// cluster + next codepoint, reused by extends_grapheme_cluster (called for each rendered codepoint)
+    static GRAPHEME_CLUSTER_BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Returns true if 'c' belongs to the grapheme cluster 'cluster' (extended grapheme clusters).<br/>
+/// 'cluster' must start at a cluster boundary.
+pub fn extends_grapheme_cluster(cluster: &str, c: char) -> bool {
+    let last = match cluster.chars().next_back() {
+        Some(last) => last,
+        None => return false,
+    };
+
+    // CR LF, the controls are clusters, two ASCII characters are two clusters
+    if last == '\r' && c == '\n' {
+        return true;
+    }
+    if last.is_control() || c.is_control() || (last.is_ascii() && c.is_ascii()) {
+        return false;
+    }
+
+    GRAPHEME_CLUSTER_BUFFER.with(|s| {
+        let mut s = s.borrow_mut();
+        s.clear();
+        s.push_str(cluster);
+        s.push(c);
+
+        let mut cursor = GraphemeCursor::new(cluster.len(), s.len(), true);
+        !cursor.is_boundary(&s, 0).unwrap_or(true)
+    })
+}
+
+/// Returns the size in bytes of the grapheme clusters found in 'cps'.<br/>
+/// 'cps' is a list of (codepoint, size) starting at a cluster boundary.
+pub fn grapheme_cluster_sizes(cps: &[(char, usize)]) -> Vec<usize> {
+    let mut sizes = vec![];
+    let mut cluster = String::new();
+    let mut count = 0;
+
+    for (c, size) in cps {
+        if count < MAX_GRAPHEME_CLUSTER_CODEPOINTS && extends_grapheme_cluster(&cluster, *c) {
+            *sizes.last_mut().unwrap() += size;
+            count += 1;
+        } else {
+            sizes.push(*size);
+            cluster.clear();
+            count = 1;
+        }
+        cluster.push(*c);
+    }
+
+    sizes
+}
+
+/// Returns the end offset of the grapheme cluster of 'data' starting at 'from_offset'.
+pub fn get_grapheme_cluster_end(data: &[u8], from_offset: u64) -> u64 {
+    let mut cps = vec![];
+    let mut off = from_offset;
+    while (off as usize) < data.len() && cps.len() <= MAX_GRAPHEME_CLUSTER_CODEPOINTS {
+        let (cp, _, size) = get_codepoint(data, off);
+        cps.push((cp, size));
+        off += size as u64;
+    }
+
+    from_offset
+        + grapheme_cluster_sizes(&cps)
+            .first()
+            .map_or(0, |sz| *sz as u64)
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+
 #[derive(Debug, Default)]
 pub struct Utf8Codec {}
 
@@ -530,3 +610,39 @@ fn test_backward_decode() {
 
     println!("new start_offset({})", start_offset);
 }
+
+#[test]
+fn test_grapheme_clusters() {
+    // the test lines of the Unicode GraphemeBreakTest.txt: "÷ 0020 × 0308 ÷ 0020 ÷"
+    let data = std::include_str!("../../../../res/unicode/GraphemeBreakTest.txt");
+
+    let mut nr_tests = 0;
+    for line in data.lines().filter(|l| l.starts_with('÷')) {
+        let mut cps = vec![];
+        let mut expected = vec![];
+        for token in line.split_whitespace() {
+            match token {
+                "÷" => expected.push(0),
+                "×" => {}
+                hex => {
+                    let c = char::from_u32(u32::from_str_radix(hex, 16).unwrap()).unwrap();
+                    cps.push((c, c.len_utf8()));
+                    *expected.last_mut().unwrap() += c.len_utf8();
+                }
+            }
+        }
+        expected.pop(); // the final boundary
+
+        assert_eq!(grapheme_cluster_sizes(&cps), expected, "{}", line);
+
+        let text: String = cps.iter().map(|(c, _)| c).collect();
+        assert_eq!(
+            get_grapheme_cluster_end(text.as_bytes(), 0),
+            expected[0] as u64,
+            "{}",
+            line
+        );
+        nr_tests += 1;
+    }
+    assert!(nr_tests > 1000);
+}
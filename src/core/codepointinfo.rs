@@ -96,6 +96,22 @@ impl TextStyle {
         (31, 36, 59)
     }
 
+    pub fn matching_bracket_bg_color() -> (u8, u8, u8) {
+        if USE_DARK_THEME {
+            (60, 70, 110) // dark theme
+        } else {
+            (200, 200, 160) // light theme
+        }
+    }
+
+    pub fn fuzzy_match_color() -> (u8, u8, u8) {
+        if USE_DARK_THEME {
+            (255, 184, 108) // dark theme
+        } else {
+            (176, 88, 0) // light theme
+        }
+    }
+
     pub fn mark_style(color: Option<(u8, u8, u8)>) -> TextStyle {
         let color = if let Some(c) = color {
             c
@@ -150,6 +166,9 @@ pub struct CodepointInfo {
     pub skip_render: bool,
     /// Style to apply when rendering
     pub style: TextStyle,
+    /// The bidi embedding level, odd levels are displayed right-to-left.<br/>
+    /// Kept out of the style: the overlays replace the style of the cells.
+    pub bidi_level: u8,
 }
 
 impl CodepointInfo {
@@ -164,6 +183,7 @@ impl CodepointInfo {
             skip_render: false,
             //
             style: TextStyle::new(),
+            bidi_level: 0,
         }
     }
 }
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 pub type ConfigVariables = HashMap<String, String>;
 
@@ -7,4 +8,16 @@ pub struct Config {
     pub files_list: Vec<String>,
     pub ui_frontend: String,
     pub vars: ConfigVariables,
+    /// name of the session to restore/save (--session NAME)
+    pub session: Option<String>,
+}
+
+/// Returns the directory used to store the user's data (sessions, history ...):<br/>
+/// $XDG_DATA_HOME/unlimited or $HOME/.local/share/unlimited
+pub fn user_data_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("unlimited"))
 }
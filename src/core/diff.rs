@@ -0,0 +1,471 @@
+//! Line diff (Myers' algorithm) and intra-line changes used by the diff mode.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Above this number of edits, the remaining lines are reported as a single change.
+const MAX_EDIT_DISTANCE: usize = 2048;
+
+/// The lines 'old' of the old text are replaced by the lines 'new' of the new text.<br/>
+/// An empty 'old' range is an insertion, an empty 'new' range is a removal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// Returns the start offset of each line of 'data' followed by data.len().<br/>
+/// The '\n' belongs to the line it ends.
+pub fn line_offsets(data: &[u8]) -> Vec<usize> {
+    let mut offsets = vec![0];
+    for (i, b) in data.iter().enumerate() {
+        if *b == b'\n' && i + 1 < data.len() {
+            offsets.push(i + 1);
+        }
+    }
+    if !data.is_empty() {
+        offsets.push(data.len());
+    }
+    offsets
+}
+
+/// Returns the lines of 'data' delimited by 'offsets' (see line_offsets).
+pub fn split_lines<'a>(data: &'a [u8], offsets: &[usize]) -> Vec<&'a [u8]> {
+    offsets.windows(2).map(|w| &data[w[0]..w[1]]).collect()
+}
+
+/// Returns the changes between the 'old' and 'new' lines, sorted by position.
+pub fn diff_lines(old: &[&[u8]], new: &[&[u8]]) -> Vec<DiffHunk> {
+    // compare integers instead of lines
+    let mut ids = HashMap::new();
+    let mut intern = |l: &[u8]| -> usize {
+        let n = ids.len();
+        *ids.entry(l.to_vec()).or_insert(n)
+    };
+    let a: Vec<usize> = old.iter().map(|l| intern(l)).collect();
+    let b: Vec<usize> = new.iter().map(|l| intern(l)).collect();
+
+    // skip the common prefix and suffix
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    // no match: a single change
+    let matches = myers_matches(a_mid, b_mid, MAX_EDIT_DISTANCE).unwrap_or_default();
+
+    // the changes are the gaps between the matching lines
+    let mut hunks = vec![];
+    let (mut x, mut y) = (0, 0);
+    for (mx, my) in matches
+        .iter()
+        .copied()
+        .chain(std::iter::once((a_mid.len(), b_mid.len())))
+    {
+        if mx > x || my > y {
+            hunks.push(DiffHunk {
+                old: prefix + x..prefix + mx,
+                new: prefix + y..prefix + my,
+            });
+        }
+        x = mx + 1;
+        y = my + 1;
+    }
+
+    hunks
+}
+
+/// Returns the pairs of matching indexes of the shortest edit script of 'a' into 'b'.<br/>
+/// Returns None if there are more than 'max_edits' edits.
+fn myers_matches(a: &[usize], b: &[usize], max_edits: usize) -> Option<Vec<(usize, usize)>> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = std::cmp::min((n + m) as usize, max_edits) as isize;
+
+    // v[k + offset] is the furthest x on the diagonal k
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    let mut end = None;
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+
+            if x >= n && y >= m {
+                end = Some(d);
+                break 'search;
+            }
+        }
+    }
+
+    // walk back the edit script
+    let mut matches = vec![];
+    let (mut x, mut y) = (n, m);
+    for d in (0..=end?).rev() {
+        let prev = &trace[d as usize];
+        let get = |k: isize| prev[(k + d) as usize];
+
+        let k = x - y;
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            let prev_x = get(prev_k);
+            (prev_x, prev_x - prev_k)
+        };
+
+        // the snake after the edit
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            matches.push((x as usize, y as usize));
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    matches.reverse();
+    Some(matches)
+}
+
+/// Returns the line of the other text matching 'line'.<br/>
+/// 'from_old' selects the side of 'line'. A changed line is mapped into its hunk.
+pub fn map_line(hunks: &[DiffHunk], line: usize, from_old: bool) -> usize {
+    let mut src_end = 0;
+    let mut dst_end = 0;
+    for h in hunks {
+        let (src, dst) = if from_old {
+            (&h.old, &h.new)
+        } else {
+            (&h.new, &h.old)
+        };
+        if line < src.start {
+            break;
+        }
+        if line < src.end {
+            let n = std::cmp::min(line - src.start, dst.len().saturating_sub(1));
+            return dst.start + n;
+        }
+        src_end = src.end;
+        dst_end = dst.end;
+    }
+    dst_end + (line - src_end)
+}
+
+/// A line of an unified diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnifiedLine {
+    /// "@@ -l,s +l,s @@", 'hunk' is the first hunk of the group
+    Header {
+        hunk: usize,
+    },
+    Context,
+    /// the old line 'line' of 'hunk'
+    Removed {
+        hunk: usize,
+        line: usize,
+    },
+    /// the new line 'line' of 'hunk'
+    Inserted {
+        hunk: usize,
+        line: usize,
+    },
+}
+
+/// Builds the unified diff text of 'hunks' with 'context' lines around the changes.<br/>
+/// Returns the text and the kind of each of its lines.
+pub fn unified_diff(
+    old: &[&[u8]],
+    new: &[&[u8]],
+    hunks: &[DiffHunk],
+    context: usize,
+) -> (Vec<u8>, Vec<UnifiedLine>) {
+    let mut text = vec![];
+    let mut kinds = vec![];
+
+    let push_line = |text: &mut Vec<u8>, prefix: u8, line: &[u8]| {
+        text.push(prefix);
+        text.extend_from_slice(line);
+        if line.last() != Some(&b'\n') {
+            text.push(b'\n');
+        }
+    };
+
+    let mut i = 0;
+    while i < hunks.len() {
+        // group the hunks whose contexts overlap
+        let mut j = i + 1;
+        while j < hunks.len() && hunks[j].old.start - hunks[j - 1].old.end <= 2 * context {
+            j += 1;
+        }
+        let (first, last) = (&hunks[i], &hunks[j - 1]);
+
+        let old_start = first.old.start.saturating_sub(context);
+        let old_end = std::cmp::min(last.old.end + context, old.len());
+        let new_start = first.new.start - (first.old.start - old_start);
+        let new_end = last.new.end + (old_end - last.old.end);
+
+        let range = |start: usize, end: usize| {
+            if end > start {
+                format!("{},{}", start + 1, end - start)
+            } else {
+                format!("{},0", start)
+            }
+        };
+        text.extend_from_slice(
+            format!(
+                "@@ -{} +{} @@\n",
+                range(old_start, old_end),
+                range(new_start, new_end)
+            )
+            .as_bytes(),
+        );
+        kinds.push(UnifiedLine::Header { hunk: i });
+
+        let mut x = old_start;
+        for (n, h) in hunks.iter().enumerate().take(j).skip(i) {
+            for l in &old[x..h.old.start] {
+                push_line(&mut text, b' ', l);
+                kinds.push(UnifiedLine::Context);
+            }
+            for (k, l) in old[h.old.clone()].iter().enumerate() {
+                push_line(&mut text, b'-', l);
+                kinds.push(UnifiedLine::Removed { hunk: n, line: k });
+            }
+            for (k, l) in new[h.new.clone()].iter().enumerate() {
+                push_line(&mut text, b'+', l);
+                kinds.push(UnifiedLine::Inserted { hunk: n, line: k });
+            }
+            x = h.old.end;
+        }
+        for l in &old[x..old_end] {
+            push_line(&mut text, b' ', l);
+            kinds.push(UnifiedLine::Context);
+        }
+
+        i = j;
+    }
+
+    (text, kinds)
+}
+
+fn is_utf8_continuation(b: u8) -> bool {
+    b & 0xc0 == 0x80
+}
+
+/// Returns the changed ranges of two versions of a line: the common prefix and suffix are excluded.<br/>
+/// The ranges do not split utf-8 sequences.
+pub fn diff_line_change(old: &[u8], new: &[u8]) -> (Range<usize>, Range<usize>) {
+    let mut prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(x, y)| x == y)
+        .count();
+    while prefix > 0
+        && ((prefix < old.len() && is_utf8_continuation(old[prefix]))
+            || (prefix < new.len() && is_utf8_continuation(new[prefix])))
+    {
+        prefix -= 1;
+    }
+
+    let max_suffix = std::cmp::min(old.len(), new.len()) - prefix;
+    let mut suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(max_suffix)
+        .take_while(|(x, y)| x == y)
+        .count();
+    while suffix > 0
+        && (is_utf8_continuation(old[old.len() - suffix])
+            || is_utf8_continuation(new[new.len() - suffix]))
+    {
+        suffix -= 1;
+    }
+
+    (prefix..old.len() - suffix, prefix..new.len() - suffix)
+}
+
+#[test]
+fn test_diff_lines() {
+    let lines = |s: &'static str| -> Vec<&'static [u8]> {
+        let data = s.as_bytes();
+        split_lines(data, &line_offsets(data))
+    };
+
+    assert_eq!(line_offsets(b""), vec![0]);
+    assert_eq!(line_offsets(b"a\nb"), vec![0, 2, 3]);
+    assert_eq!(line_offsets(b"a\nb\n"), vec![0, 2, 4]);
+
+    let old = lines("a\nb\nc\nd\n");
+    assert_eq!(diff_lines(&old, &old), vec![]);
+
+    // change, insertion, removal
+    let new = lines("a\nB\nc\nd\ne\n");
+    assert_eq!(
+        diff_lines(&old, &new),
+        vec![
+            DiffHunk {
+                old: 1..2,
+                new: 1..2
+            },
+            DiffHunk {
+                old: 4..4,
+                new: 4..5
+            },
+        ]
+    );
+
+    let new = lines("b\nc\n");
+    assert_eq!(
+        diff_lines(&old, &new),
+        vec![
+            DiffHunk {
+                old: 0..1,
+                new: 0..0
+            },
+            DiffHunk {
+                old: 3..4,
+                new: 2..2
+            },
+        ]
+    );
+
+    // moved line
+    let old = lines("x\na\nb\nc\ny\n");
+    let new = lines("x\nb\nc\na\ny\n");
+    assert_eq!(
+        diff_lines(&old, &new),
+        vec![
+            DiffHunk {
+                old: 1..2,
+                new: 1..1
+            },
+            DiffHunk {
+                old: 4..4,
+                new: 3..4
+            },
+        ]
+    );
+
+    // empty texts
+    assert_eq!(
+        diff_lines(&[], &lines("a\n")),
+        vec![DiffHunk {
+            old: 0..0,
+            new: 0..1
+        }]
+    );
+    assert_eq!(
+        diff_lines(&lines("a\n"), &[]),
+        vec![DiffHunk {
+            old: 0..1,
+            new: 0..0
+        }]
+    );
+
+    // too many edits: one hunk
+    let old: Vec<Vec<u8>> = (0..3000).map(|i| format!("{}\n", i).into_bytes()).collect();
+    let new: Vec<Vec<u8>> = (0..3000)
+        .map(|i| format!("{}\n", -i - 1).into_bytes())
+        .collect();
+    let old: Vec<&[u8]> = old.iter().map(|l| l.as_slice()).collect();
+    let new: Vec<&[u8]> = new.iter().map(|l| l.as_slice()).collect();
+    assert_eq!(
+        diff_lines(&old, &new),
+        vec![DiffHunk {
+            old: 0..3000,
+            new: 0..3000
+        }]
+    );
+}
+
+#[test]
+fn test_diff_line_change() {
+    assert_eq!(
+        diff_line_change(b"let a = 1;\n", b"let b = 1;\n"),
+        (4..5, 4..5)
+    );
+    assert_eq!(diff_line_change(b"abc\n", b"abXYc\n"), (2..2, 2..4));
+    assert_eq!(diff_line_change(b"aaa\n", b"aa\n"), (2..3, 2..2));
+    assert_eq!(diff_line_change(b"same\n", b"same\n"), (5..5, 5..5));
+
+    // 'é' (c3 a9) and 'è' (c3 a8) share their first byte
+    assert_eq!(
+        diff_line_change("xé\n".as_bytes(), "xè\n".as_bytes()),
+        (1..3, 1..3)
+    );
+}
+
+#[test]
+fn test_map_line() {
+    // old: a b c d e, new: a X c e f
+    let hunks = vec![
+        DiffHunk {
+            old: 1..2,
+            new: 1..2,
+        },
+        DiffHunk {
+            old: 3..4,
+            new: 3..3,
+        },
+        DiffHunk {
+            old: 5..5,
+            new: 4..5,
+        },
+    ];
+    assert_eq!(map_line(&hunks, 0, true), 0);
+    assert_eq!(map_line(&hunks, 1, true), 1);
+    assert_eq!(map_line(&hunks, 2, true), 2);
+    assert_eq!(map_line(&hunks, 3, true), 3);
+    assert_eq!(map_line(&hunks, 4, true), 3);
+    assert_eq!(map_line(&hunks, 4, false), 5);
+    assert_eq!(map_line(&hunks, 3, false), 4);
+    assert_eq!(map_line(&hunks, 9, false), 9);
+}
+
+#[test]
+fn test_unified_diff() {
+    let old: Vec<&[u8]> = vec![b"1\n", b"2\n", b"3\n", b"4\n", b"5\n", b"6\n", b"7\n"];
+    let new: Vec<&[u8]> = vec![b"1\n", b"two\n", b"3\n", b"4\n", b"5\n", b"6\n", b"7"];
+    let hunks = diff_lines(&old, &new);
+    assert_eq!(hunks.len(), 2);
+
+    let (text, kinds) = unified_diff(&old, &new, &hunks, 1);
+    assert_eq!(
+        std::str::from_utf8(&text).unwrap(),
+        "@@ -1,3 +1,3 @@\n 1\n-2\n+two\n 3\n@@ -6,2 +6,2 @@\n 6\n-7\n+7\n"
+    );
+    assert_eq!(kinds[0], UnifiedLine::Header { hunk: 0 });
+    assert_eq!(kinds[2], UnifiedLine::Removed { hunk: 0, line: 0 });
+    assert_eq!(kinds[8], UnifiedLine::Inserted { hunk: 1, line: 0 });
+
+    // overlapping contexts: one group
+    let (text, _) = unified_diff(&old, &new, &hunks, 3);
+    assert_eq!(text.iter().filter(|c| **c == b'@').count(), 4);
+}
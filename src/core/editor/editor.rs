@@ -29,6 +29,9 @@ use crate::core::buffer::Buffer;
 use crate::core::buffer::BufferEvent;
 
 use crate::core::config::Config;
+use crate::core::file_follow::FileFollower;
+use crate::core::session::restore_session_layout;
+use crate::core::session::SessionLayout;
 
 use crate::core::event;
 use crate::core::event::input_map::eval_input_event;
@@ -186,6 +189,10 @@ pub struct Editor<'a> {
     pub buffer_map: Arc<RwLock<HashMap<buffer::Id, Arc<RwLock<Buffer<'static>>>>>>,
     //
     pub active_views: Vec<view::Id>, // ordered ids from view_map // must rework main view
+    /// file views, most recently shown first
+    pub recent_views: Vec<view::Id>,
+    /// split layout of a restored session, applied once the views are laid out
+    pub pending_session_layout: Option<(view::Id, SessionLayout)>,
     //
     pub view_map: Arc<RwLock<HashMap<view::Id, Rc<RwLock<View<'a>>>>>>,
     pub tagged_view: Arc<RwLock<HashMap<String, HashSet<view::Id>>>>,
@@ -204,6 +211,15 @@ pub struct Editor<'a> {
         Rc<RefCell<HashMap<String, (Rc<RefCell<Box<dyn Mode>>>, HashSet<view::Id>)>>>,
 
     pub pending_editor_events: Rc<RefCell<Vec<EditorEvent>>>,
+
+    /// copy/cut history and named registers, shared by all views
+    pub kill_ring: KillRing,
+
+    /// last position of the files and recently opened files
+    pub file_history: FileHistory,
+
+    /// the files watched by the views in follow mode
+    pub file_followers: HashMap<buffer::Id, FileFollower>,
 }
 
 impl<'a> Editor<'a> {
@@ -217,11 +233,25 @@ impl<'a> Editor<'a> {
         indexer_tx: Sender<Message<'a>>,
         executor_tx: Sender<(i32, Box<dyn FnOnce() + Send>)>,
     ) -> Editor<'a> {
+        let kill_ring_size = config
+            .vars
+            .get("kill-ring:size")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(kill_ring::KILL_RING_DEFAULT_SIZE);
+
+        let file_history_size = config
+            .vars
+            .get("file-history:size")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(file_history::FILE_HISTORY_DEFAULT_SIZE);
+
         Editor {
             config,
             buffer_map: Arc::new(RwLock::new(HashMap::new())),
 
             active_views: vec![],
+            recent_views: vec![],
+            pending_session_layout: None,
 
             view_map: Arc::new(RwLock::new(HashMap::new())),
 
@@ -238,6 +268,11 @@ impl<'a> Editor<'a> {
             event_subscribers: Rc::new(RefCell::new(HashMap::new())),
 
             pending_editor_events: Rc::new(RefCell::new(vec![])),
+
+            kill_ring: KillRing::new(kill_ring_size),
+            // not persisted, see FileHistory::load
+            file_history: FileHistory::new(file_history_size),
+            file_followers: HashMap::new(),
         }
     }
 
@@ -398,6 +433,19 @@ pub fn update_view_and_send_draw_event(
         target_id,
     );
 
+    // without input the target is the root view: prepare the active view too (follow mode)
+    if let Some(active_id) = env.active_view {
+        if active_id != target_id {
+            run_stage(
+                StagePosition::Pre,
+                Stage::Compositing,
+                editor,
+                env,
+                active_id,
+            );
+        }
+    }
+
     // redraw root
     let view_id = env.root_view_id;
     run_stages(Stage::Compositing, &mut editor, &mut env, view_id);
@@ -1727,6 +1775,16 @@ fn process_buffer_event(
             }
         }
 
+        BufferEvent::BufferSyncFailed { buffer_id, error } => {
+            let name = match editor.buffer_map.read().get(buffer_id) {
+                Some(buffer) => buffer.read().file_name(),
+                None => return false,
+            };
+            let text = format!("cannot save '{}': {}", name, error);
+            crate::core::modes::core_mode::set_command_line_text(editor, env, &text);
+            refresh = true;
+        }
+
         _ => {
             // unexpected
             panic!("{:?}", event);
@@ -1760,6 +1818,10 @@ pub fn main_loop(
 
                     env.pending_events = crate::core::event::pending_input_event_dec(1);
                     update_view_and_send_draw_event(&mut editor, &mut env);
+
+                    if editor.pending_session_layout.is_some() {
+                        restore_session_layout(&mut editor, &mut env);
+                    }
                 }
 
                 Event::RefreshView => {
@@ -0,0 +1,276 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde_json::json;
+use serde_json::Value;
+
+use crate::core::config::user_data_dir;
+
+/// default number of files remembered
+pub const FILE_HISTORY_DEFAULT_SIZE: usize = 500;
+
+/// maximum number of marks/selections remembered per file
+const FILE_HISTORY_MAX_MARKS: usize = 64;
+
+/// a larger history file is ignored (and replaced on exit)
+const FILE_HISTORY_MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+const FILE_HISTORY_VERSION: u64 = 1;
+
+/// The last position of a file: first visible offset, marks, selection points and undo tag.<br/>
+/// The undo tag is the one of the buffer log position, it becomes the first undo tag of the reopened buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHistoryEntry {
+    /// absolute path
+    pub path: String,
+    pub start_offset: u64,
+    pub marks: Vec<u64>,
+    pub selections: Vec<u64>,
+    /// the marks of the undo tag, empty if the buffer log position was not a tag
+    pub undo_marks: Vec<u64>,
+    pub undo_selections: Vec<u64>,
+    /// milliseconds since UNIX_EPOCH
+    pub last_access: u64,
+}
+
+impl FileHistoryEntry {
+    pub fn new(path: &str) -> Self {
+        FileHistoryEntry {
+            path: path.to_owned(),
+            start_offset: 0,
+            marks: vec![0],
+            selections: vec![],
+            undo_marks: vec![],
+            undo_selections: vec![],
+            last_access: now_ms(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "path": self.path,
+            "start_offset": self.start_offset,
+            "marks": self.marks,
+            "selections": self.selections,
+            "undo_marks": self.undo_marks,
+            "undo_selections": self.undo_selections,
+            "last_access": self.last_access,
+        })
+    }
+
+    fn from_json(v: &Value) -> Option<Self> {
+        let offsets = |name: &str| -> Vec<u64> {
+            v.get(name)
+                .and_then(|a| a.as_array())
+                .map_or(vec![], |a| a.iter().filter_map(|o| o.as_u64()).collect())
+        };
+
+        Some(FileHistoryEntry {
+            path: v.get("path")?.as_str()?.to_owned(),
+            start_offset: v.get("start_offset")?.as_u64()?,
+            marks: offsets("marks"),
+            selections: offsets("selections"),
+            undo_marks: offsets("undo_marks"),
+            undo_selections: offsets("undo_selections"),
+            last_access: v.get("last_access").and_then(|t| t.as_u64()).unwrap_or(0),
+        })
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// The **FileHistory** remembers the last position of the opened files
+/// and gives the list of the recently opened files.<br/>
+/// It is stored in user_data_dir()/file-history.json, only the 'max_entries' most recent files are kept.
+#[derive(Debug)]
+pub struct FileHistory {
+    /// most recent first
+    pub entries: Vec<FileHistoryEntry>,
+    pub max_entries: usize,
+    /// None: not persisted
+    storage: Option<PathBuf>,
+}
+
+impl FileHistory {
+    pub fn new(max_entries: usize) -> Self {
+        FileHistory {
+            entries: vec![],
+            max_entries: std::cmp::max(1, max_entries),
+            storage: None,
+        }
+    }
+
+    pub fn storage_path() -> Option<PathBuf> {
+        Some(user_data_dir()?.join("file-history.json"))
+    }
+
+    /// Load the history from user_data_dir(), an unreadable history is ignored.
+    pub fn load(max_entries: usize) -> Self {
+        let mut history = FileHistory::new(max_entries);
+        history.storage = Self::storage_path();
+        if let Some(path) = &history.storage {
+            history.entries = read_entries(path);
+            history.entries.truncate(history.max_entries);
+        }
+        history
+    }
+
+    pub fn get(&self, path: &str) -> Option<&FileHistoryEntry> {
+        self.entries.iter().find(|e| e.path == path)
+    }
+
+    /// Store 'entry' as the most recent one.
+    pub fn update(&mut self, mut entry: FileHistoryEntry) {
+        entry.marks.truncate(FILE_HISTORY_MAX_MARKS);
+        entry.selections.truncate(FILE_HISTORY_MAX_MARKS);
+        entry.undo_marks.truncate(FILE_HISTORY_MAX_MARKS);
+        entry.undo_selections.truncate(FILE_HISTORY_MAX_MARKS);
+        entry.last_access = now_ms();
+
+        self.entries.retain(|e| e.path != entry.path);
+        self.entries.insert(0, entry);
+        self.entries.truncate(self.max_entries);
+    }
+
+    /// Move 'path' in front of the recent files, keep its position if known.
+    pub fn touch(&mut self, path: &str) {
+        let entry = match self.get(path) {
+            Some(e) => e.clone(),
+            None => FileHistoryEntry::new(path),
+        };
+        self.update(entry);
+    }
+
+    /// The recently opened files, most recent first.
+    pub fn recent_files(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.path.clone()).collect()
+    }
+
+    /// Write the history, the entries saved meanwhile by other editor instances are merged.
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = match &self.storage {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut entries = self.entries.clone();
+        for e in read_entries(path) {
+            match entries.iter_mut().find(|o| o.path == e.path) {
+                Some(o) => {
+                    if e.last_access > o.last_access {
+                        *o = e;
+                    }
+                }
+                None => entries.push(e),
+            }
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_access));
+        entries.truncate(self.max_entries);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let v = json!({
+            "version": FILE_HISTORY_VERSION,
+            "files": entries.iter().map(|e| e.to_json()).collect::<Vec<_>>(),
+        });
+
+        // do not leave a truncated file on error
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&v)?)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+fn read_entries(path: &Path) -> Vec<FileHistoryEntry> {
+    let too_big = fs::metadata(path).map_or(true, |m| m.len() > FILE_HISTORY_MAX_FILE_SIZE);
+    if too_big {
+        return vec![];
+    }
+
+    let v: Value = match fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+    {
+        Some(v) => v,
+        None => return vec![],
+    };
+
+    if v.get("version").and_then(|n| n.as_u64()) != Some(FILE_HISTORY_VERSION) {
+        return vec![];
+    }
+
+    let mut entries: Vec<FileHistoryEntry> = v
+        .get("files")
+        .and_then(|f| f.as_array())
+        .map_or(vec![], |a| {
+            a.iter().filter_map(FileHistoryEntry::from_json).collect()
+        });
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.last_access));
+    entries
+}
+
+#[test]
+fn test_file_history() {
+    let mut h = FileHistory::new(2);
+
+    h.touch("/a");
+    h.update(FileHistoryEntry {
+        path: "/b".to_owned(),
+        start_offset: 10,
+        marks: (0..100).collect(),
+        selections: vec![],
+        undo_marks: vec![3],
+        undo_selections: vec![1],
+        last_access: 0,
+    });
+    assert_eq!(h.recent_files(), vec!["/b", "/a"]);
+    assert_eq!(h.get("/b").unwrap().marks.len(), FILE_HISTORY_MAX_MARKS);
+
+    // the position is kept
+    h.touch("/a");
+    h.touch("/b");
+    assert_eq!(h.recent_files(), vec!["/b", "/a"]);
+    assert_eq!(h.get("/b").unwrap().start_offset, 10);
+
+    // size limit
+    h.touch("/c");
+    assert_eq!(h.recent_files(), vec!["/c", "/b"]);
+    assert!(h.get("/a").is_none());
+
+    let e = h.get("/b").unwrap();
+    assert_eq!(FileHistoryEntry::from_json(&e.to_json()).as_ref(), Some(e));
+}
+
+#[test]
+fn test_file_history_save() {
+    let dir = std::env::temp_dir().join(format!("unlimited-file-history-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("file-history.json");
+
+    // two editor instances
+    let mut h1 = FileHistory::new(10);
+    h1.storage = Some(path.clone());
+    let mut h2 = FileHistory::new(10);
+    h2.storage = Some(path.clone());
+
+    h1.touch("/a");
+    h1.save().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    h2.touch("/b");
+    h2.save().unwrap();
+
+    assert_eq!(read_entries(&path).len(), 2);
+    assert_eq!(read_entries(&path)[0].path, "/b");
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::core::buffer;
+
+/// default number of entries kept in the kill ring
+pub const KILL_RING_DEFAULT_SIZE: usize = 64;
+
+/// A **KillRingEntry** holds the data of a single copy/cut operation.<br/>
+/// There is one chunk per mark (multi-cursor copy/cut).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KillRingEntry {
+    pub chunks: Vec<Vec<u8>>,
+}
+
+impl KillRingEntry {
+    pub fn new(chunks: Vec<Vec<u8>>) -> Self {
+        KillRingEntry { chunks }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.iter().all(|c| c.is_empty())
+    }
+
+    pub fn size(&self) -> usize {
+        self.chunks.iter().map(|c| c.len()).sum()
+    }
+
+    /// Returns the data to insert for the mark 'mark_idx' when 'nr_marks' marks are used.<br/>
+    /// If the number of chunks does not match the number of marks,
+    /// all the chunks are joined with '\n'.
+    pub fn data_for_mark(&self, mark_idx: usize, nr_marks: usize) -> Vec<u8> {
+        if self.chunks.len() == nr_marks {
+            return self.chunks[mark_idx].clone();
+        }
+
+        let mut data = vec![];
+        for (idx, c) in self.chunks.iter().enumerate() {
+            if idx > 0 {
+                data.push(b'\n');
+            }
+            data.extend(c);
+        }
+        data
+    }
+}
+
+/// Records the buffer state after the last kill/yank.<br/>
+/// A kill (resp. yank-pop) is considered contiguous with the previous one
+/// if the buffer and the marks did not change in between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KillRingSignature {
+    pub buffer_id: buffer::Id,
+    pub nr_changes: usize,
+    pub marks_offsets: Vec<u64>,
+}
+
+/// The **KillRing** is shared by all views/buffers.<br/>
+/// It holds the copy/cut history (most recent first) and the named registers.
+#[derive(Debug)]
+pub struct KillRing {
+    pub entries: VecDeque<KillRingEntry>,
+    pub max_entries: usize,
+    /// index of the entry used by the next yank (reset on each new kill)
+    pub yank_index: usize,
+    pub registers: HashMap<char, KillRingEntry>,
+    /// set after a kill, used to append contiguous kills
+    pub last_kill: Option<KillRingSignature>,
+    /// set after a yank, used by yank-pop: (signature, inserted ranges)
+    pub last_yank: Option<(KillRingSignature, Vec<(u64, u64)>)>,
+}
+
+impl KillRing {
+    pub fn new(max_entries: usize) -> Self {
+        KillRing {
+            entries: VecDeque::new(),
+            max_entries: std::cmp::max(1, max_entries),
+            yank_index: 0,
+            registers: HashMap::new(),
+            last_kill: None,
+            last_yank: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Push a new entry in front of the ring, drop the oldest entries if needed.
+    pub fn push(&mut self, entry: KillRingEntry) {
+        if entry.is_empty() {
+            return;
+        }
+
+        self.entries.push_front(entry);
+        self.entries.truncate(self.max_entries);
+        self.yank_index = 0;
+        self.last_yank = None;
+    }
+
+    /// Append (or prepend if 'before' is set) 'chunks' to the most recent entry.<br/>
+    /// A new entry is created if the ring is empty or the chunks count does not match.
+    pub fn append(&mut self, chunks: Vec<Vec<u8>>, before: bool) {
+        let can_merge = match self.entries.front() {
+            Some(e) => e.chunks.len() == chunks.len(),
+            None => false,
+        };
+
+        if !can_merge {
+            self.push(KillRingEntry::new(chunks));
+            return;
+        }
+
+        let front = self.entries.front_mut().unwrap();
+        for (dst, mut src) in front.chunks.iter_mut().zip(chunks) {
+            if before {
+                src.append(dst);
+                *dst = src;
+            } else {
+                dst.append(&mut src);
+            }
+        }
+        self.yank_index = 0;
+        self.last_yank = None;
+    }
+
+    /// Returns the entry used by the next yank.
+    pub fn current(&self) -> Option<&KillRingEntry> {
+        self.entries.get(self.yank_index)
+    }
+
+    /// Select the next older entry (wraps around) and returns it.
+    pub fn rotate(&mut self, count: isize) -> Option<&KillRingEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let len = self.entries.len() as isize;
+        let idx = (self.yank_index as isize + count).rem_euclid(len);
+        self.yank_index = idx as usize;
+        self.entries.get(self.yank_index)
+    }
+
+    pub fn set_register(&mut self, name: char, entry: KillRingEntry) {
+        self.registers.insert(name, entry);
+    }
+
+    pub fn get_register(&self, name: char) -> Option<&KillRingEntry> {
+        self.registers.get(&name)
+    }
+
+    /// Build a human readable description of the history and the registers.
+    pub fn describe(&self, max_preview: usize) -> String {
+        fn preview(e: &KillRingEntry, max_preview: usize) -> String {
+            let data = e.data_for_mark(0, 1);
+            let s = String::from_utf8_lossy(&data);
+            let mut p: String = s
+                .chars()
+                .map(|c| if c == '\n' { '⏎' } else { c })
+                .take(max_preview)
+                .collect();
+            if s.chars().count() > max_preview {
+                p.push('…');
+            }
+            p
+        }
+
+        let mut s = String::new();
+
+        s.push_str(&format!("kill ring ({} entries)\n", self.entries.len()));
+        for (idx, e) in self.entries.iter().enumerate() {
+            let sel = if idx == self.yank_index { '>' } else { ' ' };
+            s.push_str(&format!(
+                "{} {:3} [{:2}] {}\n",
+                sel,
+                idx,
+                e.chunks.len(),
+                preview(e, max_preview)
+            ));
+        }
+
+        if !self.registers.is_empty() {
+            s.push_str("registers\n");
+            let mut names: Vec<&char> = self.registers.keys().collect();
+            names.sort();
+            for name in names {
+                let e = &self.registers[name];
+                s.push_str(&format!("  '{}' {}\n", name, preview(e, max_preview)));
+            }
+        }
+
+        s
+    }
+}
+
+impl Default for KillRing {
+    fn default() -> Self {
+        KillRing::new(KILL_RING_DEFAULT_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(s: &str) -> KillRingEntry {
+        KillRingEntry::new(vec![s.as_bytes().to_vec()])
+    }
+
+    #[test]
+    fn kill_ring_push_and_rotate() {
+        let mut kr = KillRing::new(3);
+
+        kr.push(entry("a"));
+        kr.push(entry("b"));
+        kr.push(entry("c"));
+        kr.push(entry("d"));
+
+        assert_eq!(kr.len(), 3);
+        assert_eq!(kr.current(), Some(&entry("d")));
+        assert_eq!(kr.rotate(1), Some(&entry("c")));
+        assert_eq!(kr.rotate(1), Some(&entry("b")));
+        assert_eq!(kr.rotate(1), Some(&entry("d")));
+        assert_eq!(kr.rotate(-1), Some(&entry("b")));
+
+        // a new kill resets the yank position
+        kr.push(entry("e"));
+        assert_eq!(kr.current(), Some(&entry("e")));
+    }
+
+    #[test]
+    fn kill_ring_append() {
+        let mut kr = KillRing::default();
+
+        kr.append(vec![b"hello".to_vec()], false);
+        kr.append(vec![b" world".to_vec()], false);
+        kr.append(vec![b">> ".to_vec()], true);
+        assert_eq!(kr.len(), 1);
+        assert_eq!(kr.current(), Some(&entry(">> hello world")));
+
+        // chunk count mismatch -> new entry
+        kr.append(vec![b"1".to_vec(), b"2".to_vec()], false);
+        assert_eq!(kr.len(), 2);
+        assert_eq!(kr.current().unwrap().data_for_mark(1, 2), b"2".to_vec());
+        assert_eq!(kr.current().unwrap().data_for_mark(0, 1), b"1\n2".to_vec());
+    }
+
+    #[test]
+    fn kill_ring_registers() {
+        let mut kr = KillRing::default();
+        kr.set_register('a', entry("x"));
+        assert_eq!(kr.get_register('a'), Some(&entry("x")));
+        assert_eq!(kr.get_register('b'), None);
+        assert!(kr.is_empty());
+    }
+}
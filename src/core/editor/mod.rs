@@ -1,8 +1,12 @@
 mod editor;
 mod env;
+pub mod file_history;
+pub mod kill_ring;
 
 pub use editor::Editor;
 pub use env::EditorEnv;
+pub use file_history::FileHistory;
+pub use kill_ring::KillRing;
 
 pub use editor::*;
 
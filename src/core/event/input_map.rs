@@ -218,6 +218,23 @@ fn parse_event_entry_input_key(ctx: &mut ParseCtx, _name: &String, value: &serde
     ctx.sequence.push(ev)
 }
 
+/// split "ctrl+alt+shift+value" into modifiers and value
+fn parse_mouse_modifiers(s: &str) -> (KeyModifiers, &str) {
+    let mut mods = KeyModifiers::new();
+    let mut value = "";
+
+    for k in s.split('+') {
+        match k {
+            "ctrl" => mods.ctrl = true,
+            "alt" => mods.alt = true,
+            "shift" => mods.shift = true,
+            _ => value = k,
+        }
+    }
+
+    (mods, value)
+}
+
 fn parse_event_entry_input_button_press(
     ctx: &mut ParseCtx,
     _name: &String,
@@ -234,9 +251,9 @@ fn parse_event_entry_input_button_press(
     // parse "key" value 0
     //dbg_println!("{{");
 
-    let mods = KeyModifiers::new();
+    let (mods, s) = parse_mouse_modifiers(s);
     //dbg_println!("button = {:?}", s);
-    let button: u32 = match s.as_str() {
+    let button: u32 = match s {
         "0" => 0,
         "1" => 1,
         "2" => 2,
@@ -276,9 +293,9 @@ fn parse_event_entry_input_button_release(
     // parse "key" value 0
     //dbg_println!("{{");
 
-    let mods = KeyModifiers::new();
+    let (mods, s) = parse_mouse_modifiers(s);
     //dbg_println!("button = {:?}", s);
-    let button: u32 = match s.as_str() {
+    let button: u32 = match s {
         "0" => 0,
         "1" => 1,
         "2" => 2,
@@ -336,7 +353,7 @@ fn parse_event_entry_input_pointer_motion(
     _name: &String,
     value: &serde_json::Value,
 ) {
-    let _s = if let Value::String(ref s) = value {
+    let s = if let Value::String(ref s) = value {
         //dbg_println!("value = '{}'", s);
         s
     } else {
@@ -347,7 +364,7 @@ fn parse_event_entry_input_pointer_motion(
     // parse "key" value 0
     //dbg_println!("{{");
 
-    let mods = KeyModifiers::new();
+    let (mods, _) = parse_mouse_modifiers(s);
 
     let ev = InputEvent::PointerMotion(PointerEvent { x: 0, y: 0, mods });
 
@@ -0,0 +1,265 @@
+//! Follow mode: the data appended to a file after its opening is appended to its buffer (like `tail -f`).
+//!
+//! The file is polled by a dedicated thread, the new tail is appended to the buffer
+//! with the regular insert path so the node hierarchy and the byte index are updated incrementally.<br/>
+//! A truncated file, or a rotated one (the path names a new file), replaces the content of the buffer:
+//! the file is mapped again like at the opening, and the buffer log is reset.
+
+use std::fs::File;
+use std::fs::Metadata;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+
+use crate::core::buffer::Buffer;
+use crate::core::buffer::InnerBuffer;
+use crate::core::event::pending_input_event_inc;
+use crate::core::event::Event;
+use crate::core::event::Message;
+use crate::core::stdin_stream::stream_to_buffer;
+use crate::core::view;
+use crate::core::Editor;
+
+/// delay between two checks of the file size
+const FILE_FOLLOW_POLL_MS: u64 = 250;
+
+/// The part of the followed file already in the buffer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FollowState {
+    /// the number of bytes read
+    pub pos: u64,
+    /// (device, inode) of the file, None if unknown
+    pub id: Option<(u64, u64)>,
+}
+
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// The **FileFollower** appends the growth of a file to its buffer until it is dropped.<br/>
+/// A truncated (or rotated) file replaces the content of the buffer.
+#[derive(Debug)]
+pub struct FileFollower {
+    abort: Arc<AtomicBool>,
+    buffer: Arc<RwLock<Buffer<'static>>>,
+    /// the views following the buffer
+    pub views: Vec<view::Id>,
+}
+
+impl FileFollower {
+    /// Returns None if the buffer is not backed by a regular file.
+    pub fn start(editor: &Editor<'static>, buffer: &Arc<RwLock<Buffer<'static>>>) -> Option<Self> {
+        let (path, state) = {
+            let mut b = buffer.write();
+            let path = b.file_name();
+            if path.is_empty() || b.inner.compression.is_some() {
+                return None;
+            }
+            // the file when it was mapped
+            let state = match b.inner.data.read().metadata.as_ref() {
+                Some(meta) => FollowState {
+                    pos: meta.len(),
+                    id: file_id(meta),
+                },
+                None => FollowState::default(),
+            };
+            b.is_streaming = true;
+            (path, state)
+        };
+
+        let abort = Arc::new(AtomicBool::new(false));
+        let task_abort = Arc::clone(&abort);
+        let task_buffer = Arc::clone(buffer);
+        let core_tx = editor.core_tx.clone();
+
+        // the poll loop lasts until the follower is dropped: do not use the executor thread
+        std::thread::spawn(move || {
+            let mut state = state;
+            while !task_abort.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(FILE_FOLLOW_POLL_MS));
+
+                match follow_file(&path, &mut state, &task_buffer, &task_abort) {
+                    Some(true) => {}
+                    _ => continue,
+                }
+
+                let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+                let msg = Message::new(0, 0, ts, Event::RefreshView);
+                pending_input_event_inc(1);
+                if core_tx.send(msg).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Some(FileFollower {
+            abort,
+            buffer: Arc::clone(buffer),
+            views: vec![],
+        })
+    }
+}
+
+impl Drop for FileFollower {
+    fn drop(&mut self) {
+        self.abort.store(true, Ordering::Relaxed);
+        self.buffer.write().is_streaming = false;
+    }
+}
+
+/// Append the bytes of 'path' located after 'state.pos' to 'buffer' and update 'state'.<br/>
+/// If the file was truncated or rotated, the content of 'buffer' is replaced by the new file.<br/>
+/// Returns true if the buffer was modified, None if the file cannot be read.
+pub fn follow_file(
+    path: &str,
+    state: &mut FollowState,
+    buffer: &Arc<RwLock<Buffer<'static>>>,
+    abort: &AtomicBool,
+) -> Option<bool> {
+    let mut file = File::open(path).ok()?;
+    let meta = file.metadata().ok()?;
+    let size = meta.len();
+    let id = file_id(&meta);
+
+    let rotated = state.id.is_some() && id != state.id;
+    let replaced = rotated || size < state.pos;
+    state.id = id;
+
+    if replaced {
+        state.pos = match map_file(buffer, path) {
+            Some(pos) => pos,
+            None => {
+                clear_buffer(buffer);
+                0
+            }
+        };
+    }
+    if size <= state.pos {
+        return Some(replaced);
+    }
+
+    file.seek(SeekFrom::Start(state.pos)).ok()?;
+    let n = stream_to_buffer(file.take(size - state.pos), buffer, |_| {
+        !abort.load(Ordering::Relaxed)
+    });
+    state.pos += n;
+    Some(replaced || n > 0)
+}
+
+/// Replace the content of 'buffer' by the file 'path', mapped like at the opening.<br/>
+/// The previous content cannot be restored: the buffer log is reset.<br/>
+/// Returns the size of the mapped file, None if it cannot be mapped.
+fn map_file(buffer: &Arc<RwLock<Buffer<'static>>>, path: &str) -> Option<u64> {
+    let mut b = buffer.write();
+    let inner = InnerBuffer::map(b.id, path, path, b.inner.mode())?;
+    let changed = b.changed;
+    b.replace_inner(inner);
+    b.buffer_log_reset();
+    b.changed = changed;
+    Some(b.size() as u64)
+}
+
+/// Remove the content of 'buffer' like stream_to_buffer appends: not recorded in the buffer log,
+/// the previous operations are dropped.
+fn clear_buffer(buffer: &Arc<RwLock<Buffer<'static>>>) {
+    let mut b = buffer.write();
+    let (use_buffer_log, changed) = (b.use_buffer_log, b.changed);
+    b.use_buffer_log = false;
+    b.delete_content(None);
+    b.use_buffer_log = use_buffer_log;
+    b.buffer_log_reset();
+    b.changed = changed;
+}
+
+#[test]
+fn test_follow_file() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+    use std::io::Write;
+
+    let path = std::env::temp_dir().join(format!("unlimited-follow-{}", std::process::id()));
+    let path_str = path.to_string_lossy().to_string();
+    std::fs::write(&path, "a\n").unwrap();
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name(&path_str)
+        .file_name(&path_str)
+        .use_buffer_log(true)
+        .finalize()
+        .unwrap();
+
+    let content = |b: &Arc<RwLock<Buffer<'static>>>| {
+        let b = b.read();
+        let mut data = vec![];
+        b.read(0, b.size(), &mut data);
+        String::from_utf8(data).unwrap()
+    };
+
+    let abort = AtomicBool::new(false);
+    let mut state = FollowState {
+        pos: 2,
+        id: file_id(&std::fs::metadata(&path).unwrap()),
+    };
+    assert_eq!(
+        follow_file(&path_str, &mut state, &buffer, &abort),
+        Some(false)
+    );
+
+    // growth
+    let mut f = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    f.write_all(b"b\nc\n").unwrap();
+    assert_eq!(
+        follow_file(&path_str, &mut state, &buffer, &abort),
+        Some(true)
+    );
+    assert_eq!(state.pos, 6);
+    assert_eq!(content(&buffer), "a\nb\nc\n");
+    assert!(!buffer.read().changed);
+
+    // truncation: the content is replaced
+    std::fs::write(&path, "d\n").unwrap();
+    assert_eq!(
+        follow_file(&path_str, &mut state, &buffer, &abort),
+        Some(true)
+    );
+    assert_eq!(state.pos, 2);
+    assert_eq!(content(&buffer), "d\n");
+    assert!(!buffer.read().changed);
+
+    // rotation: a new file larger than the read part, the modifications cannot be undone
+    buffer.write().insert(0, 1, b"x");
+    assert!(buffer.read().buffer_log_count() > 0);
+    let rotated = path.with_extension("1");
+    std::fs::rename(&path, &rotated).unwrap();
+    std::fs::write(&path, "e\nf\ng\n").unwrap();
+    assert_eq!(
+        follow_file(&path_str, &mut state, &buffer, &abort),
+        Some(true)
+    );
+    assert_eq!(state.pos, 6);
+    assert_eq!(content(&buffer), "e\nf\ng\n");
+    assert_eq!(buffer.read().buffer_log_count(), 0);
+    assert_eq!(
+        follow_file(&path_str, &mut state, &buffer, &abort),
+        Some(false)
+    );
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&rotated);
+}
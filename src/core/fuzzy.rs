@@ -0,0 +1,332 @@
+//! Subsequence (fuzzy) matching used by the buffer switcher and the file finder.
+
+const SCORE_MATCH: i32 = 16;
+const BONUS_BOUNDARY: i32 = 10;
+const BONUS_CAMEL_CASE: i32 = 8;
+const BONUS_CONSECUTIVE: i32 = 12;
+const BONUS_BASENAME: i32 = 6;
+const BONUS_FIRST_CHAR: i32 = 8;
+const PENALTY_GAP: i32 = 1;
+
+/// The result of a successful match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i32,
+    /// indexes of the matched chars in the text
+    pub positions: Vec<usize>,
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '/' | '\\' | '_' | '-' | '.' | ' ' | ':')
+}
+
+/// The bonus of a match at 'text[j]' given the previous char.
+fn position_bonus(text: &[char], j: usize, basename_start: usize) -> i32 {
+    let mut bonus = if j >= basename_start {
+        BONUS_BASENAME
+    } else {
+        0
+    };
+
+    if j == basename_start {
+        bonus += BONUS_FIRST_CHAR;
+    }
+
+    if j == 0 {
+        return bonus + BONUS_BOUNDARY;
+    }
+
+    let prev = text[j - 1];
+    if is_separator(prev) {
+        bonus += BONUS_BOUNDARY;
+    } else if prev.is_lowercase() && text[j].is_uppercase() {
+        bonus += BONUS_CAMEL_CASE;
+    }
+    bonus
+}
+
+/// Matches 'pattern' as a subsequence of 'text'.<br/>
+/// The match is case-insensitive unless 'pattern' contains an uppercase char.<br/>
+/// Returns None if 'text' does not contain all the chars of 'pattern' in order.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let case_sensitive = pattern.chars().any(|c| c.is_uppercase());
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+
+    let p: Vec<char> = pattern.chars().filter(|c| *c != ' ').map(fold).collect();
+    if p.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: vec![],
+        });
+    }
+
+    let orig: Vec<char> = text.chars().collect();
+    let t: Vec<char> = orig.iter().map(|c| fold(*c)).collect();
+
+    // quick check
+    let mut it = t.iter();
+    if !p.iter().all(|pc| it.any(|tc| tc == pc)) {
+        return None;
+    }
+
+    let basename_start = orig
+        .iter()
+        .rposition(|c| *c == '/' || *c == '\\')
+        .map_or(0, |i| i + 1);
+
+    let n = t.len();
+    let m = p.len();
+    const NONE: i32 = i32::MIN / 2;
+
+    // score[i][j]: best score with p[i] matched at t[j], prev[i][j]: position of p[i - 1]
+    let mut score = vec![vec![NONE; n]; m];
+    let mut prev = vec![vec![usize::MAX; n]; m];
+
+    for i in 0..m {
+        // best (score[i - 1][k] - gap penalty) for k < j - 1
+        let mut gap_best = NONE;
+        let mut gap_best_k = usize::MAX;
+
+        for j in 0..n {
+            if i > 0 && j >= 2 {
+                let candidate = score[i - 1][j - 2] - PENALTY_GAP;
+                gap_best -= PENALTY_GAP;
+                if candidate >= gap_best {
+                    gap_best = candidate;
+                    gap_best_k = j - 2;
+                }
+            }
+
+            if t[j] != p[i] {
+                continue;
+            }
+
+            let base = SCORE_MATCH + position_bonus(&orig, j, basename_start);
+
+            if i == 0 {
+                score[i][j] = base;
+                continue;
+            }
+
+            if j == 0 {
+                continue;
+            }
+
+            let consecutive = score[i - 1][j - 1];
+            let consecutive = if consecutive > NONE {
+                consecutive + BONUS_CONSECUTIVE
+            } else {
+                NONE
+            };
+
+            let (best, k) = if consecutive >= gap_best {
+                (consecutive, j - 1)
+            } else {
+                (gap_best, gap_best_k)
+            };
+
+            if best > NONE {
+                score[i][j] = best + base;
+                prev[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, best) = score[m - 1]
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| **s > NONE)
+        .max_by_key(|(j, s)| (**s, std::cmp::Reverse(*j)))?;
+    let best = *best;
+
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = prev[i][j];
+    }
+
+    Some(FuzzyMatch {
+        score: best,
+        positions,
+    })
+}
+
+/// Matches 'pattern' against all the 'items'.<br/>
+/// Returns at most 'max' (index, match) pairs, best score first, items with the same score keep their order.
+pub fn fuzzy_filter<S: AsRef<str>>(
+    items: &[S],
+    pattern: &str,
+    max: usize,
+) -> Vec<(usize, FuzzyMatch)> {
+    if pattern.chars().all(|c| c == ' ') {
+        return items
+            .iter()
+            .take(max)
+            .enumerate()
+            .map(|(idx, _)| {
+                let m = FuzzyMatch {
+                    score: 0,
+                    positions: vec![],
+                };
+                (idx, m)
+            })
+            .collect();
+    }
+
+    let matches: Vec<(usize, FuzzyMatch)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, s)| fuzzy_match(pattern, s.as_ref()).map(|m| (idx, m)))
+        .collect();
+
+    best_matches(matches, max)
+}
+
+/// Sort the matches by score, 'matches' must be sorted by index.
+fn best_matches(mut matches: Vec<(usize, FuzzyMatch)>, max: usize) -> Vec<(usize, FuzzyMatch)> {
+    matches.sort_by_key(|(_, m)| std::cmp::Reverse(m.score));
+    matches.truncate(max);
+    matches
+}
+
+/// The **FuzzyNarrowing** remembers the items matched by the previous pattern.<br/>
+/// A text matching a pattern also matches its prefixes, so when the new pattern extends the previous one
+/// only these items and the items appended to the list since are matched again.
+#[derive(Debug, Clone, Default)]
+pub struct FuzzyNarrowing {
+    pattern: String,
+    /// indexes of the items matching 'pattern', in increasing order
+    candidates: Vec<usize>,
+    /// number of items seen by the previous filter
+    scanned: usize,
+}
+
+impl FuzzyNarrowing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.pattern.clear();
+        self.candidates.clear();
+        self.scanned = 0;
+    }
+
+    /// Same as fuzzy_filter.<br/>
+    /// 'items' can only grow between two calls, call clear if the list is replaced.
+    pub fn filter<S: AsRef<str>>(
+        &mut self,
+        items: &[S],
+        pattern: &str,
+        max: usize,
+    ) -> Vec<(usize, FuzzyMatch)> {
+        if pattern.chars().all(|c| c == ' ') {
+            self.clear();
+            return fuzzy_filter(items, pattern, max);
+        }
+
+        let narrow = !self.pattern.is_empty()
+            && pattern.starts_with(self.pattern.as_str())
+            && self.scanned <= items.len();
+
+        let mut indexes = if narrow {
+            std::mem::take(&mut self.candidates)
+        } else {
+            self.scanned = 0;
+            vec![]
+        };
+        indexes.extend(self.scanned..items.len());
+
+        let matches: Vec<(usize, FuzzyMatch)> = indexes
+            .into_iter()
+            .filter_map(|idx| fuzzy_match(pattern, items[idx].as_ref()).map(|m| (idx, m)))
+            .collect();
+
+        self.pattern = pattern.to_string();
+        self.candidates = matches.iter().map(|(idx, _)| *idx).collect();
+        self.scanned = items.len();
+
+        best_matches(matches, max)
+    }
+}
+
+#[test]
+fn test_fuzzy_match() {
+    assert!(fuzzy_match("abc", "axbxc").is_some());
+    assert!(fuzzy_match("abc", "acb").is_none());
+    assert_eq!(fuzzy_match("", "abc").unwrap().score, 0);
+
+    // case
+    assert!(fuzzy_match("ABC", "abc").is_none());
+    assert!(fuzzy_match("abc", "ABC").is_some());
+
+    let m = fuzzy_match("mod", "src/core/modes/mod.rs").unwrap();
+    assert_eq!(m.positions, vec![15, 16, 17]);
+
+    let m = fuzzy_match("tbm", "src/core/modes/tab_bar_mode.rs").unwrap();
+    assert_eq!(m.positions, vec![15, 19, 23]);
+
+    // word boundaries and consecutive chars rank first
+    let a = fuzzy_match("main", "src/main.rs").unwrap();
+    let b = fuzzy_match("main", "src/my_animation.rs").unwrap();
+    assert!(a.score > b.score);
+
+    // basename matches rank first
+    let a = fuzzy_match("core", "src/core.rs").unwrap();
+    let b = fuzzy_match("core", "src/core/x.rs").unwrap();
+    assert!(a.score > b.score);
+}
+
+#[test]
+fn test_fuzzy_filter() {
+    let items = [
+        "src/main.rs",
+        "src/core/mod.rs",
+        "README.md",
+        "src/core/modes/mod.rs",
+    ];
+
+    let all = fuzzy_filter(&items, "", 3);
+    let idx: Vec<usize> = all.iter().map(|(i, _)| *i).collect();
+    assert_eq!(idx, vec![0, 1, 2]);
+
+    let m = fuzzy_filter(&items, "mod", 10);
+    let idx: Vec<usize> = m.iter().map(|(i, _)| *i).collect();
+    assert_eq!(idx, vec![1, 3]);
+
+    assert!(fuzzy_filter(&items, "zz", 10).is_empty());
+}
+
+#[test]
+fn test_fuzzy_narrowing() {
+    let mut items = vec![
+        "src/main.rs",
+        "src/core/mod.rs",
+        "README.md",
+        "src/core/modes/mod.rs",
+        "src/core/modes/mode.tmp",
+    ];
+
+    let mut narrowing = FuzzyNarrowing::new();
+    for pattern in ["m", "mo", "mod", "mod.", "mo", "M", "Mz", "", "s"] {
+        assert_eq!(
+            narrowing.filter(&items, pattern, 10),
+            fuzzy_filter(&items, pattern, 10),
+            "pattern '{}'",
+            pattern
+        );
+    }
+
+    // the items appended after the previous filter are matched too
+    narrowing.filter(&items, "mo", 10);
+    items.push("mod.rs");
+    let m = narrowing.filter(&items, "mod", 10);
+    assert_eq!(m, fuzzy_filter(&items, "mod", 10));
+    assert!(m.iter().any(|(idx, _)| *idx == 5));
+}
@@ -0,0 +1,451 @@
+//! Git repositories: the commands run the local git binary, nothing is read from the network.
+
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+use crate::core::diff::DiffHunk;
+
+/// Runs git in 'dir', 'input' is written to its standard input.<br/>
+/// Returns the standard output on success, the standard error on failure.
+pub fn git(dir: &Path, args: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let mut child = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run git: {}", e))?;
+
+    // written by another thread: git may fill its output before reading all the input
+    let writer = match (input, child.stdin.take()) {
+        (Some(input), Some(mut stdin)) => {
+            let input = input.to_vec();
+            Some(std::thread::spawn(move || {
+                let _ = stdin.write_all(&input);
+            }))
+        }
+        _ => None,
+    };
+
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr)
+            .trim_end()
+            .to_owned())
+    }
+}
+
+/// A file of a git work tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitFile {
+    /// the top level directory of the work tree
+    pub root: PathBuf,
+    /// the path of the file relative to 'root'
+    pub path: String,
+}
+
+impl GitFile {
+    /// Returns None if 'file' is not in a git work tree.
+    pub fn new(file: &str) -> Option<GitFile> {
+        let file = Path::new(file);
+        let name = file.file_name()?.to_str()?;
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let out = git(
+            dir,
+            &["rev-parse", "--show-toplevel", "--show-prefix"],
+            None,
+        )
+        .ok()?;
+        let out = String::from_utf8(out).ok()?;
+        let mut lines = out.lines();
+        let root = lines.next()?;
+        let prefix = lines.next().unwrap_or("");
+
+        Some(GitFile {
+            root: PathBuf::from(root),
+            path: format!("{}{}", prefix, name),
+        })
+    }
+
+    /// Returns the content of the file at 'rev', an empty 'rev' is the index.
+    pub fn show(&self, rev: &str) -> Option<Vec<u8>> {
+        git(
+            &self.root,
+            &["show", &format!("{}:{}", rev, self.path)],
+            None,
+        )
+        .ok()
+    }
+
+    /// Returns the blame of the file, or of 'contents' if the buffer is modified.
+    pub fn blame(&self, contents: Option<&[u8]>) -> Option<Blame> {
+        let mut args = vec!["blame", "--porcelain"];
+        if contents.is_some() {
+            args.extend(["--contents", "-"]);
+        }
+        args.extend(["--", &self.path]);
+
+        let out = git(&self.root, &args, contents).ok()?;
+        Some(parse_blame(&out))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlameCommit {
+    pub id: String,
+    pub author: String,
+    /// seconds since the epoch
+    pub time: u64,
+    pub summary: String,
+}
+
+impl BlameCommit {
+    /// The lines that are not committed yet have a null id (40 or 64 zeros).
+    pub fn is_uncommitted(&self) -> bool {
+        self.id.bytes().all(|c| c == b'0')
+    }
+
+    /// The abbreviated id (7 digits).
+    pub fn short_id(&self) -> &str {
+        &self.id[..std::cmp::min(7, self.id.len())]
+    }
+}
+
+/// The commit of each line of a file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Blame {
+    pub commits: Vec<BlameCommit>,
+    /// index in 'commits' of each line
+    pub lines: Vec<usize>,
+}
+
+impl Blame {
+    pub fn line_commit(&self, line: usize) -> Option<&BlameCommit> {
+        self.commits.get(*self.lines.get(line)?)
+    }
+}
+
+/// Parses the output of "git blame --porcelain".
+pub fn parse_blame(out: &[u8]) -> Blame {
+    let mut blame = Blame::default();
+    let mut current = None;
+
+    for line in out.split(|c| *c == b'\n') {
+        // the content of the line ends its group
+        if line.first() == Some(&b'\t') {
+            if let Some(idx) = current.take() {
+                blame.lines.push(idx);
+            }
+            continue;
+        }
+
+        let line = String::from_utf8_lossy(line);
+
+        let idx = match current {
+            Some(idx) => idx,
+            None => {
+                // "<commit> <orig line> <final line> [<lines in group>]"
+                // the id is a SHA-1 (40 digits) or a SHA-256 (64 digits)
+                let id = match line.split(' ').next() {
+                    Some(id) if !id.is_empty() && id.bytes().all(|c| c.is_ascii_hexdigit()) => id,
+                    _ => continue,
+                };
+                let idx = match blame.commits.iter().position(|c| c.id == id) {
+                    Some(idx) => idx,
+                    None => {
+                        blame.commits.push(BlameCommit {
+                            id: id.to_owned(),
+                            ..BlameCommit::default()
+                        });
+                        blame.commits.len() - 1
+                    }
+                };
+                current = Some(idx);
+                continue;
+            }
+        };
+
+        let commit = &mut blame.commits[idx];
+        if let Some(author) = line.strip_prefix("author ") {
+            commit.author = author.to_owned();
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            commit.time = time.parse().unwrap_or(0);
+        } else if let Some(summary) = line.strip_prefix("summary ") {
+            commit.summary = summary.to_owned();
+        }
+    }
+
+    blame
+}
+
+/// A line of "git status --porcelain".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry {
+    /// the status in the index and in the work tree: 'M', 'A', 'D', 'R', '?', ' ' ...
+    pub index: char,
+    pub worktree: char,
+    /// relative to the root of the work tree
+    pub path: String,
+}
+
+/// Returns the modified and untracked files of the work tree of 'root'.
+pub fn status(root: &Path) -> Option<Vec<StatusEntry>> {
+    let out = git(root, &["status", "--porcelain=v1", "-z"], None).ok()?;
+    Some(parse_status(&out))
+}
+
+/// Parses the output of "git status --porcelain=v1 -z".
+pub fn parse_status(out: &[u8]) -> Vec<StatusEntry> {
+    let mut entries = vec![];
+    let mut fields = out.split(|c| *c == 0).filter(|f| !f.is_empty());
+
+    while let Some(f) = fields.next() {
+        if f.len() < 4 {
+            continue;
+        }
+
+        let index = f[0] as char;
+        let worktree = f[1] as char;
+        entries.push(StatusEntry {
+            index,
+            worktree,
+            path: String::from_utf8_lossy(&f[3..]).to_string(),
+        });
+
+        // a rename is followed by its original path
+        if index == 'R' || index == 'C' {
+            fields.next();
+        }
+    }
+
+    entries
+}
+
+/// Returns the patch of 'hunk' with 'context' unchanged lines around it.<br/>
+/// The other changes are ignored: the patch applies to 'old'.
+pub fn hunk_patch(
+    path: &str,
+    old: &[&[u8]],
+    new: &[&[u8]],
+    hunk: &DiffHunk,
+    context: usize,
+) -> Vec<u8> {
+    let before = hunk.old.start.saturating_sub(context)..hunk.old.start;
+    let after = hunk.old.end..std::cmp::min(hunk.old.end + context, old.len());
+
+    let old_count = before.len() + hunk.old.len() + after.len();
+    let new_count = before.len() + hunk.new.len() + after.len();
+
+    // an empty range starts at the line before it
+    let start = |count: usize| {
+        if count == 0 {
+            before.start
+        } else {
+            before.start + 1
+        }
+    };
+
+    let mut patch = vec![];
+    patch.extend(format!("diff --git a/{} b/{}\n", path, path).as_bytes());
+    patch.extend(format!("--- a/{}\n+++ b/{}\n", path, path).as_bytes());
+    patch.extend(
+        format!(
+            "@@ -{},{} +{},{} @@\n",
+            start(old_count),
+            old_count,
+            start(new_count),
+            new_count
+        )
+        .as_bytes(),
+    );
+
+    let mut push_lines = |prefix: u8, lines: &[&[u8]], range: Range<usize>| {
+        for l in &lines[range] {
+            patch.push(prefix);
+            patch.extend(*l);
+            if l.last() != Some(&b'\n') {
+                patch.extend(b"\n\\ No newline at end of file\n");
+            }
+        }
+    };
+
+    push_lines(b' ', old, before.clone());
+    push_lines(b'-', old, hunk.old.clone());
+    push_lines(b'+', new, hunk.new.clone());
+    push_lines(b' ', old, after.clone());
+
+    patch
+}
+
+/// Applies 'patch' to the index of the work tree of 'root', 'reverse' removes it.
+pub fn apply_to_index(root: &Path, patch: &[u8], reverse: bool) -> Result<(), String> {
+    let mut args = vec!["apply", "--cached"];
+    if reverse {
+        args.push("-R");
+    }
+    args.push("-");
+    git(root, &args, Some(patch)).map(|_| ())
+}
+
+#[cfg(test)]
+fn temporary_repository(name: &str) -> Option<PathBuf> {
+    if Command::new("git").arg("--version").output().is_err() {
+        return None;
+    }
+
+    let dir = std::env::temp_dir().join(format!("unlimited-git-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).ok()?;
+
+    for args in [
+        &["init", "-q"][..],
+        &["config", "user.name", "Test User"],
+        &["config", "user.email", "test@example.com"],
+        &["config", "commit.gpgsign", "false"],
+    ] {
+        git(&dir, args, None).ok()?;
+    }
+
+    Some(dir)
+}
+
+#[test]
+fn test_git_repository() {
+    let dir = match temporary_repository("repo") {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    let file = dir.join("src/a.txt");
+    std::fs::write(&file, "line 1\nline 2\nline 3\n").unwrap();
+    git(&dir, &["add", "."], None).unwrap();
+    git(&dir, &["commit", "-q", "-m", "first commit"], None).unwrap();
+
+    let gf = GitFile::new(&file.to_string_lossy()).unwrap();
+    assert_eq!(gf.path, "src/a.txt");
+    assert_eq!(gf.show("HEAD").unwrap(), b"line 1\nline 2\nline 3\n");
+    assert!(GitFile::new("/").is_none());
+
+    // status
+    std::fs::write(&file, "line 1\nline two\nline 3\n").unwrap();
+    std::fs::write(dir.join("new.txt"), "new\n").unwrap();
+    let entries = status(&dir).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!((entries[0].index, entries[0].worktree), (' ', 'M'));
+    assert_eq!(entries[0].path, "src/a.txt");
+    assert_eq!((entries[1].index, entries[1].worktree), ('?', '?'));
+
+    // blame of the modified content
+    let blame = gf.blame(Some(b"line 1\nline two\nline 3\n")).unwrap();
+    assert_eq!(blame.lines.len(), 3);
+    let first = blame.line_commit(0).unwrap();
+    assert_eq!(first.author, "Test User");
+    assert_eq!(first.summary, "first commit");
+    assert!(blame.line_commit(1).unwrap().is_uncommitted());
+    assert_eq!(blame.line_commit(2).unwrap().id, first.id);
+
+    // stage and unstage the hunk
+    let old: Vec<&[u8]> = vec![b"line 1\n", b"line 2\n", b"line 3\n"];
+    let new: Vec<&[u8]> = vec![b"line 1\n", b"line two\n", b"line 3\n"];
+    let hunk = DiffHunk {
+        old: 1..2,
+        new: 1..2,
+    };
+    let patch = hunk_patch(&gf.path, &old, &new, &hunk, 3);
+
+    apply_to_index(&dir, &patch, false).unwrap();
+    assert_eq!(gf.show("").unwrap(), b"line 1\nline two\nline 3\n");
+    assert_eq!(status(&dir).unwrap()[0].index, 'M');
+    assert!(apply_to_index(&dir, &patch, false).is_err());
+
+    apply_to_index(&dir, &patch, true).unwrap();
+    assert_eq!(gf.show("").unwrap(), b"line 1\nline 2\nline 3\n");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_git_parse_blame() {
+    let sha1 = "4e1243bd22c66e76c2ba9eddc1f91394e57f9f83";
+    let sha256 = "0f3c5e1a9b8d7c6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d2e";
+    let null = "0".repeat(64);
+
+    let out = format!(
+        "{sha1} 1 1 1\nauthor A\nauthor-time 10\nsummary first\n\tline 1\n\
+         {sha256} 2 2 2\nauthor B\nauthor-time 20\nsummary second\n\tline 2\n\
+         {sha256} 3 3\n\tline 3\n\
+         {null} 4 4 1\nauthor Not Committed Yet\n\tline 4\n"
+    );
+
+    let blame = parse_blame(out.as_bytes());
+    assert_eq!(blame.commits.len(), 3);
+    assert_eq!(blame.lines, vec![0, 1, 1, 2]);
+
+    let first = blame.line_commit(0).unwrap();
+    assert_eq!((first.id.as_str(), first.short_id()), (sha1, "4e1243b"));
+    assert_eq!((first.author.as_str(), first.time), ("A", 10));
+    assert!(!first.is_uncommitted());
+
+    let second = blame.line_commit(2).unwrap();
+    assert_eq!((second.id.as_str(), second.short_id()), (sha256, "0f3c5e1"));
+    assert_eq!(second.summary, "second");
+
+    assert!(blame.line_commit(3).unwrap().is_uncommitted());
+}
+
+#[test]
+fn test_git_hunk_patch() {
+    let old: Vec<&[u8]> = vec![b"a\n", b"b\n", b"c\n", b"d"];
+    let new: Vec<&[u8]> = vec![b"a\n", b"c\n", b"d\n", b"e\n"];
+
+    // removal
+    let hunk = DiffHunk {
+        old: 1..2,
+        new: 1..1,
+    };
+    let patch = hunk_patch("f", &old, &new, &hunk, 1);
+    assert_eq!(
+        String::from_utf8(patch).unwrap(),
+        "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1,3 +1,2 @@\n a\n-b\n c\n"
+    );
+
+    // last line without new line
+    let hunk = DiffHunk {
+        old: 3..4,
+        new: 2..4,
+    };
+    let patch = hunk_patch("f", &old, &new, &hunk, 0);
+    assert_eq!(
+        String::from_utf8(patch).unwrap(),
+        "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -4,1 +4,2 @@\n-d\n\\ No newline at end of file\n+d\n+e\n"
+    );
+
+    // insertion in an empty file
+    let hunk = DiffHunk {
+        old: 0..0,
+        new: 0..1,
+    };
+    let patch = hunk_patch("f", &[], &new, &hunk, 3);
+    assert!(String::from_utf8(patch)
+        .unwrap()
+        .ends_with("@@ -0,0 +1,1 @@\n+a\n"));
+}
@@ -0,0 +1,166 @@
+//! A subset of the .gitignore rules: '*', '?' and '**' globs,
+//! '!' negation, trailing '/' for directories and leading '/' anchors.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+struct IgnorePattern {
+    glob: String,
+    negate: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+/// The **IgnoreRules** decides if a path, relative to the directory the rules were loaded from,
+/// must be hidden.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> Self {
+        let mut rules = IgnoreRules::default();
+        rules.add_line(".git/");
+        rules
+    }
+
+    /// Returns the default rules and the content of 'dir'/.gitignore and 'dir'/.ignore.
+    pub fn load(dir: &Path) -> Self {
+        let mut rules = IgnoreRules::new();
+        for name in [".gitignore", ".ignore"] {
+            if let Ok(s) = fs::read_to_string(dir.join(name)) {
+                for line in s.lines() {
+                    rules.add_line(line);
+                }
+            }
+        }
+        rules
+    }
+
+    pub fn add_line(&mut self, line: &str) {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(l) => (true, l),
+            None => (false, line),
+        };
+
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(l) => (true, l),
+            None => (false, line),
+        };
+
+        let anchored = line.contains('/');
+        let glob = line.trim_start_matches('/').to_owned();
+        if glob.is_empty() {
+            return;
+        }
+
+        self.patterns.push(IgnorePattern {
+            glob,
+            negate,
+            dir_only,
+            anchored,
+        });
+    }
+
+    /// 'rel_path' uses '/' as separator.
+    pub fn is_ignored(&self, rel_path: &str, is_dir: bool) -> bool {
+        let name = rel_path.rsplit('/').next().unwrap_or(rel_path);
+
+        let mut ignored = false;
+        for p in &self.patterns {
+            if p.dir_only && !is_dir {
+                continue;
+            }
+            let text = if p.anchored { rel_path } else { name };
+            if glob_match(&p.glob, text) {
+                ignored = !p.negate;
+            }
+        }
+        ignored
+    }
+}
+
+/// Returns true if 'path' is ignored by the rules loaded from one of its parent directories.<br/>
+/// 'stack' holds (directory, rules) pairs, from the root to the deepest directory.
+pub fn is_ignored_by_stack(stack: &[(PathBuf, IgnoreRules)], path: &Path, is_dir: bool) -> bool {
+    stack.iter().any(|(base, r)| {
+        path.strip_prefix(base).is_ok_and(|rel| {
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            r.is_ignored(&rel, is_dir)
+        })
+    })
+}
+
+/// Matches 'text' against 'pattern': '*' and '?' do not match '/', '**' matches anything.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    glob_match_chars(&p, &t)
+}
+
+fn glob_match_chars(p: &[char], t: &[char]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') => {
+            let any_dir = p.get(1) == Some(&'*');
+            let rest = if any_dir { &p[2..] } else { &p[1..] };
+            let rest = if any_dir && rest.first() == Some(&'/') {
+                // "**/" also matches zero directory
+                if glob_match_chars(&rest[1..], t) {
+                    return true;
+                }
+                rest
+            } else {
+                rest
+            };
+
+            for i in 0..=t.len() {
+                if glob_match_chars(rest, &t[i..]) {
+                    return true;
+                }
+                if i < t.len() && t[i] == '/' && !any_dir {
+                    return false;
+                }
+            }
+            false
+        }
+        Some('?') => !t.is_empty() && t[0] != '/' && glob_match_chars(&p[1..], &t[1..]),
+        Some(c) => !t.is_empty() && t[0] == *c && glob_match_chars(&p[1..], &t[1..]),
+    }
+}
+
+#[test]
+fn test_ignore_rules() {
+    assert!(glob_match("*.o", "main.o"));
+    assert!(!glob_match("*.o", "main.rs"));
+    assert!(!glob_match("*.o", "src/main.o"));
+    assert!(glob_match("src/*.rs", "src/main.rs"));
+    assert!(glob_match("**/*.rs", "src/core/main.rs"));
+    assert!(glob_match("**/*.rs", "main.rs"));
+    assert!(glob_match("a?c", "abc"));
+    assert!(!glob_match("a?c", "a/c"));
+
+    let mut rules = IgnoreRules::new();
+    rules.add_line("# comment");
+    rules.add_line("target/");
+    rules.add_line("*.log");
+    rules.add_line("!keep.log");
+    rules.add_line("/build");
+
+    assert!(rules.is_ignored(".git", true));
+    assert!(rules.is_ignored("target", true));
+    assert!(!rules.is_ignored("target", false));
+    assert!(rules.is_ignored("src/debug.log", false));
+    assert!(!rules.is_ignored("keep.log", false));
+    assert!(rules.is_ignored("build", true));
+    assert!(!rules.is_ignored("src/build", true));
+    assert!(!rules.is_ignored("src/main.rs", false));
+}
@@ -709,6 +709,34 @@ impl<'a> MappedFile<'a> {
         }
     }
 
+    /// Returns the size of the storage: the file as it was opened or last saved.
+    pub fn storage_size(&self) -> u64 {
+        match self.fd {
+            Some(ref fd) => fd.read().metadata().map(|m| m.len()).unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// Copies the storage data at 'offset' into 'out', the modifications are ignored.<br/>
+    /// Returns the number of copied bytes.
+    pub fn read_storage(&self, offset: u64, out: &mut [u8]) -> usize {
+        let size = self.storage_size();
+        if offset >= size {
+            return 0;
+        }
+
+        let n = std::cmp::min(out.len() as u64, size - offset) as usize;
+        if n == 0 {
+            return 0;
+        }
+
+        // a detached node on the original storage
+        let mut node = Node::new();
+        node.storage_offset = Some(offset);
+        node.size = n as u64;
+        node.do_direct_copy(&self.fd, &mut out[..n]).unwrap_or(0)
+    }
+
     fn link_prev_next_nodes(
         pool: &mut FreeListAllocator<Node>,
         prev_idx: Option<NodeIndex>,
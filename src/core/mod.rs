@@ -1,6 +1,7 @@
 use core::panic;
 //
 use std::fs;
+use std::path::Path;
 use std::sync::mpsc::channel;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::Sender;
@@ -17,16 +18,24 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 #[macro_use]
 pub(crate) mod macros;
 
+pub mod bidi;
 pub mod buffer;
 pub mod codec;
 pub mod codepointinfo;
 pub mod config;
+pub mod diff;
 pub mod editor;
 pub mod error;
 pub mod event;
+pub mod file_follow;
+pub mod fuzzy;
+pub mod git;
+pub mod ignore;
 pub mod mapped_file;
 pub mod modes;
 pub mod screen;
+pub mod session;
+pub mod stdin_stream;
 pub mod view;
 
 use crate::core::buffer::Buffer;
@@ -39,6 +48,7 @@ use crate::core::config::Config;
 use crate::core::editor::Editor;
 use crate::core::editor::EditorEnv;
 use crate::core::editor::EditorEvent;
+use crate::core::editor::FileHistory;
 
 use crate::core::event::Event;
 use crate::core::event::Message;
@@ -59,6 +69,8 @@ use crate::core::view::register_view_subscriber;
 use crate::core::editor::process_editor_events;
 use crate::core::editor::push_editor_event;
 
+use crate::core::modes::tab_bar_mode::record_file_view_position;
+
 //use crate::core::error::Error;
 //type UnlResult<T> = Result<T, Error>;
 
@@ -256,6 +268,9 @@ pub fn run(
     );
     let mut env = EditorEnv::new();
 
+    // the history of the user is read here and not by Editor::new: the tests do not use it
+    editor.file_history = FileHistory::load(editor.file_history.max_entries);
+
     // create worker thread
     let worker_th = {
         let core_tx = core_tx.clone();
@@ -273,7 +288,9 @@ pub fn run(
         Some(thread::spawn(move || indexer(&indexer_rx, &core_tx)))
     };
 
-    load_buffers(&mut editor, &mut env);
+    let session = session::load_editor_session(&editor.config);
+
+    load_buffers(&mut editor, &mut env, session.as_ref());
 
     load_modes(&mut editor, &mut env);
 
@@ -281,6 +298,10 @@ pub fn run(
 
     create_layout(&mut editor, &mut env);
 
+    if let Some(session) = session {
+        session::restore_session(&mut editor, &mut env, session);
+    }
+
     //
     process_editor_events(&mut editor, &mut env);
 
@@ -302,6 +323,16 @@ pub fn run(
 
     editor::main_loop(&mut editor, &mut env, core_rx, ui_tx);
 
+    // remember the position of the opened files
+    for id in editor.active_views.clone() {
+        record_file_view_position(&mut editor, id);
+    }
+    if let Err(e) = editor.file_history.save() {
+        dbg_println!("cannot save the file history: {}", e);
+    }
+
+    session::save_editor_session(&editor, &env);
+
     // force executor quit
     let _ = editor.executor_tx.send((1, Box::new(|| {})));
     if let Some(executor_handle) = executor_th {
@@ -329,9 +360,24 @@ pub fn worker(worker_rx: &Receiver<Message<'static>>, core_tx: &Sender<Message<'
                 }
 
                 Event::SyncTask { buffer } => {
-                    buffer::sync_to_storage(&buffer);
                     let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
 
+                    if let Err(e) = buffer::sync_to_storage(&buffer) {
+                        let buffer_id = {
+                            let mut buffer = buffer.write();
+                            buffer.is_syncing = false;
+                            buffer.id
+                        };
+
+                        let event = BufferEvent::BufferSyncFailed {
+                            buffer_id,
+                            error: e.to_string(),
+                        };
+                        let msg = Message::new(0, 0, ts, Event::Buffer { event });
+                        core_tx.send(msg).unwrap_or(());
+                        continue;
+                    }
+
                     let msg = Message::new(0, 0, ts, Event::RefreshView);
                     crate::core::event::pending_input_event_inc(1);
                     core_tx.send(msg).unwrap_or(());
@@ -491,6 +537,11 @@ fn build_buffer_options(editor: &Editor<'static>) -> Vec<ArgInfo> {
 
         let f = f.unwrap();
 
+        if f == stdin_stream::STDIN_ARG {
+            v.push(ArgInfo::new(f.clone()));
+            continue;
+        }
+
         // check file exits ?
         match fs::metadata(f) {
             // file exits ? yes -> add to list
@@ -629,10 +680,44 @@ pub fn path_to_buffer_kind(path: &String) -> BufferKind {
     }
 }
 
+/// Start the decompression of a compressed file, the views and the byte index are updated at the end.
+pub fn decompress_buffer(editor: &Editor<'static>, buffer: &Arc<RwLock<Buffer<'static>>>) {
+    let core_tx = editor.core_tx.clone();
+    let indexer_tx = editor.indexer_tx.clone();
+    let buffer_map = Arc::clone(&editor.buffer_map);
+
+    buffer::compression::decompress_in_background(buffer, move || {
+        let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+
+        let msg = Message::new(0, 0, ts, Event::IndexTask { buffer_map });
+        indexer_tx.send(msg).unwrap_or(());
+
+        let msg = Message::new(0, 0, ts, Event::RefreshView);
+        crate::core::event::pending_input_event_inc(1);
+        core_tx.send(msg).unwrap_or(());
+    });
+}
+
 /// TODO(ceg): replace this by load/unload buffer functions
 /// the ui will open the buffers on demand
-pub fn load_buffers(editor: &mut Editor<'static>, env: &mut EditorEnv<'static>) {
-    let arg_info = build_buffer_options(editor);
+pub fn load_buffers(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    session: Option<&session::Session>,
+) {
+    let mut arg_info = build_buffer_options(editor);
+
+    // add the files of the restored session (the removed files are ignored)
+    if let Some(session) = session {
+        for f in &session.files {
+            let known = arg_info
+                .iter()
+                .any(|arg| session::same_file(&arg.path, &f.path));
+            if !known && Path::new(&f.path).is_file() {
+                arg_info.push(ArgInfo::new(f.path.clone()));
+            }
+        }
+    }
 
     let arg_info = filter_arg_list(arg_info);
 
@@ -641,6 +726,14 @@ pub fn load_buffers(editor: &mut Editor<'static>, env: &mut EditorEnv<'static>)
     for arg in &arg_info {
         dbg_println!("processing arg {:?}", arg);
 
+        if arg.path == stdin_stream::STDIN_ARG {
+            if let Some(b) = stdin_stream::open_stdin_buffer(editor) {
+                let buffer_id = b.read().id;
+                editor.buffer_map.write().insert(buffer_id, b);
+            }
+            continue;
+        }
+
         // check file type
         let kind = match fs::metadata(&arg.path) {
             Ok(metadata) => {
@@ -673,6 +766,7 @@ pub fn load_buffers(editor: &mut Editor<'static>, env: &mut EditorEnv<'static>)
             .finalize();
 
         if let Some(b) = b {
+            decompress_buffer(editor, &b);
             let buffer_id = b.read().id;
             editor.buffer_map.write().insert(buffer_id, b);
         }
@@ -740,7 +834,10 @@ pub fn configure_modes(editor: &mut Editor<'static>, env: &mut EditorEnv<'static
 }
 
 use crate::core::modes::CoreMode;
+use crate::core::modes::DiffMode;
 use crate::core::modes::FindMode;
+use crate::core::modes::GitBlameMode;
+use crate::core::modes::GitMode;
 use crate::core::modes::TextMode;
 
 use crate::core::modes::EmptyLineMode;
@@ -762,6 +859,8 @@ use crate::core::modes::LineNumberMode;
 
 use crate::core::modes::OpenDocMode;
 
+use crate::core::modes::BufferSwitcherMode;
+
 use crate::core::modes::DirMode;
 
 pub fn load_modes(editor: &mut Editor, _env: &mut EditorEnv) {
@@ -792,6 +891,13 @@ pub fn load_modes(editor: &mut Editor, _env: &mut EditorEnv) {
 
     editor.register_mode(Box::new(OpenDocMode::new()));
 
+    editor.register_mode(Box::new(BufferSwitcherMode::new()));
+
+    editor.register_mode(Box::new(DiffMode::new()));
+
+    editor.register_mode(Box::new(GitMode::new()));
+    editor.register_mode(Box::new(GitBlameMode::new()));
+
     editor.register_directory_mode(Box::new(DirMode::new()));
 }
 
@@ -1312,13 +1418,21 @@ pub fn create_layout(mut editor: &mut Editor<'static>, mut env: &mut EditorEnv<'
         if let Some(group_id) =
             build_view_layout_typed(&mut editor, &mut env, group_buf, &json, "group-view")
         {
-            get_view_by_id(editor, workspace_id)
-                .write()
-                .children
-                .push(ChildView {
+            let idx = {
+                let workspace = get_view_by_id(editor, workspace_id);
+                let mut workspace = workspace.write();
+                workspace.children.push(ChildView {
                     id: group_id,
                     layout_op: LayoutSize::Percent { p: 100.0 },
                 });
+                workspace.children.len() - 1
+            };
+
+            // the focus is propagated up to the root view
+            let group = get_view_by_id(editor, group_id);
+            let mut group = group.write();
+            group.parent_id = Some(workspace_id);
+            group.layout_index = Some(idx);
         }
 
         // create views
@@ -1399,6 +1513,11 @@ pub fn create_layout(mut editor: &mut Editor<'static>, mut env: &mut EditorEnv<'
                             id: id,
                             layout_op: LayoutSize::Percent { p: 100.0 },
                         });
+
+                    let v = get_view_by_id(editor, id);
+                    let mut v = v.write();
+                    v.parent_id = Some(parent_id);
+                    v.layout_index = Some(0);
                 }
             }
         }
@@ -0,0 +1,827 @@
+use std::any::Any;
+use std::rc::Rc;
+
+use parking_lot::RwLock;
+
+use super::Mode;
+
+use crate::core::buffer;
+use crate::core::buffer::BufferBuilder;
+use crate::core::buffer::BufferKind;
+use crate::core::editor::check_view_by_id;
+use crate::core::editor::get_view_by_id;
+use crate::core::editor::push_editor_event;
+use crate::core::editor::register_input_stage_action;
+use crate::core::editor::set_focus_on_view_id;
+use crate::core::editor::EditorEvent;
+use crate::core::editor::InputStageActionMap;
+use crate::core::Editor;
+use crate::core::EditorEnv;
+
+use crate::core::event::*;
+
+use crate::core::event::input_map::build_input_event_map;
+
+use crate::core::fuzzy::fuzzy_filter;
+
+use crate::core::view;
+use crate::core::view::ChildView;
+use crate::core::view::ControllerView;
+use crate::core::view::LayoutDirection;
+use crate::core::view::LayoutSize;
+use crate::core::view::View;
+
+use crate::core::modes::open_doc::open_doc_add_buffer;
+use crate::core::modes::tab_bar_mode::close_file_view;
+use crate::core::modes::tab_bar_mode::current_file_view;
+use crate::core::modes::tab_bar_mode::show_file_view;
+use crate::core::modes::text_mode::center_around_mark_if_offscreen;
+use crate::core::modes::text_mode::TextModeContext;
+
+static BUFFER_SWITCHER_TRIGGER_MAP: &str =
+    std::include_str!("../../../res/input-map/buffer-switcher-mode-trigger.json");
+
+static BUFFER_SWITCHER_CONTROLLER_MAP: &str =
+    std::include_str!("../../../res/input-map/buffer-switcher-mode-input-map.json");
+
+impl Mode for BufferSwitcherMode {
+    fn name(&self) -> &'static str {
+        "buffer-switcher-mode"
+    }
+
+    fn build_action_map(&self) -> InputStageActionMap<'static> {
+        let mut map = InputStageActionMap::new();
+        Self::register_input_stage_actions(&mut map);
+        map
+    }
+
+    fn alloc_ctx(&self, _editor: &Editor<'static>) -> Box<dyn Any> {
+        dbg_println!("alloc buffer-switcher-mode ctx");
+        Box::new(BufferSwitcherModeContext::new())
+    }
+
+    fn configure_view(
+        &mut self,
+        editor: &mut Editor<'static>,
+        env: &mut EditorEnv<'static>,
+        view: &mut View<'static>,
+    ) {
+        {
+            let input_map = build_input_event_map(BUFFER_SWITCHER_TRIGGER_MAP).unwrap();
+            let mut input_map_stack = view.input_ctx.input_map.as_ref().borrow_mut();
+            input_map_stack.push((self.name(), input_map));
+        }
+
+        create_buffer_switcher_controller_view(editor, env, view);
+        create_buffer_switcher_list_view(editor, env, view);
+    }
+}
+
+/// An opened buffer, and the view displaying it (if any).
+#[derive(Debug, Clone)]
+pub struct BufferSwitcherEntry {
+    pub buffer_id: buffer::Id,
+    pub view_id: Option<view::Id>,
+    pub name: String,
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BufferSwitcherModeContext {
+    pub controller_view_id: view::Id,
+    pub list_view_id: view::Id,
+    pub prompt: Vec<char>,
+    /// the opened buffers, most recently used first
+    pub entries: Vec<BufferSwitcherEntry>,
+    /// indexes in entries of the buffers matching the prompt, best match first
+    pub matches: Vec<usize>,
+    /// index in matches
+    pub selected: usize,
+    /// the file view displayed when the switcher was started
+    pub origin_view_id: Option<view::Id>,
+    /// editor.recent_views when the switcher was started (the preview must not change it)
+    pub recent_views: Vec<view::Id>,
+    /// the entry waiting for a kill confirmation
+    pub confirm_kill: Option<usize>,
+}
+
+impl Default for BufferSwitcherModeContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BufferSwitcherModeContext {
+    pub fn new() -> Self {
+        BufferSwitcherModeContext {
+            controller_view_id: view::Id(0),
+            list_view_id: view::Id(0),
+            prompt: vec![],
+            entries: vec![],
+            matches: vec![],
+            selected: 0,
+            origin_view_id: None,
+            recent_views: vec![],
+            confirm_kill: None,
+        }
+    }
+
+    pub fn reset(&mut self) -> &mut Self {
+        self.prompt.clear();
+        self.entries.clear();
+        self.matches.clear();
+        self.selected = 0;
+        self.origin_view_id = None;
+        self.recent_views.clear();
+        self.confirm_kill = None;
+        self
+    }
+
+    fn update_matches(&mut self) {
+        let pattern: String = self.prompt.iter().collect();
+        self.matches = filter_buffer_entries(&self.entries, &pattern);
+        self.selected = 0;
+
+        // like alt+tab: with an empty prompt the previous buffer is selected
+        if pattern.is_empty() && self.matches.len() > 1 {
+            let first = &self.entries[self.matches[0]];
+            if first.view_id.is_some() && first.view_id == self.origin_view_id {
+                self.selected = 1;
+            }
+        }
+    }
+
+    fn selected_entry(&self) -> Option<&BufferSwitcherEntry> {
+        self.matches
+            .get(self.selected)
+            .map(|idx| &self.entries[*idx])
+    }
+}
+
+#[derive(Default)]
+pub struct BufferSwitcherMode {
+    // add common fields
+}
+
+impl BufferSwitcherMode {
+    pub fn new() -> Self {
+        dbg_println!("BufferSwitcherMode");
+        BufferSwitcherMode {}
+    }
+
+    pub fn register_input_stage_actions<'a>(map: &'a mut InputStageActionMap<'a>) {
+        register_input_stage_action(map, "buffer-switcher:start", buffer_switcher_start);
+    }
+}
+
+/// List the opened buffers: the most recently shown first, then in tab order.
+pub fn collect_buffer_entries(editor: &Editor<'static>) -> Vec<BufferSwitcherEntry> {
+    let mut entries = vec![];
+
+    let buffer_map = editor.buffer_map.read();
+    for (id, b) in buffer_map.iter() {
+        let b = b.read();
+
+        let view_id = editor.active_views.iter().copied().find(|vid| {
+            check_view_by_id(editor, *vid)
+                .and_then(|v| v.read().buffer())
+                .is_some_and(|vb| vb.read().id == *id)
+        });
+
+        entries.push(BufferSwitcherEntry {
+            buffer_id: *id,
+            view_id,
+            name: b.name.clone(),
+            changed: b.changed,
+        });
+    }
+
+    let rank = |e: &BufferSwitcherEntry| {
+        let recent = e
+            .view_id
+            .and_then(|vid| editor.recent_views.iter().position(|id| *id == vid))
+            .unwrap_or(usize::MAX);
+        let tab = e
+            .view_id
+            .and_then(|vid| editor.active_views.iter().position(|id| *id == vid))
+            .unwrap_or(usize::MAX);
+        (recent, tab, e.buffer_id)
+    };
+    entries.sort_by_key(|e| rank(e));
+
+    entries
+}
+
+/// Returns the indexes of the entries whose name matches 'pattern', best match first.<br/>
+/// Entries with the same score keep their order.
+pub fn filter_buffer_entries(entries: &[BufferSwitcherEntry], pattern: &str) -> Vec<usize> {
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    fuzzy_filter(&names, pattern, usize::MAX)
+        .into_iter()
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+fn create_buffer_switcher_controller_view(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &mut View,
+) {
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("buffer-switcher-controller")
+        .internal(true)
+        .use_buffer_log(false)
+        .finalize();
+
+    let mut controller_view = View::new(
+        editor,
+        env,
+        None,
+        (0, 0),
+        (1, 1),
+        buffer,
+        &vec![],
+        &vec!["empty-line-mode".to_owned()],
+        0,
+        LayoutDirection::NotSet,
+        LayoutSize::Percent { p: 100.0 },
+    );
+
+    controller_view.ignore_focus = false;
+    controller_view.controlled_view = Some(view.id);
+
+    let ctx = view.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+    ctx.controller_view_id = controller_view.id;
+
+    {
+        controller_view.input_ctx.stack_pos = None;
+
+        {
+            let event_map = build_input_event_map(BUFFER_SWITCHER_CONTROLLER_MAP).unwrap();
+            let mut input_map_stack = controller_view.input_ctx.input_map.as_ref().borrow_mut();
+            input_map_stack.push(("buffer-switcher-controller", event_map));
+        }
+
+        let mut action_map = InputStageActionMap::new();
+
+        register_input_stage_action(
+            &mut action_map,
+            "buffer-switcher:stop",
+            buffer_switcher_stop,
+        );
+        register_input_stage_action(
+            &mut action_map,
+            "buffer-switcher:switch",
+            buffer_switcher_switch,
+        );
+        register_input_stage_action(
+            &mut action_map,
+            "buffer-switcher:add-char",
+            buffer_switcher_add_char,
+        );
+        register_input_stage_action(
+            &mut action_map,
+            "buffer-switcher:del-char",
+            buffer_switcher_del_char,
+        );
+        register_input_stage_action(
+            &mut action_map,
+            "buffer-switcher:select-next",
+            buffer_switcher_select_next,
+        );
+        register_input_stage_action(
+            &mut action_map,
+            "buffer-switcher:select-prev",
+            buffer_switcher_select_prev,
+        );
+        register_input_stage_action(
+            &mut action_map,
+            "buffer-switcher:kill-buffer",
+            buffer_switcher_kill_buffer,
+        );
+
+        controller_view.register_action_map(action_map);
+    }
+
+    editor.add_view(controller_view.id, controller_view);
+}
+
+fn create_buffer_switcher_list_view(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    text_view: &mut View,
+) {
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("buffer-switcher-list")
+        .internal(true)
+        .use_buffer_log(false)
+        .finalize();
+
+    let mut list_view = View::new(
+        editor,
+        env,
+        None,
+        (0, 0),
+        (1, 1),
+        buffer,
+        &vec![],
+        &vec!["text-mode".to_owned()],
+        0,
+        LayoutDirection::NotSet,
+        LayoutSize::Percent { p: 100.0 },
+    );
+    list_view.ignore_focus = true;
+
+    let ctx = text_view.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+    ctx.list_view_id = list_view.id;
+
+    editor.add_view(list_view.id, list_view);
+}
+
+/// Returns the view holding the switcher context.
+fn switcher_text_view(
+    editor: &Editor<'static>,
+    controller_view: &Rc<RwLock<View<'static>>>,
+) -> Rc<RwLock<View<'static>>> {
+    let text_view_id = controller_view.read().controlled_view.unwrap();
+    get_view_by_id(editor, text_view_id)
+}
+
+pub fn buffer_switcher_start(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let status_view_id = match view::get_status_line_view_id(editor, env) {
+        Some(id) => id,
+        None => {
+            dbg_println!("status view is missing");
+            return;
+        }
+    };
+
+    let entries = collect_buffer_entries(editor);
+    let origin_view_id = current_file_view(editor);
+
+    let controller_view_id = {
+        let mut v = view.write();
+        let ctx = v.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+        ctx.reset();
+        ctx.entries = entries;
+        ctx.origin_view_id = origin_view_id;
+        ctx.recent_views = editor.recent_views.clone();
+        ctx.update_matches();
+
+        let controller_view_id = ctx.controller_view_id;
+
+        v.controller = Some(ControllerView {
+            id: controller_view_id,
+            mode_name: "buffer-switcher-mode",
+        });
+
+        controller_view_id
+    };
+
+    // attach the controller to the status line
+    get_view_by_id(editor, controller_view_id).write().parent_id = Some(status_view_id);
+    {
+        let status_view = get_view_by_id(editor, status_view_id);
+        let mut status_view = status_view.write();
+        status_view.layout_direction = LayoutDirection::Horizontal;
+        status_view.children.pop();
+        status_view.children.push(ChildView {
+            id: controller_view_id,
+            layout_op: LayoutSize::Percent { p: 100.0 },
+        });
+    }
+
+    set_focus_on_view_id(editor, env, controller_view_id);
+    env.input_grab_view_id = Some(controller_view_id);
+
+    let controller_view = get_view_by_id(editor, controller_view_id);
+    buffer_switcher_refresh(editor, env, &controller_view, true);
+}
+
+/// Update the prompt and the buffer list, and display the selected buffer if 'preview' is set.
+fn buffer_switcher_refresh(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    controller_view: &Rc<RwLock<View<'static>>>,
+    preview: bool,
+) {
+    let (list_view_id, lines, selected, prompt, preview_view_id) = {
+        let text_view = switcher_text_view(editor, controller_view);
+        let text_view = text_view.read();
+        let ctx = text_view.mode_ctx::<BufferSwitcherModeContext>("buffer-switcher-mode");
+
+        let lines: Vec<String> = ctx
+            .matches
+            .iter()
+            .map(|idx| {
+                let e = &ctx.entries[*idx];
+                format!("{}{}\n", e.name, if e.changed { "*" } else { "" })
+            })
+            .collect();
+
+        let prompt = match ctx.confirm_kill {
+            Some(idx) => format!(
+                "{} has unsaved changes, kill it anyway ? (y/n) ",
+                ctx.entries[idx].name
+            ),
+            None => format!("Switch to: {}", ctx.prompt.iter().collect::<String>()),
+        };
+
+        let preview_view_id = ctx.selected_entry().and_then(|e| e.view_id);
+
+        (
+            ctx.list_view_id,
+            lines,
+            ctx.selected,
+            prompt,
+            preview_view_id,
+        )
+    };
+
+    {
+        let buffer = controller_view.read().buffer().unwrap();
+        let mut buffer = buffer.write();
+        buffer.delete_content(None);
+        buffer.append(prompt.as_bytes());
+    }
+
+    // fill the list
+    let list_view = get_view_by_id(editor, list_view_id);
+    {
+        let mut list_view = list_view.write();
+        {
+            let buffer = list_view.buffer().unwrap();
+            let mut buffer = buffer.write();
+            buffer.delete_content(None);
+            if lines.is_empty() {
+                buffer.append(b"no matching buffer\n");
+            }
+            for s in &lines {
+                buffer.append(s.as_bytes());
+            }
+        }
+
+        let offset: usize = lines.iter().take(selected).map(|s| s.len()).sum();
+        let tm = list_view.mode_ctx_mut::<TextModeContext>("text-mode");
+        tm.marks.truncate(1);
+        tm.marks[0].offset = offset as u64;
+    }
+
+    // the list is displayed on top of the workspace, the preview stays visible below
+    if let Some(parent_id) = view::get_view_by_tag(editor, env, "workspace") {
+        let (w, h) = get_view_by_id(editor, parent_id).read().dimension();
+        {
+            let mut list_view = list_view.write();
+            list_view.x = 0;
+            list_view.y = 0;
+            list_view.width = w;
+            list_view.height = lines.len().clamp(1, std::cmp::max(h / 2, 1));
+        }
+
+        let p_view = get_view_by_id(editor, parent_id);
+        let mut p_view = p_view.write();
+        p_view.floating_children.pop();
+        p_view.floating_children.push(ChildView {
+            id: list_view_id,
+            layout_op: LayoutSize::Floating,
+        });
+    }
+
+    center_around_mark_if_offscreen(editor, env, &list_view);
+
+    if preview {
+        if let Some(id) = preview_view_id {
+            show_file_view(editor, env, id);
+        }
+        let controller_view_id = controller_view.read().id;
+        set_focus_on_view_id(editor, env, controller_view_id);
+    }
+}
+
+/// Remove the prompt and the list.<br/>
+/// Returns the state of the switcher before the reset.
+fn buffer_switcher_close(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    controller_view: &Rc<RwLock<View<'static>>>,
+) -> BufferSwitcherModeContext {
+    if let Some(status_view_id) = env.status_view_id {
+        let status_view = get_view_by_id(editor, status_view_id);
+        let mut status_view = status_view.write();
+        status_view.layout_direction = LayoutDirection::Horizontal;
+        status_view.children.pop();
+    }
+
+    if let Some(parent_id) = view::get_view_by_tag(editor, env, "workspace") {
+        get_view_by_id(editor, parent_id)
+            .write()
+            .floating_children
+            .pop();
+    }
+
+    {
+        let buffer = controller_view.read().buffer().unwrap();
+        buffer.write().delete_content(None);
+    }
+
+    let text_view = switcher_text_view(editor, controller_view);
+    let (ctx, text_view_id) = {
+        let mut text_view = text_view.write();
+        text_view.controller = None;
+        let ctx = text_view.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+        let saved = ctx.clone();
+        ctx.reset();
+        (saved, text_view.id)
+    };
+
+    // the preview must not change the recently used list
+    editor.recent_views = ctx.recent_views.clone();
+
+    set_focus_on_view_id(editor, env, text_view_id);
+    env.input_grab_view_id = None;
+
+    ctx
+}
+
+pub fn buffer_switcher_stop(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let ctx = buffer_switcher_close(editor, env, view);
+
+    // restore the initial view
+    if let Some(id) = ctx.origin_view_id {
+        if current_file_view(editor) != Some(id) {
+            show_file_view(editor, env, id);
+        }
+    }
+}
+
+pub fn buffer_switcher_switch(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let ctx = buffer_switcher_close(editor, env, view);
+
+    let entry = match ctx.selected_entry() {
+        Some(e) => e.clone(),
+        None => {
+            if let Some(id) = ctx.origin_view_id {
+                show_file_view(editor, env, id);
+            }
+            return;
+        }
+    };
+
+    let view_id = match entry.view_id {
+        Some(id) => id,
+        None => {
+            // the buffer has no view yet
+            let b = editor.buffer_map.read().get(&entry.buffer_id).cloned();
+            match b {
+                Some(b) => open_doc_add_buffer(editor, env, b).0,
+                None => return,
+            }
+        }
+    };
+
+    show_file_view(editor, env, view_id);
+}
+
+pub fn buffer_switcher_add_char(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let c = {
+        let v = view.read();
+        match v.input_ctx.trigger.last() {
+            Some(InputEvent::KeyPress {
+                key: Key::Unicode(c),
+                mods:
+                    KeyModifiers {
+                        ctrl: false,
+                        alt: false,
+                        ..
+                    },
+            }) if *c != '\n' && *c != '\t' => *c,
+            _ => return,
+        }
+    };
+
+    let text_view = switcher_text_view(editor, view);
+    let confirm_kill = {
+        let mut text_view = text_view.write();
+        let ctx = text_view.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+        match ctx.confirm_kill.take() {
+            Some(idx) => Some(idx),
+            None => {
+                ctx.prompt.push(c);
+                ctx.update_matches();
+                None
+            }
+        }
+    };
+
+    match confirm_kill {
+        Some(idx) if c == 'y' || c == 'Y' => buffer_switcher_do_kill(editor, env, view, idx),
+        Some(_) => buffer_switcher_refresh(editor, env, view, false),
+        None => buffer_switcher_refresh(editor, env, view, true),
+    }
+}
+
+pub fn buffer_switcher_del_char(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    {
+        let text_view = switcher_text_view(editor, view);
+        let mut text_view = text_view.write();
+        let ctx = text_view.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+        if ctx.confirm_kill.take().is_none() {
+            if ctx.prompt.pop().is_none() {
+                return;
+            }
+            ctx.update_matches();
+        }
+    }
+
+    buffer_switcher_refresh(editor, env, view, true);
+}
+
+fn buffer_switcher_select_with_offset(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    forward: bool,
+) {
+    {
+        let text_view = switcher_text_view(editor, view);
+        let mut text_view = text_view.write();
+        let ctx = text_view.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+        if ctx.matches.is_empty() || ctx.confirm_kill.is_some() {
+            return;
+        }
+
+        let n = ctx.matches.len();
+        ctx.selected = if forward {
+            (ctx.selected + 1) % n
+        } else {
+            (ctx.selected + n - 1) % n
+        };
+    }
+
+    buffer_switcher_refresh(editor, env, view, true);
+}
+
+pub fn buffer_switcher_select_next(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    buffer_switcher_select_with_offset(editor, env, view, true);
+}
+
+pub fn buffer_switcher_select_prev(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    buffer_switcher_select_with_offset(editor, env, view, false);
+}
+
+/// Kill the selected buffer, a buffer with unsaved changes is killed after confirmation.
+pub fn buffer_switcher_kill_buffer(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let idx = {
+        let text_view = switcher_text_view(editor, view);
+        let mut text_view = text_view.write();
+        let ctx = text_view.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+        if ctx.confirm_kill.is_some() {
+            return;
+        }
+
+        let idx = match ctx.matches.get(ctx.selected) {
+            Some(idx) => *idx,
+            None => return,
+        };
+
+        if ctx.entries[idx].changed {
+            ctx.confirm_kill = Some(idx);
+            None
+        } else {
+            Some(idx)
+        }
+    };
+
+    match idx {
+        Some(idx) => buffer_switcher_do_kill(editor, env, view, idx),
+        None => buffer_switcher_refresh(editor, env, view, false),
+    }
+}
+
+fn buffer_switcher_do_kill(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    idx: usize,
+) {
+    let (entry, origin_view_id) = {
+        let text_view = switcher_text_view(editor, view);
+        let text_view = text_view.read();
+        let ctx = text_view.mode_ctx::<BufferSwitcherModeContext>("buffer-switcher-mode");
+        (ctx.entries[idx].clone(), ctx.origin_view_id)
+    };
+
+    // the changes are discarded
+    if let Some(b) = editor.buffer_map.read().get(&entry.buffer_id) {
+        b.write().changed = false;
+    }
+
+    match entry.view_id {
+        Some(id) if Some(id) == origin_view_id => {
+            // the switcher lives in this view: leave it first
+            buffer_switcher_close(editor, env, view);
+            show_file_view(editor, env, id);
+            close_file_view(editor, env, id);
+            return;
+        }
+        Some(id) => {
+            close_file_view(editor, env, id);
+        }
+        None => {
+            editor.buffer_map.write().remove(&entry.buffer_id);
+            push_editor_event(
+                editor,
+                EditorEvent::BufferRemoved {
+                    id: entry.buffer_id,
+                },
+            );
+        }
+    }
+
+    let entries = collect_buffer_entries(editor);
+    {
+        let text_view = switcher_text_view(editor, view);
+        let mut text_view = text_view.write();
+        let ctx = text_view.mode_ctx_mut::<BufferSwitcherModeContext>("buffer-switcher-mode");
+        if let Some(id) = entry.view_id {
+            ctx.recent_views.retain(|vid| *vid != id);
+        }
+        let selected = ctx.selected;
+        ctx.entries = entries;
+        ctx.update_matches();
+        ctx.selected = selected.min(ctx.matches.len().saturating_sub(1));
+    }
+
+    buffer_switcher_refresh(editor, env, view, true);
+}
+
+#[test]
+fn test_buffer_switcher_filter() {
+    let entry = |id, name: &str| BufferSwitcherEntry {
+        buffer_id: buffer::Id(id),
+        view_id: Some(view::Id(id)),
+        name: name.to_owned(),
+        changed: false,
+    };
+
+    let entries = vec![
+        entry(1, "src/core/modes/text_mode.rs"),
+        entry(2, "src/main.rs"),
+        entry(3, "src/core/modes/tab_bar_mode.rs"),
+        entry(4, "README.md"),
+    ];
+
+    // empty pattern: keep the most recently used order
+    assert_eq!(filter_buffer_entries(&entries, ""), vec![0, 1, 2, 3]);
+
+    assert_eq!(filter_buffer_entries(&entries, "main"), vec![1]);
+    assert_eq!(filter_buffer_entries(&entries, "tbm"), vec![2]);
+    assert_eq!(filter_buffer_entries(&entries, "xyz"), Vec::<usize>::new());
+
+    let m = filter_buffer_entries(&entries, "mode");
+    assert_eq!(m.len(), 2);
+
+    let mut ctx = BufferSwitcherModeContext::new();
+    ctx.entries = entries;
+    ctx.origin_view_id = Some(view::Id(1));
+    ctx.update_matches();
+    assert_eq!(ctx.selected, 1);
+    assert_eq!(ctx.selected_entry().unwrap().name, "src/main.rs");
+
+    ctx.prompt = "readme".chars().collect();
+    ctx.update_matches();
+    assert_eq!(ctx.selected, 0);
+    assert_eq!(ctx.selected_entry().unwrap().name, "README.md");
+}
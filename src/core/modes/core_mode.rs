@@ -7,6 +7,7 @@ use std::sync::Arc;
 
 use super::Mode;
 
+use crate::core::buffer::Buffer;
 use crate::core::buffer::BufferBuilder;
 use crate::core::buffer::BufferKind;
 
@@ -33,6 +34,16 @@ use crate::core::view::View;
 
 use crate::core::build_view_layout_from_json_str;
 
+use crate::core::modes::tab_bar_mode::tab_bar_close_tab;
+use crate::core::modes::tab_bar_mode::tab_bar_next_tab;
+use crate::core::modes::tab_bar_mode::tab_bar_previous_tab;
+use crate::core::modes::tab_bar_mode::tab_bar_scroll_left;
+use crate::core::modes::tab_bar_mode::tab_bar_scroll_right;
+
+use crate::core::modes::side_bar_mode::side_bar_focus;
+use crate::core::modes::text_mode::follow::release_file_followers;
+use crate::core::modes::text_mode::release_fold_tracker;
+
 use crate::core::parse_layout_str;
 use crate::core::DEFAULT_LAYOUT_JSON;
 
@@ -42,6 +53,12 @@ static CORE_INPUT_MAP: &str = r#"
     "events": [
      { "in": [{ "key": "F2" } ],                                               "action": "select-previous-view" },
      { "in": [{ "key": "F3" } ],                                               "action": "select-next-view" },
+     { "in": [{ "key": "ctrl+PageUp" } ],                                      "action": "tab-bar:previous-tab" },
+     { "in": [{ "key": "ctrl+PageDown" } ],                                    "action": "tab-bar:next-tab" },
+     { "in": [{ "key": "ctrl+shift+PageUp" } ],                                "action": "tab-bar:scroll-left" },
+     { "in": [{ "key": "ctrl+shift+PageDown" } ],                              "action": "tab-bar:scroll-right" },
+     { "in": [{ "key": "ctrl+F4" } ],                                          "action": "tab-bar:close-tab" },
+     { "in": [{ "key": "alt+t" } ],                                            "action": "side-bar:focus" },
      { "in": [{ "key": "F4"     }],                                            "action": "toggle-debug-print" },
      { "in": [{ "key": "ctrl+x" }, { "key": "ctrl+s" } ],                      "action": "save-buffer" },
      { "in": [{ "key": "ctrl+x" }, { "key": "ctrl+q" } ],                      "action": "application:quit" },
@@ -63,6 +80,18 @@ static CORE_QUIT_ABORT_MAP: &str = r#"
 
 ]"#;
 
+static CORE_SAVE_AS_MAP: &str = r#"
+[
+  {
+    "events": [
+     { "in": [{ "key": "BackSpace" } ], "action": "save-buffer-as:input" },
+     { "in": [{ "key": "Escape" } ],    "action": "save-buffer-as:input" },
+     { "default": [],                   "action": "save-buffer-as:input" }
+   ]
+  }
+
+]"#;
+
 impl<'a> Mode for CoreMode {
     fn name(&self) -> &'static str {
         &"core-mode"
@@ -76,7 +105,7 @@ impl<'a> Mode for CoreMode {
 
     fn alloc_ctx(&self, _editor: &Editor) -> Box<dyn Any> {
         dbg_println!("alloc core-mode ctx");
-        let ctx = CoreModeContext {};
+        let ctx = CoreModeContext { save_as: None };
         Box::new(ctx)
     }
 
@@ -97,7 +126,8 @@ pub struct CoreMode {
     // add common fields
 }
 pub struct CoreModeContext {
-    // add common fields
+    /// the file name typed in the "save as" prompt
+    pub save_as: Option<String>,
 }
 
 impl CoreMode {
@@ -128,7 +158,16 @@ impl CoreMode {
 
         register_input_stage_action(&mut map, "help-pop-up", help_popup);
 
+        register_input_stage_action(&mut map, "tab-bar:next-tab", tab_bar_next_tab);
+        register_input_stage_action(&mut map, "tab-bar:previous-tab", tab_bar_previous_tab);
+        register_input_stage_action(&mut map, "tab-bar:scroll-left", tab_bar_scroll_left);
+        register_input_stage_action(&mut map, "tab-bar:scroll-right", tab_bar_scroll_right);
+        register_input_stage_action(&mut map, "tab-bar:close-tab", tab_bar_close_tab);
+
+        register_input_stage_action(&mut map, "side-bar:focus", side_bar_focus);
+
         register_input_stage_action(&mut map, "save-buffer", save_buffer); // core ?
+        register_input_stage_action(map, "save-buffer-as:input", save_buffer_as_input);
         register_input_stage_action(&mut map, "split-vertically", split_vertically);
         register_input_stage_action(&mut map, "split-horizontally", split_horizontally);
         register_input_stage_action(&mut map, "destroy-view", destroy_view);
@@ -203,6 +242,22 @@ pub fn application_quit_abort_setup(
     }
 }
 
+/// Replace the content of the command line with 'text'.
+pub fn set_command_line_text(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    text: &str,
+) {
+    if let Some(id) = view::get_command_view_id(editor, env) {
+        let command_view = get_view_by_id(editor, id);
+        let buffer = command_view.read().buffer().unwrap();
+        let mut buffer = buffer.write();
+        let sz = buffer.size();
+        buffer.remove(0, sz, None);
+        buffer.insert(0, text.len(), text.as_bytes());
+    }
+}
+
 pub fn application_quit_abort_yes(
     _editor: &mut Editor,
     env: &mut EditorEnv,
@@ -240,7 +295,32 @@ pub fn toggle_dgb_print(_editor: &mut Editor, _env: &mut EditorEnv, _view: &Rc<R
     crate::core::toggle_dbg_println();
 }
 
-pub fn save_buffer(editor: &mut Editor<'static>, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+pub fn save_buffer(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    // a buffer without file (stdin) asks for one
+    let save_as = {
+        let buffer = view.read().buffer().unwrap();
+        let buffer = buffer.read();
+        if !buffer.file_name().is_empty() {
+            None
+        } else if buffer.is_streaming {
+            Some(Err(format!(
+                "cannot save '{}': the buffer is still receiving data",
+                buffer.name
+            )))
+        } else {
+            Some(Ok(()))
+        }
+    };
+    match save_as {
+        Some(Ok(())) => return save_buffer_as_setup(editor, env, view),
+        Some(Err(text)) => return set_command_line_text(editor, env, &text),
+        None => {}
+    }
+
     let v = view.write();
 
     let buffer_id = {
@@ -292,6 +372,100 @@ pub fn save_buffer(editor: &mut Editor<'static>, _env: &mut EditorEnv, view: &Rc
     }
 }
 
+/// Ask for the file of a buffer without file, see save_buffer_as_input.
+fn save_buffer_as_setup(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if view::get_command_view_id(editor, env).is_none() {
+        return;
+    }
+
+    set_command_line_text(editor, env, "Save as: ");
+
+    let mut v = view.write();
+    v.input_ctx.stack_pos = None;
+    {
+        let input_map = build_input_event_map(CORE_SAVE_AS_MAP).unwrap();
+        let mut input_map_stack = v.input_ctx.input_map.as_ref().borrow_mut();
+        input_map_stack.push(("core-mode", input_map));
+    }
+
+    let ctx = v.mode_ctx_mut::<CoreModeContext>("core-mode");
+    ctx.save_as = Some(String::new());
+}
+
+/// Handles the keys typed in the "save as" prompt:<br/>
+/// Enter gives the file to the buffer and saves it (an existing file is not replaced), Escape cancels.
+pub fn save_buffer_as_input(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let evt = view.read().input_ctx.trigger.last().cloned();
+    let key = match evt {
+        Some(InputEvent::KeyPress { key, .. }) => key,
+        // ignore the mouse
+        _ => return,
+    };
+
+    let validate = match key {
+        Key::Unicode('\n') => true,
+        Key::Escape => false,
+        key => {
+            let text = {
+                let mut v = view.write();
+                let ctx = v.mode_ctx_mut::<CoreModeContext>("core-mode");
+                let text = ctx.save_as.get_or_insert_with(String::new);
+                match key {
+                    Key::Unicode(c) if !c.is_control() => text.push(c),
+                    Key::UnicodeArray(v) => text.extend(v),
+                    Key::BackSpace => {
+                        text.pop();
+                    }
+                    _ => {}
+                }
+                text.clone()
+            };
+            set_command_line_text(editor, env, &format!("Save as: {}", text));
+            return;
+        }
+    };
+
+    let file_name = {
+        let mut v = view.write();
+        {
+            let mut input_map_stack = v.input_ctx.input_map.as_ref().borrow_mut();
+            input_map_stack.pop();
+        }
+        let ctx = v.mode_ctx_mut::<CoreModeContext>("core-mode");
+        ctx.save_as.take().unwrap_or_default()
+    };
+
+    if !validate || file_name.is_empty() {
+        set_command_line_text(editor, env, "");
+        return;
+    }
+
+    if std::path::Path::new(&file_name).exists() {
+        let text = format!("cannot save as '{}': the file exists", file_name);
+        set_command_line_text(editor, env, &text);
+        return;
+    }
+
+    {
+        let buffer = view.read().buffer().unwrap();
+        let mut buffer = buffer.write();
+        buffer.set_file_name(&file_name);
+        // the new file does not contain the buffer yet
+        buffer.changed = true;
+    }
+
+    set_command_line_text(editor, env, "");
+    save_buffer(editor, env, view);
+}
+
 pub fn layout_view_ids_with_direction(
     editor: &mut Editor<'static>,
     _env: &mut EditorEnv<'static>,
@@ -471,11 +645,24 @@ fn build_split_info(view: &Rc<RwLock<View<'static>>>, dir: view::LayoutDirection
         replace view's parent index with split group
 */
 pub fn split_view_with_direction(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    dir: view::LayoutDirection,
+) -> Option<()> {
+    split_view_with_buffer(editor, env, view, dir, None)?;
+    Some(())
+}
+
+/// Splits 'view' like split_view_with_direction, the clone displays 'buffer' if provided.<br/>
+/// Returns the id of the clone (group leader).
+pub fn split_view_with_buffer(
     mut editor: &mut Editor<'static>,
     mut env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View<'static>>>,
     dir: view::LayoutDirection,
-) -> Option<()> {
+    buffer: Option<Arc<RwLock<Buffer<'static>>>>,
+) -> Option<view::Id> {
     dbg_println!("try splitting id {:?}", view.read().id);
 
     let id = find_first_splittable_parent(editor, env, view)?; // group leader (ex: simple_view)
@@ -489,7 +676,7 @@ pub fn split_view_with_direction(
         return None;
     };
 
-    let buffer = pview.read().buffer();
+    let buffer = buffer.or_else(|| pview.read().buffer());
 
     let all_layouts = parse_layout_str(DEFAULT_LAYOUT_JSON).unwrap();
 
@@ -616,7 +803,7 @@ pub fn split_view_with_direction(
         &view_ids,
     );
 
-    Some(())
+    Some(view_clone_id)
 }
 
 pub fn split_vertically(
@@ -887,7 +1074,7 @@ pub fn decrease_right(
     fn destroy_view_sibling(vidx) { only keep parent_layout_index }
 */
 
-fn destroy_view_hierarchy(editor: &mut Editor<'static>, id: view::Id) {
+pub fn destroy_view_hierarchy(editor: &mut Editor<'static>, id: view::Id) {
     let mut ids = vec![];
 
     {
@@ -908,6 +1095,8 @@ fn destroy_view_hierarchy(editor: &mut Editor<'static>, id: view::Id) {
         destroy_view_hierarchy(editor, id);
     }
     dbg_println!("DESTROY view {id:?}");
+    release_file_followers(editor, id);
+    release_fold_tracker(editor, id);
     remove_view_by_id(editor, id);
 }
 
@@ -1022,9 +1211,19 @@ pub fn destroy_view(
 pub static HELP_MESSAGE: &str = std::include_str!("../../../res/help_screen.txt");
 
 pub fn help_popup(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View>>,
+) {
+    toggle_text_popup(editor, env, "help-pop-up", HELP_MESSAGE);
+}
+
+/// Show 'text' in a floating view, or destroy the previous pop-up if any.
+pub fn toggle_text_popup(
     mut editor: &mut Editor<'static>,
     mut env: &mut EditorEnv<'static>,
-    _view: &Rc<RwLock<View>>,
+    buffer_name: &str,
+    text: &str,
 ) {
     let root_view_id = view::Id(1);
     let (root_width, _root_height) = get_view_by_id(editor, root_view_id).read().dimension();
@@ -1043,24 +1242,24 @@ pub fn help_popup(
     }
 
     let command_buffer = BufferBuilder::new(BufferKind::File)
-        .buffer_name("help-pop-up")
+        .buffer_name(buffer_name)
         .internal(true)
         //           .use_buffer_log(false)
         .finalize();
 
     let mut pop_width = 0;
-    for l in HELP_MESSAGE.lines() {
-        pop_width = std::cmp::max(pop_width, l.len());
+    for l in text.lines() {
+        pop_width = std::cmp::max(pop_width, l.chars().count());
     }
     pop_width += 1;
 
-    let pop_height = HELP_MESSAGE.lines().count();
+    let pop_height = std::cmp::max(1, text.lines().count());
     let x = (root_width / 2).saturating_sub(pop_width / 2);
     let y = 3; //(root_height / 2).saturating_sub(pop_height / 2);
 
     {
         let mut d = command_buffer.as_ref().unwrap().write();
-        d.append(HELP_MESSAGE.as_bytes());
+        d.append(text.as_bytes());
     }
 
     // create view
@@ -0,0 +1,1361 @@
+/*
+    Diff mode: compares two buffers, or a modified buffer and its file on disk.
+
+    side by side:
+        the view is split (split_view_with_buffer), the compared buffer is displayed on the right.
+        the file on disk is read from the original storage into a snapshot buffer.
+        both views share the same DiffSession, the scrolling of the focused view is applied to the other one.
+
+    unified:
+        the diff is written in a new "*diff* name" buffer, refreshed when the compared buffers change.
+
+    git:
+        the buffer can be compared with the version of its file in HEAD (snapshot "name (HEAD)"),
+        the hunk under the mark is then staged/unstaged with "git apply --cached" (see core::git).
+
+    the diff is recomputed in the executor thread when one of the buffers changed, one diff at a time:
+    the previous diff is displayed meanwhile, and the buffers are read DIFF_DELAY_MS after the change
+    (the next keystrokes share its diff). The actions on the hunks recompute the diff immediately.
+    The buffers larger than MAX_DIFF_SIZE are not compared.
+*/
+
+use parking_lot::RwLock;
+use std::any::Any;
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::TryRecvError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::Mode;
+
+use crate::core::buffer::build_index;
+use crate::core::buffer::Buffer;
+use crate::core::buffer::BufferBuilder;
+use crate::core::buffer::BufferKind;
+
+use crate::core::diff::*;
+
+use crate::core::editor;
+use crate::core::editor::check_view_by_id;
+use crate::core::editor::register_input_stage_action;
+use crate::core::editor::set_focus_on_view_id;
+use crate::core::editor::InputStageActionMap;
+use crate::core::event::pending_input_event_inc;
+use crate::core::event::Event;
+use crate::core::event::Message;
+use crate::core::Editor;
+use crate::core::EditorEnv;
+
+use crate::core::event::input_map::build_input_event_map;
+
+use crate::core::git;
+use crate::core::git::GitFile;
+
+use crate::core::screen::screen_line_apply;
+use crate::core::screen::Screen;
+
+use crate::core::view;
+use crate::core::view::LayoutDirection;
+use crate::core::view::LayoutEnv;
+use crate::core::view::ScreenOverlayFilter;
+use crate::core::view::View;
+
+use crate::core::modes::core_mode::destroy_view;
+use crate::core::modes::core_mode::set_command_line_text;
+use crate::core::modes::core_mode::split_view_with_buffer;
+use crate::core::modes::open_doc::open_doc_add_buffer;
+use crate::core::modes::tab_bar_mode::close_file_view;
+use crate::core::modes::tab_bar_mode::find_tagged_child;
+use crate::core::modes::tab_bar_mode::show_file_view;
+use crate::core::modes::text_mode::mark::Mark;
+use crate::core::modes::text_mode::run_text_mode_actions_vec;
+use crate::core::modes::text_mode::PostInputAction;
+use crate::core::modes::text_mode::TextModeContext;
+
+static DIFF_TRIGGER_MAP: &str = std::include_str!("../../../res/input-map/diff-mode-trigger.json");
+
+/// number of unchanged lines displayed around the changes of the unified diff
+const UNIFIED_CONTEXT_LINES: usize = 3;
+
+/// number of unchanged lines around the staged/unstaged hunk
+const PATCH_CONTEXT_LINES: usize = 3;
+
+/// The buffers larger than this are not compared.
+const MAX_DIFF_SIZE: usize = 16 * 1024 * 1024;
+
+/// The delay before reading the content of a modified buffer.
+const DIFF_DELAY_MS: u64 = 300;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DiffSide {
+    Old = 0,
+    #[default]
+    New = 1,
+    Unified = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    Inserted,
+    Removed,
+    Changed,
+    Header,
+}
+
+impl DiffLineKind {
+    fn line_color(&self) -> (u8, u8, u8) {
+        match self {
+            DiffLineKind::Inserted => (18, 54, 34),
+            DiffLineKind::Removed => (60, 20, 30),
+            DiffLineKind::Changed => (46, 42, 18),
+            DiffLineKind::Header => (36, 44, 88),
+        }
+    }
+
+    fn inline_color(&self) -> (u8, u8, u8) {
+        match self {
+            DiffLineKind::Removed => (120, 36, 48),
+            _ => (30, 112, 60),
+        }
+    }
+}
+
+/// The highlighted lines and intra-line changes of a displayed buffer, sorted by offset.
+#[derive(Debug, Default)]
+pub struct DiffHighlights {
+    pub lines: Vec<(u64, u64, DiffLineKind)>,
+    pub inline: Vec<(u64, u64, DiffLineKind)>,
+}
+
+pub struct DiffSession {
+    /// the compared buffers: [old, new]
+    buffers: [Arc<RwLock<Buffer<'static>>>; 2],
+    /// the text views of the compared buffers (side by side)
+    view_ids: [view::Id; 2],
+    /// the file view and the buffer of the unified diff
+    unified: Option<(view::Id, Arc<RwLock<Buffer<'static>>>)>,
+    /// the old buffer is a snapshot of the file on disk (or in HEAD), it is not modified
+    snapshot: bool,
+    /// the old buffer is the version of this file in HEAD: its hunks can be staged
+    git_file: Option<GitFile>,
+    revisions: Option<[usize; 2]>,
+    /// the diff computed by the executor thread
+    pending: Option<Receiver<DiffData>>,
+    data: [Vec<u8>; 2],
+    /// the line start offsets of each buffer, see line_offsets
+    lines: [Vec<usize>; 2],
+    hunks: Vec<DiffHunk>,
+    /// [old, new, unified]
+    highlights: [DiffHighlights; 3],
+    /// the lines of the unified diff and their start offsets
+    unified_lines: Vec<(UnifiedLine, u64)>,
+    /// the view and start offset used for the last scroll synchronization
+    synced: Option<(view::Id, u64)>,
+}
+
+impl DiffSession {
+    fn new(
+        buffers: [Arc<RwLock<Buffer<'static>>>; 2],
+        view_ids: [view::Id; 2],
+        snapshot: bool,
+    ) -> Self {
+        DiffSession {
+            buffers,
+            view_ids,
+            unified: None,
+            snapshot,
+            git_file: None,
+            revisions: None,
+            pending: None,
+            data: [vec![], vec![]],
+            lines: [vec![0], vec![0]],
+            hunks: vec![],
+            highlights: [
+                DiffHighlights::default(),
+                DiffHighlights::default(),
+                DiffHighlights::default(),
+            ],
+            unified_lines: vec![],
+            synced: None,
+        }
+    }
+
+    pub fn hunks(&self) -> &[DiffHunk] {
+        &self.hunks
+    }
+
+    fn current_revisions(&self) -> [usize; 2] {
+        [
+            self.buffers[0].read().nr_changes(),
+            self.buffers[1].read().nr_changes(),
+        ]
+    }
+
+    /// Recomputes the diff if one of the buffers changed. Returns true if the diff was recomputed.
+    fn refresh(&mut self) -> bool {
+        if self.revisions == Some(self.current_revisions()) {
+            return false;
+        }
+
+        // a pending diff is older
+        self.pending = None;
+
+        let diff = compute_diff(&self.buffers);
+        self.apply(diff);
+        true
+    }
+
+    /// Recomputes the diff in the executor thread if one of the buffers changed.<br/>
+    /// Returns true if the diff computed by the executor thread was applied.
+    fn refresh_in_background(&mut self, editor: &Editor<'static>) -> bool {
+        let mut changed = false;
+
+        if let Some(ref pending) = self.pending {
+            match pending.try_recv() {
+                Ok(diff) => {
+                    self.pending = None;
+                    self.apply(diff);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        if self.revisions != Some(self.current_revisions()) {
+            self.pending = Some(start_diff(editor, &self.buffers));
+        }
+
+        changed
+    }
+
+    fn apply(&mut self, diff: DiffData) {
+        self.revisions = Some(diff.revisions);
+        self.data = diff.data;
+        self.lines = diff.lines;
+        self.hunks = diff.hunks;
+
+        let old = split_lines(&self.data[0], &self.lines[0]);
+        let new = split_lines(&self.data[1], &self.lines[1]);
+
+        let [old_hl, new_hl] = build_highlights(&self.lines, &old, &new, &self.hunks);
+        self.highlights[0] = old_hl;
+        self.highlights[1] = new_hl;
+
+        if let Some((_, ref buffer)) = self.unified {
+            let (text, kinds) = unified_diff(&old, &new, &self.hunks, UNIFIED_CONTEXT_LINES);
+            {
+                let mut buffer = buffer.write();
+                buffer.delete_content(None);
+                buffer.append(&text);
+                buffer.changed = false;
+            }
+
+            let offsets = line_offsets(&text);
+            self.unified_lines = kinds
+                .iter()
+                .zip(offsets.iter())
+                .map(|(k, o)| (*k, *o as u64))
+                .collect();
+            self.highlights[2] =
+                build_unified_highlights(&self.unified_lines, &old, &new, &self.hunks);
+        }
+    }
+
+    fn line_count(&self, side: usize) -> usize {
+        self.lines[side].len() - 1
+    }
+
+    /// Returns the line of 'side' containing 'offset'.
+    fn line_at(&self, side: usize, offset: u64) -> usize {
+        let n = self.lines[side].partition_point(|o| *o as u64 <= offset);
+        std::cmp::min(n.saturating_sub(1), self.line_count(side))
+    }
+
+    fn line_start(&self, side: usize, line: usize) -> u64 {
+        let line = std::cmp::min(line, self.line_count(side));
+        self.lines[side][line] as u64
+    }
+
+    /// Returns the hunk at 'line' of 'side': the changed lines or the position of the removed lines.
+    fn hunk_at(&self, side: usize, line: usize) -> Option<usize> {
+        self.hunks
+            .iter()
+            .position(|h| side_range(h, side).contains(&line))
+            .or_else(|| {
+                self.hunks.iter().position(|h| {
+                    let r = side_range(h, side);
+                    r.is_empty() && r.start == line
+                })
+            })
+    }
+
+    /// Replaces the lines of 'hunk' in the buffer 'to' by the lines of the buffer 'from'.
+    fn copy_hunk(&self, hunk: usize, from: usize, to: usize) {
+        let h = &self.hunks[hunk];
+        let src = side_range(h, from);
+        let dst = side_range(h, to);
+
+        let data = &self.data[from][self.lines[from][src.start]..self.lines[from][src.end]];
+        let start = self.lines[to][dst.start];
+        let size = self.lines[to][dst.end] - start;
+
+        let mut buffer = self.buffers[to].write();
+        buffer.remove(start as u64, size, None);
+        buffer.insert(start as u64, data.len(), data);
+    }
+}
+
+/// The content of the compared buffers and their diff.
+struct DiffData {
+    revisions: [usize; 2],
+    data: [Vec<u8>; 2],
+    lines: [Vec<usize>; 2],
+    hunks: Vec<DiffHunk>,
+}
+
+/// Returns true if one of 'buffers' is too large to be compared, the user is then notified.
+fn too_large(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    buffers: &[Arc<RwLock<Buffer<'static>>>],
+) -> bool {
+    let too_large = buffers.iter().any(|b| b.read().size() > MAX_DIFF_SIZE);
+    if too_large {
+        set_command_line_text(editor, env, "The buffers are too large to be compared");
+    }
+    too_large
+}
+
+/// Reads 'buffers' and compares them, nothing is compared if one of them is too large.
+fn compute_diff(buffers: &[Arc<RwLock<Buffer<'static>>>; 2]) -> DiffData {
+    let mut diff = DiffData {
+        revisions: [0, 0],
+        data: [vec![], vec![]],
+        lines: [vec![0], vec![0]],
+        hunks: vec![],
+    };
+
+    let buffers = [buffers[0].read(), buffers[1].read()];
+    diff.revisions = [buffers[0].nr_changes(), buffers[1].nr_changes()];
+    if buffers.iter().any(|b| b.size() > MAX_DIFF_SIZE) {
+        return diff;
+    }
+
+    for (i, buffer) in buffers.iter().enumerate() {
+        let mut data = Vec::with_capacity(buffer.size());
+        buffer.read(0, buffer.size(), &mut data);
+        diff.lines[i] = line_offsets(&data);
+        diff.data[i] = data;
+    }
+    drop(buffers);
+
+    let old = split_lines(&diff.data[0], &diff.lines[0]);
+    let new = split_lines(&diff.data[1], &diff.lines[1]);
+    diff.hunks = diff_lines(&old, &new);
+
+    diff
+}
+
+/// Compares 'buffers' in the executor thread, the ui is refreshed when the diff is available.
+fn start_diff(
+    editor: &Editor<'static>,
+    buffers: &[Arc<RwLock<Buffer<'static>>>; 2],
+) -> Receiver<DiffData> {
+    let (tx, rx) = channel();
+    let buffers = [Arc::clone(&buffers[0]), Arc::clone(&buffers[1])];
+    let core_tx = editor.core_tx.clone();
+
+    let _ = editor.executor_tx.send((
+        0,
+        Box::new(move || {
+            std::thread::sleep(Duration::from_millis(DIFF_DELAY_MS));
+
+            let diff = compute_diff(&buffers);
+            if tx.send(diff).is_ok() {
+                let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+                let msg = Message::new(0, 0, ts, Event::RefreshView);
+                pending_input_event_inc(1);
+                core_tx.send(msg).unwrap_or(());
+            }
+        }),
+    ));
+
+    rx
+}
+
+fn side_range(h: &DiffHunk, side: usize) -> Range<usize> {
+    if side == DiffSide::Old as usize {
+        h.old.clone()
+    } else {
+        h.new.clone()
+    }
+}
+
+/// The changed lines are paired: their intra-line changes are highlighted.<br/>
+/// The extra lines of a hunk are removed (old side) or inserted (new side).
+fn build_highlights(
+    lines: &[Vec<usize>; 2],
+    old: &[&[u8]],
+    new: &[&[u8]],
+    hunks: &[DiffHunk],
+) -> [DiffHighlights; 2] {
+    let mut hl = [DiffHighlights::default(), DiffHighlights::default()];
+
+    for h in hunks {
+        let paired = std::cmp::min(h.old.len(), h.new.len());
+
+        for side in 0..2 {
+            let extra_kind = if side == DiffSide::Old as usize {
+                DiffLineKind::Removed
+            } else {
+                DiffLineKind::Inserted
+            };
+            for (k, l) in side_range(h, side).enumerate() {
+                let kind = if k < paired {
+                    DiffLineKind::Changed
+                } else {
+                    extra_kind
+                };
+                let (start, end) = (lines[side][l] as u64, lines[side][l + 1] as u64);
+                hl[side].lines.push((start, end, kind));
+            }
+        }
+
+        for k in 0..paired {
+            let (o, n) = (h.old.start + k, h.new.start + k);
+            let (ro, rn) = diff_line_change(old[o], new[n]);
+
+            let start = lines[0][o] as u64;
+            hl[0].inline.push((
+                start + ro.start as u64,
+                start + ro.end as u64,
+                DiffLineKind::Removed,
+            ));
+            let start = lines[1][n] as u64;
+            hl[1].inline.push((
+                start + rn.start as u64,
+                start + rn.end as u64,
+                DiffLineKind::Inserted,
+            ));
+        }
+    }
+
+    hl
+}
+
+fn build_unified_highlights(
+    unified_lines: &[(UnifiedLine, u64)],
+    old: &[&[u8]],
+    new: &[&[u8]],
+    hunks: &[DiffHunk],
+) -> DiffHighlights {
+    let mut hl = DiffHighlights::default();
+
+    for (i, (kind, start)) in unified_lines.iter().enumerate() {
+        let end = unified_lines.get(i + 1).map(|l| l.1).unwrap_or(u64::MAX);
+
+        let kind = match *kind {
+            UnifiedLine::Header { .. } => DiffLineKind::Header,
+            UnifiedLine::Context => continue,
+            UnifiedLine::Removed { hunk, line } => {
+                let h = &hunks[hunk];
+                if line < h.new.len() {
+                    let (r, _) = diff_line_change(old[h.old.start + line], new[h.new.start + line]);
+                    // skip the '-' prefix
+                    let s = start + 1;
+                    hl.inline
+                        .push((s + r.start as u64, s + r.end as u64, DiffLineKind::Removed));
+                }
+                DiffLineKind::Removed
+            }
+            UnifiedLine::Inserted { hunk, line } => {
+                let h = &hunks[hunk];
+                if line < h.old.len() {
+                    let (_, r) = diff_line_change(old[h.old.start + line], new[h.new.start + line]);
+                    let s = start + 1;
+                    hl.inline
+                        .push((s + r.start as u64, s + r.end as u64, DiffLineKind::Inserted));
+                }
+                DiffLineKind::Inserted
+            }
+        };
+
+        hl.lines.push((*start, end, kind));
+    }
+
+    hl
+}
+
+#[derive(Default)]
+pub struct DiffModeContext {
+    pub session: Option<Rc<RefCell<DiffSession>>>,
+    pub side: DiffSide,
+}
+
+impl DiffModeContext {
+    pub fn new() -> Self {
+        DiffModeContext {
+            session: None,
+            side: DiffSide::New,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DiffMode {
+    // add common fields
+}
+
+impl DiffMode {
+    pub fn new() -> Self {
+        dbg_println!("DiffMode");
+        DiffMode {}
+    }
+
+    pub fn register_input_stage_actions<'a>(map: &'a mut InputStageActionMap<'a>) {
+        register_input_stage_action(map, "diff-mode:diff-with-disk", diff_with_disk);
+        register_input_stage_action(map, "diff-mode:diff-with-buffer", diff_with_buffer);
+        register_input_stage_action(map, "diff-mode:diff-with-head", diff_with_head);
+        register_input_stage_action(map, "diff-mode:unified-diff", unified_diff_with_disk);
+        register_input_stage_action(map, "diff-mode:next-hunk", goto_next_hunk);
+        register_input_stage_action(map, "diff-mode:previous-hunk", goto_previous_hunk);
+        register_input_stage_action(map, "diff-mode:apply-hunk", apply_hunk);
+        register_input_stage_action(map, "diff-mode:stage-hunk", stage_hunk);
+        register_input_stage_action(map, "diff-mode:unstage-hunk", unstage_hunk);
+        register_input_stage_action(map, "diff-mode:stop", diff_stop);
+    }
+}
+
+impl Mode for DiffMode {
+    fn name(&self) -> &'static str {
+        "diff-mode"
+    }
+
+    fn build_action_map(&self) -> InputStageActionMap<'static> {
+        let mut map = InputStageActionMap::new();
+        Self::register_input_stage_actions(&mut map);
+        map
+    }
+
+    fn alloc_ctx(&self, _editor: &Editor<'static>) -> Box<dyn Any> {
+        dbg_println!("alloc diff-mode ctx");
+        Box::new(DiffModeContext::new())
+    }
+
+    fn configure_view(
+        &mut self,
+        _editor: &mut Editor<'static>,
+        _env: &mut EditorEnv<'static>,
+        view: &mut View<'static>,
+    ) {
+        {
+            let input_map = build_input_event_map(DIFF_TRIGGER_MAP).unwrap();
+            let mut input_map_stack = view.input_ctx.input_map.as_ref().borrow_mut();
+            input_map_stack.push((self.name(), input_map));
+        }
+
+        // below the selections and marks
+        view.compose_screen_overlay_filters
+            .borrow_mut()
+            .insert(0, Box::new(DiffOverlay::new()));
+
+        view.stage_actions
+            .push((String::from("diff-mode"), diff_mode_stage_action));
+    }
+}
+
+/// Refreshes the diff and synchronizes the scrolling of the other view.
+fn diff_mode_stage_action(
+    editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    pos: editor::StagePosition,
+    stage: editor::Stage,
+) {
+    if (stage, pos) != (editor::Stage::Compositing, editor::StagePosition::Pre) {
+        return;
+    }
+
+    let (session, side, view_id, start_offset) = {
+        let v = view.read();
+        let ctx = v.mode_ctx::<DiffModeContext>("diff-mode");
+        match ctx.session {
+            Some(ref session) => (Rc::clone(session), ctx.side, v.id, v.start_offset),
+            None => return,
+        }
+    };
+
+    let mut session = session.borrow_mut();
+    let changed = session.refresh_in_background(editor);
+
+    if side == DiffSide::Unified {
+        return;
+    }
+
+    if !changed && session.synced == Some((view_id, start_offset)) {
+        return;
+    }
+    session.synced = Some((view_id, start_offset));
+
+    let side = side as usize;
+    let peer_side = 1 - side;
+    let peer = match check_view_by_id(editor, session.view_ids[peer_side]) {
+        Some(peer) => peer,
+        None => return,
+    };
+
+    let line = session.line_at(side, start_offset);
+    let peer_line = map_line(&session.hunks, line, side == DiffSide::Old as usize);
+    peer.write().start_offset = session.line_start(peer_side, peer_line);
+}
+
+fn set_session(
+    editor: &Editor<'static>,
+    id: view::Id,
+    session: &Rc<RefCell<DiffSession>>,
+    side: DiffSide,
+) {
+    if let Some(v) = check_view_by_id(editor, id) {
+        let mut v = v.write();
+        let ctx = v.mode_ctx_mut::<DiffModeContext>("diff-mode");
+        ctx.session = Some(Rc::clone(session));
+        ctx.side = side;
+    }
+}
+
+fn get_session(view: &Rc<RwLock<View<'static>>>) -> Option<(Rc<RefCell<DiffSession>>, DiffSide)> {
+    let v = view.read();
+    let ctx = v.mode_ctx::<DiffModeContext>("diff-mode");
+    let session = ctx.session.as_ref()?;
+    Some((Rc::clone(session), ctx.side))
+}
+
+/// Adds 'buffer' to the editor buffers and setups its modes metadata.
+fn register_buffer(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    buffer: &Arc<RwLock<Buffer<'static>>>,
+) {
+    let id = buffer.read().id;
+    editor.buffer_map.write().insert(id, Arc::clone(buffer));
+
+    let modes = editor.modes.clone();
+    for (_, mode) in modes.borrow().iter() {
+        mode.borrow_mut()
+            .configure_buffer(editor, env, &mut buffer.write());
+    }
+}
+
+/// Returns a buffer with the content of the file of 'buffer' as it is on disk.
+fn create_disk_snapshot(
+    buffer: &Arc<RwLock<Buffer<'static>>>,
+) -> Option<Arc<RwLock<Buffer<'static>>>> {
+    let (name, data) = {
+        let buffer = buffer.read();
+        if buffer.file_name().is_empty() || buffer.storage_size() > MAX_DIFF_SIZE as u64 {
+            return None;
+        }
+        let mut data = vec![];
+        buffer.read_storage(&mut data);
+        (buffer.name.clone(), data)
+    };
+
+    create_snapshot(&format!("{} (on disk)", name), &data)
+}
+
+/// Returns a buffer with the content of the file of 'buffer' in HEAD.
+fn create_head_snapshot(
+    buffer: &Arc<RwLock<Buffer<'static>>>,
+) -> Option<(Arc<RwLock<Buffer<'static>>>, GitFile)> {
+    let (name, file_name) = {
+        let buffer = buffer.read();
+        (buffer.name.clone(), buffer.file_name())
+    };
+
+    let git_file = GitFile::new(&file_name)?;
+    let data = git_file.show("HEAD")?;
+
+    let snapshot = create_snapshot(&format!("{} (HEAD)", name), &data)?;
+    Some((snapshot, git_file))
+}
+
+fn create_snapshot(name: &str, data: &[u8]) -> Option<Arc<RwLock<Buffer<'static>>>> {
+    let snapshot = BufferBuilder::new(BufferKind::File)
+        .buffer_name(name)
+        .internal(true)
+        .use_buffer_log(false)
+        .finalize()?;
+
+    {
+        let mut snapshot = snapshot.write();
+        snapshot.append(data);
+        snapshot.changed = false;
+    }
+    build_index(&snapshot);
+
+    Some(snapshot)
+}
+
+/// Splits 'view' and displays 'old' on the right. 'view' displays the new buffer.
+fn start_side_by_side(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    old: Arc<RwLock<Buffer<'static>>>,
+    snapshot: bool,
+) -> Option<Rc<RefCell<DiffSession>>> {
+    let new = view.read().buffer()?;
+    let view_id = view.read().id;
+
+    if too_large(editor, env, &[Arc::clone(&old), Arc::clone(&new)]) {
+        return None;
+    }
+
+    let clone_id = split_view_with_buffer(
+        editor,
+        env,
+        view,
+        LayoutDirection::Horizontal,
+        Some(Arc::clone(&old)),
+    )?;
+    let peer_id = find_tagged_child(editor, clone_id, "target-view")?;
+
+    let session = Rc::new(RefCell::new(DiffSession::new(
+        [old, new],
+        [peer_id, view_id],
+        snapshot,
+    )));
+    set_session(editor, peer_id, &session, DiffSide::Old);
+    set_session(editor, view_id, &session, DiffSide::New);
+    session.borrow_mut().refresh();
+
+    Some(session)
+}
+
+pub fn diff_with_disk(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if get_session(view).is_some() {
+        return;
+    }
+
+    let buffer = match view.read().buffer() {
+        Some(buffer) => buffer,
+        None => return,
+    };
+
+    if too_large(editor, env, &[Arc::clone(&buffer)]) {
+        return;
+    }
+
+    if let Some(snapshot) = create_disk_snapshot(&buffer) {
+        register_buffer(editor, env, &snapshot);
+        if start_side_by_side(editor, env, view, Arc::clone(&snapshot), true).is_none() {
+            let id = snapshot.read().id;
+            editor.buffer_map.write().remove(&id);
+        }
+    }
+}
+
+/// Compares the buffer of 'view' with the version of its file in HEAD.
+pub fn diff_with_head(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if get_session(view).is_some() {
+        return;
+    }
+
+    let buffer = match view.read().buffer() {
+        Some(buffer) => buffer,
+        None => return,
+    };
+
+    let (snapshot, git_file) = match create_head_snapshot(&buffer) {
+        Some(s) => s,
+        None => {
+            dbg_println!("diff-mode: the file is not tracked by git");
+            return;
+        }
+    };
+
+    register_buffer(editor, env, &snapshot);
+    match start_side_by_side(editor, env, view, Arc::clone(&snapshot), true) {
+        Some(session) => session.borrow_mut().git_file = Some(git_file),
+        None => {
+            let id = snapshot.read().id;
+            editor.buffer_map.write().remove(&id);
+        }
+    }
+}
+
+/// Compares the buffer of 'view' with the most recently displayed other buffer.
+pub fn diff_with_buffer(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if get_session(view).is_some() {
+        return;
+    }
+
+    let buffer_id = match view.read().buffer() {
+        Some(buffer) => buffer.read().id,
+        None => return,
+    };
+
+    let candidates: Vec<view::Id> = editor
+        .recent_views
+        .iter()
+        .chain(editor.active_views.iter())
+        .copied()
+        .collect();
+
+    let other = candidates.iter().find_map(|id| {
+        let target = find_tagged_child(editor, *id, "target-view")?;
+        let buffer = check_view_by_id(editor, target)?.read().buffer()?;
+        let b = buffer.read();
+        if b.id != buffer_id && b.kind == BufferKind::File {
+            Some(Arc::clone(&buffer))
+        } else {
+            None
+        }
+    });
+
+    if let Some(other) = other {
+        start_side_by_side(editor, env, view, other, false);
+    }
+}
+
+/// Opens the unified diff of the buffer of 'view' and its file on disk in a new tab.
+pub fn unified_diff_with_disk(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if get_session(view).is_some() {
+        return;
+    }
+
+    let (buffer, view_id) = match view.read().buffer() {
+        Some(buffer) => (buffer, view.read().id),
+        None => return,
+    };
+
+    if too_large(editor, env, &[Arc::clone(&buffer)]) {
+        return;
+    }
+
+    let snapshot = match create_disk_snapshot(&buffer) {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+
+    let name = buffer.read().name.clone();
+    let unified = match BufferBuilder::new(BufferKind::File)
+        .buffer_name(&format!("*diff* {}", name))
+        .internal(true)
+        .use_buffer_log(false)
+        .finalize()
+    {
+        Some(unified) => unified,
+        None => return,
+    };
+
+    let (file_view_id, ok) = open_doc_add_buffer(editor, env, Arc::clone(&unified));
+    if !ok {
+        return;
+    }
+
+    let mut session = DiffSession::new([snapshot, buffer], [view::Id(0), view_id], true);
+    session.unified = Some((file_view_id, unified));
+    session.refresh();
+    let session = Rc::new(RefCell::new(session));
+
+    if let Some(target) = find_tagged_child(editor, file_view_id, "target-view") {
+        set_session(editor, target, &session, DiffSide::Unified);
+    }
+
+    show_file_view(editor, env, file_view_id);
+}
+
+/// Returns the start offsets of the hunks in the buffer displayed by 'side'.
+fn hunk_offsets(session: &DiffSession, side: DiffSide) -> Vec<u64> {
+    if side == DiffSide::Unified {
+        let mut last_hunk = None;
+        let mut offsets = vec![];
+        for (kind, offset) in session.unified_lines.iter() {
+            let hunk = match kind {
+                UnifiedLine::Removed { hunk, .. } | UnifiedLine::Inserted { hunk, .. } => *hunk,
+                _ => continue,
+            };
+            if last_hunk != Some(hunk) {
+                offsets.push(*offset);
+                last_hunk = Some(hunk);
+            }
+        }
+        return offsets;
+    }
+
+    let side = side as usize;
+    session
+        .hunks
+        .iter()
+        .map(|h| session.line_start(side, side_range(h, side).start))
+        .collect()
+}
+
+fn goto_hunk(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    forward: bool,
+) {
+    let (session, side) = match get_session(view) {
+        Some(s) => s,
+        None => return,
+    };
+
+    let target = {
+        let mut session = session.borrow_mut();
+        session.refresh();
+
+        let offset = {
+            let v = view.read();
+            let tm = v.mode_ctx::<TextModeContext>("text-mode");
+            tm.marks[tm.mark_index].offset
+        };
+
+        let offsets = hunk_offsets(&session, side);
+        if forward {
+            offsets.iter().copied().find(|o| *o > offset)
+        } else {
+            let line_start = if side == DiffSide::Unified {
+                let n = session.unified_lines.partition_point(|l| l.1 <= offset);
+                session
+                    .unified_lines
+                    .get(n.wrapping_sub(1))
+                    .map_or(0, |l| l.1)
+            } else {
+                let side = side as usize;
+                session.line_start(side, session.line_at(side, offset))
+            };
+            offsets.iter().copied().rev().find(|o| *o < line_start)
+        }
+    };
+
+    let target = match target {
+        Some(target) => target,
+        None => return,
+    };
+
+    {
+        let mut v = view.write();
+        let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+        tm.marks.clear();
+        tm.marks.push(Mark::new(target));
+        tm.mark_index = 0;
+    }
+
+    run_text_mode_actions_vec(
+        editor,
+        env,
+        view,
+        &vec![PostInputAction::CenterAroundMainMark],
+    );
+}
+
+pub fn goto_next_hunk(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    goto_hunk(editor, env, view, true);
+}
+
+pub fn goto_previous_hunk(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    goto_hunk(editor, env, view, false);
+}
+
+/// Returns the hunk displayed at 'offset' by the view of 'side'.
+fn hunk_at_offset(session: &DiffSession, side: DiffSide, offset: u64) -> Option<usize> {
+    if side == DiffSide::Unified {
+        let n = session.unified_lines.partition_point(|l| l.1 <= offset);
+        return match session.unified_lines.get(n.wrapping_sub(1)) {
+            Some((UnifiedLine::Header { hunk }, _))
+            | Some((UnifiedLine::Removed { hunk, .. }, _))
+            | Some((UnifiedLine::Inserted { hunk, .. }, _)) => Some(*hunk),
+            _ => None,
+        };
+    }
+
+    let side = side as usize;
+    session.hunk_at(side, session.line_at(side, offset))
+}
+
+/// Side by side: copies the hunk under the main mark to the other buffer.<br/>
+/// Unified: reverts the hunk under the main mark in the new buffer.<br/>
+/// The snapshot of the file on disk is never modified.
+pub fn apply_hunk(
+    editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (session, side) = match get_session(view) {
+        Some(s) => s,
+        None => return,
+    };
+
+    let mut session = session.borrow_mut();
+    session.refresh();
+
+    let offset = {
+        let v = view.read();
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        tm.marks[tm.mark_index].offset
+    };
+
+    let hunk = match hunk_at_offset(&session, side, offset) {
+        Some(hunk) => hunk,
+        None => return,
+    };
+
+    let (from, to) = if side == DiffSide::Unified {
+        (DiffSide::Old as usize, DiffSide::New as usize)
+    } else {
+        let from = side as usize;
+        let to = 1 - from;
+        if session.snapshot && to == DiffSide::Old as usize {
+            dbg_println!("diff-mode: the file on disk is not modified");
+            return;
+        }
+        (from, to)
+    };
+
+    session.copy_hunk(hunk, from, to);
+
+    // the modified buffer may be displayed by the other view
+    let size = session.buffers[to].read().size() as u64;
+    if let Some(v) = check_view_by_id(editor, session.view_ids[to]) {
+        let mut v = v.write();
+        v.start_offset = std::cmp::min(v.start_offset, size);
+        let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+        for m in tm.marks.iter_mut() {
+            m.offset = std::cmp::min(m.offset, size);
+        }
+    }
+
+    session.refresh();
+}
+
+/// Returns the hunk that covers the hunks of 'hunks' touching the lines 'range'
+/// of the old side (or of the new side): the unchanged lines between them are part of it.
+fn covering_hunk(hunks: &[DiffHunk], range: &Range<usize>, old_side: bool) -> Option<DiffHunk> {
+    let touches = |r: &Range<usize>| {
+        if r.is_empty() || range.is_empty() {
+            r.start <= range.end && range.start <= r.end
+        } else {
+            r.start < range.end && range.start < r.end
+        }
+    };
+
+    hunks
+        .iter()
+        .filter(|h| touches(if old_side { &h.old } else { &h.new }))
+        .fold(None, |covering: Option<DiffHunk>, h| match covering {
+            Some(c) => Some(DiffHunk {
+                old: c.old.start..h.old.end,
+                new: c.new.start..h.new.end,
+            }),
+            None => Some(h.clone()),
+        })
+}
+
+/// Adds (or removes if 'reverse') the hunk under the main mark to the git index.<br/>
+/// The diff must compare the buffer with HEAD (diff-mode:diff-with-head).
+/// The patch is built against the content of the index: the hunks already staged
+/// can change its lines.
+fn update_index_with_hunk(view: &Rc<RwLock<View<'static>>>, reverse: bool) -> Result<(), String> {
+    let (session, side) = match get_session(view) {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let mut session = session.borrow_mut();
+    session.refresh();
+
+    let git_file = match session.git_file {
+        Some(ref git_file) => git_file,
+        None => return Err("the buffer is not compared with HEAD".to_owned()),
+    };
+
+    let offset = {
+        let v = view.read();
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        tm.marks[tm.mark_index].offset
+    };
+
+    let hunk = match hunk_at_offset(&session, side, offset) {
+        Some(hunk) => &session.hunks[hunk],
+        None => return Err("no change under the cursor".to_owned()),
+    };
+
+    let index = git_file
+        .show("")
+        .ok_or_else(|| format!("cannot read '{}' in the index", git_file.path))?;
+    let index_lines = line_offsets(&index);
+    let index = split_lines(&index, &index_lines);
+    let head = split_lines(&session.data[0], &session.lines[0]);
+    let work = split_lines(&session.data[1], &session.lines[1]);
+
+    // the index lines of the hunk are replaced by the lines of HEAD (or of the buffer)
+    let (target, target_range) = if reverse {
+        (&head, &hunk.old)
+    } else {
+        (&work, &hunk.new)
+    };
+    let index_hunk = match covering_hunk(&diff_lines(&index, target), target_range, false) {
+        Some(index_hunk) => index_hunk,
+        None if reverse => return Err("the change is not staged".to_owned()),
+        None => return Err("the change is already staged".to_owned()),
+    };
+
+    let patch = git::hunk_patch(
+        &git_file.path,
+        &index,
+        target,
+        &index_hunk,
+        PATCH_CONTEXT_LINES,
+    );
+    git::apply_to_index(&git_file.root, &patch, false)
+}
+
+pub fn stage_hunk(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if let Err(e) = update_index_with_hunk(view, false) {
+        set_command_line_text(editor, env, &format!("cannot stage the hunk: {}", e));
+    }
+}
+
+pub fn unstage_hunk(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if let Err(e) = update_index_with_hunk(view, true) {
+        set_command_line_text(editor, env, &format!("cannot unstage the hunk: {}", e));
+    }
+}
+
+/// Stops the diff: the view of the compared buffer is closed.
+pub fn diff_stop(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (session, side) = match get_session(view) {
+        Some(s) => s,
+        None => return,
+    };
+
+    let session = session.borrow();
+
+    let mut ids = session.view_ids.to_vec();
+    if let Some((file_view_id, _)) = session.unified {
+        if let Some(target) = find_tagged_child(editor, file_view_id, "target-view") {
+            ids.push(target);
+        }
+    }
+    for id in ids {
+        if let Some(v) = check_view_by_id(editor, id) {
+            let mut v = v.write();
+            let ctx = v.mode_ctx_mut::<DiffModeContext>("diff-mode");
+            ctx.session = None;
+        }
+    }
+
+    if side == DiffSide::Unified {
+        if let Some((file_view_id, _)) = session.unified {
+            close_file_view(editor, env, file_view_id);
+        }
+        return;
+    }
+
+    // the old buffer is displayed by the clone
+    if let Some(old_view) = check_view_by_id(editor, session.view_ids[0]) {
+        destroy_view(editor, env, &old_view);
+    }
+
+    if session.snapshot {
+        let id = session.buffers[0].read().id;
+        editor.buffer_map.write().remove(&id);
+    }
+
+    if check_view_by_id(editor, session.view_ids[1]).is_some() {
+        set_focus_on_view_id(editor, env, session.view_ids[1]);
+    }
+}
+
+#[derive(Default)]
+pub struct DiffOverlay {}
+
+impl DiffOverlay {
+    pub fn new() -> Self {
+        DiffOverlay {}
+    }
+}
+
+impl ScreenOverlayFilter<'_> for DiffOverlay {
+    fn name(&self) -> &'static str {
+        "DiffOverlay"
+    }
+
+    /// Displays the diff computed by the executor thread, without waiting for an input.
+    fn setup(
+        &mut self,
+        editor: &Editor<'static>,
+        _env: &mut LayoutEnv,
+        view: &Rc<RwLock<View>>,
+        _parent_view: Option<&View<'static>>,
+    ) {
+        let session = {
+            let v = view.read();
+            let ctx = v.mode_ctx::<DiffModeContext>("diff-mode");
+            match ctx.session {
+                Some(ref session) => Rc::clone(session),
+                None => return,
+            }
+        };
+
+        session.borrow_mut().refresh_in_background(editor);
+    }
+
+    fn finish(&mut self, view: &View, env: &mut LayoutEnv) {
+        if env.screen.is_off_screen {
+            return;
+        }
+
+        let ctx = view.mode_ctx::<DiffModeContext>("diff-mode");
+        if let Some(ref session) = ctx.session {
+            let session = session.borrow();
+            draw_diff_highlights(env.screen, &session.highlights[ctx.side as usize]);
+        }
+    }
+}
+
+fn find_range(ranges: &[(u64, u64, DiffLineKind)], offset: u64) -> Option<DiffLineKind> {
+    let idx = ranges.partition_point(|r| r.1 <= offset);
+    match ranges.get(idx) {
+        Some(r) if r.0 <= offset => Some(r.2),
+        _ => None,
+    }
+}
+
+/// The whole screen line is colored, the intra-line changes are drawn over it.
+fn draw_diff_highlights(screen: &mut Screen, hl: &DiffHighlights) {
+    if hl.lines.is_empty() {
+        return;
+    }
+
+    screen_line_apply(screen, |_l, line| {
+        let offset = match line.iter().find_map(|c| c.cpi.offset) {
+            Some(offset) => offset,
+            None => return true,
+        };
+
+        if let Some(kind) = find_range(&hl.lines, offset) {
+            let color = kind.line_color();
+            for cell in line.iter_mut() {
+                cell.cpi.style.bg_color = color;
+            }
+        }
+
+        for cell in line.iter_mut() {
+            if let Some(offset) = cell.cpi.offset {
+                if let Some(kind) = find_range(&hl.inline, offset) {
+                    cell.cpi.style.bg_color = kind.inline_color();
+                }
+            }
+        }
+
+        true
+    });
+}
+
+#[test]
+fn test_diff_compute_diff() {
+    let old = create_snapshot("old", b"a\nb\nc\n").unwrap();
+    let new = create_snapshot("new", b"a\nB\nc\n").unwrap();
+
+    let diff = compute_diff(&[Arc::clone(&old), Arc::clone(&new)]);
+    assert_eq!(diff.lines, [vec![0, 2, 4, 6], vec![0, 2, 4, 6]]);
+    assert_eq!(diff.hunks.len(), 1);
+    assert_eq!(side_range(&diff.hunks[0], 0), 1..2);
+    assert_eq!(side_range(&diff.hunks[0], 1), 1..2);
+
+    // the large buffers are not compared
+    new.write().append(&vec![b'x'; MAX_DIFF_SIZE]);
+    let diff = compute_diff(&[old, Arc::clone(&new)]);
+    assert_eq!(diff.revisions[1], new.read().nr_changes());
+    assert!(diff.data[1].is_empty());
+    assert!(diff.hunks.is_empty());
+}
+
+#[test]
+fn test_diff_covering_hunk() {
+    let lines = |text: &'static str| -> Vec<&'static [u8]> {
+        text.split_inclusive('\n').map(|l| l.as_bytes()).collect()
+    };
+
+    // the change of line 2 is staged, the change of line 4 is not
+    let head = lines("1\n2\n3\n4\n5\n");
+    let index = lines("1\ntwo\n3\n4\n5\n");
+    let work = lines("1\ntwo\n3\nfour\n5\n");
+
+    let hunks = diff_lines(&head, &work);
+    assert_eq!(hunks.len(), 2);
+
+    // stage: the patch applies to the index, its context has the staged line
+    let unstaged = diff_lines(&index, &work);
+    assert_eq!(covering_hunk(&unstaged, &hunks[0].new, false), None);
+    let hunk = covering_hunk(&unstaged, &hunks[1].new, false).unwrap();
+    assert_eq!((hunk.old.clone(), hunk.new.clone()), (3..4, 3..4));
+    let patch = git::hunk_patch("f", &index, &work, &hunk, 3);
+    let patch = String::from_utf8(patch).unwrap();
+    assert!(patch.contains("@@ -1,5 +1,5 @@\n 1\n two\n 3\n-4\n+four\n 5\n"));
+
+    // unstage: the index line is replaced by the line of HEAD
+    let staged = diff_lines(&index, &head);
+    assert_eq!(covering_hunk(&staged, &hunks[1].old, false), None);
+    let hunk = covering_hunk(&staged, &hunks[0].old, false).unwrap();
+    assert_eq!((hunk.old, hunk.new), (1..2, 1..2));
+
+    // the hunks touching the range are merged
+    let hunks = [
+        DiffHunk {
+            old: 1..2,
+            new: 1..1,
+        },
+        DiffHunk {
+            old: 4..4,
+            new: 3..5,
+        },
+    ];
+    let hunk = covering_hunk(&hunks, &(1..4), false).unwrap();
+    assert_eq!((hunk.old, hunk.new), (1..4, 1..5));
+}
@@ -1,90 +1,323 @@
-use parking_lot::RwLock;
-use std::rc::Rc;
+//! Background directory listing used by the dir-mode.
 
 use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Instant;
+use std::time::UNIX_EPOCH;
 
+use crate::core::event::pending_input_event_inc;
+use crate::core::event::Event;
+use crate::core::event::Message;
 use crate::core::Editor;
-use crate::core::EditorEnv;
 
-use crate::core::view::ContentFilter;
-use crate::core::view::FilterData;
-use crate::core::view::FilterIo;
-use crate::core::view::LayoutEnv;
+/// number of entries sent at once by the reader
+const DIR_FETCH_BATCH_SIZE: usize = 4096;
 
-use crate::core::codepointinfo::TextStyle;
+/// minimum delay between two screen refreshes requested by the reader
+const DIR_FETCH_REFRESH_MS: u128 = 100;
 
-use crate::core::view::View;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntryInfo {
+    pub name: String,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub size: u64,
+    /// unix permission bits
+    pub mode: u32,
+    /// seconds since UNIX_EPOCH
+    pub mtime: u64,
+}
 
-pub struct DirectoryReader {}
+impl DirEntryInfo {
+    /// The ".." entry
+    pub fn parent() -> Self {
+        DirEntryInfo {
+            name: "..".to_owned(),
+            is_dir: true,
+            is_symlink: false,
+            size: 0,
+            mode: 0,
+            mtime: 0,
+        }
+    }
 
-impl DirectoryReader {
-    pub fn new() -> Self {
-        DirectoryReader {}
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        let link_meta = fs::symlink_metadata(path).ok()?;
+        // follow the symlinks
+        let meta = fs::metadata(path).unwrap_or_else(|_| link_meta.clone());
+
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+
+        Some(DirEntryInfo {
+            name,
+            is_dir: meta.is_dir(),
+            is_symlink: link_meta.file_type().is_symlink(),
+            size: meta.len(),
+            mode: permission_bits(&meta),
+            mtime,
+        })
     }
 }
 
-impl ContentFilter<'_> for DirectoryReader {
-    fn name(&self) -> &'static str {
-        &"DirectoryReader"
-    }
+#[cfg(unix)]
+fn permission_bits(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode()
+}
 
-    fn setup(
-        &mut self,
-        _editor: &mut Editor<'static>,
-        _editor_env: &mut EditorEnv<'static>,
-        _env: &mut LayoutEnv,
-        _view: &Rc<RwLock<View>>,
-        _parent_view: Option<&View<'static>>,
-    ) {
+#[cfg(not(unix))]
+fn permission_bits(meta: &fs::Metadata) -> u32 {
+    if meta.permissions().readonly() {
+        0o444
+    } else {
+        0o644
     }
+}
+
+pub enum DirFetchEvent {
+    Entries(Vec<DirEntryInfo>),
+    Error(String),
+    Done,
+}
+
+/// The **DirectoryReader** lists a directory in the editor's executor thread
+/// and sends the entries by batches, the ui is refreshed when new entries are available.<br/>
+/// The listing is aborted when the reader is dropped.
+pub struct DirectoryReader {
+    rx: Receiver<DirFetchEvent>,
+    abort: Arc<AtomicBool>,
+    pub done: bool,
+}
+
+impl DirectoryReader {
+    pub fn start(editor: &Editor<'static>, path: &Path) -> Self {
+        let (tx, rx) = channel();
+        let abort = Arc::new(AtomicBool::new(false));
+
+        let path = path.to_path_buf();
+        let core_tx = editor.core_tx.clone();
+        let task_abort = Arc::clone(&abort);
 
-    fn run(
-        &mut self,
-        view: &View,
-        env: &mut LayoutEnv,
-        _no_input: &[FilterIo],
-        filter_out: &mut Vec<FilterIo>,
-    ) {
-        let buffer = view.buffer.clone();
-        if let Some(ref buffer) = buffer {
-            env.quit = true;
-
-            let mut listing: Vec<String> = vec![];
-
-            let path = buffer.read().name.clone();
-            let path = PathBuf::from(path);
-
-            match fs::read_dir(&path) {
-                Ok(path) => {
-                    for e in path {
-                        let s = e.unwrap().path().to_str().unwrap().to_owned();
-                        listing.push(s);
+        let _ = editor.executor_tx.send((
+            0,
+            Box::new(move || {
+                let refresh = || {
+                    let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+                    let msg = Message::new(0, 0, ts, Event::RefreshView);
+                    pending_input_event_inc(1);
+                    core_tx.send(msg).unwrap_or(());
+                };
+
+                let mut last_refresh = Instant::now();
+                let ret = fetch_directory(&path, &task_abort, |batch| {
+                    if tx.send(DirFetchEvent::Entries(batch)).is_err() {
+                        return false;
                     }
+                    if last_refresh.elapsed().as_millis() >= DIR_FETCH_REFRESH_MS {
+                        last_refresh = Instant::now();
+                        refresh();
+                    }
+                    true
+                });
+
+                let _ = match ret {
+                    Ok(()) => tx.send(DirFetchEvent::Done),
+                    Err(e) => tx.send(DirFetchEvent::Error(e)),
+                };
+                refresh();
+            }),
+        ));
+
+        DirectoryReader {
+            rx,
+            abort,
+            done: false,
+        }
+    }
+
+    /// Append the received entries to 'entries'.<br/>
+    /// Returns true if something was received.
+    pub fn poll(&mut self, entries: &mut Vec<DirEntryInfo>, error: &mut Option<String>) -> bool {
+        let mut received = false;
+        while let Ok(evt) = self.rx.try_recv() {
+            received = true;
+            match evt {
+                DirFetchEvent::Entries(mut v) => entries.append(&mut v),
+                DirFetchEvent::Error(e) => {
+                    *error = Some(e);
+                    self.done = true;
                 }
-                Err(e) => {
-                    let s = format!("cannot read {:?} content {:?}\n", path, e);
-                    listing.push(s);
-                }
+                DirFetchEvent::Done => self.done = true,
             }
+        }
+        received
+    }
+}
+
+impl Drop for DirectoryReader {
+    fn drop(&mut self) {
+        self.abort.store(true, Ordering::Relaxed);
+    }
+}
 
-            let mut raw_data: Vec<u8> = vec![];
+/// Read 'path' content and call 'on_batch' for each batch of entries.<br/>
+/// Stops when 'abort' is set or when 'on_batch' returns false.
+pub fn fetch_directory<F: FnMut(Vec<DirEntryInfo>) -> bool>(
+    path: &PathBuf,
+    abort: &AtomicBool,
+    mut on_batch: F,
+) -> Result<(), String> {
+    let rd = fs::read_dir(path).map_err(|e| format!("cannot read {:?}: {}", path, e))?;
 
-            listing.sort();
-            for s in &mut listing {
-                raw_data.append(&mut s.as_bytes().to_owned());
-                raw_data.push(b'\n');
-            }
+    let mut batch = Vec::with_capacity(DIR_FETCH_BATCH_SIZE);
+    for e in rd.flatten() {
+        if abort.load(Ordering::Relaxed) {
+            return Ok(());
+        }
 
-            let len = raw_data.len();
+        if let Some(info) = DirEntryInfo::from_path(&e.path()) {
+            batch.push(info);
+        }
 
-            (*filter_out).push(FilterIo {
-                metadata: true,
-                style: TextStyle::new(),
-                offset: Some(0),
-                size: len,
-                data: FilterData::ByteArray { vec: raw_data },
-            });
+        if batch.len() == DIR_FETCH_BATCH_SIZE {
+            let v = std::mem::replace(&mut batch, Vec::with_capacity(DIR_FETCH_BATCH_SIZE));
+            if !on_batch(v) {
+                return Ok(());
+            }
         }
     }
+
+    if !batch.is_empty() {
+        on_batch(batch);
+    }
+
+    Ok(())
+}
+
+/// "drwxr-xr-x"
+pub fn format_permissions(e: &DirEntryInfo) -> String {
+    let mut s = String::with_capacity(10);
+    s.push(if e.is_symlink {
+        'l'
+    } else if e.is_dir {
+        'd'
+    } else {
+        '-'
+    });
+
+    for shift in [6, 3, 0] {
+        let bits = (e.mode >> shift) & 0o7;
+        s.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        s.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        s.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    s
+}
+
+/// Human readable size: "512", "1.5K", "12M" ...
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+
+    if size < 1024 {
+        return size.to_string();
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+/// "YYYY-MM-DD HH:MM" (UTC)
+pub fn format_mtime(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // civil from days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60
+    )
+}
+
+#[test]
+fn test_dir_fetch_format() {
+    let mut e = DirEntryInfo::parent();
+    e.mode = 0o755;
+    assert_eq!(format_permissions(&e), "drwxr-xr-x");
+    e.is_dir = false;
+    e.mode = 0o640;
+    assert_eq!(format_permissions(&e), "-rw-r-----");
+
+    assert_eq!(format_size(512), "512");
+    assert_eq!(format_size(1536), "1.5K");
+    assert_eq!(format_size(20 * 1024 * 1024), "20M");
+
+    assert_eq!(format_mtime(0), "1970-01-01 00:00");
+    assert_eq!(format_mtime(951782400 + 3600 + 120), "2000-02-29 01:02");
+}
+
+#[test]
+fn test_dir_fetch_batches() {
+    let dir = std::env::temp_dir().join(format!("unlimited-dir-fetch-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let n = DIR_FETCH_BATCH_SIZE + 10;
+    for i in 0..n {
+        fs::write(dir.join(format!("f{}", i)), "").unwrap();
+    }
+
+    let abort = AtomicBool::new(false);
+    let mut batches = vec![];
+    fetch_directory(&dir, &abort, |b| {
+        batches.push(b.len());
+        true
+    })
+    .unwrap();
+    assert_eq!(batches, vec![DIR_FETCH_BATCH_SIZE, 10]);
+
+    // abort after the first batch
+    let mut count = 0;
+    fetch_directory(&dir, &abort, |_| {
+        count += 1;
+        false
+    })
+    .unwrap();
+    assert_eq!(count, 1);
+
+    let _ = fs::remove_dir_all(&dir);
 }
@@ -1,16 +1,40 @@
 use std::any::Any;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::TryRecvError;
+
+use parking_lot::RwLock;
+use std::rc::Rc;
 
 use super::Mode;
 
+use crate::core::buffer;
+use crate::core::buffer::BufferBuilder;
+use crate::core::buffer::BufferKind;
+
+use crate::core::editor;
+use crate::core::editor::register_input_stage_action;
 use crate::core::editor::Editor;
 use crate::core::editor::EditorEnv;
 use crate::core::editor::InputStageActionMap;
 
-use crate::core::modes::text_mode::ScreenFilter;
+use crate::core::event::input_map::build_input_event_map;
+use crate::core::event::*;
 
-use crate::core::view::View;
+use crate::core::modes::core_mode::set_command_line_text;
+use crate::core::modes::open_doc::open_doc_add_buffer;
+use crate::core::modes::open_doc::open_doc_load_path;
+use crate::core::modes::tab_bar_mode::close_file_view;
+use crate::core::modes::tab_bar_mode::show_file_view;
 
-use crate::core::screen::screen_apply;
+use crate::core::view;
+use crate::core::view::FilterIo;
+use crate::core::view::View;
 
 use crate::dbg_println;
 
@@ -18,11 +42,246 @@ use crate::core::view::ContentFilter;
 
 use crate::core::view::LayoutEnv;
 
+use crate::core::codepointinfo::CodepointInfo;
 use crate::core::codepointinfo::TextStyle;
 
+use crate::core::modes::dir_fetch::format_mtime;
+use crate::core::modes::dir_fetch::format_permissions;
+use crate::core::modes::dir_fetch::format_size;
+use crate::core::modes::dir_fetch::DirEntryInfo;
 use crate::core::modes::dir_fetch::DirectoryReader;
 
-pub struct DirModeContext {}
+static DIR_MODE_INPUT_MAP: &str = r#"
+[
+  {
+    "events": [
+     { "in": [{ "key": "Up" } ],                 "action": "dir-mode:select-previous" },
+     { "in": [{ "key": "Down" } ],               "action": "dir-mode:select-next" },
+     { "in": [{ "key": "PageUp" } ],             "action": "dir-mode:select-previous-page" },
+     { "in": [{ "key": "PageDown" } ],           "action": "dir-mode:select-next-page" },
+     { "in": [{ "key": "Home" } ],               "action": "dir-mode:select-first" },
+     { "in": [{ "key": "End" } ],                "action": "dir-mode:select-last" },
+     { "in": [{ "key": "\n" } ],                 "action": "dir-mode:open" },
+     { "in": [{ "key": "BackSpace" } ],          "action": "dir-mode:parent-directory" },
+     { "in": [{ "key": "^" } ],                  "action": "dir-mode:parent-directory" },
+     { "in": [{ "key": "s" } ],                  "action": "dir-mode:cycle-sort" },
+     { "in": [{ "key": "S" } ],                  "action": "dir-mode:reverse-sort" },
+     { "in": [{ "key": "d" } ],                  "action": "dir-mode:mark-delete" },
+     { "in": [{ "key": "c" } ],                  "action": "dir-mode:mark-copy" },
+     { "in": [{ "key": "r" } ],                  "action": "dir-mode:mark-rename" },
+     { "in": [{ "key": "u" } ],                  "action": "dir-mode:unmark" },
+     { "in": [{ "key": "U" } ],                  "action": "dir-mode:unmark-all" },
+     { "in": [{ "key": "x" } ],                  "action": "dir-mode:execute-marks" },
+     { "in": [{ "key": "g" } ],                  "action": "dir-mode:refresh" },
+     { "in": [{ "key": "E" } ],                  "action": "dir-mode:edit-names" },
+     { "in": [{ "key": "A" } ],                  "action": "dir-mode:apply-names" },
+     { "in": [{ "button-release": "0" } ],       "action": "dir-mode:button-release" },
+     { "in": [{ "wheel": "Up" } ],               "action": "dir-mode:scroll-up" },
+     { "in": [{ "wheel": "Down" } ],             "action": "dir-mode:scroll-down" }
+    ]
+  }
+]"#;
+
+static DIR_MODE_PROMPT_MAP: &str = r#"
+[
+  {
+    "events": [
+     { "default": [],              "action": "dir-mode:prompt-input" }
+   ]
+  }
+
+]"#;
+
+/// number of lines scrolled by the mouse wheel
+const WHEEL_SCROLL_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirSortKey {
+    Name,
+    Size,
+    Mtime,
+}
+
+impl DirSortKey {
+    fn next(self) -> Self {
+        match self {
+            DirSortKey::Name => DirSortKey::Size,
+            DirSortKey::Size => DirSortKey::Mtime,
+            DirSortKey::Mtime => DirSortKey::Name,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DirSortKey::Name => "name",
+            DirSortKey::Size => "size",
+            DirSortKey::Mtime => "time",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirMark {
+    Delete,
+    Copy,
+    Rename,
+}
+
+impl DirMark {
+    fn to_char(self) -> char {
+        match self {
+            DirMark::Delete => 'D',
+            DirMark::Copy => 'C',
+            DirMark::Rename => 'R',
+        }
+    }
+}
+
+/// The operation waiting for a target or a confirmation.
+#[derive(Debug, Clone)]
+pub enum DirPrompt {
+    CopyTo { names: Vec<String> },
+    MoveTo { names: Vec<String> },
+    ConfirmDelete { names: Vec<String> },
+    ConfirmRenames { renames: Vec<(String, String)> },
+}
+
+/// The names being edited as text in a separate buffer.
+pub struct DirNamesEdit {
+    pub buffer_id: buffer::Id,
+    pub view_id: view::Id,
+    pub names: Vec<String>,
+}
+
+pub struct DirModeContext {
+    pub path: PathBuf,
+    pub reader: Option<DirectoryReader>,
+    /// the first entry is ".." if the directory has a parent
+    pub entries: Vec<DirEntryInfo>,
+    pub error: Option<String>,
+    pub sort: DirSortKey,
+    pub reverse: bool,
+    /// the entries must be sorted before the next composition
+    pub need_sort: bool,
+    pub marks: HashMap<String, DirMark>,
+    pub selected: usize,
+    /// the entry to select when it is received
+    pub pending_select: Option<String>,
+    pub first_line: usize,
+    /// number of displayed entries at the last composition
+    pub height: usize,
+    pub prompt: Option<DirPrompt>,
+    pub prompt_text: String,
+    /// the prompts to display after the current one
+    pub pending_prompts: Vec<(DirPrompt, String)>,
+    pub names_edit: Option<DirNamesEdit>,
+    /// the validated prompt whose operation runs in another thread, and its result
+    pub operation: Option<(DirPrompt, Receiver<Result<usize, String>>)>,
+}
+
+impl DirModeContext {
+    fn has_parent(&self) -> bool {
+        self.entries.first().is_some_and(|e| e.name == "..")
+    }
+
+    fn selected_entry(&self) -> Option<&DirEntryInfo> {
+        self.entries.get(self.selected)
+    }
+
+    fn select(&mut self, idx: usize) {
+        self.selected = std::cmp::min(idx, self.entries.len().saturating_sub(1));
+
+        // keep the selection visible
+        let height = std::cmp::max(1, self.height);
+        if self.selected < self.first_line {
+            self.first_line = self.selected;
+        } else if self.selected >= self.first_line + height {
+            self.first_line = self.selected + 1 - height;
+        }
+    }
+
+    fn scroll(&mut self, forward: bool, n: usize) {
+        if forward {
+            let max = self.entries.len().saturating_sub(1);
+            self.first_line = std::cmp::min(self.first_line + n, max);
+        } else {
+            self.first_line = self.first_line.saturating_sub(n);
+        }
+    }
+
+    /// Restart the listing of 'path'.
+    fn load(&mut self, editor: &Editor<'static>, path: PathBuf) {
+        let keep_selection = path == self.path;
+        if keep_selection && self.pending_select.is_none() {
+            self.pending_select = self.selected_entry().map(|e| e.name.clone());
+        }
+
+        self.path = path;
+        self.entries.clear();
+        if self.path.parent().is_some() {
+            self.entries.push(DirEntryInfo::parent());
+        }
+        self.error = None;
+        self.need_sort = true;
+        self.reader = Some(DirectoryReader::start(editor, &self.path));
+
+        if !keep_selection {
+            self.marks.clear();
+            self.pending_select = None;
+            self.selected = 0;
+            self.first_line = 0;
+        }
+    }
+
+    fn sort_entries(&mut self) {
+        let selected = self
+            .pending_select
+            .clone()
+            .or_else(|| self.selected_entry().map(|e| e.name.clone()));
+
+        let start = usize::from(self.has_parent());
+        sort_dir_entries(&mut self.entries[start..], self.sort, self.reverse);
+        self.need_sort = false;
+
+        if let Some(name) = selected {
+            if let Some(idx) = self.entries.iter().position(|e| e.name == name) {
+                self.select(idx);
+                self.pending_select = None;
+            }
+        }
+
+        if self.reader.as_ref().is_some_and(|r| r.done) {
+            self.pending_select = None;
+        }
+    }
+
+    fn marked_names(&self, mark: DirMark) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .marks
+            .iter()
+            .filter(|(_, m)| **m == mark)
+            .map(|(n, _)| n.clone())
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+/// Sort the entries: directories first when sorting by name.
+pub fn sort_dir_entries(entries: &mut [DirEntryInfo], key: DirSortKey, reverse: bool) {
+    entries.sort_by(|a, b| {
+        let ord = match key {
+            DirSortKey::Name => b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)),
+            DirSortKey::Size => b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)),
+            DirSortKey::Mtime => b.mtime.cmp(&a.mtime).then_with(|| a.name.cmp(&b.name)),
+        };
+        if reverse {
+            ord.reverse()
+        } else {
+            ord
+        }
+    });
+}
 
 pub struct DirMode {}
 
@@ -32,7 +291,35 @@ impl DirMode {
         DirMode {}
     }
 
-    pub fn register_input_stage_actions<'a>(_map: &'a mut InputStageActionMap<'a>) {}
+    pub fn register_input_stage_actions<'a>(map: &'a mut InputStageActionMap<'a>) {
+        register_input_stage_action(map, "dir-mode:select-previous", dir_mode_select_previous);
+        register_input_stage_action(map, "dir-mode:select-next", dir_mode_select_next);
+        register_input_stage_action(
+            map,
+            "dir-mode:select-previous-page",
+            dir_mode_select_previous_page,
+        );
+        register_input_stage_action(map, "dir-mode:select-next-page", dir_mode_select_next_page);
+        register_input_stage_action(map, "dir-mode:select-first", dir_mode_select_first);
+        register_input_stage_action(map, "dir-mode:select-last", dir_mode_select_last);
+        register_input_stage_action(map, "dir-mode:open", dir_mode_open);
+        register_input_stage_action(map, "dir-mode:parent-directory", dir_mode_parent_directory);
+        register_input_stage_action(map, "dir-mode:cycle-sort", dir_mode_cycle_sort);
+        register_input_stage_action(map, "dir-mode:reverse-sort", dir_mode_reverse_sort);
+        register_input_stage_action(map, "dir-mode:mark-delete", dir_mode_mark_delete);
+        register_input_stage_action(map, "dir-mode:mark-copy", dir_mode_mark_copy);
+        register_input_stage_action(map, "dir-mode:mark-rename", dir_mode_mark_rename);
+        register_input_stage_action(map, "dir-mode:unmark", dir_mode_unmark);
+        register_input_stage_action(map, "dir-mode:unmark-all", dir_mode_unmark_all);
+        register_input_stage_action(map, "dir-mode:execute-marks", dir_mode_execute_marks);
+        register_input_stage_action(map, "dir-mode:refresh", dir_mode_refresh);
+        register_input_stage_action(map, "dir-mode:edit-names", dir_mode_edit_names);
+        register_input_stage_action(map, "dir-mode:apply-names", dir_mode_apply_names);
+        register_input_stage_action(map, "dir-mode:button-release", dir_mode_button_release);
+        register_input_stage_action(map, "dir-mode:scroll-up", dir_mode_scroll_up);
+        register_input_stage_action(map, "dir-mode:scroll-down", dir_mode_scroll_down);
+        register_input_stage_action(map, "dir-mode:prompt-input", dir_mode_prompt_input);
+    }
 }
 
 impl<'a> Mode for DirMode {
@@ -41,7 +328,25 @@ impl<'a> Mode for DirMode {
     }
 
     fn alloc_ctx(&self, _editor: &Editor) -> Box<dyn Any> {
-        let ctx = DirModeContext {};
+        let ctx = DirModeContext {
+            path: PathBuf::new(),
+            reader: None,
+            entries: vec![],
+            error: None,
+            sort: DirSortKey::Name,
+            reverse: false,
+            need_sort: false,
+            marks: HashMap::new(),
+            selected: 0,
+            pending_select: None,
+            first_line: 0,
+            height: 0,
+            prompt: None,
+            prompt_text: String::new(),
+            pending_prompts: vec![],
+            names_edit: None,
+            operation: None,
+        };
         Box::new(ctx)
     }
 
@@ -57,33 +362,900 @@ impl<'a> Mode for DirMode {
         _env: &mut EditorEnv<'static>,
         view: &mut View<'static>,
     ) {
-        // let ctx = view.mode_ctx_mut::<DirModeContext>("dir-mode");
+        let input_map = build_input_event_map(DIR_MODE_INPUT_MAP).unwrap();
+        let mut input_map_stack = view.input_ctx.input_map.as_ref().borrow_mut();
+        input_map_stack.push((self.name(), input_map));
 
-        // mandatory directory reader
         view.compose_content_filters
             .borrow_mut()
-            .push(Box::new(DirectoryReader::new()));
+            .push(Box::new(DirModeCompose::new()));
+
+        view.stage_actions
+            .push((String::from("dir-mode"), dir_mode_stage_action));
+    }
+}
 
-        let mut screen_filter = ScreenFilter::new();
-        screen_filter.display_eof = false;
+/// Handles the end of the file operations before the composition.
+fn dir_mode_stage_action(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    pos: editor::StagePosition,
+    stage: editor::Stage,
+) {
+    if (stage, pos) != (editor::Stage::Compositing, editor::StagePosition::Pre) {
+        return;
+    }
 
-        view.compose_content_filters
-            .borrow_mut()
-            .push(Box::new(screen_filter));
+    let done = with_dir_ctx(view, |ctx| {
+        let ret = match ctx.operation {
+            Some((_, ref rx)) => match rx.try_recv() {
+                Ok(ret) => ret,
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => Err("the operation was interrupted".to_owned()),
+            },
+            None => return None,
+        };
+        ctx.operation.take().map(|(prompt, _)| (prompt, ret))
+    });
+
+    if let Some((prompt, ret)) = done {
+        finish_operation(editor, env, view, prompt, ret);
+    }
+}
+
+fn with_dir_ctx<R, F: FnOnce(&mut DirModeContext) -> R>(
+    view: &Rc<RwLock<View<'static>>>,
+    f: F,
+) -> R {
+    let mut v = view.write();
+    let ctx = v.mode_ctx_mut::<DirModeContext>("dir-mode");
+    f(ctx)
+}
+
+pub fn dir_mode_select_previous(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| ctx.select(ctx.selected.saturating_sub(1)));
+}
+
+pub fn dir_mode_select_next(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| ctx.select(ctx.selected + 1));
+}
+
+pub fn dir_mode_select_previous_page(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| {
+        let n = std::cmp::max(1, ctx.height.saturating_sub(1));
+        ctx.select(ctx.selected.saturating_sub(n))
+    });
+}
+
+pub fn dir_mode_select_next_page(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| {
+        let n = std::cmp::max(1, ctx.height.saturating_sub(1));
+        ctx.select(ctx.selected + n)
+    });
+}
+
+pub fn dir_mode_select_first(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| ctx.select(0));
+}
+
+pub fn dir_mode_select_last(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| ctx.select(ctx.entries.len()));
+}
+
+/// Display 'path' in the view: the buffer is renamed.
+fn dir_mode_change_directory(
+    editor: &mut Editor<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    path: PathBuf,
+) {
+    let path = fs::canonicalize(&path).unwrap_or(path);
+
+    let mut v = view.write();
+    if let Some(buffer) = v.buffer() {
+        buffer.write().name = path.to_string_lossy().to_string();
+    }
+    let ctx = v.mode_ctx_mut::<DirModeContext>("dir-mode");
+    ctx.load(editor, path);
+}
+
+/// Open the selected file, or display the selected directory.
+pub fn dir_mode_open(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (path, is_dir) = match with_dir_ctx(view, |ctx| {
+        let e = ctx.selected_entry()?;
+        Some((ctx.path.join(&e.name), e.is_dir))
+    }) {
+        Some(p) => p,
+        None => return,
+    };
+
+    if is_dir {
+        dir_mode_change_directory(editor, view, path);
+        return;
+    }
+
+    let (id, ok) = open_doc_load_path(editor, env, &path.to_string_lossy());
+    if ok {
+        show_file_view(editor, env, id);
+    }
+}
+
+pub fn dir_mode_parent_directory(
+    editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (parent, name) = match with_dir_ctx(view, |ctx| {
+        let name = ctx.path.file_name()?.to_string_lossy().to_string();
+        Some((ctx.path.parent()?.to_path_buf(), name))
+    }) {
+        Some(p) => p,
+        None => return,
+    };
+
+    dir_mode_change_directory(editor, view, parent);
+
+    // select the directory we come from when it is received
+    with_dir_ctx(view, |ctx| ctx.pending_select = Some(name));
+}
+
+pub fn dir_mode_cycle_sort(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| {
+        ctx.sort = ctx.sort.next();
+        ctx.need_sort = true;
+    });
+}
+
+pub fn dir_mode_reverse_sort(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| {
+        ctx.reverse = !ctx.reverse;
+        ctx.need_sort = true;
+    });
+}
+
+fn mark_selected(view: &Rc<RwLock<View<'static>>>, mark: Option<DirMark>) {
+    with_dir_ctx(view, |ctx| {
+        let name = match ctx.selected_entry() {
+            Some(e) if e.name != ".." => e.name.clone(),
+            _ => return,
+        };
+        match mark {
+            Some(mark) => ctx.marks.insert(name, mark),
+            None => ctx.marks.remove(&name),
+        };
+        ctx.select(ctx.selected + 1);
+    });
+}
+
+pub fn dir_mode_mark_delete(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    mark_selected(view, Some(DirMark::Delete));
+}
+
+pub fn dir_mode_mark_copy(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    mark_selected(view, Some(DirMark::Copy));
+}
+
+pub fn dir_mode_mark_rename(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    mark_selected(view, Some(DirMark::Rename));
+}
+
+pub fn dir_mode_unmark(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    mark_selected(view, None);
+}
+
+pub fn dir_mode_unmark_all(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| ctx.marks.clear());
+}
+
+pub fn dir_mode_refresh(
+    editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| {
+        let path = ctx.path.clone();
+        ctx.load(editor, path);
+    });
+}
+
+/// Select the clicked entry, a click on the selected entry opens it.
+pub fn dir_mode_button_release(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let y = {
+        let v = view.read();
+        match v.input_ctx.trigger.last() {
+            Some(InputEvent::ButtonRelease(ButtonEvent { y, .. })) => std::cmp::max(0, *y) as usize,
+            _ => return,
+        }
+    };
+
+    // the first line is the header
+    if y == 0 {
+        return;
+    }
+
+    let open = with_dir_ctx(view, |ctx| {
+        let idx = ctx.first_line + y - 1;
+        if idx >= ctx.entries.len() {
+            return false;
+        }
+        let open = idx == ctx.selected;
+        ctx.select(idx);
+        open
+    });
+
+    if open {
+        dir_mode_open(editor, env, view);
+    }
+}
+
+pub fn dir_mode_scroll_up(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| ctx.scroll(false, WHEEL_SCROLL_LINES));
+}
+
+pub fn dir_mode_scroll_down(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_dir_ctx(view, |ctx| ctx.scroll(true, WHEEL_SCROLL_LINES));
+}
+
+fn prompt_message(prompt: &DirPrompt, text: &str) -> String {
+    match prompt {
+        DirPrompt::CopyTo { names } => format!("Copy {} entries to: {}", names.len(), text),
+        DirPrompt::MoveTo { names } => format!("Rename/move {} entries to: {}", names.len(), text),
+        DirPrompt::ConfirmDelete { names } => format!("Delete {} entries? y/n", names.len()),
+        DirPrompt::ConfirmRenames { renames } => format!("Rename {} entries? y/n", renames.len()),
+    }
+}
+
+fn start_prompt(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    prompt: DirPrompt,
+    text: String,
+) {
+    if view::get_command_view_id(editor, env).is_none() {
+        return;
+    }
+
+    // one operation at a time
+    let busy = with_dir_ctx(view, |ctx| {
+        let busy = ctx.operation.is_some();
+        if busy {
+            ctx.pending_prompts.clear();
+        }
+        busy
+    });
+    if busy {
+        set_command_line_text(editor, env, "Wait for the end of the current operation");
+        return;
+    }
+
+    set_command_line_text(editor, env, &prompt_message(&prompt, &text));
+
+    let mut v = view.write();
+    v.input_ctx.stack_pos = None;
+    {
+        let input_map = build_input_event_map(DIR_MODE_PROMPT_MAP).unwrap();
+        let mut input_map_stack = v.input_ctx.input_map.as_ref().borrow_mut();
+        input_map_stack.push(("dir-mode", input_map));
     }
+
+    let ctx = v.mode_ctx_mut::<DirModeContext>("dir-mode");
+    ctx.prompt = Some(prompt);
+    ctx.prompt_text = text;
+}
+
+fn stop_prompt(view: &Rc<RwLock<View<'static>>>) -> Option<(DirPrompt, String)> {
+    let mut v = view.write();
+    {
+        let mut input_map_stack = v.input_ctx.input_map.as_ref().borrow_mut();
+        input_map_stack.pop();
+    }
+    let ctx = v.mode_ctx_mut::<DirModeContext>("dir-mode");
+    let text = std::mem::take(&mut ctx.prompt_text);
+    ctx.prompt.take().map(|p| (p, text))
+}
+
+/// Ask for the copy/rename targets then for the deletion confirmation.
+pub fn dir_mode_execute_marks(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let mut prompts = with_dir_ctx(view, |ctx| {
+        let dir = format!("{}/", ctx.path.display());
+        let mut prompts = vec![];
+
+        let names = ctx.marked_names(DirMark::Copy);
+        if !names.is_empty() {
+            prompts.push((DirPrompt::CopyTo { names }, dir.clone()));
+        }
+        let names = ctx.marked_names(DirMark::Rename);
+        if !names.is_empty() {
+            prompts.push((DirPrompt::MoveTo { names }, dir));
+        }
+        let names = ctx.marked_names(DirMark::Delete);
+        if !names.is_empty() {
+            prompts.push((DirPrompt::ConfirmDelete { names }, String::new()));
+        }
+        prompts
+    });
+
+    if prompts.is_empty() {
+        set_command_line_text(editor, env, "No marked entries");
+        return;
+    }
+
+    let (prompt, text) = prompts.remove(0);
+    prompts.reverse();
+    with_dir_ctx(view, |ctx| ctx.pending_prompts = prompts);
+    start_prompt(editor, env, view, prompt, text);
+}
+
+/// Handles the keys typed while a target or a confirmation is requested.
+pub fn dir_mode_prompt_input(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (evt, prompt) = {
+        let mut v = view.write();
+        let evt = v.input_ctx.trigger.last().cloned();
+        let ctx = v.mode_ctx_mut::<DirModeContext>("dir-mode");
+        (evt, ctx.prompt.clone())
+    };
+
+    let prompt = match prompt {
+        Some(prompt) => prompt,
+        None => {
+            stop_prompt(view);
+            return;
+        }
+    };
+
+    let key = match evt {
+        Some(InputEvent::KeyPress { key, .. }) => key,
+        // ignore the mouse
+        _ => return,
+    };
+
+    let is_confirmation = matches!(
+        prompt,
+        DirPrompt::ConfirmDelete { .. } | DirPrompt::ConfirmRenames { .. }
+    );
+
+    let validate = match (is_confirmation, key) {
+        (true, Key::Unicode('y')) => true,
+        (true, _) => false,
+        (false, Key::Unicode('\n')) => true,
+        (false, Key::Escape) => false,
+        (false, key) => {
+            let text = with_dir_ctx(view, |ctx| {
+                match key {
+                    Key::Unicode(c) if !c.is_control() => ctx.prompt_text.push(c),
+                    Key::UnicodeArray(v) => ctx.prompt_text.extend(v),
+                    Key::BackSpace => {
+                        ctx.prompt_text.pop();
+                    }
+                    _ => {}
+                }
+                ctx.prompt_text.clone()
+            });
+            set_command_line_text(editor, env, &prompt_message(&prompt, &text));
+            return;
+        }
+    };
+
+    let (prompt, text) = match stop_prompt(view) {
+        Some(p) => p,
+        None => return,
+    };
+
+    if !validate {
+        with_dir_ctx(view, |ctx| ctx.pending_prompts.clear());
+        set_command_line_text(editor, env, "");
+        return;
+    }
+
+    start_operation(editor, view, prompt, text);
+    set_command_line_text(editor, env, "Processing...");
+}
+
+/// Runs the operation of the validated 'prompt' in a new thread:
+/// a large copy or deletion does not block the ui. See dir_mode_stage_action.
+fn start_operation(
+    editor: &Editor<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    prompt: DirPrompt,
+    text: String,
+) {
+    let dir = with_dir_ctx(view, |ctx| ctx.path.clone());
+    let (tx, rx) = channel();
+    let core_tx = editor.core_tx.clone();
+    let op = prompt.clone();
+
+    std::thread::spawn(move || {
+        let ret = match &op {
+            DirPrompt::CopyTo { names } => copy_entries(&dir, names, &text),
+            DirPrompt::MoveTo { names } => move_entries(&dir, names, &text),
+            DirPrompt::ConfirmDelete { names } => delete_entries(&dir, names),
+            DirPrompt::ConfirmRenames { renames } => apply_renames(&dir, renames),
+        };
+        let _ = tx.send(ret);
+
+        let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+        let msg = Message::new(0, 0, ts, Event::RefreshView);
+        pending_input_event_inc(1);
+        core_tx.send(msg).unwrap_or(());
+    });
+
+    with_dir_ctx(view, |ctx| ctx.operation = Some((prompt, rx)));
+}
+
+/// The operation of 'prompt' is done: the listing is reloaded and the next prompt is started.
+fn finish_operation(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    prompt: DirPrompt,
+    ret: Result<usize, String>,
+) {
+    let message = match ret {
+        Ok(n) => {
+            with_dir_ctx(view, |ctx| match &prompt {
+                DirPrompt::CopyTo { names }
+                | DirPrompt::MoveTo { names }
+                | DirPrompt::ConfirmDelete { names } => {
+                    for name in names {
+                        ctx.marks.remove(name);
+                    }
+                }
+                DirPrompt::ConfirmRenames { .. } => {}
+            });
+
+            if let DirPrompt::ConfirmRenames { .. } = prompt {
+                close_names_edit(editor, env, view);
+            }
+
+            format!("{} entries processed", n)
+        }
+        Err(e) => {
+            dbg_println!("dir-mode: operation failed: {}", e);
+            with_dir_ctx(view, |ctx| ctx.pending_prompts.clear());
+            format!("Error: {}", e)
+        }
+    };
+
+    dir_mode_refresh(editor, env, view);
+
+    let next = with_dir_ctx(view, |ctx| ctx.pending_prompts.pop());
+    match next {
+        Some((prompt, text)) => start_prompt(editor, env, view, prompt, text),
+        None => set_command_line_text(editor, env, &message),
+    }
+}
+
+/// Returns the (source, destination) pairs: a 'target' directory receives the entries,
+/// otherwise 'target' is the new path of the only entry.
+fn resolve_targets(
+    dir: &Path,
+    names: &[String],
+    target: &str,
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    if target.is_empty() {
+        return Err("no target".to_owned());
+    }
+
+    let target = dir.join(target);
+    let into_dir = target.is_dir();
+    if !into_dir && names.len() != 1 {
+        return Err(format!("'{}' is not a directory", target.display()));
+    }
+
+    let mut v = vec![];
+    for name in names {
+        let src = dir.join(name);
+        let dst = if into_dir {
+            target.join(name)
+        } else {
+            target.clone()
+        };
+        if dst.exists() {
+            return Err(format!("'{}' already exists", dst.display()));
+        }
+        v.push((src, dst));
+    }
+    Ok(v)
+}
+
+fn copy_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if src.is_dir() {
+        fs::create_dir(dst)?;
+        for e in fs::read_dir(src)? {
+            let e = e?;
+            copy_recursive(&e.path(), &dst.join(e.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(src, dst).map(|_| ())
+    }
+}
+
+pub fn copy_entries(dir: &Path, names: &[String], target: &str) -> Result<usize, String> {
+    let pairs = resolve_targets(dir, names, target)?;
+    for (src, dst) in &pairs {
+        if dst.starts_with(src) {
+            return Err(format!("cannot copy '{}' into itself", src.display()));
+        }
+        copy_recursive(src, dst).map_err(|e| format!("{}: {}", src.display(), e))?;
+    }
+    Ok(pairs.len())
+}
+
+pub fn move_entries(dir: &Path, names: &[String], target: &str) -> Result<usize, String> {
+    let pairs = resolve_targets(dir, names, target)?;
+    for (src, dst) in &pairs {
+        fs::rename(src, dst).map_err(|e| format!("{}: {}", src.display(), e))?;
+    }
+    Ok(pairs.len())
+}
+
+pub fn delete_entries(dir: &Path, names: &[String]) -> Result<usize, String> {
+    for name in names {
+        let path = dir.join(name);
+        let ret = if path.is_dir() && !path.is_symlink() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        ret.map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    Ok(names.len())
+}
+
+/// Compare the edited text with the original 'names', one name per line.<br/>
+/// Returns the (old, new) names of the changed lines.
+pub fn names_changes(names: &[String], text: &str) -> Result<Vec<(String, String)>, String> {
+    let lines: Vec<&str> = text
+        .strip_suffix('\n')
+        .unwrap_or(text)
+        .split('\n')
+        .collect();
+    if lines.len() != names.len() {
+        return Err(format!(
+            "{} lines expected, found {}",
+            names.len(),
+            lines.len()
+        ));
+    }
+
+    let mut renames = vec![];
+    let mut targets = HashSet::new();
+    for (old, new) in names.iter().zip(lines) {
+        let new = new.trim_end_matches('\r');
+        if new.is_empty() || new == "." || new == ".." || new.contains('/') {
+            return Err(format!("invalid name '{}'", new));
+        }
+        if !targets.insert(new) {
+            return Err(format!("duplicate name '{}'", new));
+        }
+        if old != new {
+            renames.push((old.clone(), new.to_owned()));
+        }
+    }
+    Ok(renames)
+}
+
+/// Rename the entries of 'dir', the names can be swapped.<br/>
+/// The names are checked before renaming: on failure, the renames already done are undone
+/// and the error names the failing entry.
+pub fn apply_renames(dir: &Path, renames: &[(String, String)]) -> Result<usize, String> {
+    let sources: HashSet<&str> = renames.iter().map(|(o, _)| o.as_str()).collect();
+    let mut targets = HashSet::new();
+    for (old, new) in renames {
+        if fs::symlink_metadata(dir.join(old)).is_err() {
+            return Err(format!("'{}' does not exist", old));
+        }
+        if !targets.insert(new.as_str()) {
+            return Err(format!("duplicate name '{}'", new));
+        }
+        if !sources.contains(new.as_str()) && fs::symlink_metadata(dir.join(new)).is_ok() {
+            return Err(format!("'{}' already exists", new));
+        }
+    }
+
+    // use temporary names first
+    let tmp: Vec<PathBuf> = (0..renames.len())
+        .map(|i| dir.join(format!(".{}.{}.rename-tmp", std::process::id(), i)))
+        .collect();
+    if let Some(t) = tmp.iter().find(|t| fs::symlink_metadata(t).is_ok()) {
+        return Err(format!("'{}' already exists", t.display()));
+    }
+
+    let steps = renames
+        .iter()
+        .zip(tmp.iter())
+        .map(|((old, _), t)| (dir.join(old), t.clone(), old))
+        .chain(
+            tmp.iter()
+                .zip(renames.iter())
+                .map(|(t, (old, new))| (t.clone(), dir.join(new), old)),
+        );
+
+    // the renames done, undone in reverse order on failure
+    let mut done: Vec<(PathBuf, PathBuf)> = vec![];
+    for (from, to, old) in steps {
+        if let Err(e) = fs::rename(&from, &to) {
+            let mut err = format!("cannot rename '{}': {}", old, e);
+            while let Some((from, to)) = done.pop() {
+                if let Err(e) = fs::rename(&to, &from) {
+                    err.push_str(&format!(
+                        ", '{}' is left as '{}' ({})",
+                        from.display(),
+                        to.display(),
+                        e
+                    ));
+                }
+            }
+            return Err(err);
+        }
+        done.push((from, to));
+    }
+
+    Ok(renames.len())
+}
+
+/// Open a text buffer with the names of the entries, one per line.
+pub fn dir_mode_edit_names(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (path, names, loading) = with_dir_ctx(view, |ctx| {
+        let names: Vec<String> = ctx
+            .entries
+            .iter()
+            .filter(|e| e.name != "..")
+            .map(|e| e.name.clone())
+            .collect();
+        let loading = ctx.reader.as_ref().is_some_and(|r| !r.done);
+        (ctx.path.clone(), names, loading)
+    });
+
+    if loading {
+        set_command_line_text(editor, env, "The directory is still loading");
+        return;
+    }
+
+    let mut text = names.join("\n");
+    text.push('\n');
+
+    let name = format!("{}/*names*", path.display());
+    let b = match BufferBuilder::new(BufferKind::File)
+        .buffer_name(&name)
+        .internal(false)
+        .use_buffer_log(true)
+        .finalize()
+    {
+        Some(b) => b,
+        None => return,
+    };
+
+    let buffer_id = {
+        let mut d = b.write();
+        d.tag(env.current_time, 0, vec![0], vec![]);
+        d.insert(0, text.len(), text.as_bytes());
+        d.buffer_log_reset();
+        d.changed = false;
+        d.id
+    };
+
+    let (view_id, ok) = open_doc_add_buffer(editor, env, b);
+    if !ok {
+        return;
+    }
+
+    with_dir_ctx(view, |ctx| {
+        ctx.names_edit = Some(DirNamesEdit {
+            buffer_id,
+            view_id,
+            names,
+        })
+    });
+
+    show_file_view(editor, env, view_id);
+    set_command_line_text(
+        editor,
+        env,
+        "Edit the names then press 'A' in the directory view to apply",
+    );
+}
+
+fn close_names_edit(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let edit = match with_dir_ctx(view, |ctx| ctx.names_edit.take()) {
+        Some(edit) => edit,
+        None => return,
+    };
+
+    let buffer = editor.buffer_map.read().get(&edit.buffer_id).cloned();
+    if let Some(buffer) = buffer {
+        buffer.write().changed = false;
+    }
+
+    let dir_view_id = view.read().id;
+    close_file_view(editor, env, edit.view_id);
+    show_file_view(editor, env, dir_view_id);
+}
+
+/// Rename the entries according to the edited names.
+pub fn dir_mode_apply_names(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (buffer_id, names) = match with_dir_ctx(view, |ctx| {
+        ctx.names_edit
+            .as_ref()
+            .map(|e| (e.buffer_id, e.names.clone()))
+    }) {
+        Some(e) => e,
+        None => {
+            set_command_line_text(editor, env, "Press 'E' to edit the names first");
+            return;
+        }
+    };
+
+    let buffer = editor.buffer_map.read().get(&buffer_id).cloned();
+    let buffer = match buffer {
+        Some(buffer) => buffer,
+        None => {
+            with_dir_ctx(view, |ctx| ctx.names_edit = None);
+            return;
+        }
+    };
+
+    let text = {
+        let buffer = buffer.read();
+        let mut data = vec![];
+        buffer.read(0, buffer.size(), &mut data);
+        String::from_utf8_lossy(&data).to_string()
+    };
+
+    match names_changes(&names, &text) {
+        Ok(renames) if renames.is_empty() => {
+            close_names_edit(editor, env, view);
+            set_command_line_text(editor, env, "No changes");
+        }
+        Ok(renames) => {
+            start_prompt(
+                editor,
+                env,
+                view,
+                DirPrompt::ConfirmRenames { renames },
+                String::new(),
+            );
+        }
+        Err(e) => set_command_line_text(editor, env, &format!("Error: {}", e)),
+    }
+}
+
+/// A displayed line
+struct DirLine {
+    text: String,
+    selected: bool,
+    marked: bool,
+    is_dir: bool,
 }
 
 pub struct DirModeCompose {
-    // add common fields
+    header: String,
+    lines: Vec<DirLine>,
 }
 
 impl DirModeCompose {
     pub fn new() -> Self {
         dbg_println!("DirMode");
-        DirModeCompose {}
+        DirModeCompose {
+            header: String::new(),
+            lines: vec![],
+        }
     }
+}
+
+fn entry_line(e: &DirEntryInfo, mark: Option<DirMark>) -> String {
+    let mark = mark.map_or(' ', |m| m.to_char());
+    if e.name == ".." {
+        return format!("{} {:>10} {:>6} {:16} ..", mark, "", "", "");
+    }
+
+    let suffix = if e.is_dir {
+        "/"
+    } else if e.is_symlink {
+        "@"
+    } else {
+        ""
+    };
 
-    pub fn register_input_stage_actions<'a>(_map: &'a mut InputStageActionMap<'a>) {}
+    format!(
+        "{} {} {:>6} {} {}{}",
+        mark,
+        format_permissions(e),
+        format_size(e.size),
+        format_mtime(e.mtime),
+        e.name,
+        suffix
+    )
 }
 
 impl ContentFilter<'_> for DirModeCompose {
@@ -91,13 +1263,222 @@ impl ContentFilter<'_> for DirModeCompose {
         &"DirModeCompose"
     }
 
-    fn finish(&mut self, _view: &View, env: &mut LayoutEnv) {
-        // fill the whole dir bar
-        screen_apply(&mut env.screen, |_, _, cpi| {
-            cpi.style.color = TextStyle::title_color();
-            cpi.style.bg_color = TextStyle::title_bg_color();
+    fn setup(
+        &mut self,
+        editor: &mut Editor<'static>,
+        _editor_env: &mut EditorEnv<'static>,
+        env: &mut LayoutEnv,
+        view: &Rc<RwLock<View>>,
+        _parent_view: Option<&View<'static>>,
+    ) {
+        let mut v = view.write();
+        let buffer_name = v.buffer().map(|b| b.read().name.clone());
+
+        let ctx = v.mode_ctx_mut::<DirModeContext>("dir-mode");
+
+        // first composition
+        if ctx.reader.is_none() {
+            if let Some(name) = buffer_name {
+                let path = PathBuf::from(&name);
+                let path = fs::canonicalize(&path).unwrap_or(path);
+                ctx.load(editor, path);
+            }
+        }
+
+        // the listing is sorted once complete, the entries are displayed in the reading order meanwhile
+        if let Some(reader) = ctx.reader.as_mut() {
+            if !reader.done {
+                reader.poll(&mut ctx.entries, &mut ctx.error);
+                if reader.done {
+                    ctx.need_sort = true;
+                }
+            }
+        }
+
+        if ctx.need_sort {
+            ctx.sort_entries();
+            // drop the marks of the removed entries
+            if ctx.reader.as_ref().is_some_and(|r| r.done) {
+                let names: HashSet<&String> = ctx.entries.iter().map(|e| &e.name).collect();
+                ctx.marks.retain(|n, _| names.contains(n));
+            }
+        }
+
+        // the first line is the header
+        ctx.height = env.screen.height().saturating_sub(1);
+        ctx.selected = std::cmp::min(ctx.selected, ctx.entries.len().saturating_sub(1));
+        ctx.first_line = std::cmp::min(ctx.first_line, ctx.entries.len().saturating_sub(1));
+
+        let count = ctx.entries.len() - usize::from(ctx.has_parent());
+        let loading = ctx.reader.as_ref().is_some_and(|r| !r.done);
+        self.header = format!(
+            " {}  [{} entries, sort: {}{}]{}{}",
+            ctx.path.display(),
+            count,
+            ctx.sort.name(),
+            if ctx.reverse { " (reverse)" } else { "" },
+            if loading { " loading..." } else { "" },
+            ctx.error
+                .as_ref()
+                .map(|e| format!(" {}", e))
+                .unwrap_or_default()
+        );
+
+        self.lines.clear();
+        for (i, e) in ctx
+            .entries
+            .iter()
+            .enumerate()
+            .skip(ctx.first_line)
+            .take(ctx.height)
+        {
+            let mark = ctx.marks.get(&e.name).copied();
+            self.lines.push(DirLine {
+                text: entry_line(e, mark),
+                selected: i == ctx.selected,
+                marked: mark.is_some(),
+                is_dir: e.is_dir,
+            });
+        }
+    }
+
+    fn run(
+        &mut self,
+        _view: &View,
+        env: &mut LayoutEnv,
+        _input: &[FilterIo],
+        _output: &mut Vec<FilterIo>,
+    ) {
+        env.screen.clear();
+
+        let width = env.screen.width();
+
+        let mut header_style = TextStyle::new();
+        header_style.color = TextStyle::title_color();
+        header_style.bg_color = TextStyle::title_bg_color();
+        push_line(env, &self.header, header_style, width);
+
+        for line in &self.lines {
+            let mut style = TextStyle::new();
+            if line.is_dir {
+                style.is_bold = true;
+            }
+            if line.marked {
+                style.color = (255, 128, 0);
+            }
+            if line.selected {
+                style.bg_color = TextStyle::default_selected_bg_color();
+            }
+            if !push_line(env, &line.text, style, width) {
+                break;
+            }
+        }
 
-            true
-        });
+        env.quit = true;
     }
 }
+
+/// Push 'text' truncated to 'width', the line is filled with the style's background.
+fn push_line(env: &mut LayoutEnv, text: &str, style: TextStyle, width: usize) -> bool {
+    let mut cpi = CodepointInfo::new();
+    cpi.style = style;
+
+    let mut x = 0;
+    for c in text.chars() {
+        let w = env.screen.char_width(c);
+        if x + w > width {
+            break;
+        }
+        cpi.cp = c;
+        cpi.displayed_cp = c;
+        let (ok, _) = env.screen.push(&cpi);
+        if !ok {
+            return false;
+        }
+        x += w;
+    }
+
+    cpi.cp = ' ';
+    cpi.displayed_cp = ' ';
+    for _ in x..width {
+        let (ok, _) = env.screen.push(&cpi);
+        if !ok {
+            return false;
+        }
+    }
+    true
+}
+
+#[test]
+fn test_dir_mode_operations() {
+    let dir = std::env::temp_dir().join(format!("unlimited-dir-mode-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("a"), "a").unwrap();
+    fs::write(dir.join("b"), "b").unwrap();
+    fs::write(dir.join("sub/c"), "c").unwrap();
+
+    // sort
+    let mut entries = vec![
+        DirEntryInfo::from_path(&dir.join("b")).unwrap(),
+        DirEntryInfo::from_path(&dir.join("a")).unwrap(),
+        DirEntryInfo::from_path(&dir.join("sub")).unwrap(),
+    ];
+    sort_dir_entries(&mut entries, DirSortKey::Name, false);
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["sub", "a", "b"]);
+    sort_dir_entries(&mut entries, DirSortKey::Name, true);
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["b", "a", "sub"]);
+
+    // edit names as text: swap a and b
+    let names = vec!["a".to_owned(), "b".to_owned()];
+    assert!(names_changes(&names, "a\n").is_err());
+    assert!(names_changes(&names, "a\na\n").is_err());
+    assert!(names_changes(&names, "a\nx/y\n").is_err());
+    assert_eq!(names_changes(&names, "a\nb\n").unwrap(), vec![]);
+    let renames = names_changes(&names, "b\na\n").unwrap();
+    assert_eq!(apply_renames(&dir, &renames), Ok(2));
+    assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "b");
+    assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "a");
+
+    // the second rename fails (name too long): the renames are undone
+    let long = "x".repeat(300);
+    let renames = vec![
+        ("a".to_owned(), "b".to_owned()),
+        ("b".to_owned(), long.clone()),
+    ];
+    let err = apply_renames(&dir, &renames).unwrap_err();
+    assert!(err.starts_with("cannot rename 'b'"), "{}", err);
+    assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "b");
+    assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "a");
+    let left = fs::read_dir(&dir)
+        .unwrap()
+        .flatten()
+        .any(|e| e.file_name().to_string_lossy().ends_with(".rename-tmp"));
+    assert!(!left);
+    assert!(apply_renames(&dir, &[("nope".to_owned(), "c".to_owned())]).is_err());
+
+    // copy
+    let sub = vec!["sub".to_owned()];
+    assert!(copy_entries(&dir, &sub, "sub").is_err());
+    assert_eq!(copy_entries(&dir, &sub, "sub2"), Ok(1));
+    assert_eq!(fs::read_to_string(dir.join("sub2/c")).unwrap(), "c");
+    assert!(copy_entries(&dir, &names, "nodir").is_err());
+    assert_eq!(copy_entries(&dir, &names, "sub2"), Ok(2));
+
+    // move
+    assert!(move_entries(&dir, &["a".to_owned()], "b").is_err());
+    assert_eq!(move_entries(&dir, &["a".to_owned()], "sub"), Ok(1));
+    assert!(dir.join("sub/a").is_file());
+
+    // delete
+    assert_eq!(
+        delete_entries(&dir, &["sub2".to_owned(), "b".to_owned()]),
+        Ok(2)
+    );
+    assert!(!dir.join("sub2").exists());
+    assert!(!dir.join("b").exists());
+
+    let _ = fs::remove_dir_all(&dir);
+}
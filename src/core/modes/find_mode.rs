@@ -34,6 +34,9 @@ use crate::core::modes::text_mode::center_view_around_offset;
 
 use crate::core::modes::text_mode::cancel_selection;
 use crate::core::modes::text_mode::movement::cancel_marks;
+use crate::core::modes::text_mode::occurrences::mark_occurrences_in_range;
+
+use crate::sort_pair;
 
 static FIND_TRIGGER_MAP: &str = std::include_str!("../../../res/input-map/find-mode-trigger.json");
 
@@ -85,6 +88,8 @@ pub struct FindModeContext {
     pub match_end: Option<u64>,
     pub previous_encoded_str_len: usize,
     pub controller_view_id: view::Id,
+    /// selection at find start, used to restrict "find:mark-all"
+    pub start_selection: Option<(u64, u64)>,
 }
 
 impl FindModeContext {
@@ -98,6 +103,7 @@ impl FindModeContext {
             match_end: None,
             previous_encoded_str_len: 0,
             controller_view_id: view::Id(0),
+            start_selection: None,
         }
     }
     pub fn reset(&mut self) -> &mut Self {
@@ -105,6 +111,7 @@ impl FindModeContext {
         self.match_start = None;
         self.match_end = None;
         self.previous_encoded_str_len = 0;
+        self.start_selection = None;
         self.active = false;
         self
     }
@@ -138,7 +145,25 @@ pub fn find_start(
     // start/resume ?
     let controller_id = {
         let mut v = view.write();
+
+        let start_selection = {
+            let tm = v.mode_ctx::<TextModeContext>("text-mode");
+            if tm.marks.len() == 1 && tm.select_point.len() == 1 {
+                let range = sort_pair((tm.marks[0].offset, tm.select_point[0].offset));
+                if range.0 != range.1 {
+                    Some(range)
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        };
+
         let fm = v.mode_ctx_mut::<FindModeContext>("find-mode");
+        if !fm.active {
+            fm.start_selection = start_selection;
+        }
         fm.active = true;
 
         let id = fm.controller_view_id;
@@ -281,6 +306,7 @@ fn create_find_controller_view(
         register_input_stage_action(&mut action_map, "find:del-char", find_controller_del_char);
         register_input_stage_action(&mut action_map, "find:next", find_controller_next);
         register_input_stage_action(&mut action_map, "find:prev", find_controller_prev);
+        register_input_stage_action(&mut action_map, "find:mark-all", find_controller_mark_all);
 
         controller_view.register_action_map(action_map);
     }
@@ -640,6 +666,42 @@ pub fn find_controller_prev(
     display_find_string(&mut editor, &mut env, &view);
 }
 
+/// Stop find and create a mark on every match,
+/// inside the selection active at find start or in the whole buffer.
+pub fn find_controller_mark_all(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let text_view_id = match view.read().controlled_view {
+        Some(id) => id,
+        None => return,
+    };
+    let text_view = get_view_by_id(editor, text_view_id);
+
+    let (encoded_str, range) = {
+        let text_view = text_view.read();
+        let fm = text_view.mode_ctx::<FindModeContext>("find-mode");
+        let tm = text_view.mode_ctx::<TextModeContext>("text-mode");
+        let codec = tm.text_codec.as_ref();
+
+        let mut encoded_str = vec![];
+        for c in fm.find_str.iter() {
+            let mut bin: [u8; 4] = [0; 4];
+            let nr = codec.encode(*c as u32, &mut bin);
+            encoded_str.extend_from_slice(&bin[..nr]);
+        }
+
+        (encoded_str, fm.start_selection)
+    };
+
+    find_controller_stop(editor, env, view);
+
+    if !encoded_str.is_empty() {
+        mark_occurrences_in_range(&text_view, &encoded_str, range);
+    }
+}
+
 pub fn display_find_string(
     editor: &mut Editor<'static>,
     _env: &mut EditorEnv<'static>,
@@ -0,0 +1,390 @@
+/*
+    Git blame column: displayed on the left of the line numbers when the text view enables it
+    (git:toggle-blame), its width is 0 otherwise.
+
+    The blame is read with "git blame --porcelain" (the modified content is given with --contents -)
+    when the buffer changes, and only while the column is displayed.
+    git runs in the executor thread, one blame at a time: the previous blame is displayed meanwhile,
+    and the content of a modified buffer is read BLAME_DELAY_MS after the change (the next keystrokes share its blame).
+    Each screen line shows the commit of its buffer line: "sha1 author date", a wrapped line is left blank.
+*/
+
+use std::any::Any;
+
+use parking_lot::RwLock;
+
+use std::rc::Rc;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::TryRecvError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::Mode;
+
+use crate::core::buffer;
+use crate::core::buffer::get_byte_count_at_offset;
+use crate::core::buffer::Buffer;
+
+use crate::core::codepointinfo::CodepointInfo;
+
+use crate::core::editor::get_view_by_id;
+use crate::core::editor::InputStageActionMap;
+use crate::core::event::pending_input_event_inc;
+use crate::core::event::Event;
+use crate::core::event::Message;
+use crate::core::Editor;
+use crate::core::EditorEnv;
+
+use crate::core::git::Blame;
+use crate::core::git::GitFile;
+
+use crate::core::screen::line_logical_first;
+
+use crate::core::view;
+use crate::core::view::Id;
+use crate::core::view::LayoutEnv;
+use crate::core::view::LayoutSize;
+use crate::core::view::ScreenOverlayFilter;
+use crate::core::view::View;
+use crate::core::view::ViewEvent;
+use crate::core::view::ViewEventDestination;
+use crate::core::view::ViewEventSource;
+
+use crate::core::modes::dir_fetch::format_mtime;
+use crate::core::modes::git_mode::GitModeContext;
+
+/// "1234567 author______ YYYY-MM-DD "
+const BLAME_COLUMN_WIDTH: usize = 32;
+const BLAME_AUTHOR_WIDTH: usize = 12;
+
+/// The modified buffers larger than this are not blamed.
+const MAX_BLAME_CONTENT_SIZE: usize = 16 * 1024 * 1024;
+
+/// The delay before reading the content of a modified buffer.
+const BLAME_DELAY_MS: u64 = 300;
+
+pub struct GitBlameModeContext {
+    text_view_id: view::Id,
+}
+
+#[derive(Default)]
+pub struct GitBlameMode {
+    // add common fields
+}
+
+impl GitBlameMode {
+    pub fn new() -> Self {
+        dbg_println!("GitBlameMode");
+        GitBlameMode {}
+    }
+}
+
+/// Returns true if the text view 'v' displays its blame column.
+fn blame_enabled(v: &View<'static>) -> bool {
+    v.check_mode_ctx::<GitModeContext>("git-mode") && v.mode_ctx::<GitModeContext>("git-mode").blame
+}
+
+impl Mode for GitBlameMode {
+    fn name(&self) -> &'static str {
+        "git-blame-mode"
+    }
+
+    fn build_action_map(&self) -> InputStageActionMap<'static> {
+        InputStageActionMap::new()
+    }
+
+    fn alloc_ctx(&self, _editor: &Editor<'static>) -> Box<dyn Any> {
+        dbg_println!("alloc git-blame-mode ctx");
+        Box::new(GitBlameModeContext {
+            text_view_id: view::Id(0),
+        })
+    }
+
+    fn configure_view(
+        &mut self,
+        _editor: &mut Editor<'static>,
+        _env: &mut EditorEnv<'static>,
+        view: &mut View<'static>,
+    ) {
+        view.compose_screen_overlay_filters
+            .borrow_mut()
+            .push(Box::new(GitBlameOverlayFilter::new()));
+    }
+
+    fn on_view_event(
+        &self,
+        editor: &mut Editor<'static>,
+        _env: &mut EditorEnv<'static>,
+        src: ViewEventSource,
+        dst: ViewEventDestination,
+        event: &ViewEvent,
+        src_view: &mut View<'static>,
+        parent: Option<&mut View<'static>>,
+    ) {
+        match event {
+            ViewEvent::Subscribe => {
+                if src.id == dst.id || src.id == Id(0) || dst.id == Id(0) {
+                    return;
+                }
+
+                let blame_view = get_view_by_id(editor, dst.id);
+                let mut blame_view = blame_view.write();
+                let mode_ctx = blame_view.mode_ctx_mut::<GitBlameModeContext>("git-blame-mode");
+                mode_ctx.text_view_id = src.id;
+            }
+
+            ViewEvent::PreLayoutSizing => {
+                if src_view.id == dst.id {
+                    return;
+                }
+
+                let width = if blame_enabled(src_view) {
+                    BLAME_COLUMN_WIDTH
+                } else {
+                    0
+                };
+
+                let blame_view = get_view_by_id(editor, dst.id);
+                let blame_view = blame_view.read();
+                if let (Some(p_view), Some(idx)) = (parent, blame_view.layout_index) {
+                    p_view.children[idx].layout_op = LayoutSize::Fixed { size: width };
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// (buffer, buffer revision, changed flag, blame generation)
+type BlameKey = (buffer::Id, usize, bool, usize);
+
+#[derive(Default)]
+pub struct GitBlameOverlayFilter {
+    /// the key of the last requested blame
+    key: Option<BlameKey>,
+    /// the last received blame, displayed until the next one is available
+    blame: Option<Blame>,
+    /// the blame running in the executor thread
+    pending: Option<Receiver<(BlameKey, Option<Blame>)>>,
+    /// the line of each screen line, None for the wrapped lines
+    lines: Vec<Option<usize>>,
+}
+
+impl GitBlameOverlayFilter {
+    pub fn new() -> Self {
+        GitBlameOverlayFilter {
+            key: None,
+            blame: None,
+            pending: None,
+            lines: vec![],
+        }
+    }
+}
+
+/// Blames 'buffer' in the executor thread, the ui is refreshed when the blame is available.
+fn start_blame(
+    editor: &Editor<'static>,
+    buffer: &Arc<RwLock<Buffer<'static>>>,
+    generation: usize,
+) -> Receiver<(BlameKey, Option<Blame>)> {
+    let (tx, rx) = channel();
+    let buffer = Arc::clone(buffer);
+    let core_tx = editor.core_tx.clone();
+
+    let _ = editor.executor_tx.send((
+        0,
+        Box::new(move || {
+            if buffer.read().changed {
+                std::thread::sleep(Duration::from_millis(BLAME_DELAY_MS));
+            }
+
+            // the content is copied: git does not hold the buffer lock
+            let (key, file_name, contents) = {
+                let buffer = buffer.read();
+                let key = (buffer.id, buffer.nr_changes(), buffer.changed, generation);
+                let contents = match buffer.changed {
+                    false => None,
+                    true if buffer.size() > MAX_BLAME_CONTENT_SIZE => {
+                        let _ = tx.send((key, None));
+                        return;
+                    }
+                    true => {
+                        let mut data = Vec::with_capacity(buffer.size());
+                        buffer.read(0, buffer.size(), &mut data);
+                        Some(data)
+                    }
+                };
+                (key, buffer.file_name(), contents)
+            };
+
+            let blame = GitFile::new(&file_name).and_then(|f| f.blame(contents.as_deref()));
+            if tx.send((key, blame)).is_ok() {
+                let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+                let msg = Message::new(0, 0, ts, Event::RefreshView);
+                pending_input_event_inc(1);
+                core_tx.send(msg).unwrap_or(());
+            }
+        }),
+    ));
+
+    rx
+}
+
+impl ScreenOverlayFilter<'_> for GitBlameOverlayFilter {
+    fn name(&self) -> &'static str {
+        "GitBlameOverlay"
+    }
+
+    fn setup(
+        &mut self,
+        editor: &Editor<'static>,
+        _env: &mut LayoutEnv,
+        view: &Rc<RwLock<View>>,
+        _parent_view: Option<&View<'static>>,
+    ) {
+        self.lines.clear();
+
+        let text_view_id = {
+            let view = view.read();
+            view.mode_ctx::<GitBlameModeContext>("git-blame-mode")
+                .text_view_id
+        };
+        if text_view_id == Id(0) {
+            return;
+        }
+
+        let src = get_view_by_id(editor, text_view_id);
+        let src = src.read();
+        if !blame_enabled(&src) {
+            return;
+        }
+        let generation = src.mode_ctx::<GitModeContext>("git-mode").blame_generation;
+
+        let buffer = match src.buffer() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        // receive the blame of the executor thread
+        if let Some(rx) = &self.pending {
+            match rx.try_recv() {
+                Ok((key, blame)) => {
+                    self.key = Some(key);
+                    self.blame = blame;
+                    self.pending = None;
+                }
+                Err(TryRecvError::Disconnected) => self.pending = None,
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+
+        let key = {
+            let buffer = buffer.read();
+            (buffer.id, buffer.nr_changes(), buffer.changed, generation)
+        };
+
+        // another buffer is displayed
+        if self.key.map(|k| k.0) != Some(key.0) {
+            self.blame = None;
+            self.pending = None;
+        }
+
+        // the changes made while a blame runs are blamed by the next one
+        if self.key != Some(key) && self.pending.is_none() {
+            self.key = Some(key);
+            self.pending = Some(start_blame(editor, &buffer, generation));
+        }
+
+        let buffer = buffer.read();
+        if self.blame.is_none() || !buffer.indexed {
+            return;
+        }
+
+        let screen = src.screen.read();
+        let mut prev_line = None;
+        for i in 0..screen.line_index.len() {
+            let l = match screen.get_used_line(i) {
+                Some(l) => l,
+                None => break,
+            };
+
+            let line = match l.is_empty() {
+                true => None,
+                false => line_logical_first(l)
+                    .cpi
+                    .offset
+                    .map(|offset| get_byte_count_at_offset(&buffer, '\n' as usize, offset).0),
+            };
+
+            if line.is_some() && line == prev_line {
+                self.lines.push(None);
+            } else {
+                self.lines.push(line.map(|l| l as usize));
+            }
+            prev_line = line;
+        }
+    }
+
+    fn run(&mut self, _view: &View, env: &mut LayoutEnv) {
+        env.screen.clear();
+
+        let blame = match self.blame {
+            Some(ref blame) => blame,
+            None => return,
+        };
+
+        let w = env.screen.width();
+
+        let mut color = CodepointInfo::new().style.color;
+        color.0 = color.0.saturating_sub(70);
+        color.1 = color.1.saturating_sub(70);
+        color.2 = color.2.saturating_sub(70);
+
+        for line in self.lines.iter() {
+            let cur_line_idx = env.screen.current_line_index();
+
+            if let Some(commit) = line.and_then(|l| blame.line_commit(l)) {
+                let s = if commit.is_uncommitted() {
+                    "not committed yet".to_owned()
+                } else {
+                    let author: String = commit.author.chars().take(BLAME_AUTHOR_WIDTH).collect();
+                    format!(
+                        "{} {:<width$} {}",
+                        commit.short_id(),
+                        author,
+                        &format_mtime(commit.time)[..10],
+                        width = BLAME_AUTHOR_WIDTH
+                    )
+                };
+
+                // the commit id is colored by its value
+                let id = u32::from_str_radix(commit.id.get(..6).unwrap_or(""), 16).unwrap_or(0);
+                let id_color = (
+                    100 + (id >> 16 & 0xff) as u8 / 2,
+                    100 + (id >> 8 & 0xff) as u8 / 2,
+                    100 + (id & 0xff) as u8 / 2,
+                );
+
+                for (i, c) in s.chars().take(w.saturating_sub(1)).enumerate() {
+                    let mut cpi = CodepointInfo::new();
+                    cpi.displayed_cp = c;
+                    cpi.style.color = if i < 7 && !commit.is_uncommitted() {
+                        id_color
+                    } else {
+                        color
+                    };
+                    env.screen.push(&cpi);
+                }
+            }
+
+            if cur_line_idx == env.screen.current_line_index() {
+                // NB screen.push selects next line automatically
+                env.screen.select_next_line_index();
+            }
+        }
+    }
+
+    fn finish(&mut self, _: &View, _: &mut LayoutEnv) {}
+}
@@ -0,0 +1,348 @@
+/*
+    Git mode: the git features of the text views, see core::git.
+
+    blame:
+        ctrl+x g b toggles the blame column of the view (git-blame-mode), its width is 0 when hidden.
+
+    status:
+        ctrl+x g s opens the "*git status*" buffer of the repository in a tab (reused if already opened).
+        The status view gets an extra input map: Enter opens the file under the mark, g refreshes the list.
+
+    The gutter markers against HEAD are drawn by the line-number mode (-c line-number-mode:changes=head),
+    the hunks are staged/unstaged from the diff with HEAD (see diff_mode.rs).
+*/
+
+use parking_lot::RwLock;
+use std::any::Any;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::Mode;
+
+use crate::core::buffer::Buffer;
+use crate::core::buffer::BufferBuilder;
+use crate::core::buffer::BufferKind;
+
+use crate::core::git;
+use crate::core::git::GitFile;
+use crate::core::git::StatusEntry;
+
+use crate::core::editor::check_view_by_id;
+use crate::core::editor::register_input_stage_action;
+use crate::core::editor::InputStageActionMap;
+use crate::core::Editor;
+use crate::core::EditorEnv;
+
+use crate::core::event::input_map::build_input_event_map;
+
+use crate::core::view;
+use crate::core::view::View;
+
+use crate::core::modes::core_mode::set_command_line_text;
+use crate::core::modes::open_doc::open_doc_add_buffer;
+use crate::core::modes::open_doc::open_doc_load_path;
+use crate::core::modes::tab_bar_mode::find_tagged_child;
+use crate::core::modes::tab_bar_mode::show_file_view;
+use crate::core::modes::text_mode::mark::Mark;
+use crate::core::modes::text_mode::PostInputAction;
+use crate::core::modes::text_mode::TextModeContext;
+
+static GIT_TRIGGER_MAP: &str = std::include_str!("../../../res/input-map/git-mode-trigger.json");
+
+static GIT_STATUS_MAP: &str = std::include_str!("../../../res/input-map/git-status-input-map.json");
+
+/// The files listed by a status view.
+pub struct GitStatus {
+    pub root: PathBuf,
+    /// the start offset of each listed file and its path
+    pub entries: Vec<(u64, String)>,
+}
+
+#[derive(Default)]
+pub struct GitModeContext {
+    /// the blame column is displayed
+    pub blame: bool,
+    /// incremented each time the blame column is displayed: the blame is read again
+    pub blame_generation: usize,
+    /// set on the view of a "*git status*" buffer
+    pub status: Option<GitStatus>,
+}
+
+impl GitModeContext {
+    pub fn new() -> Self {
+        GitModeContext {
+            blame: false,
+            blame_generation: 0,
+            status: None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GitMode {
+    // add common fields
+}
+
+impl GitMode {
+    pub fn new() -> Self {
+        dbg_println!("GitMode");
+        GitMode {}
+    }
+
+    pub fn register_input_stage_actions<'a>(map: &'a mut InputStageActionMap<'a>) {
+        register_input_stage_action(map, "git:toggle-blame", toggle_blame);
+        register_input_stage_action(map, "git:status", git_status);
+        register_input_stage_action(map, "git:status-open", status_open);
+        register_input_stage_action(map, "git:status-refresh", status_refresh);
+    }
+}
+
+impl Mode for GitMode {
+    fn name(&self) -> &'static str {
+        "git-mode"
+    }
+
+    fn build_action_map(&self) -> InputStageActionMap<'static> {
+        let mut map = InputStageActionMap::new();
+        Self::register_input_stage_actions(&mut map);
+        map
+    }
+
+    fn alloc_ctx(&self, _editor: &Editor<'static>) -> Box<dyn Any> {
+        dbg_println!("alloc git-mode ctx");
+        Box::new(GitModeContext::new())
+    }
+
+    fn configure_view(
+        &mut self,
+        _editor: &mut Editor<'static>,
+        _env: &mut EditorEnv<'static>,
+        view: &mut View<'static>,
+    ) {
+        let input_map = build_input_event_map(GIT_TRIGGER_MAP).unwrap();
+        let mut input_map_stack = view.input_ctx.input_map.as_ref().borrow_mut();
+        input_map_stack.push((self.name(), input_map));
+    }
+}
+
+pub fn toggle_blame(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let mut v = view.write();
+    let ctx = v.mode_ctx_mut::<GitModeContext>("git-mode");
+    ctx.blame = !ctx.blame;
+    if ctx.blame {
+        ctx.blame_generation += 1;
+    }
+}
+
+/// Returns the "*git status*" text of 'entries' and the start offset of each listed file.
+pub fn format_status(root: &Path, entries: &[StatusEntry]) -> (Vec<u8>, Vec<(u64, String)>) {
+    let mut text = format!("git status: {}\n\n", root.display());
+    let mut offsets = vec![];
+
+    for e in entries {
+        offsets.push((text.len() as u64, e.path.clone()));
+        text.push_str(&format!("{}{} {}\n", e.index, e.worktree, e.path));
+    }
+
+    if entries.is_empty() {
+        text.push_str("nothing to commit, working tree clean\n");
+    }
+
+    (text.into_bytes(), offsets)
+}
+
+/// Rewrites the status 'buffer' of the repository 'root'.
+fn refresh_status(root: &Path, buffer: &Arc<RwLock<Buffer<'static>>>) -> Vec<(u64, String)> {
+    let entries = git::status(root).unwrap_or_default();
+    let (text, offsets) = format_status(root, &entries);
+
+    let mut buffer = buffer.write();
+    buffer.delete_content(None);
+    buffer.append(&text);
+    buffer.changed = false;
+
+    offsets
+}
+
+/// Returns the status view of the repository 'root' if it is opened.
+fn find_status_view(editor: &Editor<'static>, root: &Path) -> Option<(view::Id, view::Id)> {
+    editor.active_views.iter().find_map(|id| {
+        let target = find_tagged_child(editor, *id, "target-view")?;
+        let v = check_view_by_id(editor, target)?;
+        let v = v.read();
+        if !v.check_mode_ctx::<GitModeContext>("git-mode") {
+            return None;
+        }
+        match v.mode_ctx::<GitModeContext>("git-mode").status {
+            Some(ref status) if status.root == root => Some((*id, target)),
+            _ => None,
+        }
+    })
+}
+
+/// Opens the status of the repository of the buffer of 'view' (or of the current directory).
+pub fn git_status(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let file_name = view
+        .read()
+        .buffer()
+        .map(|b| b.read().file_name())
+        .unwrap_or_default();
+
+    let root = match GitFile::new(&file_name) {
+        Some(f) => f.root,
+        None => match git::git(Path::new("."), &["rev-parse", "--show-toplevel"], None) {
+            Ok(out) => PathBuf::from(String::from_utf8_lossy(&out).trim_end()),
+            Err(e) => {
+                set_command_line_text(editor, env, &format!("git status: {}", e));
+                return;
+            }
+        },
+    };
+
+    if let Some((file_view_id, target)) = find_status_view(editor, &root) {
+        if let Some(v) = check_view_by_id(editor, target) {
+            status_refresh(editor, env, &v);
+        }
+        show_file_view(editor, env, file_view_id);
+        return;
+    }
+
+    let buffer = match BufferBuilder::new(BufferKind::File)
+        .buffer_name("*git status*")
+        .internal(true)
+        .use_buffer_log(false)
+        .finalize()
+    {
+        Some(buffer) => buffer,
+        None => return,
+    };
+
+    let entries = refresh_status(&root, &buffer);
+
+    let (file_view_id, ok) = open_doc_add_buffer(editor, env, buffer);
+    if !ok {
+        return;
+    }
+
+    if let Some(target) = find_tagged_child(editor, file_view_id, "target-view") {
+        let v = check_view_by_id(editor, target).unwrap();
+        let mut v = v.write();
+
+        {
+            let input_map = build_input_event_map(GIT_STATUS_MAP).unwrap();
+            let mut input_map_stack = v.input_ctx.input_map.as_ref().borrow_mut();
+            input_map_stack.push(("git-status", input_map));
+        }
+
+        let ctx = v.mode_ctx_mut::<GitModeContext>("git-mode");
+        ctx.status = Some(GitStatus { root, entries });
+
+        // on the first file
+        let first = ctx.status.as_ref().unwrap().entries.first().map(|e| e.0);
+        if let Some(offset) = first {
+            let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+            tm.marks.clear();
+            tm.marks.push(Mark::new(offset));
+            tm.mark_index = 0;
+        }
+    }
+
+    show_file_view(editor, env, file_view_id);
+}
+
+/// Opens the file listed at the main mark of the status view.
+pub fn status_open(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let path = {
+        let v = view.read();
+        let ctx = v.mode_ctx::<GitModeContext>("git-mode");
+        let status = match ctx.status {
+            Some(ref status) => status,
+            None => return,
+        };
+
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        let offset = tm.marks[tm.mark_index].offset;
+
+        let n = status.entries.partition_point(|e| e.0 <= offset);
+        match status.entries.get(n.wrapping_sub(1)) {
+            Some((_, path)) => status.root.join(path),
+            None => return,
+        }
+    };
+
+    let (id, ok) = open_doc_load_path(editor, env, &path.to_string_lossy());
+    if ok {
+        show_file_view(editor, env, id);
+    }
+}
+
+/// Lists the modified files of the status view again.
+pub fn status_refresh(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let mut v = view.write();
+    let buffer = match v.buffer() {
+        Some(buffer) => buffer,
+        None => return,
+    };
+
+    let ctx = v.mode_ctx_mut::<GitModeContext>("git-mode");
+    let status = match ctx.status {
+        Some(ref mut status) => status,
+        None => return,
+    };
+    status.entries = refresh_status(&status.root, &buffer);
+
+    let size = buffer.read().size() as u64;
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    for m in tm.marks.iter_mut() {
+        m.offset = std::cmp::min(m.offset, size);
+    }
+    tm.pre_compose_action.push(PostInputAction::CheckMarks);
+}
+
+#[test]
+fn test_git_status_format() {
+    let entries = vec![
+        StatusEntry {
+            index: ' ',
+            worktree: 'M',
+            path: "src/a.rs".to_owned(),
+        },
+        StatusEntry {
+            index: '?',
+            worktree: '?',
+            path: "b.txt".to_owned(),
+        },
+    ];
+
+    let (text, offsets) = format_status(Path::new("/repo"), &entries);
+    assert_eq!(
+        String::from_utf8(text).unwrap(),
+        "git status: /repo\n\n M src/a.rs\n?? b.txt\n"
+    );
+    assert_eq!(
+        offsets,
+        vec![(19, "src/a.rs".to_owned()), (31, "b.txt".to_owned())]
+    );
+
+    let (text, offsets) = format_status(Path::new("/repo"), &[]);
+    assert!(text.ends_with(b"working tree clean\n"));
+    assert!(offsets.is_empty());
+}
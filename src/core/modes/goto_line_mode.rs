@@ -1,8 +1,29 @@
+/*
+    Goto mode: ctrl+g opens the "Goto:" prompt in the status line.
+
+    The prompt accepts:
+        N           line N
+        N:C         line N, column C (in characters)
+        @N, @0xN    byte offset (decimal or hexadecimal)
+        0xN         byte offset (hexadecimal)
+        NN%         percentage of the file
+        +N, -N      N lines after/before the current line
+
+    The target is previewed as you type: Enter (or ctrl+g) validates it,
+    Escape (or ctrl+q) returns to the initial position.
+    The validated jumps are stacked: alt+g goes back to the previous position,
+    the stacked positions follow the buffer modifications (see GotoHistoryTracker).
+
+    The offsets and percentages do not use the line index: they are instant on unindexed files.
+*/
+
 use std::any::Any;
 
 use parking_lot::RwLock;
 
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Weak;
 
 use super::Mode;
 
@@ -10,7 +31,8 @@ use super::text_mode::TextModeContext;
 
 use super::text_mode::PostInputAction;
 
-use crate::core::buffer::get_byte_count;
+use crate::core::buffer::get_byte_count_at_offset;
+use crate::core::buffer::Buffer;
 
 use crate::core::buffer::BufferBuilder;
 use crate::core::buffer::BufferKind;
@@ -39,6 +61,13 @@ use crate::core::view::LayoutSize;
 use crate::core::buffer::find_nth_byte_offset;
 
 use crate::core::buffer::BufferEvent;
+use crate::core::buffer::BufferEventCb;
+
+use crate::core::codec::text::TextCodec;
+
+use super::text_mode::block_selection::line_start;
+use super::text_mode::block_selection::next_line_start;
+use super::text_mode::block_selection::previous_line_start;
 
 use super::text_mode::run_text_mode_actions_vec;
 
@@ -48,6 +77,16 @@ static GOTO_LINE_TRIGGER_MAP: &str =
 static GOTO_LINE_CONTROLLER_INTERACTIVE_MAP: &str =
     std::include_str!("../../../res/input-map/goto-line-mode-input-map.json");
 
+static GOTO_PROMPT: &str = "Goto: ";
+
+static GOTO_HINT: &str = "  (line[:column], @offset, 0xhex, NN%, +N/-N)";
+
+/// the accepted characters of the prompt
+static GOTO_CHARS: &str = "0123456789abcdefABCDEFxX@%:+-";
+
+/// the number of positions kept by the jump-back history
+const GOTO_HISTORY_SIZE: usize = 64;
+
 impl<'a> Mode for GotoLineMode {
     fn name(&self) -> &'static str {
         &"goto-line-mode"
@@ -80,6 +119,13 @@ impl<'a> Mode for GotoLineMode {
             input_map_stack.push((self.name(), input_map));
         }
 
+        // keep the jump-back history in sync with the buffer modifications
+        if let Some(buffer) = view.buffer() {
+            let gtm = view.mode_ctx::<GotoLineModeContext>("goto-line-mode");
+            let tracker = Box::new(GotoHistoryTracker::new(&gtm.history));
+            buffer.write().register_subscriber(tracker);
+        }
+
         // add controller
         create_goto_line_controller_view(editor, env, view);
     }
@@ -139,11 +185,154 @@ impl<'a> Mode for GotoLineMode {
     }
 }
 
+/// A position typed in the goto prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GotoTarget {
+    /// line and column start at 1
+    Line {
+        line: u64,
+        column: Option<u64>,
+    },
+    Offset(u64),
+    Percent(u64),
+    /// lines after/before the initial position
+    Relative(i64),
+}
+
+fn parse_goto_number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse::<u64>().ok(),
+    }
+}
+
+/// Parses the goto prompt, an incomplete input returns the target typed so far ("12:" is line 12).
+pub fn parse_goto_target(s: &str) -> Option<GotoTarget> {
+    let s = s.trim();
+
+    if let Some(offset) = s.strip_prefix('@') {
+        return parse_goto_number(offset).map(GotoTarget::Offset);
+    }
+
+    if s.starts_with("0x") || s.starts_with("0X") {
+        return parse_goto_number(s).map(GotoTarget::Offset);
+    }
+
+    if let Some(p) = s.strip_suffix('%') {
+        let p = p.parse::<u64>().ok()?;
+        return Some(GotoTarget::Percent(std::cmp::min(p, 100)));
+    }
+
+    if let Some(n) = s.strip_prefix('+') {
+        return n.parse::<i64>().ok().map(GotoTarget::Relative);
+    }
+
+    if let Some(n) = s.strip_prefix('-') {
+        return n.parse::<i64>().ok().map(|n| GotoTarget::Relative(-n));
+    }
+
+    let (line, column) = match s.split_once(':') {
+        Some((line, column)) => (line, column.parse::<u64>().ok()),
+        None => (s, None),
+    };
+
+    let line = line.parse::<u64>().ok()?;
+    Some(GotoTarget::Line { line, column })
+}
+
+/// Returns the start offset of 'line' (starting at 1), the end of the buffer if there is no such line.
+fn line_offset(buffer: &Buffer, line: u64) -> u64 {
+    if line <= 1 {
+        return 0;
+    }
+
+    match find_nth_byte_offset(buffer, b'\n', line - 1) {
+        Some(offset) => offset + 1,
+        None => buffer.size() as u64,
+    }
+}
+
+/// Returns the offset of 'target', 'from' is the offset of the relative moves.
+pub fn goto_target_offset(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    from: u64,
+    target: GotoTarget,
+) -> u64 {
+    let size = buffer.size() as u64;
+
+    match target {
+        GotoTarget::Line { line, column } => {
+            let start = line_offset(buffer, line);
+
+            let column = column.unwrap_or(1);
+            if column <= 1 {
+                return start;
+            }
+
+            let mut end = Mark::new(start);
+            end.move_to_end_of_line(buffer, codec);
+
+            let mut m = Mark::new(start);
+            for _ in 1..column {
+                if m.offset >= end.offset {
+                    break;
+                }
+                m.move_forward(buffer, codec);
+            }
+            m.offset
+        }
+
+        GotoTarget::Offset(offset) => std::cmp::min(offset, size),
+
+        GotoTarget::Percent(p) => {
+            let mut offset = (size as u128 * p as u128 / 100) as u64;
+
+            // do not stop inside a multi-byte character
+            let mut data = vec![];
+            buffer.read(offset, codec.encode_max_size(), &mut data);
+            for b in data {
+                if codec.is_sync(b) {
+                    break;
+                }
+                offset += 1;
+            }
+            std::cmp::min(offset, size)
+        }
+
+        GotoTarget::Relative(n) => {
+            if buffer.indexed {
+                let (line, _) = get_byte_count_at_offset(buffer, '\n' as usize, from);
+                let line = (1 + line).saturating_add_signed(n);
+                return line_offset(buffer, std::cmp::max(line, 1));
+            }
+
+            // walk the lines
+            let mut offset = line_start(buffer, codec, from);
+            for _ in 0..n.unsigned_abs() {
+                let next = if n > 0 {
+                    next_line_start(buffer, codec, offset)
+                } else {
+                    previous_line_start(buffer, codec, offset)
+                };
+                match next {
+                    Some(next) if next <= size => offset = next,
+                    _ => break,
+                }
+            }
+            offset
+        }
+    }
+}
+
 pub struct GotoLineModeContext {
     pub active: bool,
-    pub eof: bool,
     pub goto_line_str: Vec<char>,
     pub controller_view_id: view::Id,
+    /// the main mark and start offsets of the view when the prompt was opened
+    pub origin: Option<(u64, u64)>,
+    /// the positions before the validated jumps
+    pub history: Arc<RwLock<Vec<u64>>>,
 }
 
 impl GotoLineModeContext {
@@ -151,16 +340,17 @@ impl GotoLineModeContext {
         dbg_println!("GotoLineMode");
         GotoLineModeContext {
             active: false,
-            eof: false,
             goto_line_str: Vec::new(),
             controller_view_id: view::Id(0),
+            origin: None,
+            history: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
     pub fn reset(&mut self) -> &mut Self {
         self.goto_line_str.clear();
-        self.eof = false;
         self.active = false;
+        self.origin = None;
         self
     }
 }
@@ -168,6 +358,50 @@ pub struct GotoLineMode {
     // add common fields
 }
 
+/// Keeps the jump-back history of a view in sync with the buffer modifications.
+pub struct GotoHistoryTracker {
+    history: Weak<RwLock<Vec<u64>>>,
+}
+
+impl GotoHistoryTracker {
+    pub fn new(history: &Arc<RwLock<Vec<u64>>>) -> Self {
+        GotoHistoryTracker {
+            history: Arc::downgrade(history),
+        }
+    }
+}
+
+impl BufferEventCb for GotoHistoryTracker {
+    fn cb(&mut self, _buffer: &Buffer, event: &BufferEvent) {
+        // the view is destroyed
+        let history = match self.history.upgrade() {
+            Some(history) => history,
+            None => return,
+        };
+
+        match event {
+            BufferEvent::BufferDataInserted { offset, size, .. } => {
+                for o in history.write().iter_mut() {
+                    if *o >= *offset {
+                        *o += *size as u64;
+                    }
+                }
+            }
+            BufferEvent::BufferDataRemoved { offset, size, .. } => {
+                let end = *offset + *size as u64;
+                for o in history.write().iter_mut() {
+                    if *o >= end {
+                        *o -= *size as u64;
+                    } else if *o > *offset {
+                        *o = *offset;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 impl GotoLineMode {
     pub fn new() -> Self {
         dbg_println!("GotoLineMode");
@@ -176,6 +410,7 @@ impl GotoLineMode {
 
     pub fn register_input_stage_actions<'a>(mut map: &'a mut InputStageActionMap<'a>) {
         register_input_stage_action(&mut map, "goto-line:start", goto_line_start);
+        register_input_stage_action(&mut map, "goto-line:jump-back", goto_line_jump_back);
     }
 }
 
@@ -193,8 +428,17 @@ pub fn goto_line_start(
     // start/resume ?
     let controller_id = {
         let mut v = view.write();
+        let start_offset = v.start_offset;
+        let mark_offset = {
+            let tm = v.mode_ctx::<TextModeContext>("text-mode");
+            tm.marks[tm.mark_index].offset
+        };
+
         let gtm = v.mode_ctx_mut::<GotoLineModeContext>("goto-line-mode");
         gtm.active = true;
+        if gtm.origin.is_none() {
+            gtm.origin = Some((mark_offset, start_offset));
+        }
 
         let id = gtm.controller_view_id;
 
@@ -210,12 +454,40 @@ pub fn goto_line_start(
         id
     };
 
+    {
+        let controller = get_view_by_id(editor, controller_id);
+        let controller = controller.read();
+        let v = view.read();
+        let gtm = v.mode_ctx::<GotoLineModeContext>("goto-line-mode");
+        goto_line_render_prompt(&controller, &gtm.goto_line_str);
+    }
+
     goto_line_show_controller_view(editor, env, view);
     set_focus_on_view_id(&mut editor, &mut env, controller_id);
 
     env.input_grab_view_id = Some(controller_id);
 }
 
+/// Goes back to the position before the last validated jump.
+pub fn goto_line_jump_back(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let offset = {
+        let v = view.read();
+        let gtm = v.mode_ctx::<GotoLineModeContext>("goto-line-mode");
+        let offset = gtm.history.write().pop();
+        match offset {
+            Some(offset) => offset,
+            None => return,
+        }
+    };
+
+    let size = view.read().buffer().map_or(0, |b| b.read().size() as u64);
+    goto_line_set_target_offset(editor, env, view, std::cmp::min(offset, size));
+}
+
 fn create_goto_line_controller_view(
     mut editor: &mut Editor<'static>,
     mut env: &mut EditorEnv<'static>,
@@ -234,7 +506,11 @@ fn create_goto_line_controller_view(
         .finalize();
 
     {
-        buffer.as_ref().unwrap().write().append(b"Goto: ");
+        buffer
+            .as_ref()
+            .unwrap()
+            .write()
+            .append(GOTO_PROMPT.as_bytes());
     }
 
     // create view at mode creation
@@ -280,6 +556,11 @@ fn create_goto_line_controller_view(
             "goto-line-controller:stop",
             goto_line_controller_stop,
         );
+        register_input_stage_action(
+            &mut action_map,
+            "goto-line-controller:cancel",
+            goto_line_controller_cancel,
+        );
         register_input_stage_action(
             &mut action_map,
             "goto-line-controller:add-char",
@@ -319,29 +600,92 @@ fn goto_line_show_controller_view(
     });
 }
 
-pub fn goto_line_controller_add_char(
+/// Displays "Goto: input" in the controller view, a hint if the input is empty.
+fn goto_line_render_prompt(controller: &View<'static>, input: &[char]) {
+    let buffer = controller.buffer().unwrap();
+    let mut buffer = buffer.write();
+    buffer.delete_content(None);
+    buffer.append(GOTO_PROMPT.as_bytes());
+
+    let s: String = input.iter().collect();
+    buffer.append(s.as_bytes());
+    if input.is_empty() {
+        buffer.append(GOTO_HINT.as_bytes());
+    }
+}
+
+/// Adds 'c' to the prompt (or removes the last character), then previews the target.
+fn goto_line_controller_update(
     editor: &mut Editor<'static>,
     env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View<'static>>>,
+    c: Option<char>,
 ) {
-    let mut array = vec![];
+    let text_view_id = match view.read().controlled_view {
+        Some(id) => id,
+        None => return,
+    };
+    let text_view = get_view_by_id(editor, text_view_id);
 
-    let goto_line_str_size = {
-        let v = view.read();
+    let offset = {
+        let mut tv = text_view.write();
+        let buffer = tv.buffer().unwrap();
 
-        if let Some(text_view_id) = v.controlled_view {
-            let text_view = get_view_by_id(editor, text_view_id);
-            let text_view = text_view.read();
+        let gtm = tv.mode_ctx_mut::<GotoLineModeContext>("goto-line-mode");
+        match c {
+            Some(c) => gtm.goto_line_str.push(c),
+            None => {
+                gtm.goto_line_str.pop();
+            }
+        }
+        goto_line_render_prompt(&view.read(), &gtm.goto_line_str);
+
+        let line_str: String = gtm.goto_line_str.iter().collect();
+        let (origin, _) = gtm.origin.unwrap_or((0, 0));
 
-            let gtm = text_view.mode_ctx::<GotoLineModeContext>("goto-line-mode");
-            gtm.goto_line_str.len()
-        } else {
-            return;
+        let target = parse_goto_target(&line_str);
+        dbg_println!("goto target {:?}", target);
+
+        let tm = tv.mode_ctx::<TextModeContext>("text-mode");
+        let codec = tm.text_codec.as_ref();
+        let buffer = buffer.read();
+        match target {
+            // the lines of an unindexed buffer are walked: applied by goto_line_controller_stop only
+            Some(GotoTarget::Relative(_)) if !buffer.indexed => None,
+            Some(target) => Some(goto_target_offset(&buffer, codec, origin, target)),
+            None => None,
         }
     };
 
+    match offset {
+        Some(offset) => goto_line_set_target_offset(editor, env, &text_view, offset),
+        // nothing to preview
+        None => goto_line_restore_origin(&mut text_view.write()),
+    }
+}
+
+/// Puts the main mark and the screen of 'text_view' back to their position before the prompt.
+fn goto_line_restore_origin(text_view: &mut View<'static>) {
+    let gtm = text_view.mode_ctx::<GotoLineModeContext>("goto-line-mode");
+    let (origin, start_offset) = match gtm.origin {
+        Some(origin) => origin,
+        None => return,
+    };
+
+    text_view.start_offset = start_offset;
+    let tm = text_view.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.marks.clear();
+    tm.marks.push(Mark::new(origin));
+    tm.mark_index = 0;
+}
+
+pub fn goto_line_controller_add_char(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
     // filter input event
-    {
+    let c = {
         let v = view.read();
 
         assert!(v.input_ctx.trigger.len() > 0);
@@ -353,135 +697,76 @@ pub fn goto_line_controller_add_char(
                     KeyModifiers {
                         ctrl: false,
                         alt: false,
-                        shift: false,
+                        shift: _,
                     },
-            } => {
-                if (*c >= '0' && *c <= '9')
-                    && ((goto_line_str_size == 0 && *c != '0') || goto_line_str_size > 0)
-                {
-                    array.push(*c);
-                }
-            }
+            } if GOTO_CHARS.contains(*c) => *c,
 
             _ => {
                 return;
             }
         }
-
-        if array.is_empty() {
-            return;
-        }
-    }
-
-    // compute target line number
-    let target_line = {
-        let v = view.read();
-        if let Some(text_view_id) = v.controlled_view {
-            let text_view = get_view_by_id(editor, text_view_id);
-            let mut text_view = text_view.write();
-
-            let buffer = text_view.buffer().unwrap();
-
-            // do this at start and store in gtm
-            let nb_lines = get_byte_count(&buffer.read(), '\n' as usize).unwrap_or(0);
-
-            let gtm = text_view.mode_ctx_mut::<GotoLineModeContext>("goto-line-mode");
-            if gtm.eof {
-                return;
-            }
-
-            gtm.goto_line_str.append(&mut array);
-
-            let line_str: String = gtm.goto_line_str.iter().collect();
-
-            dbg_println!("nb_lines = {:?}", nb_lines);
-
-            let n = line_str.parse::<u64>().unwrap_or(0);
-
-            dbg_println!("goto line target {}", n);
-
-            // render line number
-            let buffer = v.buffer().unwrap();
-            let mut buffer = buffer.write();
-            buffer.delete_content(None);
-            buffer.append(b"Goto: ");
-
-            let s: String = gtm.goto_line_str.iter().collect();
-            buffer.append(s.as_bytes());
-            gtm.eof = n > nb_lines;
-            std::cmp::min(n, nb_lines + 1)
-        } else {
-            return;
-        }
     };
 
-    let v = view.read();
-    if let Some(text_view_id) = v.controlled_view {
-        let text_view = get_view_by_id(editor, text_view_id);
-        goto_line_set_target_line(editor, env, &text_view, target_line);
-    }
+    goto_line_controller_update(editor, env, view, Some(c));
 }
 
-// refactor add+del (Some(array))
 pub fn goto_line_controller_del_char(
     editor: &mut Editor<'static>,
     env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View<'static>>>,
 ) {
-    // compute target line number
-    let target_line = {
-        let v = view.read();
-        if let Some(text_view_id) = v.controlled_view {
-            let text_view = get_view_by_id(editor, text_view_id);
-            let mut text_view = text_view.write();
-
-            let buffer = text_view.buffer().unwrap();
-
-            // do this at start and store in gtm
-            let nb_lines = get_byte_count(&buffer.read(), '\n' as usize).unwrap_or(0);
-
-            let gtm = text_view.mode_ctx_mut::<GotoLineModeContext>("goto-line-mode");
+    goto_line_controller_update(editor, env, view, None);
+}
 
-            gtm.goto_line_str.pop();
+/// Closes the prompt and keeps the previewed position.<br/>
+/// The relative targets not previewed (unindexed buffer) are applied now.
+pub fn goto_line_controller_stop(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let text_view_id = view.read().controlled_view;
+    if let Some(text_view_id) = text_view_id {
+        let text_view = get_view_by_id(editor, text_view_id);
+        let offset = {
+            let tv = text_view.read();
+            let buffer = tv.buffer().unwrap();
+            let buffer = buffer.read();
 
+            let gtm = tv.mode_ctx::<GotoLineModeContext>("goto-line-mode");
             let line_str: String = gtm.goto_line_str.iter().collect();
-
-            dbg_println!("nb_lines = {:?}", nb_lines);
-
-            let n = line_str.parse::<u64>().unwrap_or(0);
-
-            gtm.eof = n > nb_lines;
-
-            dbg_println!("goto line target {}", n);
-
-            // render line number
-            let buffer = v.buffer().unwrap();
-            let mut buffer = buffer.write();
-            buffer.delete_content(None);
-            buffer.append(b"Goto: ");
-
-            let s: String = gtm.goto_line_str.iter().collect();
-            buffer.append(s.as_bytes());
-            if n > nb_lines {
-                return;
+            match (parse_goto_target(&line_str), gtm.origin) {
+                (Some(target @ GotoTarget::Relative(_)), Some((origin, _))) if !buffer.indexed => {
+                    let tm = tv.mode_ctx::<TextModeContext>("text-mode");
+                    let codec = tm.text_codec.as_ref();
+                    Some(goto_target_offset(&buffer, codec, origin, target))
+                }
+                _ => None,
             }
-            n
-        } else {
-            return;
-        }
-    };
+        };
 
-    let v = view.read();
-    if let Some(text_view_id) = v.controlled_view {
-        let text_view = get_view_by_id(editor, text_view_id);
-        goto_line_set_target_line(editor, env, &text_view, target_line);
+        if let Some(offset) = offset {
+            goto_line_set_target_offset(editor, env, &text_view, offset);
+        }
     }
+
+    goto_line_controller_close(editor, env, view, false);
 }
 
-pub fn goto_line_controller_stop(
+/// Closes the prompt and returns to the initial position.
+pub fn goto_line_controller_cancel(
     editor: &mut Editor<'static>,
     env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View<'static>>>,
+) {
+    goto_line_controller_close(editor, env, view, true);
+}
+
+fn goto_line_controller_close(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    cancel: bool,
 ) {
     {
         let status_view_id = env.status_view_id.unwrap();
@@ -501,14 +786,34 @@ pub fn goto_line_controller_stop(
 
             text_view.controller = None;
 
+            let mark_offset = {
+                let tm = text_view.mode_ctx::<TextModeContext>("text-mode");
+                tm.marks[tm.mark_index].offset
+            };
+
+            if cancel {
+                goto_line_restore_origin(&mut text_view);
+            }
+
             let gtm = text_view.mode_ctx_mut::<GotoLineModeContext>("goto-line-mode");
+            let origin = gtm.origin;
             gtm.reset();
 
+            match origin {
+                // remember the position before the jump
+                Some((origin, _)) if !cancel && origin != mark_offset => {
+                    let mut history = gtm.history.write();
+                    if history.len() == GOTO_HISTORY_SIZE {
+                        history.remove(0);
+                    }
+                    history.push(origin);
+                }
+
+                _ => {}
+            }
+
             //
-            let buffer = v.buffer().unwrap();
-            let mut buffer = buffer.write();
-            buffer.delete_content(None);
-            buffer.append(b"Goto: ");
+            goto_line_render_prompt(&v, &[]);
         }
 
         // set input focus to
@@ -520,26 +825,28 @@ pub fn goto_line_controller_stop(
 }
 
 pub fn goto_line_set_target_line(
-    mut editor: &mut Editor<'static>,
-    mut env: &mut EditorEnv<'static>,
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View<'static>>>,
     target_line: u64,
 ) {
-    {
+    let offset = {
         let buffer = view.read().buffer().unwrap();
         let buffer = buffer.read();
-        let max_offset = buffer.size();
+        line_offset(&buffer, target_line)
+    };
+
+    goto_line_set_target_offset(editor, env, view, offset);
+}
+
+pub fn goto_line_set_target_offset(
+    mut editor: &mut Editor<'static>,
+    mut env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    offset: u64,
+) {
+    {
         let mut v = view.write();
-        let offset = if target_line <= 1 {
-            0
-        } else {
-            let line_number = target_line.saturating_sub(1);
-            if let Some(offset) = find_nth_byte_offset(&buffer, '\n' as u8, line_number) {
-                offset + 1
-            } else {
-                max_offset as u64
-            }
-        };
 
         {
             // if offscreen // user option goto-line-mode:always-center-around-line = true ?
@@ -563,3 +870,95 @@ pub fn goto_line_set_target_line(
         );
     }
 }
+
+#[test]
+fn test_goto_parse_target() {
+    let line = |line, column| Some(GotoTarget::Line { line, column });
+
+    assert_eq!(parse_goto_target("42"), line(42, None));
+    assert_eq!(parse_goto_target("42:7"), line(42, Some(7)));
+    assert_eq!(parse_goto_target("42:"), line(42, None));
+    assert_eq!(parse_goto_target("@1024"), Some(GotoTarget::Offset(1024)));
+    assert_eq!(parse_goto_target("@0x400"), Some(GotoTarget::Offset(1024)));
+    assert_eq!(parse_goto_target("0xFF"), Some(GotoTarget::Offset(255)));
+    assert_eq!(parse_goto_target("50%"), Some(GotoTarget::Percent(50)));
+    assert_eq!(parse_goto_target("150%"), Some(GotoTarget::Percent(100)));
+    assert_eq!(parse_goto_target("+10"), Some(GotoTarget::Relative(10)));
+    assert_eq!(parse_goto_target("-3"), Some(GotoTarget::Relative(-3)));
+
+    // incomplete
+    assert_eq!(parse_goto_target(""), None);
+    assert_eq!(parse_goto_target("@"), None);
+    assert_eq!(parse_goto_target("0x"), None);
+    assert_eq!(parse_goto_target("+"), None);
+    assert_eq!(parse_goto_target("%"), None);
+}
+
+#[test]
+fn test_goto_target_offset() {
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("goto-target-offset")
+        .internal(true)
+        .finalize()
+        .unwrap();
+    let mut buffer = buffer.write();
+    // 4 lines of 10 bytes, "é" is 2 bytes
+    buffer.append("line 1 ab\nline 2 é\nline 3 ab\nline 4 ab\n".as_bytes());
+    buffer.indexed = true;
+
+    let codec = Utf8Codec::new();
+    let line = |line, column| GotoTarget::Line { line, column };
+
+    assert_eq!(goto_target_offset(&buffer, &codec, 0, line(1, None)), 0);
+    assert_eq!(goto_target_offset(&buffer, &codec, 0, line(3, None)), 20);
+    assert_eq!(goto_target_offset(&buffer, &codec, 0, line(9, None)), 40);
+
+    // columns are characters, they stop at the end of the line
+    assert_eq!(goto_target_offset(&buffer, &codec, 0, line(2, Some(8))), 17);
+    assert_eq!(goto_target_offset(&buffer, &codec, 0, line(2, Some(9))), 19);
+    assert_eq!(
+        goto_target_offset(&buffer, &codec, 0, line(2, Some(99))),
+        19
+    );
+
+    assert_eq!(
+        goto_target_offset(&buffer, &codec, 0, GotoTarget::Offset(12)),
+        12
+    );
+    assert_eq!(
+        goto_target_offset(&buffer, &codec, 0, GotoTarget::Offset(99)),
+        40
+    );
+
+    // 18 is inside "é" (17..19)
+    assert_eq!(
+        goto_target_offset(&buffer, &codec, 0, GotoTarget::Percent(45)),
+        19
+    );
+    assert_eq!(
+        goto_target_offset(&buffer, &codec, 0, GotoTarget::Percent(100)),
+        40
+    );
+
+    for indexed in [true, false] {
+        buffer.indexed = indexed;
+        assert_eq!(
+            goto_target_offset(&buffer, &codec, 25, GotoTarget::Relative(1)),
+            30
+        );
+        assert_eq!(
+            goto_target_offset(&buffer, &codec, 25, GotoTarget::Relative(-2)),
+            0
+        );
+        assert_eq!(
+            goto_target_offset(&buffer, &codec, 25, GotoTarget::Relative(-9)),
+            0
+        );
+        assert_eq!(
+            goto_target_offset(&buffer, &codec, 25, GotoTarget::Relative(9)),
+            40
+        );
+    }
+}
@@ -0,0 +1,700 @@
+/*
+    The changed lines of a buffer compared with its last saved state, drawn by the line-number gutter.
+
+    The saved content is read back from the storage of the buffer (the file as it was
+    opened or last saved). The current lines are copied at the first modification
+    and are then updated by the BufferDataInserted/BufferDataRemoved events:
+    only the lines touched by an operation are read again.
+    The saved content is read and compared with the buffer in the executor thread.
+    Then, when the gutter is drawn, only the changed lines and the hunks they touch
+    are compared again.
+
+    Saving resets the changed flag of the buffer: the copies are dropped
+    and are rebuilt at the next modification.
+
+    With the GitHead base, the lines are compared with the HEAD revision of the file
+    (git show HEAD:path), the tracking starts with the first display.
+    The HEAD revision is read again after each save.
+*/
+
+use parking_lot::RwLock;
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+
+use crate::core::buffer;
+use crate::core::buffer::Buffer;
+use crate::core::buffer::BufferEvent;
+use crate::core::buffer::BufferEventCb;
+use crate::core::diff::diff_lines;
+use crate::core::diff::line_offsets;
+use crate::core::diff::split_lines;
+use crate::core::diff::DiffHunk;
+use crate::core::editor::Editor;
+use crate::core::editor::EditorEnv;
+use crate::core::event::pending_input_event_inc;
+use crate::core::event::Event;
+use crate::core::event::Message;
+use crate::core::git::GitFile;
+use crate::core::view::View;
+
+use crate::core::modes::text_mode::PostInputAction;
+use crate::core::modes::text_mode::TextModeAction;
+use crate::core::modes::text_mode::TextModeContext;
+
+/// The buffers larger than this (current or saved size) are not tracked.
+const MAX_TRACKED_SIZE: u64 = 16 * 1024 * 1024;
+
+// buffer line changes map
+lazy_static! {
+    static ref BUFFER_LINE_CHANGES_MAP: RwLock<HashMap<buffer::Id, Arc<RwLock<LineChanges>>>> =
+        RwLock::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// lines were removed after this line
+    Removed,
+    /// lines were removed before the first line
+    RemovedAbove,
+}
+
+/// The content compared with the buffer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChangesBase {
+    /// the file as it was opened or last saved
+    #[default]
+    Saved,
+    /// the HEAD revision of the file in its git repository
+    GitHead,
+}
+
+#[derive(Debug, Default)]
+pub struct LineChanges {
+    base: ChangesBase,
+    /// the base cannot be read
+    failed: bool,
+    /// the base is read in the executor thread
+    loading: bool,
+    /// identifies the last load, the results of the previous ones are dropped
+    load_id: usize,
+    /// the changed flag of the buffer at the last sync
+    was_changed: bool,
+    saved: Vec<u8>,
+    saved_lines: Vec<usize>,
+    /// the current lines, None while the buffer is not modified
+    lines: Option<Vec<Vec<u8>>>,
+    /// the offset of each current line followed by the size of the lines
+    starts: Vec<u64>,
+    hunks: Vec<DiffHunk>,
+    /// sorted, non overlapping (lines, change)
+    markers: Vec<(Range<usize>, LineChange)>,
+    /// the current lines modified since the hunks were computed
+    changed_lines: Option<Range<usize>>,
+    /// the number of lines when the hunks were computed
+    diffed_lines: usize,
+}
+
+impl LineChanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn reset(&mut self) {
+        *self = LineChanges {
+            base: self.base,
+            load_id: self.load_id,
+            ..LineChanges::default()
+        };
+    }
+
+    pub fn set_base(&mut self, base: ChangesBase) {
+        if self.base != base {
+            self.base = base;
+            self.reset();
+        }
+    }
+
+    /// All the current lines are compared with the saved ones at the next update_hunks.
+    fn load(&mut self, saved: Vec<u8>, current: &[u8]) {
+        self.saved_lines = line_offsets(&saved);
+        self.saved = saved;
+
+        let lines = to_lines(current);
+        self.starts = line_starts(0, &lines);
+        self.changed_lines = Some(0..lines.len());
+        self.diffed_lines = self.saved_lines.len() - 1;
+        self.hunks.clear();
+        self.lines = Some(lines);
+    }
+
+    /// Starts the tracking at the first modification (GitHead: first call), drops it after a save.<br/>
+    /// The base is read in the executor thread, the changed lines are compared again.
+    pub fn sync(&mut self, editor: &Editor<'static>, buffer: &Buffer) {
+        let saved_now = self.was_changed && !buffer.changed;
+        self.was_changed = buffer.changed;
+
+        let drop = match self.base {
+            ChangesBase::Saved => !buffer.changed,
+            ChangesBase::GitHead => saved_now,
+        };
+        if drop && (self.lines.is_some() || self.failed || self.loading) {
+            self.reset();
+        }
+
+        if self.base == ChangesBase::Saved && !buffer.changed {
+            return;
+        }
+
+        if self.lines.is_none() {
+            // no saved state
+            if self.loading || self.failed || buffer.file_name().is_empty() {
+                return;
+            }
+
+            let size = buffer.size() as u64;
+            if size > MAX_TRACKED_SIZE || buffer.storage_size() > MAX_TRACKED_SIZE {
+                return;
+            }
+
+            self.loading = true;
+            self.load_id += 1;
+            start_loading(editor, buffer, self.base, self.load_id);
+            return;
+        }
+
+        self.update_hunks();
+    }
+
+    /// Keeps the load/sync state of 'self'.
+    fn install(&mut self, loaded: LineChanges) {
+        *self = LineChanges {
+            load_id: self.load_id,
+            was_changed: self.was_changed,
+            ..loaded
+        };
+    }
+
+    /// Compares the changed lines, extended to the hunks they touch, with the matching saved lines.<br/>
+    /// The other hunks are kept.
+    fn update_hunks(&mut self) {
+        let (lines, changed) = match (&self.lines, self.changed_lines.take()) {
+            (Some(lines), Some(changed)) => (lines, changed),
+            _ => return,
+        };
+
+        // the lines after the changed ones are not modified
+        let delta = lines.len() as isize - self.diffed_lines as isize;
+        let mut start = changed.start;
+        let mut end = (changed.end as isize - delta) as usize;
+
+        let first = self.hunks.partition_point(|h| h.new.end < start);
+        let mut last = first;
+        while last < self.hunks.len() && self.hunks[last].new.start <= end {
+            start = std::cmp::min(start, self.hunks[last].new.start);
+            end = std::cmp::max(end, self.hunks[last].new.end);
+            last += 1;
+        }
+
+        let size_diff = |hunks: &[DiffHunk]| -> isize {
+            hunks
+                .iter()
+                .map(|h| h.old.len() as isize - h.new.len() as isize)
+                .sum()
+        };
+        let before = size_diff(&self.hunks[..first]);
+        let old_start = (start as isize + before) as usize;
+        let old_end = (end as isize + before + size_diff(&self.hunks[first..last])) as usize;
+        let new_end = (end as isize + delta) as usize;
+
+        let old = split_lines(&self.saved, &self.saved_lines[old_start..=old_end]);
+        let new: Vec<&[u8]> = lines[start..new_end].iter().map(|l| l.as_slice()).collect();
+
+        let region = diff_lines(&old, &new).into_iter().map(|h| DiffHunk {
+            old: h.old.start + old_start..h.old.end + old_start,
+            new: h.new.start + start..h.new.end + start,
+        });
+        let after = self.hunks[last..].iter().map(|h| DiffHunk {
+            old: h.old.clone(),
+            new: (h.new.start as isize + delta) as usize..(h.new.end as isize + delta) as usize,
+        });
+
+        let mut hunks = self.hunks[..first].to_vec();
+        hunks.extend(region);
+        hunks.extend(after);
+
+        self.hunks = hunks;
+        self.markers = build_markers(&self.hunks);
+        self.diffed_lines = lines.len();
+    }
+
+    pub fn hunks(&self) -> &[DiffHunk] {
+        &self.hunks
+    }
+
+    /// Returns the change displayed at 'line' (0-based).
+    pub fn line_change(&self, line: usize) -> Option<LineChange> {
+        let idx = self.markers.partition_point(|m| m.0.end <= line);
+        match self.markers.get(idx) {
+            Some((r, change)) if r.start <= line => Some(*change),
+            _ => None,
+        }
+    }
+
+    /// Returns the line (0-based) of 'offset'.
+    pub fn line_at(&self, offset: u64) -> usize {
+        match self.lines {
+            Some(_) => self.find_line(offset).0,
+            None => 0,
+        }
+    }
+
+    /// Returns the index of the line of 'offset' and the offset of this line.<br/>
+    /// Past the end, returns the number of lines and the size of the lines.
+    fn find_line(&self, offset: u64) -> (usize, u64) {
+        let n = self.starts.partition_point(|s| *s <= offset);
+        if n == 0 || n == self.starts.len() {
+            let last = self.starts.len().saturating_sub(1);
+            return (last, self.starts.last().copied().unwrap_or(0));
+        }
+        (n - 1, self.starts[n - 1])
+    }
+
+    /// Replaces the current lines 'range' by 'new_lines'.
+    fn replace_lines(&mut self, range: Range<usize>, new_lines: Vec<Vec<u8>>) {
+        let lines = match self.lines {
+            Some(ref mut lines) => lines,
+            None => return,
+        };
+
+        let count = new_lines.len();
+        let removed: u64 = self.starts[range.end] - self.starts[range.start];
+        let added: u64 = new_lines.iter().map(|l| l.len() as u64).sum();
+        let starts = line_starts(self.starts[range.start], &new_lines);
+
+        lines.splice(range.clone(), new_lines);
+
+        // the offsets of the following lines move
+        self.starts
+            .splice(range.clone(), starts[..count].iter().cloned());
+        for s in self.starts[range.start + count..].iter_mut() {
+            *s = *s + added - removed;
+        }
+
+        // the previous changes move with the lines
+        let moved = |l: usize| {
+            if l >= range.end {
+                l + count - range.len()
+            } else {
+                std::cmp::min(l, range.start + count)
+            }
+        };
+        self.changed_lines = Some(match self.changed_lines.take() {
+            Some(r) => {
+                std::cmp::min(r.start, range.start)
+                    ..std::cmp::max(moved(r.end), range.start + count)
+            }
+            None => range.start..range.start + count,
+        });
+    }
+
+    /// Returns the hunk displayed at 'line': its changed lines or the line before its removed lines.
+    fn hunk_at(&self, line: usize) -> Option<&DiffHunk> {
+        self.hunks.iter().find(|h| {
+            h.new.contains(&line)
+                || (h.new.is_empty()
+                    && (h.new.start == line + 1 || (h.new.start == 0 && line == 0)))
+        })
+    }
+
+    /// Returns the offset and the size of the lines of the hunk at 'line' and their saved content.
+    pub fn revert_hunk_at(&self, line: usize) -> Option<(u64, usize, Vec<u8>)> {
+        self.lines.as_ref()?;
+        let h = self.hunk_at(line)?;
+
+        let start = self.starts[h.new.start];
+        let size = (self.starts[h.new.end] - start) as usize;
+        let data = self.saved[self.saved_lines[h.old.start]..self.saved_lines[h.old.end]].to_vec();
+
+        Some((start, size, data))
+    }
+
+    /// 'size' bytes were inserted at 'offset', 'read' returns the new content of the buffer.
+    fn on_insert(&mut self, offset: u64, size: usize, read: impl FnOnce(u64, usize) -> Vec<u8>) {
+        let lines = match self.lines {
+            Some(ref lines) => lines,
+            None => return,
+        };
+
+        let (mut first, mut start) = self.find_line(offset);
+
+        // the inserted data continues the last line
+        if first == lines.len() && first > 0 && lines[first - 1].last() != Some(&b'\n') {
+            first -= 1;
+            start -= lines[first].len() as u64;
+        }
+
+        let end = std::cmp::min(first + 1, lines.len());
+        let len = (self.starts[end] - start) as usize;
+
+        self.replace_lines(first..end, to_lines(&read(start, len + size)));
+    }
+
+    /// 'size' bytes were removed at 'offset', 'read' returns the new content of the buffer.
+    fn on_remove(&mut self, offset: u64, size: usize, read: impl FnOnce(u64, usize) -> Vec<u8>) {
+        let lines = match self.lines {
+            Some(ref lines) => lines,
+            None => return,
+        };
+
+        // the line after the removed bytes is joined
+        let (first, start) = self.find_line(offset);
+        let (last, _) = self.find_line(offset + size as u64);
+
+        let end = std::cmp::min(last + 1, lines.len());
+        let len = (self.starts[end] - start) as usize;
+
+        self.replace_lines(first..end, to_lines(&read(start, len.saturating_sub(size))));
+    }
+}
+
+fn to_lines(data: &[u8]) -> Vec<Vec<u8>> {
+    split_lines(data, &line_offsets(data))
+        .iter()
+        .map(|l| l.to_vec())
+        .collect()
+}
+
+/// Returns the offset of each line of 'lines' starting at 'offset', followed by the end offset.
+fn line_starts(offset: u64, lines: &[Vec<u8>]) -> Vec<u64> {
+    let mut starts = Vec::with_capacity(lines.len() + 1);
+    let mut start = offset;
+    starts.push(start);
+    for l in lines {
+        start += l.len() as u64;
+        starts.push(start);
+    }
+    starts
+}
+
+/// Reads the base of 'buffer' and compares it with the content of the buffer.<br/>
+/// Returns the revision of the compared content.
+fn load_changes(
+    buffer: &Arc<RwLock<Buffer<'static>>>,
+    base: ChangesBase,
+) -> Option<(usize, LineChanges)> {
+    let (file_name, revision, current, mut saved) = {
+        let buffer = buffer.read();
+        let mut current = Vec::with_capacity(buffer.size());
+        buffer.read(0, buffer.size(), &mut current);
+        let mut saved = vec![];
+        if base == ChangesBase::Saved {
+            buffer.read_storage(&mut saved);
+        }
+        (buffer.file_name(), buffer.nr_changes(), current, saved)
+    };
+
+    if base == ChangesBase::GitHead {
+        saved = GitFile::new(&file_name).and_then(|f| f.show("HEAD"))?;
+    }
+
+    let mut changes = LineChanges {
+        base,
+        ..LineChanges::default()
+    };
+    changes.load(saved, &current);
+    changes.update_hunks();
+
+    Some((revision, changes))
+}
+
+/// Reads the base of 'buffer' in the executor thread, the ui is refreshed when the changes are available.<br/>
+/// The changes are dropped if the buffer was modified meanwhile: they are read again at the next sync.
+fn start_loading(editor: &Editor<'static>, buffer: &Buffer, base: ChangesBase, load_id: usize) {
+    let buffer = match editor.buffer_map.read().get(&buffer.id) {
+        Some(buffer) => Arc::clone(buffer),
+        None => return,
+    };
+    let core_tx = editor.core_tx.clone();
+
+    let _ = editor.executor_tx.send((
+        0,
+        Box::new(move || {
+            let loaded = load_changes(&buffer, base);
+
+            {
+                let buffer = buffer.read();
+                let changes = match BUFFER_LINE_CHANGES_MAP.read().get(&buffer.id) {
+                    Some(changes) => Arc::clone(changes),
+                    None => return,
+                };
+                let mut changes = changes.write();
+                if !changes.loading || changes.load_id != load_id {
+                    return;
+                }
+
+                changes.loading = false;
+                match loaded {
+                    Some((revision, loaded)) if revision == buffer.nr_changes() => {
+                        changes.install(loaded)
+                    }
+                    Some(_) => {}
+                    None => changes.failed = true,
+                }
+            }
+
+            let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+            let msg = Message::new(0, 0, ts, Event::RefreshView);
+            pending_input_event_inc(1);
+            core_tx.send(msg).unwrap_or(());
+        }),
+    ));
+}
+
+fn build_markers(hunks: &[DiffHunk]) -> Vec<(Range<usize>, LineChange)> {
+    let mut markers: Vec<(Range<usize>, LineChange)> = vec![];
+
+    for h in hunks {
+        let marker = if h.new.is_empty() {
+            if h.new.start == 0 {
+                (0..1, LineChange::RemovedAbove)
+            } else {
+                (h.new.start - 1..h.new.start, LineChange::Removed)
+            }
+        } else if h.old.is_empty() {
+            (h.new.clone(), LineChange::Added)
+        } else {
+            (h.new.clone(), LineChange::Modified)
+        };
+
+        // keep the changed lines of the previous hunk
+        match markers.last() {
+            Some(prev) if prev.0.end > marker.0.start => {}
+            _ => markers.push(marker),
+        }
+    }
+
+    markers
+}
+
+/// Returns the changed lines of the buffer, its modifications are tracked from now on.
+pub fn get_buffer_line_changes(buffer: &Arc<RwLock<Buffer<'static>>>) -> Arc<RwLock<LineChanges>> {
+    let buffer_id = buffer.read().id;
+
+    if let Some(changes) = BUFFER_LINE_CHANGES_MAP.read().get(&buffer_id) {
+        return Arc::clone(changes);
+    }
+
+    let changes = Arc::new(RwLock::new(LineChanges::new()));
+    BUFFER_LINE_CHANGES_MAP
+        .write()
+        .insert(buffer_id, Arc::clone(&changes));
+
+    let tracker = LineChangesTracker {
+        changes: Arc::clone(&changes),
+    };
+    buffer.write().register_subscriber(Box::new(tracker));
+
+    changes
+}
+
+struct LineChangesTracker {
+    changes: Arc<RwLock<LineChanges>>,
+}
+
+impl BufferEventCb for LineChangesTracker {
+    fn cb(&mut self, buffer: &Buffer, event: &BufferEvent) {
+        let read = |offset: u64, size: usize| {
+            let mut data = Vec::with_capacity(size);
+            buffer.read(offset, size, &mut data);
+            data
+        };
+
+        match event {
+            BufferEvent::BufferDataInserted { offset, size, .. } => {
+                self.changes.write().on_insert(*offset, *size, read);
+            }
+
+            BufferEvent::BufferDataRemoved { offset, size, .. } => {
+                self.changes.write().on_remove(*offset, *size, read);
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// Restores the saved content of the changed lines at the main mark.
+pub fn revert_hunk(
+    editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let v = &mut view.write();
+    let buffer = match v.buffer() {
+        Some(buffer) => buffer,
+        None => return,
+    };
+
+    let offset = {
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        tm.marks[tm.mark_index].offset
+    };
+
+    let changes = get_buffer_line_changes(&buffer);
+    let revert = {
+        let buffer = buffer.read();
+        let mut changes = changes.write();
+        changes.sync(editor, &buffer);
+        changes.revert_hunk_at(changes.line_at(offset))
+    };
+
+    let (start, size, data) = match revert {
+        Some(revert) => revert,
+        None => return,
+    };
+
+    {
+        let mut buffer = buffer.write();
+        if size > 0 {
+            buffer.remove(start, size, None);
+        }
+        if !data.is_empty() {
+            buffer.insert(start, data.len(), &data);
+        }
+    }
+
+    // the marks of the reverted lines go to their start
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    let end = start + size as u64;
+    for m in tm.marks.iter_mut() {
+        if m.offset >= end {
+            m.offset = m.offset - size as u64 + data.len() as u64;
+        } else if m.offset > start {
+            m.offset = start;
+        }
+    }
+
+    tm.pre_compose_action.push(PostInputAction::CheckMarks);
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+    tm.prev_action = TextModeAction::BufferModification;
+}
+
+#[cfg(test)]
+fn apply_edits(edits: &[(bool, u64, &[u8])], text: &[u8]) {
+    let mut data = text.to_vec();
+    let mut changes = LineChanges::new();
+    changes.load(text.to_vec(), &data);
+
+    for (insert, offset, bytes) in edits {
+        let offset = *offset as usize;
+        if *insert {
+            data.splice(offset..offset, bytes.iter().cloned());
+            changes.on_insert(offset as u64, bytes.len(), |o, n| {
+                data[o as usize..o as usize + n].to_vec()
+            });
+        } else {
+            data.drain(offset..offset + bytes.len());
+            changes.on_remove(offset as u64, bytes.len(), |o, n| {
+                data[o as usize..o as usize + n].to_vec()
+            });
+        }
+        assert_eq!(changes.lines.as_ref().unwrap(), &to_lines(&data));
+        assert_eq!(
+            changes.starts,
+            line_starts(0, changes.lines.as_ref().unwrap())
+        );
+
+        changes.update_hunks();
+        check_hunks(&changes);
+    }
+}
+
+/// The lines outside of the hunks are the saved ones.
+#[cfg(test)]
+fn check_hunks(changes: &LineChanges) {
+    let old = split_lines(&changes.saved, &changes.saved_lines);
+    let new = changes.lines.as_ref().unwrap();
+    let end = DiffHunk {
+        old: old.len()..old.len(),
+        new: new.len()..new.len(),
+    };
+
+    let (mut o, mut n) = (0, 0);
+    for h in changes.hunks.iter().chain(std::iter::once(&end)) {
+        assert_eq!(h.old.start - o, h.new.start - n);
+        for k in 0..h.old.start - o {
+            assert_eq!(old[o + k], new[n + k].as_slice());
+        }
+        o = h.old.end;
+        n = h.new.end;
+    }
+}
+
+#[test]
+fn test_line_changes_incremental_update() {
+    let text = b"line 1\nline 2\nline 3\n";
+
+    apply_edits(&[(true, 0, b"x")], text);
+    apply_edits(&[(true, 7, b"new\n")], text);
+    apply_edits(&[(true, 21, b"end")], text);
+    apply_edits(&[(true, 21, b"end"), (true, 24, b" of file")], text);
+    apply_edits(&[(true, 21, b"end"), (true, 23, b"\n")], text);
+    apply_edits(&[(false, 6, b"\n")], text);
+    apply_edits(&[(false, 5, b"1\nline 2\n")], text);
+    apply_edits(&[(false, 20, b"\n")], text);
+    apply_edits(&[(false, 0, b"line 1\nline 2\nline 3\n")], text);
+    apply_edits(&[(false, 0, b"line 1\n"), (true, 0, b"a\nb\nc")], text);
+    apply_edits(&[(true, 0, b"first\n")], b"");
+    apply_edits(&[(true, 0, b"no newline")], b"");
+
+    // several hunks, the changed lines are compared again
+    let text = b"0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    apply_edits(
+        &[
+            (true, 2, b"x"),
+            (true, 15, b"new\n"),
+            (false, 2, b"x2\n"),
+            (true, 2, b"a\nb\n"),
+            (false, 8, b"4\n5\n"),
+            (true, 0, b"first\n"),
+        ],
+        text,
+    );
+}
+
+#[test]
+fn test_line_changes_markers() {
+    let mut changes = LineChanges::new();
+    changes.load(b"a\nb\nc\nd\ne\n".to_vec(), b"a\nB\nc\nnew\nd\n");
+    changes.update_hunks();
+
+    assert_eq!(changes.line_change(0), None);
+    assert_eq!(changes.line_change(1), Some(LineChange::Modified));
+    assert_eq!(changes.line_change(2), None);
+    assert_eq!(changes.line_change(3), Some(LineChange::Added));
+    assert_eq!(changes.line_change(4), Some(LineChange::Removed));
+    assert_eq!(changes.line_change(5), None);
+
+    changes.load(b"a\nb\n".to_vec(), b"b\n");
+    changes.update_hunks();
+    assert_eq!(changes.line_change(0), Some(LineChange::RemovedAbove));
+
+    // revert
+    changes.load(b"a\nb\nc\n".to_vec(), b"a\nB\nx\nc\n");
+    changes.update_hunks();
+    assert_eq!(changes.revert_hunk_at(0), None);
+    assert_eq!(changes.revert_hunk_at(2), Some((2, 4, b"b\n".to_vec())));
+
+    changes.load(b"a\nb\nc\n".to_vec(), b"a\nc\n");
+    changes.update_hunks();
+    assert_eq!(changes.line_at(2), 1);
+    assert_eq!(changes.revert_hunk_at(0), Some((2, 0, b"b\n".to_vec())));
+}
@@ -31,6 +31,11 @@
     and keep a per node buffer_revision
 
     Must we re-index before remove ?
+
+    While the buffer is indexed (in the worker thread) the nodes are indexed in order:
+    the BufferNodeIndexed events extend the indexed prefix of the buffer (size, newlines).
+    The lines inside the prefix are numbered exactly, the others are estimated with the
+    newline density of the prefix and displayed as "~N" until the indexing completes.
 */
 
 use std::any::Any;
@@ -38,7 +43,10 @@ use std::any::Any;
 use parking_lot::RwLock;
 
 use std::rc::Rc;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use crate::core::event::*;
 
@@ -79,10 +87,18 @@ use crate::core::view::ViewEvent;
 use crate::core::view::ViewEventDestination;
 use crate::core::view::ViewEventSource;
 
+use crate::core::modes::text_mode::block_selection::line_start;
+use crate::core::modes::text_mode::block_selection::next_line_start;
 use crate::core::modes::text_mode::mark::Mark;
+use crate::core::modes::text_mode::PostInputAction;
 use crate::core::modes::text_mode::TextModeContext;
+use crate::core::screen::{line_logical_first, line_logical_last};
 
 use crate::core::editor::config_var_get;
+use crate::core::modes::line_changes::get_buffer_line_changes;
+use crate::core::modes::line_changes::ChangesBase;
+use crate::core::modes::line_changes::LineChange;
+use crate::core::screen::Screen;
 
 use crate::core::event::InputEvent;
 
@@ -111,6 +127,62 @@ fn num_digit(v: u64) -> u64 {
     }
 }
 
+const DISPLAY_ABSOLUTE: usize = 0;
+const DISPLAY_RELATIVE: usize = 1;
+/// relative numbers, the line of the main mark shows its absolute number
+const DISPLAY_HYBRID: usize = 2;
+const DISPLAY_OFFSET: usize = 3;
+const DISPLAY_HEX_OFFSET: usize = 4;
+const DISPLAY_MODE_COUNT: usize = 5;
+
+/// the interval between two refreshes while the buffer is indexed
+const INDEXING_REFRESH_DELAY: Duration = Duration::from_millis(1000);
+
+/// the number of bytes checked for nul bytes to detect the binary files
+const BINARY_CHECK_SIZE: usize = 8192;
+
+/// "line-number-mode:display": absolute, relative, hybrid, offset, hex-offset (or their index).<br/>
+/// The binary files use hex-offset when the variable is not set.
+fn parse_display_mode(value: &str) -> Option<usize> {
+    match value.trim() {
+        "absolute" => Some(DISPLAY_ABSOLUTE),
+        "relative" => Some(DISPLAY_RELATIVE),
+        "hybrid" => Some(DISPLAY_HYBRID),
+        "offset" => Some(DISPLAY_OFFSET),
+        "hex-offset" => Some(DISPLAY_HEX_OFFSET),
+        v => v
+            .parse::<usize>()
+            .ok()
+            .map(|v| std::cmp::min(v, DISPLAY_MODE_COUNT - 1)),
+    }
+}
+
+/// Returns true if the start of 'buffer' contains a nul byte.
+fn looks_binary(buffer: &Buffer) -> bool {
+    let mut data = vec![];
+    buffer.read(
+        0,
+        std::cmp::min(buffer.size(), BINARY_CHECK_SIZE),
+        &mut data,
+    );
+    data.contains(&0)
+}
+
+fn num_hex_digit(v: u64) -> u64 {
+    // at least 8 digits, like hexdump
+    let bits = 64 - v.leading_zeros() as u64;
+    std::cmp::max(8, bits.div_ceil(4))
+}
+
+/// "line-number-mode:changes": 0 (hidden), head (compared with the git HEAD revision), the last save otherwise.
+fn get_changes_base(editor: &Editor<'static>) -> Option<ChangesBase> {
+    match config_var_get(editor, "line-number-mode:changes").map(|v| v.trim()) {
+        Some("0") => None,
+        Some("head") => Some(ChangesBase::GitHead),
+        _ => Some(ChangesBase::Saved),
+    }
+}
+
 static LINENUM_INPUT_MAP: &str = r#"
 [
   {
@@ -133,7 +205,8 @@ lazy_static! {
 
 struct LineNumberBufferMetaData {
     cb_installed: bool,
-    _root_idx: Option<usize>,
+    /// the size and newline count of the indexed nodes at the start of the buffer
+    indexed_prefix: (u64, u64),
 }
 
 impl LineNumberBufferMetaData {
@@ -141,11 +214,36 @@ impl LineNumberBufferMetaData {
         dbg_println!("LineNumberBufferMetaData");
         LineNumberBufferMetaData {
             cb_installed: false,
-            _root_idx: None,
+            indexed_prefix: (0, 0),
         }
     }
 }
 
+/// Returns the size and newline count of the indexed start of the buffer 'buffer_id'.
+fn get_indexed_prefix(buffer_id: buffer::Id) -> (u64, u64) {
+    match BUFFER_METADATA_MAP.read().get(&buffer_id) {
+        Some(meta) => meta.read().indexed_prefix,
+        None => (0, 0),
+    }
+}
+
+/// Returns the line of 'offset' (starting at 1) and true if it is estimated.<br/>
+/// An unindexed buffer is estimated with the newline density of its indexed prefix.
+fn line_at_offset(buffer: &Buffer, prefix: (u64, u64), offset: u64) -> Option<(u64, bool)> {
+    if buffer.indexed || offset <= prefix.0 {
+        let n = get_byte_count_at_offset(buffer, '\n' as usize, offset);
+        return Some((1 + n.0, false));
+    }
+
+    let (size, lines) = prefix;
+    if size == 0 {
+        return None;
+    }
+
+    let extra = (offset - size) as u128 * lines as u128 / size as u128;
+    Some((1 + lines + extra as u64, true))
+}
+
 struct _LineNumberBufferNodeMetaData {
     nl_count: u64,
     cr_count: u64,
@@ -182,123 +280,162 @@ impl LineNumberMode {
 }
 
 pub fn linenum_input_event(
-    mut editor: &mut Editor<'static>,
-    mut env: &mut EditorEnv<'static>,
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View>>,
 ) {
-    let display_mode = {
+    let (linenum_view_id, text_view_id, mut display_mode, mut drag_start, evt) = {
         let v = view.read();
-
-        // explicit focus on text view
         let mode_ctx = v.mode_ctx::<LineNumberModeContext>("line-number-mode");
-        let mut display_mode = mode_ctx.display_mode;
-        env.focus_locked_on_view_id = None;
-
-        let evt = v.input_ctx.trigger.last();
-        match evt {
-            Some(InputEvent::ButtonPress(ref button_event)) => match button_event {
-                ButtonEvent {
-                    mods:
-                        KeyModifiers {
-                            ctrl: _,
-                            alt: _,
-                            shift: _,
-                        },
-                    x: _,
-                    y: _,
-                    button,
-                } => {
-                    if *button == 0 {
-                        // TODO(ceg): move mark to selected line and start selection
-                    }
+        (
+            v.id,
+            mode_ctx.text_view_id,
+            mode_ctx.display_mode,
+            mode_ctx.drag_start,
+            v.input_ctx.trigger.last().cloned(),
+        )
+    };
 
-                    if *button == 1 {
-                        // set mode
-                        display_mode += 1;
-                        display_mode %= 4;
+    if text_view_id == Id(0) {
+        return;
+    }
 
-                        set_focus_on_view_id(&mut editor, &mut env, mode_ctx.text_view_id);
-                    }
+    match evt {
+        Some(InputEvent::ButtonPress(ButtonEvent {
+            x: _, y, button, ..
+        })) => {
+            if button == 0 {
+                // select the line and start the selection of the next ones
+                let y = std::cmp::max(0, y) as usize;
+                drag_start = line_range_at(editor, text_view_id, y);
+                if let Some(start) = drag_start {
+                    select_lines(editor, text_view_id, start, start);
+                    env.focus_locked_on_view_id = Some(linenum_view_id);
                 }
-            },
-
-            Some(InputEvent::ButtonRelease(ref button_event)) => match button_event {
-                ButtonEvent {
-                    mods:
-                        KeyModifiers {
-                            ctrl: _,
-                            alt: _,
-                            shift: _,
-                        },
-                    x: _,
-                    y,
-                    button,
-                } => {
-                    if *button == 1 {
-                        // ignore right button release
-                        return;
-                    }
+            }
 
-                    set_focus_on_view_id(&mut editor, &mut env, mode_ctx.text_view_id);
-                    // TODO: move mark to selected line and update selection
+            if button == 1 {
+                // set mode
+                display_mode += 1;
+                display_mode %= DISPLAY_MODE_COUNT;
 
-                    // select line
-                    let lnm = v.mode_ctx::<LineNumberModeContext>("line-number-mode");
-                    let text_view_id = lnm.text_view_id;
+                set_focus_on_view_id(editor, env, text_view_id);
+            }
+        }
 
-                    let text_view = get_view_by_id(editor, text_view_id);
-                    let mut text_view = text_view.write();
+        Some(InputEvent::ButtonRelease(ButtonEvent { button, .. })) => {
+            if button == 1 {
+                // ignore right button release
+                return;
+            }
 
-                    // check offscreen
-                    let idx = *y as usize;
-                    let offset = {
-                        let screen = text_view.screen.read();
-                        if let Some(l) = screen.get_used_line(idx) {
-                            l[0].cpi.offset
-                        } else {
-                            None
-                        }
-                    };
-                    if let Some(offset) = offset {
-                        // update marks
-                        let tm = text_view.mode_ctx_mut::<TextModeContext>("text-mode");
-                        tm.marks.clear();
-                        tm.marks.push(Mark::new(offset));
-
-                        // TODO(ceg): ignore if view was change by user
-                        // let msg = Message::new(0, 0, 0, Event::RefreshView);
-                        // crate::core::event::pending_input_event_inc(1);
-                        // editor.core_tx.send(msg).unwrap_or(());
-                    }
-                }
-            },
+            drag_start = None;
 
-            Some(InputEvent::PointerMotion(PointerEvent {
-                x: _,
-                y: _,
-                mods: _,
-            })) => { /* TODO(ceg): update selection */ }
+            // explicit focus on text view
+            env.focus_locked_on_view_id = None;
+            set_focus_on_view_id(editor, env, text_view_id);
+        }
 
-            _ => {
-                dbg_println!("LINENUM unhandled event {:?}", evt);
-                return;
+        Some(InputEvent::PointerMotion(PointerEvent { x: _, y, mods: _ })) => {
+            let start = match drag_start {
+                Some(start) => start,
+                None => return,
+            };
+
+            // the pointer can leave the view while the button is pressed
+            let height = view.read().screen.read().height();
+            let y = std::cmp::min(std::cmp::max(0, y) as usize, height.saturating_sub(1));
+
+            if let Some(range) = line_range_at(editor, text_view_id, y) {
+                select_lines(editor, text_view_id, start, range);
             }
-        };
 
-        display_mode
+            // scroll at the top/bottom of the view
+            let scroll = if y + 1 >= height {
+                Some(PostInputAction::ScrollDown { n: 1 })
+            } else if y == 0 {
+                Some(PostInputAction::ScrollUp { n: 1 })
+            } else {
+                None
+            };
+            if let Some(scroll) = scroll {
+                let text_view = get_view_by_id(editor, text_view_id);
+                let mut text_view = text_view.write();
+                let tm = text_view.mode_ctx_mut::<TextModeContext>("text-mode");
+                tm.pre_compose_action.push(scroll);
+            }
+        }
+
+        _ => {
+            dbg_println!("LINENUM unhandled event {:?}", evt);
+            return;
+        }
     };
 
     // save
     let mut v = view.write();
     let mode_ctx = v.mode_ctx_mut::<LineNumberModeContext>("line-number-mode");
     mode_ctx.display_mode = display_mode;
+    mode_ctx.drag_start = drag_start;
+}
+
+/// Returns the start and end offsets of the buffer line displayed at the screen line 'y' of
+/// the text view (or of its last line). The end is the start of the next line.
+fn line_range_at(editor: &Editor<'static>, text_view_id: view::Id, y: usize) -> Option<(u64, u64)> {
+    let text_view = get_view_by_id(editor, text_view_id);
+    let text_view = text_view.read();
+
+    let offset = {
+        let screen = text_view.screen.read();
+        let used = screen.line_index.len();
+        (0..=std::cmp::min(y, used.saturating_sub(1)))
+            .rev()
+            .filter_map(|i| screen.get_used_line(i))
+            .find(|l| !l.is_empty())
+            .and_then(|l| line_logical_first(l).cpi.offset)?
+    };
+
+    let buffer = text_view.buffer()?;
+    let buffer = buffer.read();
+    let tm = text_view.mode_ctx::<TextModeContext>("text-mode");
+    let codec = tm.text_codec.as_ref();
+
+    let start = line_start(&buffer, codec, offset);
+    let end = next_line_start(&buffer, codec, start).unwrap_or(buffer.size() as u64);
+    Some((start, end))
+}
+
+/// Selects the lines from 'start' to 'current': the main mark follows the pointer.
+fn select_lines(
+    editor: &Editor<'static>,
+    text_view_id: view::Id,
+    start: (u64, u64),
+    current: (u64, u64),
+) {
+    let (select_point, mark) = if current.0 >= start.0 {
+        (start.0, current.1)
+    } else {
+        (start.1, current.0)
+    };
+
+    let text_view = get_view_by_id(editor, text_view_id);
+    let mut text_view = text_view.write();
+    let tm = text_view.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.block_selection = None;
+    tm.mark_index = 0;
+    tm.marks.clear();
+    tm.marks.push(Mark::new(mark));
+    tm.select_point.clear();
+    tm.select_point.push(Mark::new(select_point));
 }
 
 pub struct LineNumberModeContext {
     // add per view fields
     linenum_view_id: view::Id,
     text_view_id: view::Id,
-    display_mode: usize, // 0: absolute, 1:relative, 2:hybrid 4:offset
+    display_mode: usize, // see DISPLAY_ABSOLUTE ...
+    /// the line range (start, end) where the gutter selection started
+    drag_start: Option<(u64, u64)>,
 
     pub start_line: Option<u64>,
     pub start_col: Option<u64>,
@@ -306,6 +443,41 @@ pub struct LineNumberModeContext {
 
 struct LineNumberModeBufferEventHandler {
     pub count: usize,
+    /// the first node after the indexed prefix, None at the end of the buffer
+    next_node: Option<usize>,
+    /// the nodes changed: the prefix is computed again from the first node
+    restart: bool,
+    indexed_prefix: (u64, u64),
+    core_tx: Sender<Message<'static>>,
+    last_refresh: Instant,
+}
+
+impl LineNumberModeBufferEventHandler {
+    /// Extends the indexed prefix with the following indexed nodes.
+    fn update_indexed_prefix(&mut self, buffer: &Buffer) {
+        {
+            let file = buffer.inner.data.read();
+            if self.restart {
+                self.restart = false;
+                self.indexed_prefix = (0, 0);
+                self.next_node = file.find_node_by_offset(0).0;
+            }
+
+            while let Some(idx) = self.next_node {
+                let node = &file.pool[idx];
+                if !node.indexed {
+                    break;
+                }
+                self.indexed_prefix.0 += node.size;
+                self.indexed_prefix.1 += node.byte_count[b'\n' as usize];
+                self.next_node = node.link.next;
+            }
+        }
+
+        if let Some(meta) = BUFFER_METADATA_MAP.read().get(&buffer.id) {
+            meta.write().indexed_prefix = self.indexed_prefix;
+        }
+    }
 }
 
 impl<'a> Mode for LineNumberMode {
@@ -325,7 +497,8 @@ impl<'a> Mode for LineNumberMode {
         let ctx = LineNumberModeContext {
             linenum_view_id: view::Id(0),
             text_view_id: view::Id(0),
-            display_mode: 0,
+            display_mode: DISPLAY_ABSOLUTE,
+            drag_start: None,
             start_line: None,
             start_col: None,
         };
@@ -334,7 +507,7 @@ impl<'a> Mode for LineNumberMode {
 
     fn configure_buffer(
         &mut self,
-        _editor: &mut Editor<'static>,
+        editor: &mut Editor<'static>,
         _env: &mut EditorEnv<'static>,
         buffer: &mut Buffer<'static>,
     ) {
@@ -352,7 +525,14 @@ impl<'a> Mode for LineNumberMode {
         let mut meta = meta.as_ref().unwrap().write();
 
         if !meta.cb_installed {
-            let cb = Box::new(LineNumberModeBufferEventHandler { count: 0 });
+            let cb = Box::new(LineNumberModeBufferEventHandler {
+                count: 0,
+                next_node: None,
+                restart: true,
+                indexed_prefix: (0, 0),
+                core_tx: editor.core_tx.clone(),
+                last_refresh: Instant::now(),
+            });
 
             self.buffer_subscription = buffer.register_subscriber(cb);
 
@@ -392,14 +572,9 @@ impl<'a> Mode for LineNumberMode {
 
             let mode_ctx = view.mode_ctx_mut::<LineNumberModeContext>("line-number-mode");
 
-            let v = if let Some(display_mode) = config_var_get(&editor, "line-number-mode:display")
-            {
-                display_mode.trim_end().parse::<usize>().unwrap_or(0)
-            } else {
-                0
-            };
-
-            mode_ctx.display_mode = std::cmp::min(v, 3);
+            mode_ctx.display_mode = config_var_get(&editor, "line-number-mode:display")
+                .and_then(|v| parse_display_mode(v))
+                .unwrap_or(DISPLAY_ABSOLUTE);
         }
     }
 
@@ -446,6 +621,15 @@ impl<'a> Mode for LineNumberMode {
 
                 mode_ctx.text_view_id = src.id;
                 mode_ctx.linenum_view_id = dst.id;
+
+                // the offsets of the binary files
+                if config_var_get(editor, "line-number-mode:display").is_none() {
+                    if let Some(buffer) = src_view.buffer() {
+                        if looks_binary(&buffer.read()) {
+                            mode_ctx.display_mode = DISPLAY_HEX_OFFSET;
+                        }
+                    }
+                }
             }
 
             ViewEvent::PreLayoutSizing => {
@@ -470,15 +654,28 @@ impl<'a> Mode for LineNumberMode {
                 let buffer = text_view.buffer();
                 let buffer = buffer.as_ref().unwrap().read();
                 let max_offset = buffer.size() as u64 + 1;
-                let width = if !buffer.indexed || display_mode == 3 {
+                let width = if display_mode == DISPLAY_HEX_OFFSET {
+                    num_hex_digit(max_offset)
+                } else if display_mode == DISPLAY_OFFSET {
                     // '@offset '
                     1 + num_digit(max_offset)
-                } else {
+                } else if buffer.indexed {
                     let ret = get_byte_count(&buffer, '\n' as usize).unwrap_or(0);
                     let n = num_digit(ret + 1); // nb line = line count + 1
 
-                    // 'xxxx '
-                    n
+                    // 'xxxx' + change marker
+                    if get_changes_base(editor).is_some() {
+                        n + 1
+                    } else {
+                        n
+                    }
+                } else if let Some((n, _)) =
+                    line_at_offset(&buffer, get_indexed_prefix(buffer.id), buffer.size() as u64)
+                {
+                    // '~xxxx'
+                    1 + num_digit(n)
+                } else {
+                    1 + num_digit(max_offset)
                 };
 
                 let width = match std::env::var("SINGLE_VIEW") {
@@ -515,37 +712,52 @@ impl BufferEventCb for LineNumberModeBufferEventHandler {
             self.count
         );
 
+        buffer.show_root_node_bytes_stats();
+
+        if buffer.indexed {
+            // the exact line numbers are available
+            return;
+        }
+
         match event {
             BufferEvent::BufferNodeIndexed {
                 buffer_id: _,
                 node_index,
             } => {
-                dbg_println!(
-                    "TODO index node {} with target codec  {:?}",
-                    node_index,
-                    event
-                );
+                dbg_println!("index node {} {:?}", node_index, event);
+                self.update_indexed_prefix(buffer);
+
+                // display the new numbers
+                if self.last_refresh.elapsed() >= INDEXING_REFRESH_DELAY {
+                    self.last_refresh = Instant::now();
+                    let msg = Message::new(0, 0, 0, Event::RefreshView);
+                    pending_input_event_inc(1);
+                    self.core_tx.send(msg).unwrap_or(());
+                }
             }
 
             BufferEvent::BufferNodeAdded {
                 buffer_id: _,
                 node_index: _,
-            } => {}
-
-            BufferEvent::BufferNodeRemoved {
+            }
+            | BufferEvent::BufferNodeRemoved {
                 buffer_id: _,
                 node_index: _,
-            } => {}
-
-            BufferEvent::BufferNodeChanged {
+            }
+            | BufferEvent::BufferNodeChanged {
                 buffer_id: _,
                 node_index: _,
-            } => {}
+            } => {
+                self.restart = true;
+                self.update_indexed_prefix(buffer);
+            }
 
             BufferEvent::BufferFullyIndexed { buffer_id: _ } => {}
-        }
 
-        buffer.show_root_node_bytes_stats();
+            BufferEvent::BufferDataInserted { .. } | BufferEvent::BufferDataRemoved { .. } => {}
+
+            BufferEvent::BufferSyncFailed { .. } => {}
+        }
     }
 }
 
@@ -557,6 +769,9 @@ pub struct LineNumberOverlayFilter {
     mark_line: u64,
     line_offsets: Vec<(u64, u64)>,
     line_number: Vec<(u64, u64, (u64, Option<usize>))>, // (offset, end_offset, (line_num, node_index))
+    show_changes: bool,
+    changes: Vec<Option<LineChange>>, // the change of each line_number entry
+    estimated: bool, // the buffer is not fully indexed: the numbers are approximated
 }
 
 impl LineNumberOverlayFilter {
@@ -566,6 +781,9 @@ impl LineNumberOverlayFilter {
             mark_line: 0,
             line_offsets: vec![],
             line_number: vec![],
+            show_changes: false,
+            changes: vec![],
+            estimated: false,
         }
     }
 }
@@ -597,21 +815,40 @@ impl ScreenOverlayFilter<'_> for LineNumberOverlayFilter {
         // get main mark offset
         let tm = src.mode_ctx::<TextModeContext>("text-mode");
         self.mark_offset = tm.marks[tm.mark_index].offset;
+        let folds = tm.folds.read();
 
-        let buffer = src.buffer();
-        let buffer = buffer.as_ref().unwrap().read();
+        let changes_base = get_changes_base(editor);
+        self.show_changes = changes_base.is_some();
+        self.changes.clear();
 
-        if !buffer.indexed {
-            return;
-        }
+        let buffer = src.buffer().unwrap();
+        let line_changes = changes_base.map(|base| {
+            let line_changes = get_buffer_line_changes(&buffer);
+            line_changes.write().set_base(base);
+            line_changes
+        });
+        let buffer = buffer.read();
 
         self.line_number.clear();
+        self.estimated = false;
         let screen = src.screen.as_ref().read();
 
         if self.line_offsets.is_empty() {
             return;
         }
 
+        // exact or estimated (in the unindexed part of the buffer) line numbers
+        let prefix = get_indexed_prefix(buffer.id);
+        match line_at_offset(&buffer, prefix, self.line_offsets[0].0) {
+            Some((_, estimated)) => self.estimated = estimated,
+            None => return, // show offsets
+        }
+
+        // the main mark can be offscreen
+        if let Some((line, _)) = line_at_offset(&buffer, prefix, self.mark_offset) {
+            self.mark_line = line;
+        }
+
         let mut prev_end_cpi: Option<CodepointInfo> = None;
         let mut line_number = 0;
         for i in 0..screen.line_index.len() {
@@ -619,13 +856,19 @@ impl ScreenOverlayFilter<'_> for LineNumberOverlayFilter {
             let mut end_offset = 0;
 
             if let Some(l) = screen.get_used_line(i) {
-                if let Some(cell) = l.first() {
-                    offset = cell.cpi.offset.unwrap();
+                if !l.is_empty() {
+                    offset = line_logical_first(l).cpi.offset.unwrap();
                 }
 
+                // the previous line hides folded lines
+                let prev_folded = match self.line_number.last() {
+                    Some((start, end, _)) => folds.has_fold_in(*start, *end),
+                    None => false,
+                };
+
                 line_number = {
                     // avoid slow call to get_byte_count_at_offset
-                    if let Some(prev_cpi) = prev_end_cpi {
+                    if let (Some(prev_cpi), false) = (prev_end_cpi, prev_folded) {
                         let n = if prev_cpi.cp == '\n' && prev_cpi.metadata == false {
                             1
                         } else {
@@ -634,12 +877,18 @@ impl ScreenOverlayFilter<'_> for LineNumberOverlayFilter {
                         line_number + n
                     } else {
                         let offset = &self.line_offsets[i];
-                        let n = get_byte_count_at_offset(&buffer, '\n' as usize, offset.0);
-                        1 + n.0
+                        match line_at_offset(&buffer, prefix, offset.0) {
+                            Some((n, estimated)) => {
+                                self.estimated |= estimated;
+                                n
+                            }
+                            None => line_number,
+                        }
                     }
                 };
 
-                if let Some(cell) = l.last() {
+                if !l.is_empty() {
+                    let cell = line_logical_last(l);
                     prev_end_cpi = Some(cell.cpi.clone());
                     end_offset = cell.cpi.offset.unwrap();
                 }
@@ -654,6 +903,19 @@ impl ScreenOverlayFilter<'_> for LineNumberOverlayFilter {
                 self.line_number.push(v);
             }
         }
+
+        // changed lines since the last save
+        if let (Some(line_changes), false) = (line_changes, self.estimated) {
+            let mut line_changes = line_changes.write();
+            line_changes.sync(editor, &buffer);
+            if !line_changes.hunks().is_empty() {
+                self.changes = self
+                    .line_number
+                    .iter()
+                    .map(|e| line_changes.line_change(e.2 .0.saturating_sub(1) as usize))
+                    .collect();
+            }
+        }
     }
 
     fn run(&mut self, view: &View, env: &mut LayoutEnv) {
@@ -676,46 +938,72 @@ impl ScreenOverlayFilter<'_> for LineNumberOverlayFilter {
         let display_mode = mode_ctx.display_mode;
 
         // show line numbers
-        if !self.line_number.is_empty() && display_mode != 3 {
+        if !self.line_number.is_empty()
+            && display_mode != DISPLAY_OFFSET
+            && display_mode != DISPLAY_HEX_OFFSET
+        {
+            // the last column shows the changes
+            let w = if self.show_changes {
+                w.saturating_sub(1)
+            } else {
+                w
+            };
+
             let mut prev_line = 0;
             for (idx, e) in self.line_number.iter().enumerate() {
                 let cur_line_num = e.2 .0;
+                let change = self.changes.get(idx).copied().flatten();
 
                 if idx > 0 && cur_line_num == prev_line {
-                    // skip wrapped line
-                    env.screen.select_next_line_index();
+                    // wrapped line: only continue the added/modified marker
+                    match change {
+                        Some(LineChange::Added) | Some(LineChange::Modified) => {
+                            for _ in 0..w {
+                                env.screen.push(&CodepointInfo::new());
+                            }
+                            push_change_marker(env.screen, change);
+                        }
+                        _ => {
+                            env.screen.select_next_line_index();
+                        }
+                    }
                     continue;
                 }
 
                 let mut enable_padding = true;
 
-                // show relative lines (add keyboard toggle)
-                let s = if display_mode > 0 {
+                // the estimated numbers are prefixed with '~'
+                let approx = if self.estimated { "~" } else { "" };
+
+                // show relative lines
+                let s = if display_mode == DISPLAY_RELATIVE || display_mode == DISPLAY_HYBRID {
                     if self.mark_line > cur_line_num {
                         format!("{}", self.mark_line - cur_line_num)
                     } else if self.mark_line < cur_line_num {
                         format!("{}", cur_line_num - self.mark_line)
                     } else {
                         enable_padding = false;
-                        if display_mode == 1 {
+                        if display_mode == DISPLAY_RELATIVE {
                             format!("0")
                         } else {
-                            format!("{}", self.mark_line)
+                            format!("{}{}", approx, self.mark_line)
                         }
                     }
                 } else {
                     // absolute
-                    format!("{}", cur_line_num)
+                    format!("{}{}", approx, cur_line_num)
                 };
 
                 prev_line = cur_line_num;
 
+                let mut used = s.chars().count();
                 if enable_padding {
-                    let padding = w.saturating_sub(s.len());
+                    let padding = w.saturating_sub(used);
                     // left-pad
                     for _ in 0..padding {
                         env.screen.push(&CodepointInfo::new());
                     }
+                    used += padding;
                 }
 
                 let has_mark = self.mark_line == cur_line_num;
@@ -730,6 +1018,13 @@ impl ScreenOverlayFilter<'_> for LineNumberOverlayFilter {
                     cpi.style.is_bold = has_mark;
                     env.screen.push(&cpi);
                 }
+                if self.show_changes && cur_line_idx == env.screen.current_line_index() {
+                    // right-pad
+                    for _ in used..w {
+                        env.screen.push(&CodepointInfo::new());
+                    }
+                    push_change_marker(env.screen, change);
+                }
                 if cur_line_idx == env.screen.current_line_index() {
                     // NB screen.push selects next line automatically
                     env.screen.select_next_line_index();
@@ -739,8 +1034,13 @@ impl ScreenOverlayFilter<'_> for LineNumberOverlayFilter {
         }
 
         // show offsets
+        let hex_width = env.screen.width();
         for e in self.line_offsets.iter() {
-            let s = format!("@{}", e.0);
+            let s = if display_mode == DISPLAY_HEX_OFFSET {
+                format!("{:0width$x}", e.0, width = hex_width)
+            } else {
+                format!("@{}", e.0)
+            };
             let has_mark = self.mark_offset >= e.0 && self.mark_offset <= e.1;
             let final_color = if has_mark { has_mark_color } else { color };
 
@@ -761,3 +1061,62 @@ impl ScreenOverlayFilter<'_> for LineNumberOverlayFilter {
 
     fn finish(&mut self, _: &View, _: &mut LayoutEnv) {}
 }
+
+/// Pushes the marker of the changed line: a bar for the added/modified lines,
+/// a bottom/top line for the removed lines.
+fn push_change_marker(screen: &mut Screen, change: Option<LineChange>) {
+    let (cp, color) = match change {
+        Some(LineChange::Added) => ('┃', (80, 190, 90)),
+        Some(LineChange::Modified) => ('┃', (90, 140, 230)),
+        Some(LineChange::Removed) => ('▁', (220, 70, 70)),
+        Some(LineChange::RemovedAbove) => ('▔', (220, 70, 70)),
+        None => (' ', CodepointInfo::new().style.color),
+    };
+
+    let mut cpi = CodepointInfo::new();
+    cpi.displayed_cp = cp;
+    cpi.style.color = color;
+    screen.push(&cpi);
+}
+
+#[test]
+fn test_line_number_display_mode() {
+    assert_eq!(parse_display_mode("relative"), Some(DISPLAY_RELATIVE));
+    assert_eq!(parse_display_mode(" hybrid\n"), Some(DISPLAY_HYBRID));
+    assert_eq!(parse_display_mode("hex-offset"), Some(DISPLAY_HEX_OFFSET));
+    assert_eq!(parse_display_mode("3"), Some(DISPLAY_OFFSET));
+    assert_eq!(parse_display_mode("42"), Some(DISPLAY_MODE_COUNT - 1));
+    assert_eq!(parse_display_mode("none"), None);
+
+    assert_eq!(num_hex_digit(0), 8);
+    assert_eq!(num_hex_digit(0xffff_ffff), 8);
+    assert_eq!(num_hex_digit(0x1_0000_0000), 9);
+}
+
+#[test]
+fn test_line_number_estimate() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("line-number-estimate")
+        .internal(true)
+        .finalize()
+        .unwrap();
+    let mut buffer = buffer.write();
+    for _ in 0..10 {
+        buffer.append(b"123456789\n");
+    }
+
+    // nothing indexed: no number
+    buffer.indexed = false;
+    assert_eq!(line_at_offset(&buffer, (0, 0), 30), None);
+
+    // exact in the indexed prefix, estimated after it
+    assert_eq!(line_at_offset(&buffer, (50, 5), 30), Some((4, false)));
+    assert_eq!(line_at_offset(&buffer, (50, 5), 80), Some((9, true)));
+    assert_eq!(line_at_offset(&buffer, (50, 10), 80), Some((17, true)));
+
+    buffer.indexed = true;
+    assert_eq!(line_at_offset(&buffer, (0, 0), 80), Some((9, false)));
+}
@@ -1,6 +1,8 @@
 use std::any::Any;
 
+pub mod buffer_switcher;
 pub mod core_mode;
+pub mod diff_mode;
 pub mod dir_fetch;
 pub mod dir_mode;
 
@@ -11,10 +13,15 @@ pub mod tab_bar_mode;
 pub mod status_line_mode;
 
 pub mod find_mode;
+pub mod git_blame_mode;
+pub mod git_mode;
 pub mod goto_line_mode;
 pub mod hsplit_mode;
+pub mod line_changes;
 pub mod line_number;
 pub mod open_doc;
+pub mod overview;
+pub mod project_files;
 
 pub mod title_bar_mode;
 
@@ -29,8 +36,12 @@ use crate::core::editor::EditorEvent;
 use crate::core::editor::InputStageActionMap;
 use crate::core::view::View;
 
+pub use buffer_switcher::BufferSwitcherMode;
 pub use core_mode::CoreMode;
+pub use diff_mode::DiffMode;
 pub use find_mode::FindMode;
+pub use git_blame_mode::GitBlameMode;
+pub use git_mode::GitMode;
 pub use goto_line_mode::GotoLineMode;
 pub use hsplit_mode::HsplitMode;
 pub use line_number::LineNumberMode;
@@ -2,8 +2,10 @@
 
 use core::panic;
 use std::any::Any;
+use std::cell::RefCell;
 use std::env;
 use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
 
 use parking_lot::RwLock;
@@ -13,11 +15,16 @@ use std::sync::Arc;
 
 use super::Mode;
 
+use crate::core::buffer::Buffer;
 use crate::core::buffer::BufferBuilder;
 use crate::core::buffer::BufferKind;
+use crate::core::codepointinfo::TextStyle;
+use crate::core::editor::check_view_by_id;
 use crate::core::editor::get_view_by_id;
+use crate::core::editor::push_editor_event;
 use crate::core::editor::register_input_stage_action;
 use crate::core::editor::set_focus_on_view_id;
+use crate::core::editor::EditorEvent;
 
 use crate::core::path_to_buffer_kind;
 
@@ -29,18 +36,32 @@ use crate::core::event::*;
 
 use crate::core::event::input_map::build_input_event_map;
 
+use crate::core::fuzzy::fuzzy_filter;
+use crate::core::fuzzy::FuzzyNarrowing;
+
+use crate::core::screen::screen_apply;
+
 use crate::core::view;
 use crate::core::view::ChildView;
 
 use crate::core::view::View;
 
+use crate::core::view::ContentFilter;
 use crate::core::view::ControllerView;
+use crate::core::view::FilterIo;
 use crate::core::view::LayoutDirection;
+use crate::core::view::LayoutEnv;
 use crate::core::view::LayoutSize;
+use crate::core::view::ScreenOverlayFilter;
 
 use crate::core::modes::text_mode::center_around_mark_if_offscreen;
 use crate::core::modes::text_mode::TextModeContext;
 
+use crate::core::modes::tab_bar_mode::show_file_view;
+
+use crate::core::modes::project_files::project_root;
+use crate::core::modes::project_files::ProjectFiles;
+
 use crate::core::build_view_layout_typed;
 use crate::core::parse_layout_str;
 use crate::core::DEFAULT_LAYOUT_JSON;
@@ -51,6 +72,9 @@ static OPEN_DOC_TRIGGER_MAP: &str =
 static OPEN_DOC_CONTROLLER_MAP: &str =
     std::include_str!("../../../res/input-map/open-doc-mode-input-map.json");
 
+/// maximum number of files listed by the file finder
+const OPEN_DOC_FINDER_MAX_RESULTS: usize = 1000;
+
 impl<'a> Mode for OpenDocMode {
     fn name(&self) -> &'static str {
         &"open-doc-mode"
@@ -100,6 +124,18 @@ pub struct OpenDocModeContext {
     pub completion_list: Vec<String>,
     pub completion_index: usize,
     pub error_msg: Option<String>,
+    /// the prompt is a fuzzy pattern matched against the project files
+    pub fuzzy: bool,
+    /// the project files, listed once and kept between two searches
+    pub project: Option<Rc<RefCell<ProjectFiles>>>,
+    /// the project files matched by the previous prompt, narrowed while the prompt grows
+    pub narrowing: FuzzyNarrowing,
+    /// the recently opened files, matched instead of the project files (fuzzy mode)
+    pub recent: Option<Vec<String>>,
+    /// the matched chars of each completion (fuzzy mode)
+    pub completion_positions: Vec<Vec<usize>>,
+    /// the offsets of the matched chars in the completion view, shared with its overlay
+    pub highlight: Rc<RefCell<Vec<u64>>>,
 }
 
 impl OpenDocModeContext {
@@ -114,6 +150,12 @@ impl OpenDocModeContext {
             completion_list: vec![],
             completion_index: 0,
             error_msg: None,
+            fuzzy: false,
+            project: None,
+            narrowing: FuzzyNarrowing::new(),
+            recent: None,
+            completion_positions: vec![],
+            highlight: Rc::new(RefCell::new(vec![])),
         }
     }
     pub fn reset(&mut self) -> &mut Self {
@@ -122,6 +164,11 @@ impl OpenDocModeContext {
         self.prompt.clear();
         self.completion_list = vec![];
         self.completion_index = 0;
+        self.fuzzy = false;
+        self.recent = None;
+        self.narrowing.clear();
+        self.completion_positions.clear();
+        self.highlight.borrow_mut().clear();
 
         self
     }
@@ -138,6 +185,7 @@ impl OpenDocMode {
 
     pub fn register_input_stage_actions<'a>(mut map: &'a mut InputStageActionMap<'a>) {
         register_input_stage_action(&mut map, "open-doc:start", open_doc_start);
+        register_input_stage_action(&mut map, "open-doc:find-file", open_doc_find_file_start);
     }
 }
 
@@ -193,6 +241,94 @@ pub fn open_doc_start(
     }
 }
 
+/// Start the file finder: the prompt is matched against all the files of the project.<br/>
+/// The project (see project_root) is listed in background once.
+pub fn open_doc_find_file_start(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let root = match env::current_dir() {
+        Ok(dir) => project_root(&dir),
+        Err(_) => return,
+    };
+
+    {
+        let mut v = view.write();
+        let odm = v.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
+        odm.reset();
+        odm.fuzzy = true;
+
+        let same_root = odm
+            .project
+            .as_ref()
+            .is_some_and(|p| p.borrow().root == root);
+        if !same_root {
+            odm.project = Some(Rc::new(RefCell::new(ProjectFiles::start(editor, &root))));
+        }
+    }
+
+    open_doc_start(editor, env, view);
+}
+
+/// List the project files again.
+pub fn open_doc_controller_rescan_project(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    {
+        let v = view.read();
+        let text_view = get_view_by_id(editor, v.controlled_view.unwrap());
+        let mut text_view = text_view.write();
+        let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
+        if !odm.fuzzy || odm.recent.is_some() {
+            return;
+        }
+
+        let root = match &odm.project {
+            Some(p) => p.borrow().root.clone(),
+            None => return,
+        };
+        odm.project = Some(Rc::new(RefCell::new(ProjectFiles::start(editor, &root))));
+        odm.narrowing.clear();
+    }
+
+    open_doc_refresh_completion(editor, env, view);
+}
+
+/// Switch the prompt to the list of the recently opened files (see FileHistory), or back to the path prompt.
+pub fn open_doc_controller_recent_files(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let recent: Vec<String> = editor
+        .file_history
+        .recent_files()
+        .into_iter()
+        .filter(|path| Path::new(path).is_file())
+        .collect();
+
+    {
+        let text_view = get_view_by_id(editor, view.read().controlled_view.unwrap());
+        let mut text_view = text_view.write();
+        let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
+
+        odm.prompt.clear();
+        odm.completion_index = 0;
+        if odm.recent.is_some() {
+            odm.recent = None;
+            odm.fuzzy = false;
+        } else {
+            odm.recent = Some(recent);
+            odm.fuzzy = true;
+        }
+    }
+
+    open_doc_refresh_completion(editor, env, view);
+}
+
 pub fn open_doc_controller_stop(
     editor: &mut Editor<'static>,
     env: &mut EditorEnv<'static>,
@@ -339,9 +475,27 @@ fn create_open_doc_controller_view(
             open_doc_controller_select_first_completion,
         );
 
+        register_input_stage_action(
+            &mut action_map,
+            "open-doc:rescan-project",
+            open_doc_controller_rescan_project,
+        );
+
+        register_input_stage_action(
+            &mut action_map,
+            "open-doc:recent-files",
+            open_doc_controller_recent_files,
+        );
+
         controller_view.register_action_map(action_map);
     }
 
+    // collect the project files before the prompt is displayed
+    controller_view
+        .compose_content_filters
+        .borrow_mut()
+        .insert(0, Box::new(OpenDocFinderPoll::new()));
+
     editor.add_view(controller_view.id, controller_view);
 }
 
@@ -394,6 +548,29 @@ fn open_doc_display_prompt(
     let mut buffer = buffer.as_ref().unwrap().write();
 
     buffer.delete_content(None);
+
+    if odm.recent.is_some() {
+        buffer.append(b"Recent file: ");
+        let s: String = odm.prompt.iter().collect();
+        buffer.append(s.as_bytes());
+        return;
+    }
+
+    if odm.fuzzy {
+        buffer.append(b"Find file: ");
+        let s: String = odm.prompt.iter().collect();
+        buffer.append(s.as_bytes());
+
+        if let Some(project) = &odm.project {
+            let project = project.borrow();
+            if !project.done {
+                let s = format!("  (scanning: {} files)", project.files.len());
+                buffer.append(s.as_bytes());
+            }
+        }
+        return;
+    }
+
     buffer.append(b"Open: ");
 
     // setup working directory
@@ -448,6 +625,13 @@ fn create_open_doc_completion_view(
     let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
     odm.completion_view_id = popup_view.id;
 
+    popup_view
+        .compose_screen_overlay_filters
+        .borrow_mut()
+        .push(Box::new(HighlightMatchedCharsOverlay::new(Rc::clone(
+            &odm.highlight,
+        ))));
+
     editor.add_view(popup_view.id, popup_view);
 }
 
@@ -548,7 +732,7 @@ pub fn open_doc_controller_del_char(
         let mut text_view = text_view.write();
 
         let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
-        if odm.prompt.len() <= 1 {
+        if odm.prompt.is_empty() || (!odm.fuzzy && odm.prompt.len() <= 1) {
             return;
         }
         odm.completion_index = 0;
@@ -591,6 +775,18 @@ pub fn open_doc_do_completion(
 ) {
     dbg_println!("open file : do completion");
 
+    let fuzzy = {
+        let text_view = get_view_by_id(editor, view.read().controlled_view.unwrap());
+        let text_view = text_view.read();
+        text_view
+            .mode_ctx::<OpenDocModeContext>("open-doc-mode")
+            .fuzzy
+    };
+    if fuzzy {
+        open_doc_do_fuzzy_completion(editor, env, view);
+        return;
+    }
+
     {
         let v = view.read();
         let text_view_view_id = v.controlled_view.unwrap();
@@ -682,6 +878,103 @@ pub fn open_doc_do_completion(
     }
 }
 
+/// Fill the completion list with the project files matching the prompt, best match first.
+fn open_doc_do_fuzzy_completion(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    {
+        let text_view = get_view_by_id(editor, view.read().controlled_view.unwrap());
+        let mut text_view = text_view.write();
+        let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
+
+        odm.completion_list.clear();
+        odm.completion_positions.clear();
+        odm.completion_index = 0;
+        odm.error_msg = None;
+
+        let pattern: String = odm.prompt.iter().collect();
+
+        let (matches, done) = if let Some(recent) = &odm.recent {
+            let matches = fuzzy_filter(recent, &pattern, OPEN_DOC_FINDER_MAX_RESULTS)
+                .into_iter()
+                .map(|(idx, m)| (recent[idx].clone(), m))
+                .collect();
+            (matches, true)
+        } else if let Some(project) = odm.project.clone() {
+            let mut project = project.borrow_mut();
+            project.poll();
+
+            let matches = odm
+                .narrowing
+                .filter(&project.files, &pattern, OPEN_DOC_FINDER_MAX_RESULTS)
+                .into_iter()
+                .map(|(idx, m)| (project.files[idx].clone(), m))
+                .collect();
+            (matches, project.done)
+        } else {
+            (vec![], false)
+        };
+
+        for (path, m) in matches {
+            odm.completion_list.push(format!("{}\n", path));
+            odm.completion_positions.push(m.positions);
+        }
+
+        if odm.completion_list.is_empty() && done {
+            odm.error_msg = Some(format!("no file matching '{}'\n", pattern));
+        }
+    }
+
+    if let Some(id) = show_completion_popup(editor, env, view) {
+        set_focus_on_view_id(editor, env, id);
+    }
+}
+
+/// Compute the completions again and keep the selected index if possible.
+fn open_doc_refresh_completion(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let text_view = get_view_by_id(editor, view.read().controlled_view.unwrap());
+
+    let index = text_view
+        .read()
+        .mode_ctx::<OpenDocModeContext>("open-doc-mode")
+        .completion_index;
+
+    open_doc_do_completion(editor, env, view, false);
+
+    let completion_view_id = {
+        let mut text_view = text_view.write();
+        let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
+        odm.completion_index = index.min(odm.completion_list.len().saturating_sub(1));
+
+        let offset: usize = odm
+            .completion_list
+            .iter()
+            .take(odm.completion_index)
+            .map(|s| s.len())
+            .sum();
+
+        let completion_view = get_view_by_id(editor, odm.completion_view_id);
+        let mut completion_view = completion_view.write();
+        let tm = completion_view.mode_ctx_mut::<TextModeContext>("text-mode");
+        tm.marks[0].offset = offset as u64;
+
+        odm.completion_view_id
+    };
+
+    let completion_view = get_view_by_id(editor, completion_view_id);
+    center_around_mark_if_offscreen(editor, env, &completion_view);
+
+    let mut controller_view = view.write();
+    let mut text_view = text_view.write();
+    open_doc_display_prompt(editor, env, &mut controller_view, &mut text_view);
+}
+
 fn show_completion_popup(
     editor: &mut Editor<'static>,
     env: &mut EditorEnv<'static>,
@@ -712,6 +1005,25 @@ fn show_completion_popup(
         }
     }
 
+    // offsets of the matched chars
+    {
+        let mut highlight = odm.highlight.borrow_mut();
+        highlight.clear();
+
+        if odm.error_msg.is_none() {
+            let mut line_offset = 0;
+            for (s, positions) in odm.completion_list.iter().zip(&odm.completion_positions) {
+                let mut positions = positions.iter().peekable();
+                for (char_idx, (byte_idx, _)) in s.char_indices().enumerate() {
+                    if positions.next_if_eq(&&char_idx).is_some() {
+                        highlight.push((line_offset + byte_idx) as u64);
+                    }
+                }
+                line_offset += s.len();
+            }
+        }
+    }
+
     // TODO: get view global coordinates, update on  resize
     let parent_id = view::get_view_by_tag(editor, env, "workspace").unwrap();
 
@@ -837,7 +1149,7 @@ pub fn open_doc_controller_apply_current_completion(
         let mut text_view = text_view.write();
         let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
 
-        if odm.completion_list.is_empty() {
+        if odm.completion_list.is_empty() || odm.fuzzy {
             return;
         }
 
@@ -904,6 +1216,9 @@ pub fn open_doc_controller_discard_prompt_suffix(
         let text_view = get_view_by_id(editor, text_view_view_id);
         let mut text_view = text_view.write();
         let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
+        if odm.fuzzy {
+            return;
+        }
 
         let completion_view = get_view_by_id(editor, odm.completion_view_id);
         {
@@ -1020,61 +1335,115 @@ pub fn open_doc_controller_show_buffer(
     env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View<'static>>>,
 ) {
-    let (_new_view_id, _ok) = open_doc_controller_load_buffer(editor, env, view);
-    //if !ok {
-    //    //return;
-    //}
+    let (new_view_id, ok) = open_doc_controller_load_buffer(editor, env, view);
 
     open_doc_controller_stop(editor, env, view);
+
+    // display the new document in the file slot
+    if ok && new_view_id != view::Id(0) {
+        show_file_view(editor, env, new_view_id);
+    }
 }
 
-// FIXME(ceg): core::open-new-buffer(path)
 fn open_doc_controller_load_buffer(
-    mut editor: &mut Editor<'static>,
-    mut env: &mut EditorEnv<'static>,
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View<'static>>>,
 ) -> (view::Id, bool) {
-    // walk through buffer list/view
-    // if ! already opened create new buffer + new view
-    // else return previous view
-    // show view
-    // split code and reuse in main loader
-
-    let controller_view = view.write();
-    let text_view_view_id = controller_view.controlled_view.unwrap();
-    let text_view = get_view_by_id(editor, text_view_view_id);
-    let mut text_view = text_view.write();
-    let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
+    let path = {
+        let controller_view = view.write();
+        let text_view_view_id = controller_view.controlled_view.unwrap();
+        let text_view = get_view_by_id(editor, text_view_view_id);
+        let mut text_view = text_view.write();
+        let odm = text_view.mode_ctx_mut::<OpenDocModeContext>("open-doc-mode");
 
-    let path = if odm.completion_list.is_empty() {
-        // create
-        let s: String = odm.prompt.iter().collect();
-        s
-    } else {
-        let mut path = odm.completion_list[odm.completion_index].clone();
-        path.pop(); // remove ending \n
-        path
+        if odm.recent.is_some() {
+            match odm.completion_list.get(odm.completion_index) {
+                Some(s) => s.trim_end_matches('\n').to_owned(),
+                None => return (view::Id(0), false),
+            }
+        } else if odm.fuzzy {
+            let root = match &odm.project {
+                Some(p) => p.borrow().root.clone(),
+                None => return (view::Id(0), false),
+            };
+            match odm.completion_list.get(odm.completion_index) {
+                Some(s) => root
+                    .join(s.trim_end_matches('\n'))
+                    .to_string_lossy()
+                    .to_string(),
+                None => return (view::Id(0), false),
+            }
+        } else if odm.completion_list.is_empty() {
+            // create
+            let s: String = odm.prompt.iter().collect();
+            s
+        } else {
+            let mut path = odm.completion_list[odm.completion_index].clone();
+            path.pop(); // remove ending \n
+            path
+        }
     };
 
+    open_doc_load_path(editor, env, &path)
+}
+
+/// Returns the view of the document 'path' if it is already opened.
+fn find_opened_doc_view(editor: &Editor<'static>, path: &str) -> Option<view::Id> {
+    let path = fs::canonicalize(path).ok()?;
+
+    editor.active_views.iter().copied().find(|id| {
+        check_view_by_id(editor, *id)
+            .and_then(|v| v.read().buffer())
+            .and_then(|b| fs::canonicalize(&b.read().name).ok())
+            .is_some_and(|p| p == path)
+    })
+}
+
+// FIXME(ceg): core::open-new-buffer(path)
+/// Load 'path' in a new buffer and create its view, the view is added to editor.active_views.<br/>
+/// If 'path' is already opened its view is returned.
+pub fn open_doc_load_path(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    path: &str,
+) -> (view::Id, bool) {
+    if let Some(id) = find_opened_doc_view(editor, path) {
+        return (id, true);
+    }
+
     dbg_println!("open-doc: try opening '{}'", path);
 
-    let kind = path_to_buffer_kind(&path);
+    let kind = path_to_buffer_kind(&path.to_owned());
 
     let b = BufferBuilder::new(kind)
-        .buffer_name(&path)
-        .file_name(&path)
+        .buffer_name(path)
+        .file_name(path)
         .internal(false)
         .use_buffer_log(true)
         .finalize();
 
+    match b {
+        Some(b) => {
+            crate::core::decompress_buffer(editor, &b);
+            open_doc_add_buffer(editor, env, b)
+        }
+        None => (env.root_view_id, false),
+    }
+}
+
+/// Register the new buffer 'b' and create its view, the view is added to editor.active_views.
+pub fn open_doc_add_buffer(
+    mut editor: &mut Editor<'static>,
+    mut env: &mut EditorEnv<'static>,
+    b: Arc<RwLock<Buffer<'static>>>,
+) -> (view::Id, bool) {
     // TODO: buffer id allocator fn
-    let buffer_id = if let Some(b) = b {
-        let buffer_id = b.read().id;
-        editor.buffer_map.write().insert(buffer_id, b);
-        buffer_id
-    } else {
-        return (env.root_view_id, false);
+    let (buffer_id, kind) = {
+        let b = b.read();
+        (b.id, b.kind)
     };
+    editor.buffer_map.write().insert(buffer_id, b);
 
     //    return (view::Id(0), true);
     // FIXME!
@@ -1136,8 +1505,11 @@ fn open_doc_controller_load_buffer(
 
     dbg_println!("open-doc : create view id {:?}", id);
 
+    push_editor_event(editor, EditorEvent::BufferAdded { id: buffer_id });
+
     if let Some(id) = id {
         editor.active_views.push(id);
+        push_editor_event(editor, EditorEvent::ViewAdded { id });
     }
 
     let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
@@ -1156,5 +1528,106 @@ fn open_doc_controller_load_buffer(
         editor.indexer_tx.send(msg).unwrap_or(());
     }
 
-    return (view::Id(0), true);
+    return (id.unwrap_or(view::Id(0)), true);
+}
+
+/// The **OpenDocFinderPoll** collects the files found by the project walker
+/// and updates the file finder completions before the prompt is composed.
+#[derive(Default)]
+pub struct OpenDocFinderPoll {}
+
+impl OpenDocFinderPoll {
+    pub fn new() -> Self {
+        OpenDocFinderPoll {}
+    }
+}
+
+impl ContentFilter<'_> for OpenDocFinderPoll {
+    fn name(&self) -> &'static str {
+        "OpenDocFinderPoll"
+    }
+
+    fn setup(
+        &mut self,
+        editor: &mut Editor<'static>,
+        editor_env: &mut EditorEnv<'static>,
+        _env: &mut LayoutEnv,
+        view: &Rc<RwLock<View>>,
+        _parent_view: Option<&View<'static>>,
+    ) {
+        let (controller_id, text_view_id) = {
+            let v = view.read();
+            match v.controlled_view {
+                Some(id) => (v.id, id),
+                None => return,
+            }
+        };
+
+        let received = {
+            let text_view = get_view_by_id(editor, text_view_id);
+            let text_view = text_view.read();
+            let odm = text_view.mode_ctx::<OpenDocModeContext>("open-doc-mode");
+            if !odm.active || !odm.fuzzy || odm.recent.is_some() {
+                return;
+            }
+            odm.project.as_ref().is_some_and(|p| p.borrow_mut().poll())
+        };
+
+        if !received {
+            return;
+        }
+
+        let controller = get_view_by_id(editor, controller_id);
+        open_doc_refresh_completion(editor, editor_env, &controller);
+
+        // the completion view may have been composed before the controller
+        let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+        let msg = Message::new(0, 0, ts, Event::RefreshView);
+        crate::core::event::pending_input_event_inc(1);
+        editor.core_tx.send(msg).unwrap_or(());
+    }
+
+    fn run(
+        &mut self,
+        _view: &View,
+        _env: &mut LayoutEnv,
+        filter_in: &[FilterIo],
+        filter_out: &mut Vec<FilterIo>,
+    ) {
+        filter_out.extend_from_slice(filter_in);
+    }
+}
+
+/// The **HighlightMatchedCharsOverlay** colors the chars matched by the file finder pattern.
+pub struct HighlightMatchedCharsOverlay {
+    offsets: Rc<RefCell<Vec<u64>>>,
+}
+
+impl HighlightMatchedCharsOverlay {
+    pub fn new(offsets: Rc<RefCell<Vec<u64>>>) -> Self {
+        HighlightMatchedCharsOverlay { offsets }
+    }
+}
+
+impl ScreenOverlayFilter<'_> for HighlightMatchedCharsOverlay {
+    fn name(&self) -> &'static str {
+        "HighlightMatchedCharsOverlay"
+    }
+
+    fn finish(&mut self, _view: &View, env: &mut LayoutEnv) {
+        let offsets = self.offsets.borrow();
+        if offsets.is_empty() {
+            return;
+        }
+
+        screen_apply(env.screen, |_c, _l, cpi| {
+            if let (Some(o), false) = (cpi.offset, cpi.metadata) {
+                if offsets.binary_search(&o).is_ok() {
+                    cpi.style.color = TextStyle::fuzzy_match_color();
+                    cpi.style.is_bold = true;
+                }
+            }
+            true
+        });
+    }
 }
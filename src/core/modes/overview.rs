@@ -0,0 +1,464 @@
+/*
+    The overview of a whole buffer, drawn by the vscrollbar in overview mode.
+
+    Each row of the overview column covers the same number of bytes of the buffer.
+    The density of a row is its number of lines, computed from the byte index
+    of the buffer leaf nodes (node.byte_count['\n']).
+
+    The node statistics are kept per buffer and are updated by the buffer events:
+    a modified node is refreshed alone, the list of leaves is only rebuilt
+    when nodes are added or removed. The file data is never read,
+    except to find the matches of the search (buffers up to MAX_MATCH_SCAN_SIZE bytes).
+*/
+
+use parking_lot::RwLock;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Weak;
+
+use lazy_static::lazy_static;
+
+use crate::core::buffer;
+use crate::core::buffer::Buffer;
+use crate::core::buffer::BufferEvent;
+use crate::core::buffer::BufferEventCb;
+
+// buffer overview map: the overview is owned by the tracker registered in the buffer,
+// it is released with the buffer
+lazy_static! {
+    static ref BUFFER_OVERVIEW_MAP: RwLock<HashMap<buffer::Id, Weak<RwLock<OverviewIndex>>>> =
+        RwLock::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverviewNode {
+    pub size: u64,
+    pub lines: u64,
+    pub indexed: bool,
+}
+
+#[derive(Debug)]
+pub struct OverviewIndex {
+    nodes: HashMap<usize, OverviewNode>,
+    /// the leaf nodes in buffer order
+    leaves: Vec<usize>,
+    leaves_changed: bool,
+    /// modified ranges since the last save, a removal is an empty range
+    modified: Vec<(u64, u64)>,
+    revision: usize,
+    rows: Vec<Option<u64>>,
+    rows_revision: usize,
+}
+
+impl Default for OverviewIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OverviewIndex {
+    pub fn new() -> Self {
+        OverviewIndex {
+            nodes: HashMap::new(),
+            leaves: vec![],
+            leaves_changed: true,
+            modified: vec![],
+            revision: 1,
+            rows: vec![],
+            rows_revision: 0,
+        }
+    }
+
+    pub fn modified(&self) -> &[(u64, u64)] {
+        &self.modified
+    }
+
+    pub fn clear_modified(&mut self) {
+        self.modified.clear();
+    }
+
+    fn update_node(&mut self, buffer: &Buffer, node_index: usize) {
+        let file = buffer.inner.data.read();
+        let node = &file.pool[node_index];
+        self.nodes.insert(
+            node_index,
+            OverviewNode {
+                size: node.size,
+                lines: node.byte_count[b'\n' as usize],
+                indexed: node.indexed,
+            },
+        );
+        self.revision += 1;
+    }
+
+    fn remove_node(&mut self, node_index: usize) {
+        self.nodes.remove(&node_index);
+        self.leaves_changed = true;
+        self.revision += 1;
+    }
+
+    /// Rebuilds the list of leaves if nodes were added or removed.
+    pub fn sync(&mut self, buffer: &Buffer) {
+        if !self.leaves_changed {
+            return;
+        }
+
+        self.leaves.clear();
+        self.nodes.clear();
+
+        let file = buffer.inner.data.read();
+        let (mut idx, _, _) = file.find_node_by_offset(0);
+        while let Some(i) = idx {
+            let node = &file.pool[i];
+            self.leaves.push(i);
+            self.nodes.insert(
+                i,
+                OverviewNode {
+                    size: node.size,
+                    lines: node.byte_count[b'\n' as usize],
+                    indexed: node.indexed,
+                },
+            );
+            idx = node.link.next;
+        }
+
+        self.leaves_changed = false;
+        self.revision += 1;
+    }
+
+    /// Returns the number of lines of each row, None if the row is not indexed yet.
+    pub fn rows(&mut self, height: usize) -> &[Option<u64>] {
+        if self.rows.len() != height || self.rows_revision != self.revision {
+            let nodes = &self.nodes;
+            let leaves = self.leaves.iter().filter_map(|idx| nodes.get(idx));
+            self.rows = overview_rows(leaves, height);
+            self.rows_revision = self.revision;
+        }
+        &self.rows
+    }
+
+    pub fn on_insert(&mut self, offset: u64, size: u64) {
+        for r in self.modified.iter_mut() {
+            if r.0 >= offset {
+                r.0 += size;
+                r.1 += size;
+            } else if r.1 >= offset {
+                r.1 += size;
+            }
+        }
+        self.modified.push((offset, offset + size));
+        merge_ranges(&mut self.modified);
+    }
+
+    pub fn on_remove(&mut self, offset: u64, size: u64) {
+        let end = offset + size;
+        let map = |p: u64| {
+            if p <= offset {
+                p
+            } else if p >= end {
+                p - size
+            } else {
+                offset
+            }
+        };
+        for r in self.modified.iter_mut() {
+            *r = (map(r.0), map(r.1));
+        }
+        self.modified.push((offset, offset));
+        merge_ranges(&mut self.modified);
+    }
+}
+
+fn merge_ranges(ranges: &mut Vec<(u64, u64)>) {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for r in ranges.iter() {
+        match merged.last_mut() {
+            Some(last) if r.0 <= last.1 => last.1 = std::cmp::max(last.1, r.1),
+            _ => merged.push(*r),
+        }
+    }
+    *ranges = merged;
+}
+
+/// Distributes the lines of the leaves over 'height' rows of the same byte size.<br/>
+/// The lines of a leaf spanning several rows are shared according to the covered bytes.
+pub fn overview_rows<'a>(
+    leaves: impl Iterator<Item = &'a OverviewNode> + Clone,
+    height: usize,
+) -> Vec<Option<u64>> {
+    let mut rows = vec![None; height];
+    let total: u64 = leaves.clone().map(|n| n.size).sum();
+    if total == 0 || height == 0 {
+        return rows;
+    }
+
+    let mut offset = 0;
+    for n in leaves {
+        if n.size == 0 {
+            continue;
+        }
+        let end = offset + n.size;
+
+        let first = overview_offset_to_row(offset, total, height);
+        let last = overview_offset_to_row(end - 1, total, height);
+        for (r, row) in rows.iter_mut().enumerate().take(last + 1).skip(first) {
+            if !n.indexed {
+                continue;
+            }
+            let row_start = std::cmp::max(offset, overview_row_to_offset(r, total, height));
+            let row_end = std::cmp::min(end, overview_row_to_offset(r + 1, total, height));
+            let covered = row_end.saturating_sub(row_start) as u128;
+            let lines = (n.lines as u128 * covered / n.size as u128) as u64;
+            *row = Some(row.unwrap_or(0) + lines);
+        }
+
+        offset = end;
+    }
+
+    rows
+}
+
+/// Returns the first offset covered by 'row'.
+pub fn overview_row_to_offset(row: usize, total: u64, height: usize) -> u64 {
+    if height == 0 {
+        return 0;
+    }
+    (row as u128 * total as u128 / height as u128) as u64
+}
+
+/// Returns the row covering 'offset'.
+pub fn overview_offset_to_row(offset: u64, total: u64, height: usize) -> usize {
+    if total == 0 || height == 0 {
+        return 0;
+    }
+    let row = (offset as u128 * height as u128 / total as u128) as usize;
+    std::cmp::min(row, height - 1)
+}
+
+/// The matches are only looked for in the buffers smaller than this.
+pub const MAX_MATCH_SCAN_SIZE: usize = 16 * 1024 * 1024;
+
+/// Returns the ranges of the matches of 'pattern', one match per row at most.<br/>
+/// Returns None if the buffer is too large to be scanned.
+pub fn find_overview_matches(
+    buffer: &Buffer,
+    pattern: &[u8],
+    height: usize,
+) -> Option<Vec<(u64, u64)>> {
+    let total = buffer.size() as u64;
+    if buffer.size() > MAX_MATCH_SCAN_SIZE {
+        return None;
+    }
+
+    let mut matches = vec![];
+    if pattern.is_empty() || height == 0 {
+        return Some(matches);
+    }
+
+    let mut from = 0;
+    while let Some(offset) = buffer.find(pattern, from, None) {
+        let end = offset + pattern.len() as u64;
+        matches.push((offset, end));
+
+        // the row is already marked: continue at the next row
+        let row = overview_offset_to_row(offset, total, height);
+        from = std::cmp::max(end, overview_row_to_offset(row + 1, total, height));
+        if from >= total {
+            break;
+        }
+    }
+
+    Some(matches)
+}
+
+/// Returns the overview of the buffer, the statistics of the nodes are tracked from now on.
+pub fn get_buffer_overview(buffer: &Arc<RwLock<Buffer<'static>>>) -> Arc<RwLock<OverviewIndex>> {
+    let buffer_id = buffer.read().id;
+
+    let overview = BUFFER_OVERVIEW_MAP
+        .read()
+        .get(&buffer_id)
+        .and_then(|overview| overview.upgrade());
+    if let Some(overview) = overview {
+        return overview;
+    }
+
+    let overview = Arc::new(RwLock::new(OverviewIndex::new()));
+    {
+        let mut map = BUFFER_OVERVIEW_MAP.write();
+        // forget the closed buffers
+        map.retain(|_, overview| overview.strong_count() > 0);
+        map.insert(buffer_id, Arc::downgrade(&overview));
+    }
+
+    let tracker = OverviewTracker {
+        overview: Arc::clone(&overview),
+    };
+    buffer.write().register_subscriber(Box::new(tracker));
+
+    overview
+}
+
+struct OverviewTracker {
+    overview: Arc<RwLock<OverviewIndex>>,
+}
+
+impl BufferEventCb for OverviewTracker {
+    fn cb(&mut self, buffer: &Buffer, event: &BufferEvent) {
+        let mut overview = self.overview.write();
+
+        match event {
+            BufferEvent::BufferNodeChanged { node_index, .. }
+            | BufferEvent::BufferNodeIndexed { node_index, .. } => {
+                overview.update_node(buffer, *node_index);
+            }
+
+            BufferEvent::BufferNodeAdded { node_index, .. } => {
+                overview.update_node(buffer, *node_index);
+                overview.leaves_changed = true;
+            }
+
+            BufferEvent::BufferNodeRemoved { node_index, .. } => {
+                overview.remove_node(*node_index);
+            }
+
+            BufferEvent::BufferFullyIndexed { .. } => {
+                overview.leaves_changed = true;
+            }
+
+            BufferEvent::BufferDataInserted { offset, size, .. } => {
+                overview.on_insert(*offset, *size as u64);
+            }
+
+            BufferEvent::BufferDataRemoved { offset, size, .. } => {
+                overview.on_remove(*offset, *size as u64);
+            }
+
+            BufferEvent::BufferSyncFailed { .. } => {}
+        }
+    }
+}
+
+#[test]
+fn test_overview_rows() {
+    let node = |size, lines, indexed| OverviewNode {
+        size,
+        lines,
+        indexed,
+    };
+
+    // 2 leaves on 4 rows
+    let leaves = vec![node(100, 10, true), node(100, 40, true)];
+    let rows = overview_rows(leaves.iter(), 4);
+    assert_eq!(rows, vec![Some(5), Some(5), Some(20), Some(20)]);
+
+    // a leaf shared by 2 rows
+    let leaves = vec![node(30, 3, true), node(60, 60, true), node(30, 0, true)];
+    let rows = overview_rows(leaves.iter(), 3);
+    assert_eq!(rows, vec![Some(3 + 10), Some(40), Some(10)]);
+
+    // unindexed leaves
+    let leaves = vec![node(100, 0, false), node(100, 8, true)];
+    let rows = overview_rows(leaves.iter(), 2);
+    assert_eq!(rows, vec![None, Some(8)]);
+
+    assert_eq!(overview_rows(leaves.iter(), 0), vec![]);
+    assert_eq!(overview_rows(vec![].iter(), 2), vec![None, None]);
+
+    assert_eq!(overview_offset_to_row(0, 200, 4), 0);
+    assert_eq!(overview_offset_to_row(49, 200, 4), 0);
+    assert_eq!(overview_offset_to_row(50, 200, 4), 1);
+    assert_eq!(overview_offset_to_row(200, 200, 4), 3);
+    assert_eq!(overview_row_to_offset(3, 200, 4), 150);
+}
+
+#[test]
+fn test_overview_modified_ranges() {
+    let mut overview = OverviewIndex::new();
+
+    overview.on_insert(10, 5);
+    assert_eq!(overview.modified(), &[(10, 15)]);
+
+    // before: shifted
+    overview.on_insert(0, 2);
+    assert_eq!(overview.modified(), &[(0, 2), (12, 17)]);
+
+    // at the end: extended
+    overview.on_insert(17, 1);
+    assert_eq!(overview.modified(), &[(0, 2), (12, 18)]);
+
+    // removal inside a range
+    overview.on_remove(13, 2);
+    assert_eq!(overview.modified(), &[(0, 2), (12, 16)]);
+
+    // removal point
+    overview.on_remove(30, 10);
+    assert_eq!(overview.modified(), &[(0, 2), (12, 16), (30, 30)]);
+
+    // removal across ranges
+    overview.on_remove(1, 14);
+    assert_eq!(overview.modified(), &[(0, 2), (16, 16)]);
+
+    overview.clear_modified();
+    assert!(overview.modified().is_empty());
+}
+
+#[test]
+fn test_overview_released_with_buffer() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+
+    let new_buffer = || {
+        BufferBuilder::new(BufferKind::File)
+            .buffer_name("test-overview")
+            .internal(true)
+            .finalize()
+            .unwrap()
+    };
+
+    let buffer = new_buffer();
+    let buffer_id = buffer.read().id;
+    let overview = get_buffer_overview(&buffer);
+    assert!(Arc::ptr_eq(&overview, &get_buffer_overview(&buffer)));
+
+    let weak = Arc::downgrade(&overview);
+    drop(overview);
+    drop(buffer);
+    assert!(weak.upgrade().is_none());
+
+    // the entry of the closed buffer is removed
+    let other = new_buffer();
+    get_buffer_overview(&other);
+    assert!(!BUFFER_OVERVIEW_MAP.read().contains_key(&buffer_id));
+}
+
+#[test]
+fn test_overview_matches() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("test-overview-matches")
+        .internal(true)
+        .finalize()
+        .unwrap();
+
+    // 10 rows of 10 bytes
+    let mut data = vec![b'x'; 100];
+    for offset in [0, 3, 6, 28, 50, 98] {
+        data[offset..offset + 2].copy_from_slice(b"ab");
+    }
+    buffer.write().append(&data);
+    let buffer = buffer.read();
+    assert_eq!(buffer.size(), 100);
+
+    assert_eq!(
+        find_overview_matches(&buffer, b"ab", 10),
+        Some(vec![(0, 2), (28, 30), (50, 52), (98, 100)])
+    );
+    assert_eq!(find_overview_matches(&buffer, b"ab", 1), Some(vec![(0, 2)]));
+    assert_eq!(find_overview_matches(&buffer, b"none", 10), Some(vec![]));
+    assert_eq!(find_overview_matches(&buffer, b"", 10), Some(vec![]));
+}
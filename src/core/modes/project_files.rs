@@ -0,0 +1,240 @@
+//! Background listing of the files of a project, used by the open-doc file finder.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::core::event::pending_input_event_inc;
+use crate::core::event::Event;
+use crate::core::event::Message;
+use crate::core::ignore::is_ignored_by_stack;
+use crate::core::ignore::IgnoreRules;
+use crate::core::Editor;
+
+/// number of paths sent at once by the walker
+const PROJECT_FILES_BATCH_SIZE: usize = 1024;
+
+/// the walk stops after this number of files
+const PROJECT_FILES_MAX: usize = 500_000;
+
+/// minimum delay between two screen refreshes requested by the walker
+const PROJECT_FILES_REFRESH_MS: u128 = 100;
+
+/// Returns the first parent of 'dir' (or 'dir' itself) containing a .git directory,
+/// or 'dir' if there is none.
+pub fn project_root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// The **ProjectFiles** walks a directory tree once in a dedicated thread.<br/>
+/// The files are relative to 'root' and use '/' as separator, the ignored files are skipped.<br/>
+/// The walk is aborted when the **ProjectFiles** is dropped.
+#[derive(Debug)]
+pub struct ProjectFiles {
+    rx: Receiver<Vec<String>>,
+    abort: Arc<AtomicBool>,
+    pub root: PathBuf,
+    pub files: Vec<String>,
+    pub done: bool,
+}
+
+impl ProjectFiles {
+    pub fn start(editor: &Editor<'static>, root: &Path) -> Self {
+        let (tx, rx) = channel();
+        let abort = Arc::new(AtomicBool::new(false));
+
+        let walk_root = root.to_path_buf();
+        let core_tx = editor.core_tx.clone();
+        let task_abort = Arc::clone(&abort);
+
+        // the walk of a large tree can last: do not use the executor thread
+        std::thread::spawn(move || {
+            let refresh = || {
+                let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+                let msg = Message::new(0, 0, ts, Event::RefreshView);
+                pending_input_event_inc(1);
+                core_tx.send(msg).unwrap_or(());
+            };
+
+            let mut last_refresh = Instant::now();
+            walk_project(&walk_root, &task_abort, |batch| {
+                if tx.send(batch).is_err() {
+                    return false;
+                }
+                if last_refresh.elapsed().as_millis() >= PROJECT_FILES_REFRESH_MS {
+                    last_refresh = Instant::now();
+                    refresh();
+                }
+                true
+            });
+
+            // an empty batch marks the end of the walk
+            let _ = tx.send(vec![]);
+            refresh();
+        });
+
+        ProjectFiles {
+            rx,
+            abort,
+            root: root.to_path_buf(),
+            files: vec![],
+            done: false,
+        }
+    }
+
+    /// Append the received paths to 'files'.<br/>
+    /// Returns true if something was received.
+    pub fn poll(&mut self) -> bool {
+        let mut received = false;
+        while let Ok(mut batch) = self.rx.try_recv() {
+            received = true;
+            if batch.is_empty() {
+                self.done = true;
+            }
+            self.files.append(&mut batch);
+        }
+        received
+    }
+}
+
+impl Drop for ProjectFiles {
+    fn drop(&mut self) {
+        self.abort.store(true, Ordering::Relaxed);
+    }
+}
+
+/// List the files of 'root' and call 'on_batch' for each batch of paths.<br/>
+/// Stops when 'abort' is set or when 'on_batch' returns false.
+pub fn walk_project<F: FnMut(Vec<String>) -> bool>(
+    root: &Path,
+    abort: &AtomicBool,
+    mut on_batch: F,
+) {
+    let mut stack = vec![];
+    let mut batch = Vec::with_capacity(PROJECT_FILES_BATCH_SIZE);
+    let mut count = 0;
+
+    let go_on = walk_project_rec(
+        root,
+        root,
+        abort,
+        &mut stack,
+        &mut batch,
+        &mut count,
+        &mut on_batch,
+    );
+
+    if go_on && !batch.is_empty() {
+        on_batch(batch);
+    }
+}
+
+fn walk_project_rec<F: FnMut(Vec<String>) -> bool>(
+    root: &Path,
+    dir: &Path,
+    abort: &AtomicBool,
+    stack: &mut Vec<(PathBuf, IgnoreRules)>,
+    batch: &mut Vec<String>,
+    count: &mut usize,
+    on_batch: &mut F,
+) -> bool {
+    let rd = match fs::read_dir(dir) {
+        Ok(rd) => rd,
+        Err(_) => return true,
+    };
+
+    // the order of read_dir is not specified
+    let mut entries: Vec<(PathBuf, bool)> = rd
+        .flatten()
+        .filter_map(|e| {
+            // do not follow the symlinks to directories (loops)
+            let is_dir = e.file_type().ok()?.is_dir();
+            Some((e.path(), is_dir))
+        })
+        .collect();
+    entries.sort();
+
+    stack.push((dir.to_path_buf(), IgnoreRules::load(dir)));
+
+    for (path, is_dir) in entries {
+        if abort.load(Ordering::Relaxed) || *count >= PROJECT_FILES_MAX {
+            stack.pop();
+            return false;
+        }
+
+        if is_ignored_by_stack(stack, &path, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            if !walk_project_rec(root, &path, abort, stack, batch, count, on_batch) {
+                stack.pop();
+                return false;
+            }
+            continue;
+        }
+
+        if let Ok(rel) = path.strip_prefix(root) {
+            batch.push(rel.to_string_lossy().replace('\\', "/"));
+            *count += 1;
+        }
+
+        if batch.len() == PROJECT_FILES_BATCH_SIZE {
+            let v = std::mem::replace(batch, Vec::with_capacity(PROJECT_FILES_BATCH_SIZE));
+            if !on_batch(v) {
+                stack.pop();
+                return false;
+            }
+        }
+    }
+
+    stack.pop();
+    true
+}
+
+#[test]
+fn test_project_files_walk() {
+    let dir = std::env::temp_dir().join(format!("unlimited-project-files-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join(".git")).unwrap();
+    fs::create_dir_all(dir.join("src/modes")).unwrap();
+    fs::create_dir_all(dir.join("target/debug")).unwrap();
+
+    fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+    fs::write(dir.join(".git/config"), "").unwrap();
+    fs::write(dir.join("src/main.rs"), "").unwrap();
+    fs::write(dir.join("src/modes/.ignore"), "*.tmp\n").unwrap();
+    fs::write(dir.join("src/modes/mode.rs"), "").unwrap();
+    fs::write(dir.join("src/modes/mode.tmp"), "").unwrap();
+    fs::write(dir.join("target/debug/app"), "").unwrap();
+
+    assert_eq!(project_root(&dir.join("src/modes")), dir);
+
+    let abort = AtomicBool::new(false);
+    let mut files = vec![];
+    walk_project(&dir, &abort, |mut b| {
+        files.append(&mut b);
+        true
+    });
+
+    assert_eq!(
+        files,
+        vec![
+            ".gitignore",
+            "src/main.rs",
+            "src/modes/.ignore",
+            "src/modes/mode.rs"
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -1,4 +1,8 @@
 use std::any::Any;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
 
 use parking_lot::RwLock;
 use std::rc::Rc;
@@ -6,11 +10,24 @@ use std::rc::Rc;
 use super::Mode;
 
 use crate::core::codepointinfo::CodepointInfo;
+use crate::core::codepointinfo::TextStyle;
+use crate::core::editor::get_view_ids_by_tags;
+use crate::core::editor::register_input_stage_action;
+use crate::core::editor::set_focus_on_view_id;
 use crate::core::editor::Editor;
 use crate::core::editor::EditorEnv;
+use crate::core::editor::EditorEvent;
 use crate::core::editor::InputStageActionMap;
-use crate::core::modes::text_mode::RawDataFilter;
-use crate::core::modes::text_mode::ScreenFilter;
+use crate::core::event::input_map::build_input_event_map;
+use crate::core::event::*;
+use crate::core::ignore::is_ignored_by_stack;
+use crate::core::ignore::IgnoreRules;
+use crate::core::modes::core_mode::set_command_line_text;
+use crate::core::modes::open_doc::open_doc_load_path;
+use crate::core::modes::tab_bar_mode::current_file_view;
+use crate::core::modes::tab_bar_mode::show_file_view;
+use crate::core::view;
+use crate::core::view::FilterIo;
 use crate::core::view::View;
 
 use crate::dbg_println;
@@ -21,7 +38,118 @@ use crate::core::view::LayoutEnv;
 
 use crate::core::buffer;
 
-pub struct SideBarModeContext {}
+static SIDE_BAR_INPUT_MAP: &str = r#"
+[
+  {
+    "events": [
+     { "in": [{ "key": "Up" } ],                 "action": "side-bar:select-previous" },
+     { "in": [{ "key": "Down" } ],               "action": "side-bar:select-next" },
+     { "in": [{ "key": "PageUp" } ],             "action": "side-bar:select-previous-page" },
+     { "in": [{ "key": "PageDown" } ],           "action": "side-bar:select-next-page" },
+     { "in": [{ "key": "Home" } ],               "action": "side-bar:select-first" },
+     { "in": [{ "key": "End" } ],                "action": "side-bar:select-last" },
+     { "in": [{ "key": "Left" } ],               "action": "side-bar:collapse" },
+     { "in": [{ "key": "Right" } ],              "action": "side-bar:expand" },
+     { "in": [{ "key": "\n" } ],                 "action": "side-bar:open" },
+     { "in": [{ "key": "Esc" } ],                "action": "side-bar:leave" },
+     { "in": [{ "key": "n" } ],                  "action": "side-bar:new-file" },
+     { "in": [{ "key": "+" } ],                  "action": "side-bar:new-directory" },
+     { "in": [{ "key": "r" } ],                  "action": "side-bar:rename" },
+     { "in": [{ "key": "d" } ],                  "action": "side-bar:delete" },
+     { "in": [{ "key": "Delete" } ],             "action": "side-bar:delete" },
+     { "in": [{ "key": "h" } ],                  "action": "side-bar:toggle-ignored" },
+     { "in": [{ "key": "g" } ],                  "action": "side-bar:refresh" },
+     { "in": [{ "button-release": "0" } ],       "action": "side-bar:button-release" },
+     { "in": [{ "wheel": "Up" } ],               "action": "side-bar:scroll-up" },
+     { "in": [{ "wheel": "Down" } ],             "action": "side-bar:scroll-down" }
+    ]
+  }
+]"#;
+
+static SIDE_BAR_PROMPT_MAP: &str = r#"
+[
+  {
+    "events": [
+     { "default": [],              "action": "side-bar:prompt-input" }
+   ]
+  }
+
+]"#;
+
+/// number of lines scrolled by the mouse wheel
+const WHEEL_SCROLL_LINES: usize = 3;
+
+/// A line of the file tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTreeEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+/// The file operation waiting for a name or a confirmation.
+#[derive(Debug, Clone)]
+pub enum SideBarPrompt {
+    NewFile { dir: PathBuf },
+    NewDirectory { dir: PathBuf },
+    Rename { path: PathBuf },
+    Delete { path: PathBuf },
+}
+
+pub struct SideBarModeContext {
+    pub roots: Vec<PathBuf>,
+    pub expanded: HashSet<PathBuf>,
+    pub entries: Vec<FileTreeEntry>,
+    /// offset of each entry in the view's buffer, plus the buffer size
+    pub line_offsets: Vec<u64>,
+    pub selected: usize,
+    pub first_line: usize,
+    pub show_ignored: bool,
+    /// number of displayed lines at the last composition
+    pub height: usize,
+    /// the tree must be rebuilt from the file system
+    pub dirty: bool,
+    pub prompt: Option<SideBarPrompt>,
+    pub prompt_text: String,
+}
+
+impl SideBarModeContext {
+    fn selected_entry(&self) -> Option<&FileTreeEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// The directory where new files are created: the selected directory or the selected file's parent.
+    fn selected_dir(&self) -> Option<PathBuf> {
+        let e = self.selected_entry()?;
+        if e.is_dir {
+            Some(e.path.clone())
+        } else {
+            e.path.parent().map(|p| p.to_path_buf())
+        }
+    }
+
+    fn select(&mut self, idx: usize) {
+        self.selected = std::cmp::min(idx, self.entries.len().saturating_sub(1));
+
+        // keep the selection visible
+        let height = std::cmp::max(1, self.height);
+        if self.selected < self.first_line {
+            self.first_line = self.selected;
+        } else if self.selected >= self.first_line + height {
+            self.first_line = self.selected + 1 - height;
+        }
+    }
+
+    fn scroll(&mut self, forward: bool, n: usize) {
+        if forward {
+            let max = self.entries.len().saturating_sub(1);
+            self.first_line = std::cmp::min(self.first_line + n, max);
+        } else {
+            self.first_line = self.first_line.saturating_sub(n);
+        }
+    }
+}
 
 pub struct SideBarMode {}
 
@@ -31,7 +159,32 @@ impl SideBarMode {
         SideBarMode {}
     }
 
-    pub fn register_input_stage_actions<'a>(_map: &'a mut InputStageActionMap<'a>) {}
+    pub fn register_input_stage_actions<'a>(map: &'a mut InputStageActionMap<'a>) {
+        register_input_stage_action(map, "side-bar:select-previous", side_bar_select_previous);
+        register_input_stage_action(map, "side-bar:select-next", side_bar_select_next);
+        register_input_stage_action(
+            map,
+            "side-bar:select-previous-page",
+            side_bar_select_previous_page,
+        );
+        register_input_stage_action(map, "side-bar:select-next-page", side_bar_select_next_page);
+        register_input_stage_action(map, "side-bar:select-first", side_bar_select_first);
+        register_input_stage_action(map, "side-bar:select-last", side_bar_select_last);
+        register_input_stage_action(map, "side-bar:collapse", side_bar_collapse);
+        register_input_stage_action(map, "side-bar:expand", side_bar_expand);
+        register_input_stage_action(map, "side-bar:open", side_bar_open);
+        register_input_stage_action(map, "side-bar:leave", side_bar_leave);
+        register_input_stage_action(map, "side-bar:new-file", side_bar_new_file);
+        register_input_stage_action(map, "side-bar:new-directory", side_bar_new_directory);
+        register_input_stage_action(map, "side-bar:rename", side_bar_rename);
+        register_input_stage_action(map, "side-bar:delete", side_bar_delete);
+        register_input_stage_action(map, "side-bar:toggle-ignored", side_bar_toggle_ignored);
+        register_input_stage_action(map, "side-bar:refresh", side_bar_refresh);
+        register_input_stage_action(map, "side-bar:button-release", side_bar_button_release);
+        register_input_stage_action(map, "side-bar:scroll-up", side_bar_scroll_up);
+        register_input_stage_action(map, "side-bar:scroll-down", side_bar_scroll_down);
+        register_input_stage_action(map, "side-bar:prompt-input", side_bar_prompt_input);
+    }
 }
 
 impl<'a> Mode for SideBarMode {
@@ -40,7 +193,19 @@ impl<'a> Mode for SideBarMode {
     }
 
     fn alloc_ctx(&self, _editor: &Editor<'static>) -> Box<dyn Any> {
-        let ctx = SideBarModeContext {};
+        let ctx = SideBarModeContext {
+            roots: vec![],
+            expanded: HashSet::new(),
+            entries: vec![],
+            line_offsets: vec![0],
+            selected: 0,
+            first_line: 0,
+            show_ignored: false,
+            height: 0,
+            dirty: true,
+            prompt: None,
+            prompt_text: String::new(),
+        };
         Box::new(ctx)
     }
 
@@ -56,54 +221,690 @@ impl<'a> Mode for SideBarMode {
         _env: &mut EditorEnv<'static>,
         view: &mut View<'static>,
     ) {
-        // mandatory data reader
+        let input_map = build_input_event_map(SIDE_BAR_INPUT_MAP).unwrap();
+        let mut input_map_stack = view.input_ctx.input_map.as_ref().borrow_mut();
+        input_map_stack.push((self.name(), input_map));
+
         view.compose_content_filters
             .borrow_mut()
-            .push(Box::new(RawDataFilter::new()));
-
-        /*
-            if use_utf8_codec {
-                /* DEBUG codec error */
-                view.compose_content_filters
-                    .borrow_mut()
-                    .push(Box::new(Utf8Filter::new()));
+            .push(Box::new(SideBarModeCompose::new()));
+    }
+
+    fn watch_editor_event(&self) -> bool {
+        true
+    }
+
+    fn on_editor_event(
+        &self,
+        _editor: &mut Editor<'static>,
+        _env: &mut EditorEnv<'static>,
+        event: &EditorEvent,
+        watcher_view: &mut View<'static>,
+    ) {
+        dbg_println!(
+            "mode '{}' on_editor_event: event {:?} watcher_view {:?}",
+            self.name(),
+            event,
+            watcher_view.id
+        );
+
+        // the roots may have changed
+        if let EditorEvent::BufferAdded { .. } | EditorEvent::BufferRemoved { .. } = event {
+            let ctx = watcher_view.mode_ctx_mut::<SideBarModeContext>("side-bar-mode");
+            ctx.dirty = true;
+        }
+    }
+}
+
+/// Returns the tree roots: the opened directories, or the current directory.
+fn tree_roots(editor: &Editor<'static>) -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    for (_id, b) in editor.buffer_map.read().iter() {
+        let b = b.read();
+        if b.kind == buffer::BufferKind::Directory {
+            dirs.push((b.id, PathBuf::from(&b.name)));
+        }
+    }
+
+    // order by cmd-line
+    dirs.sort_by_key(|(id, _)| *id);
+
+    if dirs.is_empty() {
+        return vec![PathBuf::from(".")];
+    }
+
+    dirs.into_iter().map(|(_, p)| p).collect()
+}
+
+/// Returns the sorted content of 'dir', directories first.
+fn read_dir_sorted(dir: &Path) -> Vec<(PathBuf, String, bool)> {
+    let mut v = vec![];
+
+    let rd = match fs::read_dir(dir) {
+        Ok(rd) => rd,
+        Err(_) => return v,
+    };
+
+    for e in rd.flatten() {
+        let name = e.file_name().to_string_lossy().to_string();
+        let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+        v.push((e.path(), name, is_dir));
+    }
+
+    v.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.cmp(&b.1)));
+    v
+}
+
+fn build_file_tree_rec(
+    dir: &Path,
+    depth: usize,
+    expanded: &HashSet<PathBuf>,
+    show_ignored: bool,
+    rules: &mut Vec<(PathBuf, IgnoreRules)>,
+    entries: &mut Vec<FileTreeEntry>,
+) {
+    rules.push((dir.to_path_buf(), IgnoreRules::load(dir)));
+
+    for (path, name, is_dir) in read_dir_sorted(dir) {
+        if !show_ignored && is_ignored_by_stack(rules, &path, is_dir) {
+            continue;
+        }
+
+        let open = is_dir && expanded.contains(&path);
+        entries.push(FileTreeEntry {
+            path: path.clone(),
+            name,
+            depth,
+            is_dir,
+        });
+
+        if open {
+            build_file_tree_rec(&path, depth + 1, expanded, show_ignored, rules, entries);
+        }
+    }
+
+    rules.pop();
+}
+
+/// Flatten the visible part of the tree.
+pub fn build_file_tree(
+    roots: &[PathBuf],
+    expanded: &HashSet<PathBuf>,
+    show_ignored: bool,
+) -> Vec<FileTreeEntry> {
+    let mut entries = vec![];
+    for root in roots {
+        entries.push(FileTreeEntry {
+            path: root.clone(),
+            name: root.to_string_lossy().to_string(),
+            depth: 0,
+            is_dir: true,
+        });
+
+        if expanded.contains(root) {
+            let mut rules = vec![];
+            build_file_tree_rec(root, 1, expanded, show_ignored, &mut rules, &mut entries);
+        }
+    }
+    entries
+}
+
+fn entry_line(e: &FileTreeEntry, expanded: &HashSet<PathBuf>) -> String {
+    let marker = match (e.is_dir, expanded.contains(&e.path)) {
+        (true, true) => "▾ ",
+        (true, false) => "▸ ",
+        (false, _) => "  ",
+    };
+    format!("{}{}{}", "  ".repeat(e.depth), marker, e.name)
+}
+
+/// Rebuild the tree and keep the selection on the same path if it still exists.
+fn refresh_tree(ctx: &mut SideBarModeContext) {
+    let selected_path = ctx.selected_entry().map(|e| e.path.clone());
+
+    ctx.entries = build_file_tree(&ctx.roots, &ctx.expanded, ctx.show_ignored);
+    ctx.dirty = false;
+
+    if let Some(path) = selected_path {
+        if let Some(idx) = ctx.entries.iter().position(|e| e.path == path) {
+            ctx.selected = idx;
+        }
+    }
+    ctx.selected = std::cmp::min(ctx.selected, ctx.entries.len().saturating_sub(1));
+    ctx.first_line = std::cmp::min(ctx.first_line, ctx.entries.len().saturating_sub(1));
+}
+
+/// Returns the text of the tree and the offset of each line.
+fn tree_text(ctx: &SideBarModeContext) -> (String, Vec<u64>) {
+    let mut text = String::new();
+    let mut offsets = vec![];
+    for e in &ctx.entries {
+        offsets.push(text.len() as u64);
+        text.push_str(&entry_line(e, &ctx.expanded));
+        text.push('\n');
+    }
+    offsets.push(text.len() as u64);
+    (text, offsets)
+}
+
+fn with_side_bar_ctx<R, F: FnOnce(&mut SideBarModeContext) -> R>(
+    view: &Rc<RwLock<View<'static>>>,
+    f: F,
+) -> R {
+    let mut v = view.write();
+    let ctx = v.mode_ctx_mut::<SideBarModeContext>("side-bar-mode");
+    f(ctx)
+}
+
+pub fn side_bar_select_previous(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| ctx.select(ctx.selected.saturating_sub(1)));
+}
+
+pub fn side_bar_select_next(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| ctx.select(ctx.selected + 1));
+}
+
+pub fn side_bar_select_previous_page(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| {
+        let n = std::cmp::max(1, ctx.height.saturating_sub(1));
+        ctx.select(ctx.selected.saturating_sub(n))
+    });
+}
+
+pub fn side_bar_select_next_page(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| {
+        let n = std::cmp::max(1, ctx.height.saturating_sub(1));
+        ctx.select(ctx.selected + n)
+    });
+}
+
+pub fn side_bar_select_first(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| ctx.select(0));
+}
+
+pub fn side_bar_select_last(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| ctx.select(ctx.entries.len()));
+}
+
+/// Collapse the selected directory, or select the parent directory.
+pub fn side_bar_collapse(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| {
+        let e = match ctx.selected_entry() {
+            Some(e) => e.clone(),
+            None => return,
+        };
+
+        if e.is_dir && ctx.expanded.remove(&e.path) {
+            ctx.dirty = true;
+            return;
+        }
+
+        if let Some(idx) = ctx.entries[..ctx.selected]
+            .iter()
+            .rposition(|p| p.depth + 1 == e.depth)
+        {
+            ctx.select(idx);
+        }
+    });
+}
+
+pub fn side_bar_expand(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| {
+        if let Some(e) = ctx.selected_entry() {
+            if e.is_dir {
+                let path = e.path.clone();
+                ctx.dirty |= ctx.expanded.insert(path);
             }
+        }
+    });
+}
 
-            view.compose_content_filters
-            .borrow_mut()
-            .push(Box::new(UnicodeToTextFilter::new()));
+/// Open the file 'path' and display it in the workspace.
+fn side_bar_open_path(editor: &mut Editor<'static>, env: &mut EditorEnv<'static>, path: &Path) {
+    let (id, ok) = open_doc_load_path(editor, env, &path.to_string_lossy());
+    if ok {
+        show_file_view(editor, env, id);
+    }
+}
 
-        */
+/// Toggle the selected directory or open the selected file.
+pub fn side_bar_open(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let file = with_side_bar_ctx(view, |ctx| {
+        let e = ctx.selected_entry()?.clone();
+        if e.is_dir {
+            if !ctx.expanded.remove(&e.path) {
+                ctx.expanded.insert(e.path);
+            }
+            ctx.dirty = true;
+            None
+        } else {
+            Some(e.path)
+        }
+    });
 
-        let mut screen_filter = ScreenFilter::new();
-        screen_filter.display_eof = false;
+    if let Some(path) = file {
+        side_bar_open_path(editor, env, &path);
+    }
+}
 
-        /*
-            view.compose_content_filters
-            .borrow_mut()
-            .push(Box::new(screen_filter));
-        */
+/// Give the focus back to the displayed file.
+pub fn side_bar_leave(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View<'static>>>,
+) {
+    if let Some(id) = current_file_view(editor) {
+        show_file_view(editor, env, id);
+    }
+}
 
-        view.compose_content_filters
-            .borrow_mut()
-            .push(Box::new(SideBarModeCompose::new()));
+/// Give the focus to the file tree.
+pub fn side_bar_focus(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View<'static>>>,
+) {
+    let ids = get_view_ids_by_tags(editor, "file-tree").unwrap_or_default();
+    if let Some(id) = ids.first() {
+        set_focus_on_view_id(editor, env, *id);
     }
 }
 
+pub fn side_bar_toggle_ignored(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| {
+        ctx.show_ignored = !ctx.show_ignored;
+        ctx.dirty = true;
+    });
+}
+
+pub fn side_bar_refresh(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| ctx.dirty = true);
+}
+
+/// Select the clicked line: a directory is expanded/collapsed, a file is opened.
+pub fn side_bar_button_release(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let y = {
+        let v = view.read();
+        match v.input_ctx.trigger.last() {
+            Some(InputEvent::ButtonRelease(ButtonEvent { y, .. })) => std::cmp::max(0, *y) as usize,
+            _ => return,
+        }
+    };
+
+    let valid = with_side_bar_ctx(view, |ctx| {
+        let idx = ctx.first_line + y;
+        if idx >= ctx.entries.len() {
+            return false;
+        }
+        ctx.select(idx);
+        true
+    });
+
+    if valid {
+        side_bar_open(editor, env, view);
+    }
+}
+
+pub fn side_bar_scroll_up(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| ctx.scroll(false, WHEEL_SCROLL_LINES));
+}
+
+pub fn side_bar_scroll_down(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    with_side_bar_ctx(view, |ctx| ctx.scroll(true, WHEEL_SCROLL_LINES));
+}
+
+fn prompt_message(prompt: &SideBarPrompt, text: &str) -> String {
+    match prompt {
+        SideBarPrompt::NewFile { .. } => format!("New file: {}", text),
+        SideBarPrompt::NewDirectory { .. } => format!("New directory: {}", text),
+        SideBarPrompt::Rename { .. } => format!("Rename to: {}", text),
+        SideBarPrompt::Delete { path } => {
+            if path.is_dir() {
+                format!("Delete directory '{}' and its content? y/n", path.display())
+            } else {
+                format!("Delete '{}'? y/n", path.display())
+            }
+        }
+    }
+}
+
+fn start_prompt(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    prompt: SideBarPrompt,
+    text: String,
+) {
+    if view::get_command_view_id(editor, env).is_none() {
+        return;
+    }
+
+    set_command_line_text(editor, env, &prompt_message(&prompt, &text));
+
+    let mut v = view.write();
+    v.input_ctx.stack_pos = None;
+    {
+        let input_map = build_input_event_map(SIDE_BAR_PROMPT_MAP).unwrap();
+        let mut input_map_stack = v.input_ctx.input_map.as_ref().borrow_mut();
+        input_map_stack.push(("side-bar-mode", input_map));
+    }
+
+    let ctx = v.mode_ctx_mut::<SideBarModeContext>("side-bar-mode");
+    ctx.prompt = Some(prompt);
+    ctx.prompt_text = text;
+}
+
+fn stop_prompt(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) -> Option<(SideBarPrompt, String)> {
+    let ret = {
+        let mut v = view.write();
+        {
+            let mut input_map_stack = v.input_ctx.input_map.as_ref().borrow_mut();
+            input_map_stack.pop();
+        }
+        let ctx = v.mode_ctx_mut::<SideBarModeContext>("side-bar-mode");
+        let text = std::mem::take(&mut ctx.prompt_text);
+        ctx.prompt.take().map(|p| (p, text))
+    };
+
+    set_command_line_text(editor, env, "");
+    ret
+}
+
+pub fn side_bar_new_file(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if let Some(dir) = with_side_bar_ctx(view, |ctx| ctx.selected_dir()) {
+        start_prompt(
+            editor,
+            env,
+            view,
+            SideBarPrompt::NewFile { dir },
+            String::new(),
+        );
+    }
+}
+
+pub fn side_bar_new_directory(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    if let Some(dir) = with_side_bar_ctx(view, |ctx| ctx.selected_dir()) {
+        start_prompt(
+            editor,
+            env,
+            view,
+            SideBarPrompt::NewDirectory { dir },
+            String::new(),
+        );
+    }
+}
+
+pub fn side_bar_rename(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    // the roots cannot be renamed
+    let e = with_side_bar_ctx(view, |ctx| ctx.selected_entry().cloned());
+    if let Some(e) = e.filter(|e| e.depth > 0) {
+        start_prompt(
+            editor,
+            env,
+            view,
+            SideBarPrompt::Rename { path: e.path },
+            e.name,
+        );
+    }
+}
+
+pub fn side_bar_delete(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    // the roots cannot be deleted
+    let e = with_side_bar_ctx(view, |ctx| ctx.selected_entry().cloned());
+    if let Some(e) = e.filter(|e| e.depth > 0) {
+        start_prompt(
+            editor,
+            env,
+            view,
+            SideBarPrompt::Delete { path: e.path },
+            String::new(),
+        );
+    }
+}
+
+/// A name must not be empty nor contain a path separator.
+fn valid_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+fn run_file_operation(prompt: &SideBarPrompt, text: &str) -> std::io::Result<Option<PathBuf>> {
+    let invalid_name = || {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid name '{}'", text),
+        )
+    };
+
+    match prompt {
+        SideBarPrompt::NewFile { dir } => {
+            if !valid_file_name(text) {
+                return Err(invalid_name());
+            }
+            let path = dir.join(text);
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+            Ok(Some(path))
+        }
+        SideBarPrompt::NewDirectory { dir } => {
+            if !valid_file_name(text) {
+                return Err(invalid_name());
+            }
+            let path = dir.join(text);
+            fs::create_dir(&path)?;
+            Ok(Some(path))
+        }
+        SideBarPrompt::Rename { path } => {
+            if !valid_file_name(text) {
+                return Err(invalid_name());
+            }
+            let new_path = path.with_file_name(text);
+            if new_path.exists() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("'{}' already exists", new_path.display()),
+                ));
+            }
+            fs::rename(path, &new_path)?;
+            Ok(Some(new_path))
+        }
+        SideBarPrompt::Delete { path } => {
+            if path.is_dir() {
+                fs::remove_dir_all(path)?;
+            } else {
+                fs::remove_file(path)?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Handles the keys typed while a name or a confirmation is requested.
+pub fn side_bar_prompt_input(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (evt, prompt) = {
+        let mut v = view.write();
+        let evt = v.input_ctx.trigger.last().cloned();
+        let ctx = v.mode_ctx_mut::<SideBarModeContext>("side-bar-mode");
+        (evt, ctx.prompt.clone())
+    };
+
+    let prompt = match prompt {
+        Some(prompt) => prompt,
+        None => {
+            stop_prompt(editor, env, view);
+            return;
+        }
+    };
+
+    let key = match evt {
+        Some(InputEvent::KeyPress { key, .. }) => key,
+        // ignore the mouse
+        _ => return,
+    };
+
+    let validate = match (&prompt, key) {
+        (SideBarPrompt::Delete { .. }, Key::Unicode('y')) => true,
+        (SideBarPrompt::Delete { .. }, _) => false,
+        (_, Key::Unicode('\n')) => true,
+        (_, Key::Escape) => false,
+        (_, key) => {
+            let text = with_side_bar_ctx(view, |ctx| {
+                match key {
+                    Key::Unicode(c) if !c.is_control() => ctx.prompt_text.push(c),
+                    Key::UnicodeArray(v) => ctx.prompt_text.extend(v),
+                    Key::BackSpace => {
+                        ctx.prompt_text.pop();
+                    }
+                    _ => {}
+                }
+                ctx.prompt_text.clone()
+            });
+            set_command_line_text(editor, env, &prompt_message(&prompt, &text));
+            return;
+        }
+    };
+
+    let (prompt, text) = match stop_prompt(editor, env, view) {
+        Some(p) => p,
+        None => return,
+    };
+
+    if !validate {
+        return;
+    }
+
+    match run_file_operation(&prompt, &text) {
+        Ok(path) => {
+            let new_file = with_side_bar_ctx(view, |ctx| {
+                // show the new entry
+                if let SideBarPrompt::NewFile { dir } | SideBarPrompt::NewDirectory { dir } =
+                    &prompt
+                {
+                    ctx.expanded.insert(dir.clone());
+                }
+                refresh_tree(ctx);
+                if let Some(idx) = path
+                    .as_ref()
+                    .and_then(|p| ctx.entries.iter().position(|e| &e.path == p))
+                {
+                    ctx.select(idx);
+                }
+                ctx.dirty = true;
+                path.filter(|_| matches!(prompt, SideBarPrompt::NewFile { .. }))
+            });
+
+            if let Some(path) = new_file {
+                side_bar_open_path(editor, env, &path);
+            }
+        }
+        Err(e) => {
+            dbg_println!("side-bar: file operation failed: {}", e);
+            set_command_line_text(editor, env, &format!("Error: {}", e));
+        }
+    }
+}
+
+/// A displayed line: text, buffer offset, selected.
+struct SideBarLine {
+    text: String,
+    offset: u64,
+    selected: bool,
+}
+
 pub struct SideBarModeCompose {
-    // add common fields
-    dir_buffer_list: Vec<String>,
+    lines: Vec<SideBarLine>,
+    focused: bool,
 }
 
 impl SideBarModeCompose {
     pub fn new() -> Self {
         dbg_println!("SideBarMode");
         SideBarModeCompose {
-            dir_buffer_list: vec![],
+            lines: vec![],
+            focused: false,
         }
     }
-
-    pub fn register_input_stage_actions<'a>(_map: &'a mut InputStageActionMap<'a>) {}
 }
 
 impl ContentFilter<'_> for SideBarModeCompose {
@@ -114,56 +915,216 @@ impl ContentFilter<'_> for SideBarModeCompose {
     fn setup(
         &mut self,
         editor: &mut Editor<'static>,
-        _editor_env: &mut EditorEnv<'static>,
-
-        _env: &mut LayoutEnv,
-        _view: &Rc<RwLock<View>>,
+        editor_env: &mut EditorEnv<'static>,
+        env: &mut LayoutEnv,
+        view: &Rc<RwLock<View>>,
         _parent_view: Option<&View<'static>>,
     ) {
-        // TODO(ceg): cache list
-        self.dir_buffer_list.clear();
+        let roots = tree_roots(editor);
+
+        let mut v = view.write();
+        self.focused = editor_env.active_view == Some(v.id);
 
-        // get editor document list
-        for (_id, b) in editor.buffer_map.read().iter() {
-            let b = b.read();
+        let start_offset = v.start_offset;
+        let buffer = v.buffer();
 
-            if b.kind == buffer::BufferKind::Directory {
-                self.dir_buffer_list.push(b.name.clone());
+        let ctx = v.mode_ctx_mut::<SideBarModeContext>("side-bar-mode");
+        ctx.height = env.screen.height();
+
+        if ctx.roots != roots {
+            for r in &roots {
+                if !ctx.roots.contains(r) {
+                    ctx.expanded.insert(r.clone());
+                }
             }
+            ctx.roots = roots;
+            ctx.dirty = true;
         }
 
-        let _ = editor.executor_tx.send((
-            0,
-            Box::new(|| {
-                println!("");
-            }),
-        ));
+        if ctx.dirty {
+            refresh_tree(ctx);
 
-        // order by cmd-line -> sort by buffer id order
-        // order by name
-        // self.dir_buffer_list.sort();
-    }
+            // the tree text is kept in the view's buffer for the scrollbar
+            let (text, offsets) = tree_text(ctx);
+            ctx.line_offsets = offsets;
+            if let Some(buffer) = &buffer {
+                let mut buffer = buffer.write();
+                let sz = buffer.size();
+                buffer.remove(0, sz, None);
+                buffer.insert(0, text.len(), text.as_bytes());
+            }
+        } else if ctx.line_offsets.get(ctx.first_line) != Some(&start_offset) {
+            // the start offset was changed by the scrollbar
+            let line = ctx.line_offsets.partition_point(|o| *o <= start_offset);
+            ctx.first_line = std::cmp::min(line.saturating_sub(1), ctx.entries.len());
+        }
 
-    fn finish(&mut self, _view: &View, env: &mut LayoutEnv) {
-        // fill the whole status bar
+        let first_line = ctx.first_line;
+        let new_start_offset = ctx.line_offsets.get(first_line).copied().unwrap_or(0);
 
-        // TODO(ceg): add screen.println("")
-        // TODO(ceg): add screen.println_truncate("")
+        self.lines.clear();
+        for (i, e) in ctx
+            .entries
+            .iter()
+            .enumerate()
+            .skip(first_line)
+            .take(ctx.height)
+        {
+            self.lines.push(SideBarLine {
+                text: entry_line(e, &ctx.expanded),
+                offset: ctx.line_offsets[i],
+                selected: i == ctx.selected,
+            });
+        }
 
+        v.start_offset = new_start_offset;
+    }
+
+    fn run(
+        &mut self,
+        _view: &View,
+        env: &mut LayoutEnv,
+        _input: &[FilterIo],
+        _output: &mut Vec<FilterIo>,
+    ) {
         env.screen.clear();
-        let w = env.screen.width().saturating_sub(0);
-        for s in &self.dir_buffer_list {
-            let slen = s.len();
-            for c in s.chars().take(w) {
-                let mut cpi = CodepointInfo::new();
-                cpi.displayed_cp = c;
+
+        // keep the last column for the line feed
+        let width = env.screen.width().saturating_sub(1);
+
+        for line in &self.lines {
+            let mut style = TextStyle::new();
+            if line.selected {
+                style.bg_color = if self.focused {
+                    TextStyle::default_selected_bg_color()
+                } else {
+                    TextStyle::default_mark_line_bg_color()
+                };
+            }
+
+            let mut cpi = CodepointInfo::new();
+            cpi.style = style;
+            cpi.metadata = false;
+            cpi.used = true;
+
+            let mut x = 0;
+            let mut offset = line.offset;
+            for c in line.text.chars() {
+                let w = env.screen.char_width(c);
+                if x + w > width {
+                    break;
+                }
                 cpi.cp = c;
+                cpi.displayed_cp = c;
+                cpi.offset = Some(offset);
+                cpi.size = c.len_utf8();
                 env.screen.push(&cpi);
-                // push no newline
+                x += w;
+                offset += c.len_utf8() as u64;
             }
-            if slen < w {
-                env.screen.select_next_line_index();
+
+            // fill the selected line
+            cpi.cp = ' ';
+            cpi.displayed_cp = ' ';
+            cpi.offset = None;
+            cpi.size = 0;
+            cpi.metadata = true;
+            if line.selected {
+                for _ in x..width {
+                    env.screen.push(&cpi);
+                }
+            }
+
+            // the line feed is at the end of the line text
+            let text_end = line.offset + line.text.len() as u64;
+            cpi.cp = '\n';
+            cpi.displayed_cp = '\n';
+            cpi.offset = Some(text_end);
+            cpi.size = 1;
+            cpi.metadata = false;
+            let (ok, _) = env.screen.push(&cpi);
+            if !ok {
+                break;
             }
         }
+
+        env.quit = true;
     }
+
+    fn finish(&mut self, _view: &View, _env: &mut LayoutEnv) {}
+}
+
+#[test]
+fn test_side_bar_file_tree() {
+    let root = std::env::temp_dir().join(format!("unlimited-side-bar-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("src/core")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("src/main.rs"), "").unwrap();
+    fs::write(root.join("src/core/mod.rs"), "").unwrap();
+    fs::write(root.join("README"), "").unwrap();
+    fs::write(root.join("debug.log"), "").unwrap();
+    fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+    fs::write(root.join("src/.gitignore"), "core/\n").unwrap();
+
+    let roots = vec![root.clone()];
+    let mut expanded = HashSet::new();
+    expanded.insert(root.clone());
+
+    let names = |entries: &[FileTreeEntry]| {
+        entries
+            .iter()
+            .skip(1)
+            .map(|e| (e.name.clone(), e.depth))
+            .collect::<Vec<_>>()
+    };
+
+    // directories first, ignored files hidden
+    let entries = build_file_tree(&roots, &expanded, false);
+    assert_eq!(
+        names(&entries),
+        vec![
+            ("src".to_owned(), 1),
+            (".gitignore".to_owned(), 1),
+            ("README".to_owned(), 1)
+        ]
+    );
+    assert_eq!(entry_line(&entries[1], &expanded), "  ▸ src");
+
+    // nested .gitignore
+    expanded.insert(root.join("src"));
+    let entries = build_file_tree(&roots, &expanded, false);
+    assert_eq!(
+        names(&entries),
+        vec![
+            ("src".to_owned(), 1),
+            (".gitignore".to_owned(), 2),
+            ("main.rs".to_owned(), 2),
+            (".gitignore".to_owned(), 1),
+            ("README".to_owned(), 1)
+        ]
+    );
+    assert_eq!(entry_line(&entries[3], &expanded), "      main.rs");
+
+    let entries = build_file_tree(&roots, &expanded, true);
+    assert_eq!(entries.len(), 9);
+
+    // file operations
+    let dir = root.join("src");
+    let prompt = SideBarPrompt::NewFile { dir: dir.clone() };
+    assert!(run_file_operation(&prompt, "../x").is_err());
+    assert!(run_file_operation(&prompt, "main.rs").is_err());
+    let path = run_file_operation(&prompt, "lib.rs").unwrap().unwrap();
+    assert!(path.is_file());
+
+    let prompt = SideBarPrompt::Rename { path: path.clone() };
+    assert!(run_file_operation(&prompt, "main.rs").is_err());
+    let path = run_file_operation(&prompt, "lib2.rs").unwrap().unwrap();
+    assert_eq!(path, dir.join("lib2.rs"));
+
+    let prompt = SideBarPrompt::Delete { path: path.clone() };
+    assert_eq!(run_file_operation(&prompt, "").unwrap(), None);
+    assert!(!path.exists());
+
+    let _ = fs::remove_dir_all(&root);
 }
@@ -159,8 +159,11 @@ impl ContentFilter<'_> for StatusLineModeCompose {
             if let Some(f) = check_view_by_id(editor, env.active_view_id) {
                 let f = f.read();
                 let b = f.buffer().unwrap();
-                let n = &b.read().name;
-                buffer_info.push_str(&format!("buffer:[{}]", n));
+                let b = b.read();
+                buffer_info.push_str(&format!("buffer:[{}]", b.name));
+                if let Some(compression) = b.inner.compression {
+                    buffer_info.push_str(&format!(" [{}]", compression.name()));
+                }
                 buffer_info.push_str(&format!(" act vid({})", env.active_view_id.0));
             }
         }
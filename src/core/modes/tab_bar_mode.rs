@@ -5,19 +5,26 @@ use parking_lot::RwLock;
 
 use super::Mode;
 
+use crate::core::codepointinfo::CodepointInfo;
+use crate::core::codepointinfo::TextStyle;
+
+use crate::core::editor::check_view_by_id;
 use crate::core::editor::get_view_by_id;
-use crate::core::editor::remove_view_by_id;
+use crate::core::editor::get_view_ids_by_tags;
+use crate::core::editor::push_editor_event;
+use crate::core::editor::register_input_stage_action;
+use crate::core::editor::set_focus_on_view_id;
 
 use crate::core::editor::Editor;
 use crate::core::editor::EditorEnv;
 
 use crate::core::editor::InputStageActionMap;
+use crate::core::event::input_map::build_input_event_map;
+use crate::core::event::*;
 
-use crate::core::modes::text_mode::RawDataFilter;
-use crate::core::modes::text_mode::ScreenFilter;
-
-use crate::core::buffer::BufferBuilder;
-use crate::core::buffer::BufferKind;
+use crate::core::modes::core_mode::destroy_view_hierarchy;
+use crate::core::modes::core_mode::set_command_line_text;
+use crate::core::modes::text_mode::record_view_position;
 
 use crate::core::view;
 use crate::core::view::ChildView;
@@ -26,21 +33,49 @@ use crate::core::view::ViewEvent;
 use crate::core::view::ViewEventDestination;
 use crate::core::view::ViewEventSource;
 
-use crate::core::editor::check_view_by_id;
-
-//use crate::core::view::FilterData;
 use crate::core::view::FilterIo;
 
 use crate::dbg_println;
 
 use crate::core::view::ContentFilter;
 
-use crate::core::view::LayoutDirection;
 use crate::core::view::LayoutEnv;
 use crate::core::view::LayoutSize;
 
+static TAB_BAR_INPUT_MAP: &str = r#"
+[
+  {
+    "events": [
+     { "default": [],                    "action": "tab-bar:input-event" }
+   ]
+  }
+
+]"#;
+
+/// width of the "◀ " and " ▶" scroll arrows
+const ARROW_WIDTH: usize = 2;
+
+/// The position of a displayed tab in the tab bar.
+#[derive(Debug, Clone, Copy)]
+pub struct TabBarEntry {
+    pub view_id: view::Id,
+    pub x: usize,
+    pub width: usize,
+    pub close_x: usize,
+}
+
 pub struct TabBarModeContext {
     pub hover: bool,
+    /// displayed tabs
+    pub tabs: Vec<TabBarEntry>,
+    /// index in editor.active_views of the first displayed tab
+    pub first_tab: usize,
+    pub left_arrow: bool,
+    pub right_arrow: bool,
+    pub width: usize,
+    /// the view shown in the file slot at the last composition
+    pub current: Option<view::Id>,
+    pub tab_count: usize,
 }
 
 pub struct TabBarMode {}
@@ -51,7 +86,9 @@ impl TabBarMode {
         TabBarMode {}
     }
 
-    pub fn register_input_stage_actions<'a>(_map: &'a mut InputStageActionMap<'a>) {}
+    pub fn register_input_stage_actions<'a>(map: &'a mut InputStageActionMap<'a>) {
+        register_input_stage_action(map, "tab-bar:input-event", tab_bar_input_event);
+    }
 }
 
 impl<'a> Mode for TabBarMode {
@@ -60,7 +97,16 @@ impl<'a> Mode for TabBarMode {
     }
 
     fn alloc_ctx(&self, _editor: &Editor<'static>) -> Box<dyn Any> {
-        let ctx = TabBarModeContext { hover: false };
+        let ctx = TabBarModeContext {
+            hover: false,
+            tabs: vec![],
+            first_tab: 0,
+            left_arrow: false,
+            right_arrow: false,
+            width: 0,
+            current: None,
+            tab_count: 0,
+        };
         Box::new(ctx)
     }
 
@@ -76,47 +122,10 @@ impl<'a> Mode for TabBarMode {
         _env: &mut EditorEnv<'static>,
         view: &mut View<'static>,
     ) {
-        //
-        let _use_utf8_codec = true;
-        let _use_tabulation_exp = !true; // char map ? with <tab>
+        let input_map = build_input_event_map(TAB_BAR_INPUT_MAP).unwrap();
+        let mut input_map_stack = view.input_ctx.input_map.as_ref().borrow_mut();
+        input_map_stack.push((self.name(), input_map));
 
-        // mandatory data reader
-        view.compose_content_filters
-            .borrow_mut()
-            .push(Box::new(RawDataFilter::new()));
-        //
-        /*
-                if use_utf8_codec {
-                    //
-                    // DEBUG codec error
-                    view.compose_content_filters
-                        .borrow_mut()
-                        .push(Box::new(Utf8Filter::new()));
-                } else {
-                    view.compose_content_filters
-                        .borrow_mut()
-                        .push(Box::new(TextCodecFilter::new()));
-                }
-                //
-                view.compose_content_filters
-                    .borrow_mut()
-                    .push(Box::new(UnicodeToTextFilter::new()));
-
-                // TODO: char map 0x9 -> "\t"
-                if use_tabulation_exp {
-                    view.compose_content_filters
-                        .borrow_mut()
-                        .push(Box::new(TabFilter::new()));
-                }
-        */
-        let mut screen_filter = ScreenFilter::new();
-        screen_filter.display_eof = false;
-
-        view.compose_content_filters
-            .borrow_mut()
-            .push(Box::new(screen_filter));
-
-        // move to overlay
         view.compose_content_filters
             .borrow_mut()
             .push(Box::new(TabBarModeCompose::new()));
@@ -129,7 +138,7 @@ impl<'a> Mode for TabBarMode {
     //
     fn on_editor_event(
         &self,
-        _editor: &mut Editor<'static>,
+        editor: &mut Editor<'static>,
         _env: &mut EditorEnv<'static>,
         event: &EditorEvent,
         watcher_view: &mut View<'static>,
@@ -140,6 +149,17 @@ impl<'a> Mode for TabBarMode {
             event,
             watcher_view.id
         );
+
+        match event {
+            EditorEvent::BufferRemoved { .. } | EditorEvent::ViewRemoved { .. } => {
+                sync_active_views(editor);
+            }
+            EditorEvent::BufferAdded { .. } | EditorEvent::ViewAdded { .. } => {}
+        }
+
+        // force the refresh of the scroll position
+        let ctx = watcher_view.mode_ctx_mut::<TabBarModeContext>("tab-bar-mode");
+        ctx.tab_count = 0;
     }
 
     //
@@ -199,62 +219,415 @@ impl EditorEventCb for TabBarMode {
     }
 }
 
-pub fn create_tab_entry(
-    mut editor: &mut Editor<'static>,
-    mut env: &mut EditorEnv<'static>,
-    parent_id: view::Id,
-    label: &str,
-) {
-    let label_buffer = BufferBuilder::new(BufferKind::File)
-        .buffer_name("tab:entry")
-        .internal(true)
-        .use_buffer_log(false)
-        .finalize();
+/// Remove from editor.active_views (and editor.recent_views) the views that were destroyed
+/// or whose buffer is no longer in editor.buffer_map.
+pub fn sync_active_views(editor: &mut Editor<'static>) {
+    let buffer_map = editor.buffer_map.clone();
+    let buffer_map = buffer_map.read();
+
+    let view_map = editor.view_map.clone();
+    let view_map = view_map.read();
+
+    editor.active_views.retain(|id| {
+        view_map.get(id).is_some_and(|v| {
+            v.read()
+                .buffer()
+                .is_some_and(|b| buffer_map.contains_key(&b.read().id))
+        })
+    });
+    let active_views = &editor.active_views;
+    editor.recent_views.retain(|id| active_views.contains(id));
+}
+
+fn file_slot_id(editor: &Editor<'static>) -> Option<view::Id> {
+    let ids = get_view_ids_by_tags(editor, "file-slot")?;
+    ids.first().copied()
+}
+
+/// Returns the id of the view displayed in the file slot.
+pub fn current_file_view(editor: &Editor<'static>) -> Option<view::Id> {
+    let slot = check_view_by_id(editor, file_slot_id(editor)?)?;
+    let slot = slot.read();
+    slot.children.first().map(|c| c.id)
+}
+
+/// Returns the first view tagged with 'tag' in the hierarchy of 'id'.
+pub fn find_tagged_child(editor: &Editor<'static>, id: view::Id, tag: &str) -> Option<view::Id> {
+    let v = check_view_by_id(editor, id)?;
+    let v = v.read();
+    if v.tags.contains(tag) {
+        return Some(id);
+    }
+
+    v.children
+        .iter()
+        .find_map(|c| find_tagged_child(editor, c.id, tag))
+}
+
+fn focus_file_view(editor: &mut Editor<'static>, env: &mut EditorEnv<'static>, id: view::Id) {
+    let target = find_tagged_child(editor, id, "target-view").unwrap_or(id);
+    set_focus_on_view_id(editor, env, target);
+}
+
+/// Display the (file) view 'id' in the file slot and give it the focus.
+pub fn show_file_view(editor: &mut Editor<'static>, env: &mut EditorEnv<'static>, id: view::Id) {
+    let slot_id = match file_slot_id(editor) {
+        Some(slot_id) => slot_id,
+        None => return,
+    };
+
+    if check_view_by_id(editor, id).is_none() {
+        return;
+    }
 
     {
-        let mut d = label_buffer.as_ref().unwrap().write();
-        d.append(label.as_bytes());
-    }
-
-    let width = label.len();
-    // create view
-    let label_view = View::new(
-        &mut editor,
-        &mut env,
-        Some(parent_id),
-        (0, 0),
-        (width, 1),
-        label_buffer,
-        &vec![], // tags
-        &vec!["empty-line-mode".to_owned()],
-        0,
-        LayoutDirection::Horizontal,
-        LayoutSize::Floating,
-    );
+        let slot = get_view_by_id(editor, slot_id);
+        let mut slot = slot.write();
+        slot.children.clear();
+        slot.children.push(ChildView {
+            id,
+            layout_op: LayoutSize::Percent { p: 100.0 },
+        });
+    }
 
-    let id = label_view.id;
+    // split_view_with_direction replaces the view in its parent
     {
-        let tab_bar = get_view_by_id(editor, parent_id);
-        let mut tab_bar = tab_bar.write();
+        let v = get_view_by_id(editor, id);
+        let mut v = v.write();
+        v.parent_id = Some(slot_id);
+        v.layout_index = Some(0);
+    }
+
+    editor.recent_views.retain(|vid| *vid != id);
+    editor.recent_views.insert(0, id);
 
-        tab_bar.children.push(ChildView {
-            id: id,
-            layout_op: LayoutSize::Fixed { size: width },
+    focus_file_view(editor, env, id);
+}
+
+/// Remember the position of the (file) view 'id' in the file history.
+pub fn record_file_view_position(editor: &mut Editor<'static>, id: view::Id) {
+    if let Some(target) = find_tagged_child(editor, id, "target-view") {
+        let v = get_view_by_id(editor, target);
+        let v = v.read();
+        record_view_position(editor, &v);
+    }
+}
+
+/// Close the tab of the view 'id': destroy the view and release its buffer.<br/>
+/// A tab with unsaved changes is not closed, the command line tells why.
+pub fn close_file_view(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    id: view::Id,
+) -> bool {
+    let idx = match editor.active_views.iter().position(|vid| *vid == id) {
+        Some(idx) => idx,
+        None => return false,
+    };
+
+    let (buffer_id, unsaved) = {
+        let v = get_view_by_id(editor, id);
+        let v = v.read();
+        match v.buffer() {
+            Some(b) => {
+                let b = b.read();
+                let unsaved = b.changed.then(|| b.name.clone());
+                (Some(b.id), unsaved)
+            }
+            None => (None, None),
+        }
+    };
+
+    if let Some(name) = unsaved {
+        dbg_println!("tab-bar: buffer '{}' has unsaved changes", name);
+        let text = format!(
+            "'{}' has unsaved changes, save it (ctrl+x ctrl+s) before closing its tab",
+            name
+        );
+        set_command_line_text(editor, env, &text);
+        return false;
+    }
+
+    record_file_view_position(editor, id);
+
+    let was_current = current_file_view(editor) == Some(id);
+
+    editor.active_views.remove(idx);
+    editor.recent_views.retain(|vid| *vid != id);
+
+    if was_current {
+        if editor.active_views.is_empty() {
+            if let Some(slot_id) = file_slot_id(editor) {
+                get_view_by_id(editor, slot_id).write().children.clear();
+            }
+            if let Some(workspace_id) = view::get_view_by_tag(editor, env, "workspace") {
+                set_focus_on_view_id(editor, env, workspace_id);
+            }
+        } else {
+            let next = editor.active_views[idx.min(editor.active_views.len() - 1)];
+            show_file_view(editor, env, next);
+        }
+    }
+
+    // release the buffer if no other tab uses it
+    if let Some(buffer_id) = buffer_id {
+        let used = editor.active_views.iter().any(|vid| {
+            check_view_by_id(editor, *vid)
+                .and_then(|v| v.read().buffer())
+                .is_some_and(|b| b.read().id == buffer_id)
         });
+        if !used {
+            editor.buffer_map.write().remove(&buffer_id);
+            push_editor_event(editor, EditorEvent::BufferRemoved { id: buffer_id });
+        }
+    }
+
+    destroy_view_hierarchy(editor, id);
+    push_editor_event(editor, EditorEvent::ViewRemoved { id });
+
+    true
+}
+
+fn select_tab_with_offset(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    forward: bool,
+) {
+    sync_active_views(editor);
+
+    let n = editor.active_views.len();
+    if n == 0 {
+        return;
+    }
+
+    let idx = current_file_view(editor)
+        .and_then(|id| editor.active_views.iter().position(|vid| *vid == id));
+
+    let next = match (idx, forward) {
+        (Some(idx), true) => (idx + 1) % n,
+        (Some(idx), false) => (idx + n - 1) % n,
+        (None, _) => 0,
+    };
+
+    let id = editor.active_views[next];
+    show_file_view(editor, env, id);
+}
+
+/// Show the next tab.
+pub fn tab_bar_next_tab(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View<'static>>>,
+) {
+    select_tab_with_offset(editor, env, true);
+}
+
+/// Show the previous tab.
+pub fn tab_bar_previous_tab(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View<'static>>>,
+) {
+    select_tab_with_offset(editor, env, false);
+}
+
+/// Close the current tab.
+pub fn tab_bar_close_tab(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View<'static>>>,
+) {
+    if let Some(id) = current_file_view(editor) {
+        close_file_view(editor, env, id);
+    }
+}
+
+fn scroll_tab_bars(editor: &mut Editor<'static>, forward: bool) {
+    let ids = get_view_ids_by_tags(editor, "tab-bar").unwrap_or_default();
+    for id in ids {
+        if let Some(v) = check_view_by_id(editor, id) {
+            let mut v = v.write();
+            let ctx = v.mode_ctx_mut::<TabBarModeContext>("tab-bar-mode");
+            ctx.scroll(forward);
+        }
+    }
+}
+
+/// Scroll the tab bar to the left when all the tabs cannot be displayed.
+pub fn tab_bar_scroll_left(
+    editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View<'static>>>,
+) {
+    scroll_tab_bars(editor, false);
+}
+
+/// Scroll the tab bar to the right when all the tabs cannot be displayed.
+pub fn tab_bar_scroll_right(
+    editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View<'static>>>,
+) {
+    scroll_tab_bars(editor, true);
+}
+
+enum TabBarClick {
+    Switch(view::Id),
+    Close(view::Id),
+    Scroll { forward: bool },
+}
+
+impl TabBarModeContext {
+    fn scroll(&mut self, forward: bool) {
+        if forward {
+            if self.right_arrow {
+                self.first_tab += 1;
+            }
+        } else {
+            self.first_tab = self.first_tab.saturating_sub(1);
+        }
+    }
+
+    fn click(&self, x: usize, button: u32) -> Option<TabBarClick> {
+        if button == 0 {
+            if self.left_arrow && x < ARROW_WIDTH {
+                return Some(TabBarClick::Scroll { forward: false });
+            }
+            if self.right_arrow && x + ARROW_WIDTH >= self.width {
+                return Some(TabBarClick::Scroll { forward: true });
+            }
+        }
+
+        let tab = self.tabs.iter().find(|t| x >= t.x && x < t.x + t.width)?;
+        match button {
+            0 if x == tab.close_x => Some(TabBarClick::Close(tab.view_id)),
+            0 => Some(TabBarClick::Switch(tab.view_id)),
+            2 => Some(TabBarClick::Close(tab.view_id)),
+            _ => None,
+        }
     }
+}
 
-    editor.add_view(id, label_view);
+/// Handles the mouse events of the tab bar:<br/>
+/// button-1 switches to the clicked tab (or closes it on ⨯), button-2 closes it, the wheel scrolls the tabs.
+pub fn tab_bar_input_event(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let click = {
+        let mut v = view.write();
+        let evt = v.input_ctx.trigger.last().cloned();
+        let ctx = v.mode_ctx_mut::<TabBarModeContext>("tab-bar-mode");
+
+        match evt {
+            Some(InputEvent::ButtonRelease(ButtonEvent { x, button, .. })) => {
+                ctx.click(std::cmp::max(0, x) as usize, button)
+            }
+            Some(InputEvent::WheelUp { .. }) => Some(TabBarClick::Scroll { forward: false }),
+            Some(InputEvent::WheelDown { .. }) => Some(TabBarClick::Scroll { forward: true }),
+            _ => None,
+        }
+    };
+
+    match click {
+        Some(TabBarClick::Switch(id)) => show_file_view(editor, env, id),
+        Some(TabBarClick::Close(id)) => {
+            close_file_view(editor, env, id);
+        }
+        Some(TabBarClick::Scroll { forward }) => {
+            let mut v = view.write();
+            let ctx = v.mode_ctx_mut::<TabBarModeContext>("tab-bar-mode");
+            ctx.scroll(forward);
+        }
+        None => {}
+    }
+
+    // give the focus back to the displayed file
+    if let Some(id) = current_file_view(editor) {
+        focus_file_view(editor, env, id);
+    }
+}
+
+/// Returns the x position of the tabs displayed from 'first',
+/// and if the left/right scroll arrows are needed.
+fn layout_tabs(widths: &[usize], first: usize, width: usize) -> (Vec<usize>, bool, bool) {
+    let left_arrow = first > 0;
+    let start = if left_arrow { ARROW_WIDTH } else { 0 };
+
+    let place = |limit: usize| {
+        let mut xs = vec![];
+        let mut x = start;
+        for w in widths.iter().skip(first) {
+            if x + w > limit && !xs.is_empty() {
+                break;
+            }
+            xs.push(x);
+            x += w;
+        }
+        xs
+    };
+
+    let xs = place(width);
+    if first + xs.len() >= widths.len() {
+        return (xs, left_arrow, false);
+    }
+
+    let xs = place(width.saturating_sub(ARROW_WIDTH));
+    (xs, left_arrow, true)
 }
 
-pub struct TabBarModeCompose {}
+/// Returns the first displayed tab index that keeps the tab 'idx' visible.
+fn first_tab_showing(widths: &[usize], mut first: usize, idx: usize, width: usize) -> usize {
+    if idx < first {
+        return idx;
+    }
+
+    while first < idx {
+        let (xs, _, _) = layout_tabs(widths, first, width);
+        if idx < first + xs.len() {
+            break;
+        }
+        first += 1;
+    }
+
+    first
+}
+
+fn tab_label(name: &str, changed: bool, streaming: bool) -> String {
+    let base_name = std::path::Path::new(name)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.to_owned());
+
+    format!(
+        " {}{}{} ⨯ ",
+        base_name,
+        if changed { "*" } else { "" },
+        if streaming { " (streaming)" } else { "" }
+    )
+}
+
+pub struct TabBarModeCompose {
+    cells: Vec<CodepointInfo>,
+}
 
 impl TabBarModeCompose {
     pub fn new() -> Self {
         dbg_println!("TabBarMode");
-        TabBarModeCompose {}
+        TabBarModeCompose { cells: vec![] }
     }
 
-    pub fn register_input_stage_actions<'a>(_map: &'a mut InputStageActionMap<'a>) {}
+    fn push_str(&mut self, s: &str, color: (u8, u8, u8), bg_color: (u8, u8, u8), bold: bool) {
+        for c in s.chars() {
+            let mut cpi = CodepointInfo::new();
+            cpi.displayed_cp = c;
+            cpi.style.color = color;
+            cpi.style.bg_color = bg_color;
+            cpi.style.is_bold = bold;
+            self.cells.push(cpi);
+        }
+    }
 }
 
 impl ContentFilter<'_> for TabBarModeCompose {
@@ -264,65 +637,150 @@ impl ContentFilter<'_> for TabBarModeCompose {
 
     fn setup(
         &mut self,
-        mut editor: &mut Editor<'static>,
-        mut editor_env: &mut EditorEnv<'static>,
-        _layout_env: &mut LayoutEnv,
+        editor: &mut Editor<'static>,
+        _editor_env: &mut EditorEnv<'static>,
+        layout_env: &mut LayoutEnv,
         view: &Rc<RwLock<View>>,
         _parent_view: Option<&View<'static>>,
     ) {
-        // hack
-        // FIXME: move to event base populate
-        // destroy/recreate all children (labels + cross)
-        // TODO(ceg): destroy_view((&editor, c.id);
-        {
-            let mut v = view.write();
+        sync_active_views(editor);
 
-            let _mode_ctx = v.mode_ctx_mut::<TabBarModeContext>("tab-bar-mode");
+        let current = current_file_view(editor);
 
-            // MUST will remove view from parent.children
-            for c in &v.children {
-                remove_view_by_id(&editor, c.id);
+        // (view id, label)
+        let mut entries = vec![];
+        for id in editor.active_views.iter() {
+            if let Some(v) = check_view_by_id(editor, *id) {
+                if let Some(b) = v.read().buffer() {
+                    let b = b.read();
+                    entries.push((*id, tab_label(&b.name, b.changed, b.is_streaming)));
+                }
             }
-            v.children.clear();
         }
 
-        // scan active group/view and populate children
-        {
-            let mut labels = vec![];
-
-            // scroll widget
-            // labels.push("⮜ ⮞ ".to_owned());
-
-            let parent_id = { view.read().id };
-            let list = editor.active_views.clone();
-            for (idx, vid) in list.iter().enumerate() {
-                if let Some(cv) = check_view_by_id(editor, *vid) {
-                    if let Some(b) = cv.read().buffer() {
-                        let mut l = String::new();
-                        if idx != 0 {
-                            l.push_str(&"|");
-                        }
-                        l.push_str(&b.read().name);
-                        l.push_str(&" ⨯");
-                        labels.push(l);
-                    }
-                };
+        let widths: Vec<usize> = entries.iter().map(|(_, l)| l.chars().count()).collect();
+        let width = layout_env.screen.width();
+
+        let (first_tab, xs, left_arrow, right_arrow) = {
+            let mut v = view.write();
+            let ctx = v.mode_ctx_mut::<TabBarModeContext>("tab-bar-mode");
+
+            ctx.first_tab = std::cmp::min(ctx.first_tab, entries.len().saturating_sub(1));
+
+            // keep the current tab visible
+            if ctx.current != current || ctx.tab_count != entries.len() {
+                ctx.current = current;
+                ctx.tab_count = entries.len();
+                if let Some(idx) = entries.iter().position(|(id, _)| Some(*id) == current) {
+                    ctx.first_tab = first_tab_showing(&widths, ctx.first_tab, idx, width);
+                }
             }
-            // (re)create new children
-            for n in labels {
-                create_tab_entry(&mut editor, &mut editor_env, parent_id, &n);
+
+            let (xs, left_arrow, right_arrow) = layout_tabs(&widths, ctx.first_tab, width);
+
+            ctx.tabs.clear();
+            for (i, x) in xs.iter().enumerate() {
+                let idx = ctx.first_tab + i;
+                ctx.tabs.push(TabBarEntry {
+                    view_id: entries[idx].0,
+                    x: *x,
+                    width: widths[idx],
+                    close_x: x + widths[idx] - 2,
+                });
+            }
+            ctx.left_arrow = left_arrow;
+            ctx.right_arrow = right_arrow;
+            ctx.width = width;
+
+            (ctx.first_tab, xs, left_arrow, right_arrow)
+        };
+
+        // build the cells
+        self.cells.clear();
+
+        let arrow_color = TextStyle::default_color();
+        let bg_color = TextStyle::default_mark_line_bg_color();
+        let inactive_color = (128, 128, 128);
+
+        if left_arrow {
+            self.push_str("◀ ", arrow_color, bg_color, false);
+        }
+
+        for i in 0..xs.len() {
+            let (id, label) = &entries[first_tab + i];
+            if Some(*id) == current {
+                self.push_str(
+                    label,
+                    TextStyle::default_color(),
+                    TextStyle::default_bg_color(),
+                    true,
+                );
+            } else {
+                self.push_str(label, inactive_color, bg_color, false);
             }
         }
+
+        if right_arrow {
+            let used = self.cells.len();
+            let pad = width.saturating_sub(used + ARROW_WIDTH);
+            self.push_str(&" ".repeat(pad), arrow_color, bg_color, false);
+            self.push_str(" ▶", arrow_color, bg_color, false);
+        }
     }
 
     fn run(
         &mut self,
         _view: &View,
-        _env: &mut LayoutEnv,
+        env: &mut LayoutEnv,
         _input: &[FilterIo],
         _output: &mut Vec<FilterIo>,
     ) {
+        let width = env.screen.width();
+        let mut count = 0;
+        for cpi in self.cells.iter().take(width) {
+            let (b, _) = env.screen.push(cpi);
+            if !b {
+                break;
+            }
+            count += 1;
+        }
+
+        let mut cpi = CodepointInfo::new();
+        cpi.style.bg_color = TextStyle::default_mark_line_bg_color();
+        for _i in count..width {
+            let (b, _) = env.screen.push(&cpi);
+            if !b {
+                break;
+            }
+        }
+
+        env.quit = true;
     }
 
     fn finish(&mut self, _view: &View, _env: &mut LayoutEnv) {}
 }
+
+#[test]
+fn test_tab_bar_layout() {
+    // all the tabs fit
+    assert_eq!(
+        layout_tabs(&[5, 5, 5], 0, 20),
+        (vec![0, 5, 10], false, false)
+    );
+
+    // overflow: the right arrow takes 2 columns
+    assert_eq!(layout_tabs(&[5, 5, 5, 5], 0, 16), (vec![0, 5], false, true));
+    assert_eq!(layout_tabs(&[5, 5, 5, 5], 1, 16), (vec![2, 7], true, true));
+    assert_eq!(layout_tabs(&[5, 5, 5, 5], 2, 16), (vec![2, 7], true, false));
+
+    // a tab larger than the bar is still displayed
+    assert_eq!(layout_tabs(&[30], 0, 10), (vec![0], false, false));
+
+    assert_eq!(first_tab_showing(&[5, 5, 5, 5], 0, 1, 16), 0);
+    assert_eq!(first_tab_showing(&[5, 5, 5, 5], 0, 3, 16), 2);
+    assert_eq!(first_tab_showing(&[5, 5, 5, 5], 2, 0, 16), 0);
+
+    assert_eq!(tab_label("/tmp/foo.rs", false, false), " foo.rs ⨯ ");
+    assert_eq!(tab_label("foo.rs", true, false), " foo.rs* ⨯ ");
+    assert_eq!(tab_label("*stdin*", false, true), " *stdin* (streaming) ⨯ ");
+}
@@ -0,0 +1,185 @@
+use parking_lot::RwLock;
+use std::rc::Rc;
+
+use crate::core::bidi::bidi_class;
+use crate::core::bidi::mirrored_char;
+use crate::core::bidi::needs_reordering;
+use crate::core::bidi::resolve_levels;
+use crate::core::codec::text::u32_to_char;
+use crate::core::view::ContentFilter;
+use crate::core::view::FilterData;
+use crate::core::view::FilterIo;
+use crate::core::view::LayoutEnv;
+use crate::core::view::View;
+use crate::core::Editor;
+use crate::core::EditorEnv;
+
+/// The BidiFilter resolves the bidi embedding levels of each paragraph (up to '\n')
+/// and stores them in the bidi_level of the ios, the Screen reorders the visual lines.<br/>
+/// Must be placed before the screen filter (after the word wrapping).
+pub struct BidiFilter {
+    /// the ios of the current paragraph
+    paragraph: Vec<FilterIo>,
+    /// the current paragraph contains right-to-left or explicit formatting codepoints
+    needs_reordering: bool,
+    /// long paragraphs are resolved by chunks of 'max_paragraph_size' ios
+    max_paragraph_size: usize,
+}
+
+impl Default for BidiFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BidiFilter {
+    pub fn new() -> Self {
+        BidiFilter {
+            paragraph: vec![],
+            needs_reordering: false,
+            max_paragraph_size: 0,
+        }
+    }
+
+    fn flush_paragraph(&mut self, filter_out: &mut Vec<FilterIo>) {
+        if self.needs_reordering {
+            set_bidi_levels(&mut self.paragraph);
+            self.needs_reordering = false;
+        }
+        filter_out.append(&mut self.paragraph);
+    }
+}
+
+/// Set the bidi level of the ios of a paragraph, the metadata (wrap points...) take the paragraph level.<br/>
+/// The mirrored characters are displayed in the right-to-left runs (rule L4).
+pub fn set_bidi_levels(paragraph: &mut [FilterIo]) {
+    let text: String = paragraph
+        .iter()
+        .filter(|io| !io.metadata)
+        .filter_map(|io| match io.data {
+            FilterData::TextInfo { real_cp, .. } => Some(u32_to_char(real_cp)),
+            _ => None,
+        })
+        .collect();
+
+    let (para_level, levels) = resolve_levels(&text);
+    let mut levels = levels.into_iter();
+
+    for io in paragraph.iter_mut() {
+        let level = match (&io.data, io.metadata) {
+            (FilterData::TextInfo { .. }, false) => levels.next().unwrap_or(para_level),
+            _ => para_level,
+        };
+        io.bidi_level = level;
+
+        if level % 2 == 0 {
+            continue;
+        }
+        if let FilterData::TextInfo {
+            real_cp,
+            displayed_cp,
+        } = &mut io.data
+        {
+            if *real_cp == *displayed_cp {
+                if let Some(m) = mirrored_char(u32_to_char(*real_cp)) {
+                    *displayed_cp = m as u32;
+                }
+            }
+        }
+    }
+}
+
+impl ContentFilter<'_> for BidiFilter {
+    fn name(&self) -> &'static str {
+        "BidiFilter"
+    }
+
+    fn setup(
+        &mut self,
+        _editor: &mut Editor<'static>,
+        _editor_env: &mut EditorEnv<'static>,
+
+        env: &mut LayoutEnv,
+        _view: &Rc<RwLock<View>>,
+        _parent_view: Option<&View<'static>>,
+    ) {
+        self.paragraph.clear();
+        self.needs_reordering = false;
+        self.max_paragraph_size = env.screen.push_capacity().max(1);
+    }
+
+    fn run(
+        &mut self,
+        _view: &View,
+        _env: &mut LayoutEnv,
+        filter_in: &[FilterIo],
+        filter_out: &mut Vec<FilterIo>,
+    ) {
+        for io in filter_in.iter() {
+            match io.data {
+                FilterData::TextInfo { real_cp, .. } => {
+                    let c = u32_to_char(real_cp);
+                    if !io.metadata && !self.needs_reordering {
+                        self.needs_reordering = needs_reordering(bidi_class(c));
+                    }
+
+                    self.paragraph.push(io.clone());
+
+                    let end_of_paragraph = !io.metadata && c == '\n';
+                    if end_of_paragraph || self.paragraph.len() >= self.max_paragraph_size {
+                        self.flush_paragraph(filter_out);
+                    }
+                }
+
+                _ => {
+                    // end of stream, limit reached ...
+                    self.flush_paragraph(filter_out);
+                    filter_out.push(io.clone());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_set_bidi_levels() {
+    use crate::core::codepointinfo::TextStyle;
+
+    let text_io = |c: char, offset: u64| FilterIo {
+        metadata: false,
+        style: TextStyle::new(),
+        offset: Some(offset),
+        size: c.len_utf8(),
+        data: FilterData::TextInfo {
+            real_cp: c as u32,
+            displayed_cp: c as u32,
+        },
+        bidi_level: 0,
+    };
+
+    // hebrew alef ( bet ) \n
+    let mut offset = 0;
+    let mut paragraph = vec![];
+    for c in "\u{5d0}(\u{5d1})\n".chars() {
+        paragraph.push(text_io(c, offset));
+        offset += c.len_utf8() as u64;
+    }
+
+    set_bidi_levels(&mut paragraph);
+
+    let levels: Vec<u8> = paragraph.iter().map(|io| io.bidi_level).collect();
+    assert_eq!(levels, vec![1, 1, 1, 1, 1]);
+
+    // the brackets are mirrored, the offsets are kept
+    match paragraph[1].data {
+        FilterData::TextInfo {
+            real_cp,
+            displayed_cp,
+        } => {
+            assert_eq!(real_cp, '(' as u32);
+            assert_eq!(displayed_cp, ')' as u32);
+        }
+        _ => panic!(),
+    }
+    assert_eq!(paragraph[1].offset, Some(2));
+}
@@ -0,0 +1,518 @@
+use parking_lot::RwLock;
+use std::rc::Rc;
+
+use unicode_width::UnicodeWidthChar;
+
+use super::mark::read_char_forward;
+use super::mark::Mark;
+use super::*;
+
+use crate::core::buffer::Buffer;
+use crate::core::codec::text::TextCodec;
+use crate::core::editor::Editor;
+use crate::core::editor::EditorEnv;
+use crate::core::event::ButtonEvent;
+use crate::core::event::InputEvent;
+use crate::core::event::PointerEvent;
+use crate::core::view::View;
+use crate::sort_pair;
+
+/// A **BlockSelection** describes a rectangular (column) selection.<br/>
+/// Lines are identified by their start offset, columns are display columns
+/// (tabs are expanded and wide characters use 2 columns).<br/>
+/// The cursor column can go past the end of the line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockSelection {
+    pub anchor_line: u64,
+    pub anchor_column: u64,
+    pub cursor_line: u64,
+    pub cursor_column: u64,
+}
+
+impl BlockSelection {
+    pub fn new(line: u64, column: u64) -> Self {
+        BlockSelection {
+            anchor_line: line,
+            anchor_column: column,
+            cursor_line: line,
+            cursor_column: column,
+        }
+    }
+}
+
+/// Returns the number of screen columns used by 'c' when displayed at 'column'.
+pub fn codepoint_columns(c: char, column: u64, tab_width: u64) -> u64 {
+    match c {
+        '\t' => tab_width - (column % tab_width),
+        _ => std::cmp::max(1, UnicodeWidthChar::width(c).unwrap_or(1)) as u64,
+    }
+}
+
+pub fn line_start(buffer: &Buffer, codec: &dyn TextCodec, offset: u64) -> u64 {
+    let mut m = Mark::new(offset);
+    m.move_to_start_of_line(buffer, codec);
+    m.offset
+}
+
+pub fn next_line_start(buffer: &Buffer, codec: &dyn TextCodec, line_start: u64) -> Option<u64> {
+    let mut encode = [0; 4];
+    let sz = codec.encode('\n' as u32, &mut encode);
+    buffer
+        .find(&encode[..sz], line_start, None)
+        .map(|offset| offset + sz as u64)
+}
+
+pub fn previous_line_start(buffer: &Buffer, codec: &dyn TextCodec, line_start: u64) -> Option<u64> {
+    if line_start == 0 {
+        return None;
+    }
+    Some(self::line_start(buffer, codec, line_start - 1))
+}
+
+/// Returns the display column of 'offset', 'line_start' must be the start of offset's line.
+pub fn column_at_offset(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    line_start: u64,
+    offset: u64,
+    tab_width: u64,
+) -> u64 {
+    let mut column = 0;
+    let mut pos = line_start;
+
+    while pos < offset {
+        let (c, _, size) = read_char_forward(buffer, pos, codec);
+        if size == 0 || c == '\n' {
+            break;
+        }
+        column += codepoint_columns(c, column, tab_width);
+        pos += size as u64;
+    }
+
+    column
+}
+
+/// Returns the offset of display column 'column' in the line starting at 'line_start'
+/// and the column really reached (the line can be shorter).<br/>
+/// If 'column' falls inside a tab or a wide character, the returned offset is the
+/// start of this character, or its end if 'after_partial' is set.
+pub fn offset_at_column(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    line_start: u64,
+    column: u64,
+    after_partial: bool,
+    tab_width: u64,
+) -> (u64, u64) {
+    let mut current = 0;
+    let mut pos = line_start;
+
+    while current < column {
+        let (c, _, size) = read_char_forward(buffer, pos, codec);
+        if size == 0 || c == '\n' {
+            break;
+        }
+
+        let width = codepoint_columns(c, current, tab_width);
+        if current + width > column {
+            if after_partial {
+                return (pos + size as u64, current + width);
+            }
+            return (pos, current);
+        }
+
+        current += width;
+        pos += size as u64;
+    }
+
+    (pos, current)
+}
+
+/// Returns the byte ranges (start, end) covered by 'block', one per line, in buffer order.
+pub fn block_selection_ranges(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    block: &BlockSelection,
+    tab_width: u64,
+) -> Vec<(u64, u64)> {
+    let (first_line, last_line) = sort_pair((block.anchor_line, block.cursor_line));
+    let (min_column, max_column) = sort_pair((block.anchor_column, block.cursor_column));
+
+    let mut ranges = vec![];
+    let mut line = first_line;
+    loop {
+        let (start, _) = offset_at_column(buffer, codec, line, min_column, false, tab_width);
+        let (end, _) = offset_at_column(buffer, codec, line, max_column, true, tab_width);
+        ranges.push((start, std::cmp::max(start, end)));
+
+        if line >= last_line {
+            break;
+        }
+
+        match next_line_start(buffer, codec, line) {
+            Some(next) => line = next,
+            None => break,
+        }
+    }
+
+    ranges
+}
+
+/// Converts the block selection into marks and selection points (one pair per line).
+fn apply_block_selection(tm: &mut TextModeContext, buffer: &Buffer) {
+    let block = match tm.block_selection {
+        Some(block) => block,
+        None => return,
+    };
+
+    let ranges =
+        block_selection_ranges(buffer, tm.text_codec.as_ref(), &block, tm.indent.tab_width);
+
+    let marks_at_end = block.cursor_column >= block.anchor_column;
+
+    tm.marks.clear();
+    tm.select_point.clear();
+    for (start, end) in ranges {
+        if marks_at_end {
+            tm.marks.push(Mark::new(end));
+            tm.select_point.push(Mark::new(start));
+        } else {
+            tm.marks.push(Mark::new(start));
+            tm.select_point.push(Mark::new(end));
+        }
+    }
+
+    tm.mark_index = if block.cursor_line >= block.anchor_line {
+        tm.marks.len().saturating_sub(1)
+    } else {
+        0
+    };
+}
+
+/// Starts a new block selection at the main mark if none is active.
+fn block_selection_begin(tm: &mut TextModeContext, buffer: &Buffer) {
+    if tm.block_selection.is_some() && !tm.select_point.is_empty() {
+        return;
+    }
+
+    let codec = tm.text_codec.as_ref();
+    let offset = tm.marks[tm.mark_index].offset;
+    let line = line_start(buffer, codec, offset);
+    let column = column_at_offset(buffer, codec, line, offset, tm.indent.tab_width);
+
+    tm.block_selection = Some(BlockSelection::new(line, column));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockMove {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+fn block_selection_move(view: &Rc<RwLock<View>>, direction: BlockMove) {
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    block_selection_begin(tm, &buffer);
+
+    let codec = tm.text_codec.as_ref();
+    let mut block = tm.block_selection.unwrap();
+    match direction {
+        BlockMove::Up => {
+            if let Some(line) = previous_line_start(&buffer, codec, block.cursor_line) {
+                block.cursor_line = line;
+            }
+        }
+        BlockMove::Down => {
+            if let Some(line) = next_line_start(&buffer, codec, block.cursor_line) {
+                block.cursor_line = line;
+            }
+        }
+        BlockMove::Left => {
+            block.cursor_column = block.cursor_column.saturating_sub(1);
+        }
+        BlockMove::Right => {
+            block.cursor_column += 1;
+        }
+    }
+    tm.block_selection = Some(block);
+
+    apply_block_selection(tm, &buffer);
+
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+    tm.prev_action = TextModeAction::MarksMove;
+}
+
+pub fn block_select_up(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    block_selection_move(view, BlockMove::Up);
+}
+
+pub fn block_select_down(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    block_selection_move(view, BlockMove::Down);
+}
+
+pub fn block_select_left(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    block_selection_move(view, BlockMove::Left);
+}
+
+pub fn block_select_right(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    block_selection_move(view, BlockMove::Right);
+}
+
+/// Returns the (line start, column) under the screen position (x, y).<br/>
+/// The column is extended past the end of the line.
+fn block_position_at_xy(v: &View, buffer: &Buffer, x: usize, y: usize) -> Option<(u64, u64)> {
+    let screen = v.screen.read();
+    let tm = v.mode_ctx::<TextModeContext>("text-mode");
+    let codec = tm.text_codec.as_ref();
+
+    for i in (0..=x).rev() {
+        let offset = match screen.get_cpinfo(i, y) {
+            Some(cpi) => match cpi.offset {
+                Some(offset) => offset,
+                None => continue,
+            },
+            None => continue,
+        };
+
+        let line = line_start(buffer, codec, offset);
+        let column =
+            column_at_offset(buffer, codec, line, offset, tm.indent.tab_width) + (x - i) as u64;
+        return Some((line, column));
+    }
+
+    None
+}
+
+/// Starts a block selection at the clicked area (alt + button 0).
+pub fn block_select_button_press(
+    _editor: &mut Editor,
+    _env: &mut EditorEnv,
+    view: &Rc<RwLock<View>>,
+) {
+    let v = &mut view.write();
+
+    let (x, y) = match v.input_ctx.trigger[0] {
+        InputEvent::ButtonPress(ButtonEvent {
+            button: 0, x, y, ..
+        }) => (x, y),
+        _ => return,
+    };
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let x = std::cmp::max(0, x) as usize;
+    let y = std::cmp::max(0, y) as usize;
+    let pos = block_position_at_xy(v, &buffer, x, y);
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.button_state[0] = 1;
+
+    if let Some((line, column)) = pos {
+        tm.block_selection = Some(BlockSelection::new(line, column));
+        apply_block_selection(tm, &buffer);
+        tm.prev_action = TextModeAction::MarksMove;
+    }
+}
+
+/// Extends the block selection while the button 0 is pressed.<br/>
+/// Returns false if there is no block selection in progress.
+pub fn block_select_pointer_motion(view: &Rc<RwLock<View>>) -> bool {
+    let v = &mut view.write();
+
+    {
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        if tm.block_selection.is_none() || tm.button_state[0] != 1 {
+            return false;
+        }
+    }
+
+    let (x, y) = match v.input_ctx.trigger[0] {
+        InputEvent::PointerMotion(PointerEvent { x, y, .. }) => (x, y),
+        _ => return false,
+    };
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let x = std::cmp::max(0, x) as usize;
+    let y = std::cmp::max(0, y) as usize;
+    let pos = block_position_at_xy(v, &buffer, x, y);
+    let height = v.screen.read().height();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    if let Some((line, column)) = pos {
+        let mut block = tm.block_selection.unwrap();
+        block.cursor_line = line;
+        block.cursor_column = column;
+        tm.block_selection = Some(block);
+        apply_block_selection(tm, &buffer);
+    }
+
+    // if on last line scroll down 1 line
+    if y >= height.saturating_sub(1) {
+        tm.pre_compose_action
+            .push(PostInputAction::ScrollDown { n: 1 });
+    } else if y <= 1 {
+        tm.pre_compose_action
+            .push(PostInputAction::ScrollUp { n: 1 });
+    }
+
+    true
+}
+
+/// Insert the current kill ring entry as a block at the main mark column.<br/>
+/// Each chunk (or line if the entry has a single chunk) goes on its own line,
+/// short lines are padded with spaces and new lines are added at the end of the buffer if needed.
+pub fn yank_block(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let entry = match editor.kill_ring.current() {
+        Some(entry) => entry.clone(),
+        None => return,
+    };
+
+    let rows: Vec<Vec<u8>> = if entry.chunks.len() > 1 {
+        entry.chunks.clone()
+    } else {
+        entry.chunks[0]
+            .split(|b| *b == b'\n')
+            .map(|r| r.to_vec())
+            .collect()
+    };
+
+    {
+        let v = view.read();
+        let buffer = v.buffer().unwrap();
+        let buffer = buffer.read();
+        if buffer.is_syncing {
+            return;
+        }
+    }
+
+    // save marks
+    run_text_mode_actions_vec(
+        editor,
+        env,
+        view,
+        &vec![PostInputAction::SaveMarks {
+            caller: "yank_block",
+        }],
+    );
+
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let mut buffer = buffer.write();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    let codec = tm.text_codec.as_ref();
+
+    let offset = tm.marks[tm.mark_index].offset;
+    let mut line = line_start(&buffer, codec, offset);
+    let tab_width = tm.indent.tab_width;
+    let column = column_at_offset(&buffer, codec, line, offset, tab_width);
+
+    let mut last_offset = offset;
+    for (idx, row) in rows.iter().enumerate() {
+        if idx > 0 {
+            line = match next_line_start(&buffer, codec, line) {
+                Some(next) => next,
+                None => {
+                    let end = buffer.size() as u64;
+                    buffer.insert(end, 1, b"\n");
+                    end + 1
+                }
+            };
+        }
+
+        let (mut pos, reached) = offset_at_column(&buffer, codec, line, column, false, tab_width);
+        if reached < column {
+            let padding = vec![b' '; (column - reached) as usize];
+            buffer.insert(pos, padding.len(), &padding);
+            pos += padding.len() as u64;
+        }
+
+        buffer.insert(pos, row.len(), row);
+        last_offset = pos + row.len() as u64;
+    }
+
+    tm.block_selection = None;
+    tm.select_point.clear();
+    tm.marks.clear();
+    tm.marks.push(Mark::new(last_offset));
+    tm.mark_index = 0;
+
+    editor.kill_ring.last_kill = None;
+    editor.kill_ring.last_yank = None;
+
+    tm.prev_action = TextModeAction::BufferModification;
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+}
+
+#[test]
+fn test_block_selection_columns() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+    use crate::core::buffer::OpenMode;
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+
+    let mut builder = BufferBuilder::new(BufferKind::File);
+    let buffer = builder
+        .buffer_name("test-block-selection")
+        .internal(false)
+        .mode(OpenMode::ReadWrite)
+        .finalize()
+        .unwrap();
+    let mut bb = buffer.write();
+
+    // "ab\tc" : tab expands from column 2 to 8
+    // "日本x" : wide chars use 2 columns each
+    // "0123456789"
+    let data = "ab\tc\n日本x\n0123456789".as_bytes();
+    bb.insert(0, data.len(), data);
+
+    assert_eq!(column_at_offset(&bb, codec, 0, 3, TAB_SIZE), 8);
+    assert_eq!(offset_at_column(&bb, codec, 0, 4, false, TAB_SIZE), (2, 2));
+    assert_eq!(offset_at_column(&bb, codec, 0, 4, true, TAB_SIZE), (3, 8));
+    assert_eq!(offset_at_column(&bb, codec, 0, 20, false, TAB_SIZE), (4, 9));
+
+    let l1 = next_line_start(&bb, codec, 0).unwrap();
+    assert_eq!(l1, 5);
+    assert_eq!(column_at_offset(&bb, codec, l1, l1 + 6, TAB_SIZE), 4);
+    assert_eq!(
+        offset_at_column(&bb, codec, l1, 3, false, TAB_SIZE),
+        (l1 + 3, 2)
+    );
+    assert_eq!(
+        offset_at_column(&bb, codec, l1, 3, true, TAB_SIZE),
+        (l1 + 6, 4)
+    );
+
+    let l2 = next_line_start(&bb, codec, l1).unwrap();
+    assert_eq!(previous_line_start(&bb, codec, l2), Some(l1));
+    assert_eq!(previous_line_start(&bb, codec, l1), Some(0));
+    assert_eq!(next_line_start(&bb, codec, l2), None);
+
+    // columns [1, 3) on the 3 lines
+    let block = BlockSelection {
+        anchor_line: 0,
+        anchor_column: 1,
+        cursor_line: l2,
+        cursor_column: 3,
+    };
+    let ranges = block_selection_ranges(&bb, codec, &block, TAB_SIZE);
+    assert_eq!(ranges, vec![(1, 3), (l1, l1 + 6), (l2 + 1, l2 + 3)]);
+}
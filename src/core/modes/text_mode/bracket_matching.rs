@@ -0,0 +1,465 @@
+use parking_lot::RwLock;
+use std::rc::Rc;
+
+use super::mark::read_char_backward;
+use super::mark::read_char_forward;
+use super::occurrences::set_selection_pairs;
+use super::*;
+
+use crate::core::buffer::Buffer;
+use crate::core::codec::text::TextCodec;
+use crate::core::codepointinfo::TextStyle;
+use crate::core::editor::Editor;
+use crate::core::editor::EditorEnv;
+use crate::core::screen::screen_apply;
+use crate::core::screen::Screen;
+use crate::core::view::LayoutEnv;
+use crate::core::view::ScreenOverlayFilter;
+use crate::core::view::View;
+use crate::sort_pair;
+
+/// The partner of a closing bracket typed by the user is looked for in the previous 64 KiB only.
+pub const AUTO_CLOSE_PAIR_SCAN_SIZE: u64 = 64 * 1024;
+
+/// The partner of a bracket (or the enclosing bracket) is looked for in the next/previous 1 MiB only.
+pub const BRACKET_SCAN_SIZE: u64 = 1024 * 1024;
+
+/// Returns (open, close, is_open) if 'c' is a bracket.
+pub fn bracket_pair(c: char) -> Option<(char, char, bool)> {
+    match c {
+        '(' => Some(('(', ')', true)),
+        ')' => Some(('(', ')', false)),
+        '[' => Some(('[', ']', true)),
+        ']' => Some(('[', ']', false)),
+        '{' => Some(('{', '}', true)),
+        '}' => Some(('{', '}', false)),
+        _ => None,
+    }
+}
+
+/// Returns the offset of the bracket matching the one at 'offset'.<br/>
+/// The buffer is scanned until the partner is found (not only the visible part),
+/// at most BRACKET_SCAN_SIZE bytes.
+pub fn find_matching_bracket(buffer: &Buffer, codec: &dyn TextCodec, offset: u64) -> Option<u64> {
+    find_matching_bracket_within(buffer, codec, offset, BRACKET_SCAN_SIZE)
+}
+
+/// Same as find_matching_bracket, the scan stops after 'max_distance' bytes.
+pub fn find_matching_bracket_within(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    offset: u64,
+    max_distance: u64,
+) -> Option<u64> {
+    let (c, _, size) = read_char_forward(buffer, offset, codec);
+    if size == 0 {
+        return None;
+    }
+    let (open, close, is_open) = bracket_pair(c)?;
+
+    let mut depth = 1;
+    if is_open {
+        let mut pos = offset + size as u64;
+        loop {
+            let (c, _, size) = read_char_forward(buffer, pos, codec);
+            if size == 0 || pos - offset > max_distance {
+                return None;
+            }
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            pos += size as u64;
+        }
+    } else {
+        let mut pos = offset;
+        loop {
+            let (c, prev, size) = read_char_backward(buffer, pos, codec);
+            if size == 0 || offset - prev > max_distance {
+                return None;
+            }
+            pos = prev;
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the offset of the bracket under 'offset', or just before 'offset'.
+pub fn bracket_at(buffer: &Buffer, codec: &dyn TextCodec, offset: u64) -> Option<u64> {
+    let (c, _, size) = read_char_forward(buffer, offset, codec);
+    if size > 0 && bracket_pair(c).is_some() {
+        return Some(offset);
+    }
+
+    let (c, prev, size) = read_char_backward(buffer, offset, codec);
+    if size > 0 && bracket_pair(c).is_some() {
+        return Some(prev);
+    }
+
+    None
+}
+
+/// Returns the offset of the innermost opening bracket that encloses 'offset',
+/// in the previous BRACKET_SCAN_SIZE bytes.
+pub fn find_enclosing_open_bracket(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    offset: u64,
+) -> Option<u64> {
+    let mut closing = vec![];
+    let mut pos = offset;
+    loop {
+        let (c, prev, size) = read_char_backward(buffer, pos, codec);
+        if size == 0 || offset - prev > BRACKET_SCAN_SIZE {
+            return None;
+        }
+        pos = prev;
+
+        match bracket_pair(c) {
+            Some((_, close, false)) => closing.push(close),
+            Some((_, _, true)) if closing.is_empty() => return Some(pos),
+            Some((_, _, true)) => {
+                closing.pop();
+            }
+            None => {}
+        }
+    }
+}
+
+/// Move the marks on their matching bracket.
+pub fn goto_matching_bracket(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    let codec = tm.text_codec.as_ref();
+
+    let mut moved = false;
+    for m in tm.marks.iter_mut() {
+        if let Some(bracket) = bracket_at(&buffer, codec, m.offset) {
+            if let Some(offset) = find_matching_bracket(&buffer, codec, bracket) {
+                m.offset = offset;
+                moved = true;
+            }
+        }
+    }
+
+    if !moved {
+        return;
+    }
+
+    tm.prev_action = TextModeAction::MarksMove;
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+}
+
+/// Returns the range inside the innermost brackets that contain [min, max).<br/>
+/// If [min, max) is already the inside of the brackets, the brackets are included.
+fn inside_brackets_range(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    min: u64,
+    max: u64,
+) -> Option<(u64, u64)> {
+    let mut from = min;
+    loop {
+        let open = find_enclosing_open_bracket(buffer, codec, from)?;
+        if min - open > BRACKET_SCAN_SIZE {
+            return None;
+        }
+        let close = find_matching_bracket(buffer, codec, open)?;
+
+        if close >= max {
+            if (open + 1, close) == (min, max) {
+                return Some((open, close + 1));
+            }
+            return Some((open + 1, close));
+        }
+
+        // unbalanced, try the next level
+        from = open;
+    }
+}
+
+/// Select the content of the innermost brackets around each mark.<br/>
+/// Calling it again extends the selection to the brackets, then to the next level.
+pub fn select_inside_brackets(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    let codec = tm.text_codec.as_ref();
+
+    let symmetric = tm.marks.len() == tm.select_point.len();
+
+    let mut ranges = vec![];
+    let mut main = None;
+    for (idx, m) in tm.marks.iter().enumerate() {
+        let (min, max) = if symmetric {
+            sort_pair((tm.select_point[idx].offset, m.offset))
+        } else {
+            (m.offset, m.offset)
+        };
+
+        let range = inside_brackets_range(&buffer, codec, min, max).unwrap_or((min, max));
+        if idx == tm.mark_index {
+            main = Some(range.0);
+        }
+        ranges.push(range);
+    }
+
+    if ranges.iter().all(|(min, max)| min == max) {
+        return;
+    }
+
+    set_selection_pairs(tm, ranges, main.unwrap_or(0));
+
+    tm.prev_action = TextModeAction::MarksMove;
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+}
+
+/// Returns the data to insert and the mark advance when 'c' is typed at 'offset'.<br/>
+/// An opening bracket inserts its closing pair if it is followed by a blank or a closing bracket,
+/// a closing bracket typed in front of the same balanced closing bracket is skipped
+/// (its opening bracket must be in the previous AUTO_CLOSE_PAIR_SCAN_SIZE bytes).
+pub fn auto_close_pair_data(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    offset: u64,
+    c: char,
+) -> Option<(Vec<u8>, u64)> {
+    let (open, close, is_open) = bracket_pair(c)?;
+
+    let (next, _, next_size) = read_char_forward(buffer, offset, codec);
+
+    let encode = |c: char| {
+        let mut data = [0; 4];
+        let size = codec.encode(c as u32, &mut data);
+        data[..size].to_vec()
+    };
+
+    if is_open {
+        let followed_by_blank_or_close = next_size == 0
+            || next.is_whitespace()
+            || matches!(bracket_pair(next), Some((_, _, false)));
+        if !followed_by_blank_or_close {
+            return None;
+        }
+
+        let mut data = encode(open);
+        let advance = data.len() as u64;
+        data.extend(encode(close));
+        return Some((data, advance));
+    }
+
+    if next_size > 0
+        && next == close
+        && find_matching_bracket_within(buffer, codec, offset, AUTO_CLOSE_PAIR_SCAN_SIZE).is_some()
+    {
+        return Some((vec![], next_size as u64));
+    }
+
+    None
+}
+
+/// Returns the screen offsets of the bracket at (or just before) 'offset' and of its partner.<br/>
+/// 'cells' are the (offset, codepoint) of the screen, in offset order.
+fn match_on_screen(cells: &[(u64, char)], offset: u64) -> Option<(u64, u64)> {
+    let idx = cells.iter().position(|(o, _)| *o >= offset)?;
+
+    let idx = if cells[idx].0 == offset && bracket_pair(cells[idx].1).is_some() {
+        idx
+    } else if idx > 0 && bracket_pair(cells[idx - 1].1).is_some() {
+        idx - 1
+    } else {
+        return None;
+    };
+
+    let (open, close, is_open) = bracket_pair(cells[idx].1)?;
+
+    let mut depth = 0;
+    let mut check = |i: usize| {
+        let c = cells[i].1;
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+        }
+        depth == 0
+    };
+
+    if is_open {
+        for i in idx..cells.len() {
+            if check(i) {
+                return Some((cells[idx].0, cells[i].0));
+            }
+        }
+    } else {
+        for i in (0..=idx).rev() {
+            if check(i) {
+                return Some((cells[i].0, cells[idx].0));
+            }
+        }
+    }
+
+    None
+}
+
+/// The **HighlightMatchingBracketOverlay** highlights the bracket under the main mark and its partner
+/// if both are visible.
+#[derive(Default)]
+pub struct HighlightMatchingBracketOverlay {}
+
+impl HighlightMatchingBracketOverlay {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl ScreenOverlayFilter<'_> for HighlightMatchingBracketOverlay {
+    fn name(&self) -> &'static str {
+        "HighlightMatchingBracketOverlay"
+    }
+
+    fn finish(&mut self, view: &View, env: &mut LayoutEnv) {
+        if env.screen.is_off_screen {
+            return;
+        }
+
+        let tm = view.mode_ctx::<TextModeContext>("text-mode");
+        if tm.marks.is_empty() {
+            return;
+        }
+
+        let offset = tm.marks[tm.mark_index].offset;
+        if !env.screen.contains_offset(offset) {
+            return;
+        }
+
+        refresh_screen_matching_bracket(env.screen, offset);
+    }
+}
+
+pub fn refresh_screen_matching_bracket(screen: &mut Screen, offset: u64) {
+    let mut cells = vec![];
+    screen_apply(screen, |_c, _l, cpi| {
+        if let (Some(o), false) = (cpi.offset, cpi.metadata) {
+            if cells.last().is_none_or(|(last, _)| *last < o) {
+                cells.push((o, cpi.cp));
+            }
+        }
+        true
+    });
+
+    let (first, second) = match match_on_screen(&cells, offset) {
+        Some(pair) => pair,
+        None => return,
+    };
+
+    screen_apply(screen, |_c, _l, cpi| {
+        if let (Some(o), false) = (cpi.offset, cpi.metadata) {
+            if (o == first || o == second) && !cpi.style.is_inverse {
+                cpi.style.bg_color = TextStyle::matching_bracket_bg_color();
+                cpi.style.is_bold = true;
+            }
+            return o <= second;
+        }
+        true
+    });
+}
+
+#[test]
+fn test_bracket_matching() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+    use crate::core::buffer::OpenMode;
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+
+    let mut builder = BufferBuilder::new(BufferKind::File);
+    let buffer = builder
+        .buffer_name("test-bracket-matching")
+        .internal(false)
+        .mode(OpenMode::ReadWrite)
+        .finalize()
+        .unwrap();
+    let mut bb = buffer.write();
+
+    // 0         1         2
+    // 0123456789012345678901234
+    // fn f(a: [u8]) { g(a) }
+    let data = "fn f(a: [u8]) { g(a) }".as_bytes();
+    bb.insert(0, data.len(), data);
+
+    assert_eq!(find_matching_bracket(&bb, codec, 4), Some(12));
+    assert_eq!(find_matching_bracket(&bb, codec, 12), Some(4));
+    assert_eq!(find_matching_bracket(&bb, codec, 8), Some(11));
+    assert_eq!(find_matching_bracket(&bb, codec, 14), Some(21));
+    assert_eq!(find_matching_bracket(&bb, codec, 0), None);
+
+    assert_eq!(bracket_at(&bb, codec, 4), Some(4));
+    assert_eq!(bracket_at(&bb, codec, 13), Some(12));
+    assert_eq!(bracket_at(&bb, codec, 2), None);
+
+    assert_eq!(find_enclosing_open_bracket(&bb, codec, 18), Some(17));
+    assert_eq!(find_enclosing_open_bracket(&bb, codec, 20), Some(14));
+    assert_eq!(find_enclosing_open_bracket(&bb, codec, 2), None);
+
+    assert_eq!(inside_brackets_range(&bb, codec, 10, 10), Some((9, 11)));
+    assert_eq!(inside_brackets_range(&bb, codec, 9, 11), Some((8, 12)));
+    assert_eq!(inside_brackets_range(&bb, codec, 8, 12), Some((5, 12)));
+    assert_eq!(inside_brackets_range(&bb, codec, 1, 1), None);
+
+    assert_eq!(
+        auto_close_pair_data(&bb, codec, 22, '('),
+        Some((b"()".to_vec(), 1))
+    );
+    assert_eq!(auto_close_pair_data(&bb, codec, 0, '('), None);
+    assert_eq!(auto_close_pair_data(&bb, codec, 11, ']'), Some((vec![], 1)));
+    assert_eq!(auto_close_pair_data(&bb, codec, 11, ')'), None);
+
+    assert_eq!(find_matching_bracket_within(&bb, codec, 12, 8), Some(4));
+    assert_eq!(find_matching_bracket_within(&bb, codec, 12, 7), None);
+    assert_eq!(find_matching_bracket_within(&bb, codec, 4, 8), Some(12));
+    assert_eq!(find_matching_bracket_within(&bb, codec, 4, 7), None);
+
+    // the opening bracket is too far: the closing bracket is inserted
+    let far = vec![b' '; AUTO_CLOSE_PAIR_SCAN_SIZE as usize];
+    bb.insert(5, far.len(), &far);
+    let close = 12 + far.len() as u64;
+    assert_eq!(find_matching_bracket(&bb, codec, close), Some(4));
+    assert_eq!(auto_close_pair_data(&bb, codec, close, ')'), None);
+
+    // the commands do not scan the whole buffer
+    let far = vec![b' '; BRACKET_SCAN_SIZE as usize];
+    bb.insert(5, far.len(), &far);
+    let close = close + far.len() as u64;
+    assert_eq!(find_matching_bracket(&bb, codec, close), None);
+    assert_eq!(find_matching_bracket(&bb, codec, 4), None);
+    assert_eq!(find_enclosing_open_bracket(&bb, codec, close), None);
+    assert_eq!(inside_brackets_range(&bb, codec, close, close), None);
+
+    let cells: Vec<(u64, char)> = "a(b[c]d)e".chars().zip(0..).map(|(c, o)| (o, c)).collect();
+    assert_eq!(match_on_screen(&cells, 1), Some((1, 7)));
+    assert_eq!(match_on_screen(&cells, 8), Some((1, 7)));
+    assert_eq!(match_on_screen(&cells, 5), Some((3, 5)));
+    assert_eq!(match_on_screen(&cells, 0), None);
+    assert_eq!(match_on_screen(&cells[..5], 1), None);
+}
@@ -179,6 +179,7 @@ pub fn transform_io_data(
                 real_cp: real_cp as u32,
                 displayed_cp: displayed_cp as u32,
             },
+            bidi_level: 0,
         });
         return cp_vec;
     }
@@ -196,6 +197,7 @@ pub fn transform_io_data(
                 real_cp: real_cp as u32,
                 displayed_cp: displayed_cp as u32,
             },
+            bidi_level: 0,
         });
         return cp_vec;
     }
@@ -218,6 +220,7 @@ pub fn transform_io_data(
                 real_cp: real_cp as u32,
                 displayed_cp: displayed_cp as u32,
             },
+            bidi_level: 0,
         });
     }
 
@@ -87,24 +87,12 @@ pub fn refresh_screen_marks(
 
     let mut lines_with_marks = vec![];
     if idx < idx_max && marks[idx].offset <= last_offset {
+        // the offsets of the cells are not sorted in the reordered (bidi) lines
+        let marks = &marks[idx..];
         screen_apply(screen, |_c, l, cpi| {
             if let Some(offset) = cpi.offset {
-                // get next mark
-                while idx < idx_max {
-                    if marks[idx].offset >= offset {
-                        break;
-                    }
-                    idx += 1;
-                }
-                if idx == idx_max {
-                    return false;
-                }
-                if marks[idx].offset > last_offset {
-                    return false;
-                }
-
                 // check offset
-                if offset == marks[idx].offset {
+                if marks.binary_search_by_key(&offset, |m| m.offset).is_ok() {
                     cpi.style = TextStyle::mark_style(Some(cpi.style.color));
 
                     // save line index
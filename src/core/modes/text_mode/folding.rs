@@ -0,0 +1,789 @@
+use parking_lot::RwLock;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Weak;
+
+use super::block_selection::codepoint_columns;
+use super::block_selection::line_start;
+use super::block_selection::next_line_start;
+use super::bracket_matching::bracket_at;
+use super::bracket_matching::find_enclosing_open_bracket;
+use super::bracket_matching::find_matching_bracket;
+use super::mark::read_char_forward;
+use super::mark::Mark;
+use super::*;
+
+use crate::core::buffer::Buffer;
+use crate::core::buffer::BufferEvent;
+use crate::core::buffer::BufferEventCb;
+use crate::core::codec::text::TextCodec;
+use crate::core::codepointinfo::TextStyle;
+use crate::core::editor::check_view_by_id;
+use crate::core::editor::Editor;
+use crate::core::editor::EditorEnv;
+use crate::core::view;
+use crate::core::view::ContentFilter;
+use crate::core::view::FilterData;
+use crate::core::view::FilterIo;
+use crate::core::view::LayoutEnv;
+use crate::core::view::View;
+use crate::sort_pair;
+
+/// A **Fold** hides the bytes [start, end) of the buffer.<br/>
+/// The hidden bytes are displayed as a placeholder at 'start'.<br/>
+/// 'lines' is the number of hidden new lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fold {
+    pub start: u64,
+    pub end: u64,
+    pub lines: u64,
+}
+
+/// The sorted, non overlapping folds of a view.
+#[derive(Debug, Default)]
+pub struct FoldSet {
+    folds: Vec<Fold>,
+}
+
+impl FoldSet {
+    pub fn new() -> Self {
+        FoldSet { folds: vec![] }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    pub fn folds(&self) -> &[Fold] {
+        &self.folds
+    }
+
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Add 'fold', the folds it overlaps are merged into it.<br/>
+    /// Returns the added fold, the lines of a merged fold must be recounted by the caller.
+    pub fn add(&mut self, fold: Fold) -> Fold {
+        if fold.start >= fold.end {
+            return fold;
+        }
+
+        let mut fold = fold;
+        let first = self.folds.partition_point(|f| f.end <= fold.start);
+        let mut last = first;
+        while last < self.folds.len() && self.folds[last].start < fold.end {
+            let f = self.folds[last];
+            fold.start = std::cmp::min(fold.start, f.start);
+            fold.end = std::cmp::max(fold.end, f.end);
+            last += 1;
+        }
+
+        self.folds.splice(first..last, std::iter::once(fold));
+        fold
+    }
+
+    /// Returns the fold that hides 'offset': start < offset < end.
+    pub fn find(&self, offset: u64) -> Option<Fold> {
+        let idx = self.folds.partition_point(|f| f.end <= offset);
+        match self.folds.get(idx) {
+            Some(f) if f.start < offset => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Returns the fold that contains 'offset': start <= offset <= end.
+    pub fn find_at(&self, offset: u64) -> Option<Fold> {
+        let idx = self.folds.partition_point(|f| f.end < offset);
+        match self.folds.get(idx) {
+            Some(f) if f.start <= offset => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Returns true if a fold starts in [min, max].
+    pub fn has_fold_in(&self, min: u64, max: u64) -> bool {
+        let idx = self.folds.partition_point(|f| f.start < min);
+        matches!(self.folds.get(idx), Some(f) if f.start <= max)
+    }
+
+    /// Remove the folds whose start is in [min, max].
+    pub fn remove_range(&mut self, min: u64, max: u64) -> bool {
+        let len = self.folds.len();
+        self.folds.retain(|f| f.start < min || f.start > max);
+        len != self.folds.len()
+    }
+
+    /// Returns the offset of a mark moved to 'offset' out of the hidden bytes.
+    pub fn skip(&self, offset: u64, forward: bool) -> u64 {
+        match self.find(offset) {
+            Some(f) if forward => f.end,
+            Some(f) => f.start,
+            None => offset,
+        }
+    }
+
+    /// 'size' bytes were inserted at 'offset':
+    /// the next folds are shifted, a fold that receives data is removed.
+    pub fn on_insert(&mut self, offset: u64, size: u64) {
+        self.folds.retain(|f| !(f.start < offset && offset < f.end));
+        for f in self.folds.iter_mut() {
+            if f.start >= offset {
+                f.start += size;
+                f.end += size;
+            }
+        }
+    }
+
+    /// 'size' bytes were removed at 'offset':
+    /// the next folds are shifted, a fold that loses data is removed.
+    pub fn on_remove(&mut self, offset: u64, size: u64) {
+        let end = offset + size;
+        self.folds.retain(|f| !(f.start < end && offset < f.end));
+        for f in self.folds.iter_mut() {
+            if f.start >= end {
+                f.start -= size;
+                f.end -= size;
+            }
+        }
+    }
+}
+
+/// Keeps the folds of a view in sync with the buffer modifications.
+pub struct FoldTracker {
+    folds: Weak<RwLock<FoldSet>>,
+}
+
+impl FoldTracker {
+    pub fn new(folds: &Arc<RwLock<FoldSet>>) -> Self {
+        FoldTracker {
+            folds: Arc::downgrade(folds),
+        }
+    }
+}
+
+impl BufferEventCb for FoldTracker {
+    fn cb(&mut self, _buffer: &Buffer, event: &BufferEvent) {
+        // the view is destroyed
+        let folds = match self.folds.upgrade() {
+            Some(folds) => folds,
+            None => return,
+        };
+
+        match event {
+            BufferEvent::BufferDataInserted { offset, size, .. } => {
+                folds.write().on_insert(*offset, *size as u64);
+            }
+            BufferEvent::BufferDataRemoved { offset, size, .. } => {
+                folds.write().on_remove(*offset, *size as u64);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The view 'view_id' is destroyed: its FoldTracker is removed from the buffer.
+pub fn release_fold_tracker(editor: &Editor<'static>, view_id: view::Id) {
+    let view = match check_view_by_id(editor, view_id) {
+        Some(view) => view,
+        None => return,
+    };
+    let v = view.read();
+    if !v.check_mode_ctx::<TextModeContext>("text-mode") {
+        return;
+    }
+    let id = v.mode_ctx::<TextModeContext>("text-mode").fold_tracker;
+    if let Some(buffer) = v.buffer() {
+        buffer.write().unregister_subscriber(id);
+    }
+}
+
+/// Returns the number of '\n' in [start, end).
+pub fn count_lines(buffer: &Buffer, codec: &dyn TextCodec, start: u64, end: u64) -> u64 {
+    let mut encode = [0; 4];
+    let sz = codec.encode('\n' as u32, &mut encode);
+
+    let mut count = 0;
+    let mut offset = start;
+    while let Some(nl) = buffer.find(&encode[..sz], offset, Some(end)) {
+        if nl >= end {
+            break;
+        }
+        count += 1;
+        offset = nl + sz as u64;
+    }
+    count
+}
+
+fn build_fold(buffer: &Buffer, codec: &dyn TextCodec, start: u64, end: u64) -> Fold {
+    Fold {
+        start,
+        end,
+        lines: count_lines(buffer, codec, start, end),
+    }
+}
+
+/// Returns the indentation width of the line starting at 'line_start',
+/// or None if the line is blank.
+fn line_indent_width(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    line_start: u64,
+    tab_width: u64,
+) -> Option<u64> {
+    let mut column = 0;
+    let mut pos = line_start;
+    loop {
+        let (c, _, size) = read_char_forward(buffer, pos, codec);
+        match c {
+            _ if size == 0 => return None,
+            '\n' | '\r' => return None,
+            ' ' | '\t' => column += codepoint_columns(c, column, tab_width),
+            _ => return Some(column),
+        }
+        pos += size as u64;
+    }
+}
+
+/// Returns the offset of the '\n' that ends the line starting at 'line_start', or the buffer size.
+fn line_end(buffer: &Buffer, codec: &dyn TextCodec, line_start: u64) -> u64 {
+    let mut m = Mark::new(line_start);
+    m.move_to_end_of_line(buffer, codec);
+    m.offset
+}
+
+/// Returns the fold of the indented block below the line of 'offset'.<br/>
+/// If the next lines are not more indented, the block containing the line of 'offset' is folded.
+/// The header line of the block stays visible.
+pub fn find_indentation_fold(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    offset: u64,
+    tab_width: u64,
+) -> Option<Fold> {
+    let mut header = line_start(buffer, codec, offset);
+    let mut indent = line_indent_width(buffer, codec, header, tab_width);
+
+    // is the line of 'offset' a block header ?
+    let mut next = next_line_start(buffer, codec, header);
+    let mut next_indent = None;
+    while let Some(line) = next {
+        next_indent = line_indent_width(buffer, codec, line, tab_width);
+        if next_indent.is_some() {
+            break;
+        }
+        next = next_line_start(buffer, codec, line);
+    }
+
+    let is_header = match (indent, next_indent) {
+        (Some(indent), Some(next_indent)) => next_indent > indent,
+        _ => false,
+    };
+
+    if !is_header {
+        // look for the header of the enclosing block
+        let level = indent.or(next_indent)?;
+        loop {
+            if header == 0 {
+                return None;
+            }
+            header = line_start(buffer, codec, header - 1);
+            indent = line_indent_width(buffer, codec, header, tab_width);
+            if let Some(indent) = indent {
+                if indent < level {
+                    break;
+                }
+            }
+        }
+    }
+    let indent = indent?;
+
+    // the block ends at the last line more indented than the header
+    let start = line_end(buffer, codec, header);
+    let mut end = start;
+    let mut line = next_line_start(buffer, codec, header);
+    while let Some(l) = line {
+        match line_indent_width(buffer, codec, l, tab_width) {
+            Some(w) if w <= indent => break,
+            Some(_) => end = line_end(buffer, codec, l),
+            None => {}
+        }
+        line = next_line_start(buffer, codec, l);
+    }
+
+    if end <= start {
+        return None;
+    }
+
+    Some(build_fold(buffer, codec, start, end))
+}
+
+/// Returns the offset of the last '{' of the line of 'offset' that is closed on another line.
+fn line_open_brace(buffer: &Buffer, codec: &dyn TextCodec, offset: u64) -> Option<u64> {
+    let start = line_start(buffer, codec, offset);
+    let end = line_end(buffer, codec, start);
+
+    let mut open = None;
+    let mut pos = start;
+    while pos < end {
+        let (c, _, size) = read_char_forward(buffer, pos, codec);
+        if size == 0 {
+            break;
+        }
+        if c == '{' {
+            open = Some(pos);
+        }
+        pos += size as u64;
+    }
+
+    let open = open?;
+    match find_matching_bracket(buffer, codec, open) {
+        Some(close) if close > end => Some(open),
+        _ => None,
+    }
+}
+
+/// Returns the fold of the content of the braces pair under 'offset', opened on the line
+/// of 'offset' or around 'offset'.<br/>
+/// The braces stay visible, the pair must span several lines.
+pub fn find_brace_fold(buffer: &Buffer, codec: &dyn TextCodec, offset: u64) -> Option<Fold> {
+    let char_at = |offset: u64| read_char_forward(buffer, offset, codec).0;
+
+    let mut open = match bracket_at(buffer, codec, offset) {
+        Some(b) if char_at(b) == '{' => b,
+        Some(b) if char_at(b) == '}' => find_matching_bracket(buffer, codec, b)?,
+        _ => match line_open_brace(buffer, codec, offset) {
+            Some(b) => b,
+            None => find_enclosing_open_bracket(buffer, codec, offset)?,
+        },
+    };
+
+    // skip the enclosing parentheses, brackets
+    while char_at(open) != '{' {
+        open = find_enclosing_open_bracket(buffer, codec, open)?;
+    }
+
+    let close = find_matching_bracket(buffer, codec, open)?;
+    let start = open + 1;
+    let fold = build_fold(buffer, codec, start, close);
+    if fold.lines == 0 {
+        return None;
+    }
+
+    Some(fold)
+}
+
+fn add_folds(view: &Rc<RwLock<View>>, folds: Vec<Fold>) {
+    if folds.is_empty() {
+        return;
+    }
+
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    let codec = tm.text_codec.as_ref();
+
+    {
+        let mut set = tm.folds.write();
+        for f in folds {
+            let merged = set.add(f);
+            if (merged.start, merged.end) != (f.start, f.end) {
+                set.add(build_fold(&buffer, codec, merged.start, merged.end));
+            }
+        }
+    }
+
+    // the marks move out of the new folds
+    tm.pre_compose_action.push(PostInputAction::CheckMarks);
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+    tm.prev_action = TextModeAction::ScreenMove;
+}
+
+/// Fold the indented blocks at the marks.
+pub fn fold_indentation(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    let folds = {
+        let v = view.read();
+        let buffer = v.buffer().unwrap();
+        let buffer = buffer.read();
+
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        let codec = tm.text_codec.as_ref();
+        let tab_width = tm.indent.tab_width;
+
+        tm.marks
+            .iter()
+            .filter_map(|m| find_indentation_fold(&buffer, codec, m.offset, tab_width))
+            .collect()
+    };
+
+    add_folds(view, folds);
+}
+
+/// Fold the content of the braces at the marks.
+pub fn fold_braces(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    let folds = {
+        let v = view.read();
+        let buffer = v.buffer().unwrap();
+        let buffer = buffer.read();
+
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        let codec = tm.text_codec.as_ref();
+
+        tm.marks
+            .iter()
+            .filter_map(|m| find_brace_fold(&buffer, codec, m.offset))
+            .collect()
+    };
+
+    add_folds(view, folds);
+}
+
+/// Fold the selections.
+pub fn fold_selection(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    let folds = {
+        let v = view.read();
+        let buffer = v.buffer().unwrap();
+        let buffer = buffer.read();
+
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        let codec = tm.text_codec.as_ref();
+
+        if tm.select_point.len() != tm.marks.len() {
+            return;
+        }
+
+        tm.marks
+            .iter()
+            .zip(tm.select_point.iter())
+            .map(|(m, s)| sort_pair((m.offset, s.offset)))
+            .filter(|(min, max)| min < max)
+            .map(|(min, max)| build_fold(&buffer, codec, min, max))
+            .collect()
+    };
+
+    add_folds(view, folds);
+
+    let v = &mut view.write();
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.pre_compose_action.push(PostInputAction::CancelSelection);
+}
+
+/// Remove the folds that start on the lines of the marks.
+pub fn unfold(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    let codec = tm.text_codec.as_ref();
+
+    let mut folds = tm.folds.write();
+    for m in tm.marks.iter() {
+        // the mark is on the header line, on the placeholder or at the end of the fold
+        let offset = folds.find_at(m.offset).map_or(m.offset, |f| f.start);
+        let min = line_start(&buffer, codec, offset);
+        let max = line_end(&buffer, codec, offset);
+        folds.remove_range(min, max);
+    }
+}
+
+/// Remove all the folds of the view.
+pub fn unfold_all(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    let v = &mut view.write();
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.folds.write().clear();
+}
+
+fn placeholder_text(fold: &Fold) -> String {
+    match fold.lines {
+        0 => "⋯".to_owned(),
+        1 => "⋯ 1 line ⋯".to_owned(),
+        n => format!("⋯ {} lines ⋯", n),
+    }
+}
+
+fn build_placeholder(fold: &Fold, out: &mut Vec<FilterIo>) {
+    let mut style = TextStyle::new();
+    style.color = (130, 170, 255);
+    style.is_italic = true;
+
+    for (idx, c) in placeholder_text(fold).chars().enumerate() {
+        // the first cell covers the hidden bytes
+        let (metadata, size) = if idx == 0 {
+            (false, (fold.end - fold.start) as usize)
+        } else {
+            (true, 0)
+        };
+
+        out.push(FilterIo {
+            metadata,
+            style,
+            offset: Some(fold.start),
+            size,
+            data: FilterData::TextInfo {
+                real_cp: c as u32,
+                displayed_cp: c as u32,
+            },
+            bidi_level: 0,
+        });
+    }
+}
+
+/// Returns the folds of the view that end after 'base_offset'.
+pub fn layout_folds(view: &View, base_offset: u64) -> Vec<Fold> {
+    if !view.check_mode_ctx::<TextModeContext>("text-mode") {
+        return vec![];
+    }
+    let tm = view.mode_ctx::<TextModeContext>("text-mode");
+    let folds = tm.folds.read();
+
+    // the layout can start inside a fold (wrapped placeholder)
+    let index = folds.folds().partition_point(|f| f.end <= base_offset);
+    folds.folds()[index..].to_vec()
+}
+
+/// Returns the io that stands for the hidden bytes [offset, fold.end) in the decoded text.<br/>
+/// The data source emits it instead of reading the hidden bytes.
+/// The io at the start of the fold is replaced by the placeholder (see FoldFilter),
+/// it is a new line for the previous filters if the fold hides new lines.
+/// A layout starting inside the fold skips the hidden bytes with a metadata io.
+pub fn build_fold_io(fold: &Fold, offset: u64) -> FilterIo {
+    let cp = if fold.lines > 0 { '\n' } else { '⋯' };
+
+    FilterIo {
+        metadata: offset != fold.start,
+        style: TextStyle::new(),
+        offset: Some(offset),
+        size: (fold.end - offset) as usize,
+        data: FilterData::TextInfo {
+            real_cp: cp as u32,
+            displayed_cp: cp as u32,
+        },
+        bidi_level: 0,
+    }
+}
+
+/// The FoldFilter replaces the bytes hidden by the folds of the view by a placeholder.<br/>
+/// The data source does not read the hidden bytes (see build_fold_io),
+/// the offsets of the surrounding text are preserved.
+/// Must be placed after the line checkpoints, before the word wrapping.
+pub struct FoldFilter {
+    folds: Vec<Fold>,
+    /// index of the next fold
+    index: usize,
+}
+
+impl Default for FoldFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FoldFilter {
+    pub fn new() -> Self {
+        FoldFilter {
+            folds: vec![],
+            index: 0,
+        }
+    }
+}
+
+impl ContentFilter<'_> for FoldFilter {
+    fn name(&self) -> &'static str {
+        "FoldFilter"
+    }
+
+    fn setup(
+        &mut self,
+        _editor: &mut Editor<'static>,
+        _editor_env: &mut EditorEnv<'static>,
+        env: &mut LayoutEnv,
+        view: &Rc<RwLock<View>>,
+        _parent_view: Option<&View<'static>>,
+    ) {
+        self.folds = layout_folds(&view.read(), env.base_offset);
+        self.index = 0;
+    }
+
+    fn run(
+        &mut self,
+        _view: &View,
+        _env: &mut LayoutEnv,
+        filter_in: &[FilterIo],
+        filter_out: &mut Vec<FilterIo>,
+    ) {
+        for io in filter_in.iter() {
+            let offset = match (io.offset, &io.data) {
+                (Some(offset), FilterData::TextInfo { .. }) => offset,
+                _ => {
+                    filter_out.push(io.clone());
+                    continue;
+                }
+            };
+
+            while self.index < self.folds.len() && self.folds[self.index].end <= offset {
+                self.index += 1;
+            }
+
+            let fold = match self.folds.get(self.index) {
+                Some(f) if f.start <= offset => *f,
+                _ => {
+                    filter_out.push(io.clone());
+                    continue;
+                }
+            };
+
+            // hidden
+            if offset == fold.start && !io.metadata {
+                build_placeholder(&fold, filter_out);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fold_set() {
+    let fold = |start, end| Fold {
+        start,
+        end,
+        lines: 1,
+    };
+
+    let mut set = FoldSet::new();
+    set.add(fold(10, 20));
+    set.add(fold(30, 40));
+    assert_eq!(set.folds(), &[fold(10, 20), fold(30, 40)]);
+
+    // hidden offsets
+    assert_eq!(set.find(10), None);
+    assert_eq!(set.find(11), Some(fold(10, 20)));
+    assert_eq!(set.find(20), None);
+    assert_eq!(set.skip(15, true), 20);
+    assert_eq!(set.skip(15, false), 10);
+    assert_eq!(set.skip(25, true), 25);
+    assert_eq!(set.find_at(10), Some(fold(10, 20)));
+    assert_eq!(set.find_at(20), Some(fold(10, 20)));
+    assert_eq!(set.find_at(21), None);
+    assert!(set.has_fold_in(5, 10));
+    assert!(!set.has_fold_in(11, 29));
+
+    // nested fold
+    set.add(fold(12, 18));
+    assert_eq!(set.folds(), &[fold(10, 20), fold(30, 40)]);
+
+    // edits before, after and inside the folds
+    set.on_insert(5, 2);
+    assert_eq!(set.folds(), &[fold(12, 22), fold(32, 42)]);
+    set.on_insert(22, 3);
+    assert_eq!(set.folds(), &[fold(12, 22), fold(35, 45)]);
+    set.on_remove(0, 2);
+    assert_eq!(set.folds(), &[fold(10, 20), fold(33, 43)]);
+    set.on_insert(15, 1);
+    assert_eq!(set.folds(), &[fold(34, 44)]);
+    set.on_remove(40, 10);
+    assert!(set.is_empty());
+
+    // merge
+    set.add(fold(10, 20));
+    set.add(fold(30, 40));
+    set.add(fold(15, 35));
+    assert_eq!(set.folds().len(), 1);
+    assert_eq!((set.folds()[0].start, set.folds()[0].end), (10, 40));
+    assert!(set.remove_range(0, 10));
+    assert!(set.is_empty());
+}
+
+#[test]
+fn test_find_folds() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("test-folds")
+        .internal(false)
+        .finalize()
+        .unwrap();
+
+    let text = "fn a() {\n    let x = (1,\n        2);\n\n    x\n}\nb\n";
+    buffer.write().insert(0, text.len(), text.as_bytes());
+    let bb = buffer.read();
+
+    // a block and its nested block
+    for (offset, start, end, lines) in [
+        (0, 8, 43, 4),
+        (40, 8, 43, 4),
+        (12, 24, 36, 1),
+        (30, 24, 36, 1),
+    ] {
+        let f = find_indentation_fold(&bb, codec, offset, 8).unwrap();
+        assert_eq!(
+            (f.start, f.end, f.lines),
+            (start, end, lines),
+            "fold at {}",
+            offset
+        );
+    }
+
+    // nothing to fold at top level
+    let b = text.find("b\n").unwrap() as u64;
+    assert_eq!(find_indentation_fold(&bb, codec, b, 8), None);
+
+    // braces: the inner parentheses are skipped
+    let close = text.find('}').unwrap() as u64;
+    for offset in [0, 8, 22, close, close + 1] {
+        let f = find_brace_fold(&bb, codec, offset).unwrap();
+        assert_eq!(
+            (f.start, f.end, f.lines),
+            (8, close, 5),
+            "fold at {}",
+            offset
+        );
+    }
+    assert_eq!(find_brace_fold(&bb, codec, b), None);
+}
+
+#[test]
+fn test_fold_tracker() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("test-fold-tracker")
+        .internal(false)
+        .finalize()
+        .unwrap();
+    buffer.write().insert(0, 6, b"a{\n}\nb");
+
+    let folds = Arc::new(RwLock::new(FoldSet::new()));
+    let fold = Fold {
+        start: 2,
+        end: 3,
+        lines: 1,
+    };
+    folds.write().add(fold);
+
+    let id = buffer
+        .write()
+        .register_subscriber(Box::new(FoldTracker::new(&folds)));
+    buffer.write().insert(0, 1, b"c");
+    assert_eq!(folds.read().folds()[0].start, 3);
+
+    // the fold data source
+    let io = build_fold_io(&folds.read().folds()[0], 3);
+    assert_eq!((io.offset, io.size, io.metadata), (Some(3), 1, false));
+    assert!(build_fold_io(&folds.read().folds()[0], 4).metadata);
+
+    // the view is destroyed
+    buffer.write().unregister_subscriber(id);
+    assert!(buffer.read().subscribers.is_empty());
+    buffer.write().insert(0, 1, b"c");
+    assert_eq!(folds.read().folds()[0].start, 3);
+}
@@ -0,0 +1,137 @@
+use parking_lot::RwLock;
+use std::rc::Rc;
+
+use super::mark::Mark;
+use super::movement::move_mark_to_end_of_file;
+use super::*;
+
+use crate::core::editor::Editor;
+use crate::core::editor::EditorEnv;
+use crate::core::file_follow::FileFollower;
+use crate::core::view;
+use crate::core::view::View;
+
+/// Returns true if the main mark must follow the end of a buffer resized from 'prev_size' to 'size'
+/// (it shrinks when the file is truncated or rotated).<br/>
+/// The mark is left in place if the user moved it away from the end.
+pub fn follow_end(prev_size: u64, size: u64, main_mark: u64) -> bool {
+    size != prev_size && (main_mark == prev_size || main_mark == size)
+}
+
+/// Toggle the follow mode of the view: the appended data of the file is loaded
+/// and the view stays at the end of the buffer.
+pub fn toggle_follow(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let (view_id, buffer) = {
+        let v = view.read();
+        match v.buffer() {
+            Some(buffer) => (v.id, buffer),
+            None => return,
+        }
+    };
+    let buffer_id = buffer.read().id;
+
+    let follow = {
+        let mut v = view.write();
+        let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+        tm.follow = !tm.follow;
+        tm.follow
+    };
+
+    if !follow {
+        release_file_followers(editor, view_id);
+        return;
+    }
+
+    // the buffers without file (stdin) are already fed by their producer
+    if !editor.file_followers.contains_key(&buffer_id) {
+        if let Some(follower) = FileFollower::start(editor, &buffer) {
+            editor.file_followers.insert(buffer_id, follower);
+        }
+    }
+    if let Some(follower) = editor.file_followers.get_mut(&buffer_id) {
+        follower.views.push(view_id);
+    }
+
+    move_mark_to_end_of_file(editor, env, view);
+
+    let size = buffer.read().size() as u64;
+    let mut v = view.write();
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.follow_size = size;
+}
+
+/// The view 'view_id' stops following its file (or is destroyed):
+/// the last view following a file stops its watcher.
+pub fn release_file_followers(editor: &mut Editor<'static>, view_id: view::Id) {
+    editor.file_followers.retain(|_, follower| {
+        follower.views.retain(|id| *id != view_id);
+        !follower.views.is_empty()
+    });
+}
+
+/// Called before the composition: keep the main mark and the last line
+/// of a followed buffer on screen.
+pub fn follow_buffer_end(view: &mut View<'static>) {
+    let size = match view.buffer() {
+        Some(buffer) => buffer.read().size() as u64,
+        None => return,
+    };
+    let height = view.screen.read().height();
+
+    let follow = {
+        let tm = view.mode_ctx_mut::<TextModeContext>("text-mode");
+        if !tm.follow {
+            return;
+        }
+
+        let prev_size = tm.follow_size;
+        tm.follow_size = size;
+
+        let main_mark = tm.marks.get(tm.mark_index).map_or(prev_size, |m| m.offset);
+
+        // the content was replaced by a smaller file
+        if size < prev_size {
+            for m in tm.marks.iter_mut().chain(tm.select_point.iter_mut()) {
+                m.offset = std::cmp::min(m.offset, size);
+            }
+        }
+
+        follow_end(prev_size, size, main_mark)
+    };
+
+    if !follow {
+        if view.start_offset > size {
+            view.start_offset = 0;
+        }
+        return;
+    }
+
+    let tm = view.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.mark_index = 0;
+    tm.marks.clear();
+    tm.marks.push(Mark::new(size));
+    tm.pre_compose_action.push(PostInputAction::ScrollUp {
+        n: height.saturating_sub(1),
+    });
+
+    view.start_offset = size;
+}
+
+#[test]
+fn test_follow_end() {
+    // growth, mark at the previous end
+    assert!(follow_end(10, 20, 10));
+    // the mark moved away
+    assert!(!follow_end(10, 20, 5));
+    // no growth
+    assert!(!follow_end(20, 20, 20));
+    // the user went back to the end (new size)
+    assert!(follow_end(10, 20, 20));
+    // truncated/rotated file
+    assert!(follow_end(20, 5, 20));
+    assert!(!follow_end(20, 5, 3));
+}
@@ -45,37 +45,18 @@ impl ScreenOverlayFilter<'_> for HighlightSelectionOverlay {
 }
 
 pub fn refresh_screen_selections(screen: &mut Screen, sel: &Vec<(u64, u64)>) {
-    let idx_max = sel.len();
-    let mut idx = 0;
-
-    if idx_max == 0 {
+    if sel.is_empty() {
         return;
     }
 
-    // this is slow
+    // the offsets of the cells are not sorted in the reordered (bidi) lines:
+    // look for the first range that ends after offset
     screen_apply(screen, |_c, _l, cpi| {
         if let Some(offset) = cpi.offset {
-            // get next range
-            while idx < idx_max {
-                // offset < sel.min
-                if offset < sel[idx].0 {
-                    return true;
-                }
-
-                // offset is >= sel.min
-
-                // offet is <= sel.max -> match
-                if offset <= sel[idx].1 {
-                    cpi.style.bg_color = TextStyle::default_selected_bg_color();
-                    return true;
-                }
-
-                // offset is > sel.max -> out of range: select next range
-                idx += 1;
-                continue;
+            let idx = sel.partition_point(|r| r.1 < offset);
+            if idx < sel.len() && sel[idx].0 <= offset {
+                cpi.style.bg_color = TextStyle::default_selected_bg_color();
             }
-
-            return false;
         }
 
         true
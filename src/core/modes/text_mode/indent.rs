@@ -0,0 +1,389 @@
+use parking_lot::RwLock;
+use std::rc::Rc;
+
+use super::block_selection::column_at_offset;
+use super::block_selection::line_start;
+use super::block_selection::next_line_start;
+use super::*;
+
+use crate::core::buffer::Buffer;
+use crate::core::codec::text::TextCodec;
+use crate::core::editor::config_var_get;
+use crate::core::editor::Editor;
+use crate::core::editor::EditorEnv;
+use crate::core::view::View;
+use crate::sort_pair;
+
+/// The **IndentPolicy** describes how a file is indented.<br/>
+/// 'indent_width' is the number of columns of one indentation level,
+/// 'tab_width' is the number of columns between two tab stops (see TabFilter).<br/>
+/// By default, the tab key inserts a tab: the spaces are enabled by the file type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndentPolicy {
+    pub use_tabs: bool,
+    pub indent_width: u64,
+    pub tab_width: u64,
+}
+
+impl Default for IndentPolicy {
+    fn default() -> Self {
+        IndentPolicy::tabs(TAB_SIZE)
+    }
+}
+
+impl IndentPolicy {
+    pub fn spaces(width: u64) -> Self {
+        IndentPolicy {
+            use_tabs: false,
+            indent_width: width,
+            tab_width: TAB_SIZE,
+        }
+    }
+
+    pub fn tabs(width: u64) -> Self {
+        IndentPolicy {
+            use_tabs: true,
+            indent_width: width,
+            tab_width: width,
+        }
+    }
+
+    /// Parse "tabs", "tabs:N", "spaces" or "spaces:N".
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut it = s.trim().splitn(2, ':');
+        let kind = it.next()?;
+        let width = match it.next() {
+            Some(w) => match w.parse::<u64>() {
+                Ok(w) if w > 0 => Some(w),
+                _ => return None,
+            },
+            None => None,
+        };
+
+        match kind {
+            "tabs" => Some(IndentPolicy::tabs(width.unwrap_or(TAB_SIZE))),
+            "spaces" => Some(IndentPolicy::spaces(width.unwrap_or(4))),
+            _ => None,
+        }
+    }
+
+    /// Returns the data of one indentation level.
+    pub fn indent_string(&self) -> Vec<u8> {
+        if self.use_tabs {
+            vec![b'\t']
+        } else {
+            vec![b' '; self.indent_width as usize]
+        }
+    }
+}
+
+/// Returns the extension and the built-in indentation policy of 'file_name', if its type has one.
+fn default_indent_policy_for_file(file_name: &str) -> (String, Option<IndentPolicy>) {
+    let path = std::path::Path::new(file_name);
+    let base_name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    if base_name == "Makefile" || base_name == "makefile" || base_name == "GNUmakefile" {
+        return ("makefile".to_owned(), Some(IndentPolicy::tabs(TAB_SIZE)));
+    }
+
+    let ext = path
+        .extension()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let policy = match ext.as_str() {
+        "mk" | "go" => Some(IndentPolicy::tabs(TAB_SIZE)),
+        "rs" | "py" => Some(IndentPolicy::spaces(4)),
+        "js" | "ts" | "json" | "html" | "css" | "yaml" | "yml" | "toml" | "lua" | "rb" => {
+            Some(IndentPolicy::spaces(2))
+        }
+        _ => None,
+    };
+
+    (ext, policy)
+}
+
+/// Returns the indentation policy for 'file_name'.<br/>
+/// Can be overridden with the "indent:<extension>" and "indent:default" config variables,
+/// ex: -c indent:c=tabs:8
+pub fn indent_policy_for_file(editor: &Editor<'static>, file_name: &str) -> IndentPolicy {
+    let (ext, policy) = default_indent_policy_for_file(file_name);
+
+    if !ext.is_empty() {
+        if let Some(p) = config_var_get(editor, &format!("indent:{}", ext)) {
+            if let Some(p) = IndentPolicy::parse(p) {
+                return p;
+            }
+        }
+    }
+
+    if let Some(policy) = policy {
+        return policy;
+    }
+
+    if let Some(p) = config_var_get(editor, "indent:default") {
+        if let Some(p) = IndentPolicy::parse(p) {
+            return p;
+        }
+    }
+
+    IndentPolicy::default()
+}
+
+/// Returns the leading blanks of the line starting at 'line_start', up to 'max_offset'.
+pub fn line_indentation(buffer: &Buffer, line_start: u64, max_offset: u64) -> Vec<u8> {
+    let mut indent = vec![];
+    let mut offset = line_start;
+
+    while offset < max_offset {
+        let mut data = Vec::with_capacity(1);
+        if buffer.read(offset, 1, &mut data) != 1 {
+            break;
+        }
+        match data[0] {
+            b' ' | b'\t' => indent.push(data[0]),
+            _ => break,
+        }
+        offset += 1;
+    }
+
+    indent
+}
+
+/// Returns the data inserted by the tab key at 'offset' according to 'policy'.
+pub fn tab_insert_data(
+    buffer: &Buffer,
+    codec: &dyn TextCodec,
+    policy: &IndentPolicy,
+    offset: u64,
+) -> Vec<u8> {
+    if policy.use_tabs {
+        return vec![b'\t'];
+    }
+
+    let line = line_start(buffer, codec, offset);
+    let column = column_at_offset(buffer, codec, line, offset, policy.tab_width);
+    let n = policy.indent_width - (column % policy.indent_width);
+    vec![b' '; n as usize]
+}
+
+/// Returns the start offsets of the lines covered by the marks (and their selections).<br/>
+/// A selection ending at the start of a line does not include this line.
+fn lines_under_marks(tm: &TextModeContext, buffer: &Buffer) -> Vec<u64> {
+    let codec = tm.text_codec.as_ref();
+    let symmetric = tm.marks.len() == tm.select_point.len();
+
+    let mut lines = vec![];
+    for (idx, m) in tm.marks.iter().enumerate() {
+        let (min, mut max) = if symmetric {
+            sort_pair((m.offset, tm.select_point[idx].offset))
+        } else {
+            (m.offset, m.offset)
+        };
+
+        if max > min && line_start(buffer, codec, max) == max {
+            max -= 1;
+        }
+
+        let last = line_start(buffer, codec, max);
+        let mut line = line_start(buffer, codec, min);
+        loop {
+            lines.push(line);
+            if line >= last {
+                break;
+            }
+            match next_line_start(buffer, codec, line) {
+                Some(next) => line = next,
+                None => break,
+            }
+        }
+    }
+
+    lines.sort_unstable();
+    lines.dedup();
+    lines
+}
+
+fn is_empty_line(buffer: &Buffer, line_start: u64) -> bool {
+    let mut data = Vec::with_capacity(1);
+    buffer.read(line_start, 1, &mut data) == 0 || data[0] == b'\n'
+}
+
+/// Applies the ordered, non overlapping operations 'ops' (offset, removed, inserted) to 'offset'.
+fn shift_offset(offset: u64, ops: &[(u64, u64, u64)]) -> u64 {
+    let mut new_offset = offset;
+    for (op_offset, removed, inserted) in ops {
+        if offset < *op_offset {
+            break;
+        }
+        if offset < op_offset + removed {
+            // inside a removed range
+            new_offset -= offset - op_offset;
+        } else {
+            new_offset = new_offset - removed + inserted;
+        }
+    }
+    new_offset
+}
+
+fn indent_or_outdent(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    indent: bool,
+) {
+    {
+        let v = view.read();
+        let buffer = v.buffer().unwrap();
+        let buffer = buffer.read();
+        if buffer.is_syncing {
+            return;
+        }
+    }
+
+    // save marks
+    run_text_mode_actions_vec(
+        editor,
+        env,
+        view,
+        &vec![PostInputAction::SaveMarks {
+            caller: "indent_or_outdent",
+        }],
+    );
+
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let mut buffer = buffer.write();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+
+    let lines = lines_under_marks(tm, &buffer);
+    let skip_empty_lines = lines.len() > 1;
+    let policy = tm.indent;
+
+    // (offset, removed, inserted) in the original buffer coordinates
+    let mut ops = vec![];
+    for line in lines.iter() {
+        if indent {
+            if skip_empty_lines && is_empty_line(&buffer, *line) {
+                continue;
+            }
+            ops.push((*line, 0, policy.indent_string().len() as u64));
+        } else {
+            let blanks = line_indentation(&buffer, *line, buffer.size() as u64);
+            let n = match blanks.first() {
+                Some(b'\t') => 1,
+                Some(_) => blanks
+                    .iter()
+                    .take(policy.indent_width as usize)
+                    .take_while(|b| **b == b' ')
+                    .count(),
+                None => 0,
+            };
+            if n > 0 {
+                ops.push((*line, n as u64, 0));
+            }
+        }
+    }
+
+    if ops.is_empty() {
+        return;
+    }
+
+    // apply from the end to keep the offsets valid
+    let indent_data = policy.indent_string();
+    for (offset, removed, inserted) in ops.iter().rev() {
+        if *removed > 0 {
+            buffer.remove(*offset, *removed as usize, None);
+        }
+        if *inserted > 0 {
+            buffer.insert(*offset, indent_data.len(), &indent_data);
+        }
+    }
+
+    for m in tm.marks.iter_mut() {
+        m.offset = shift_offset(m.offset, &ops);
+    }
+    for m in tm.select_point.iter_mut() {
+        m.offset = shift_offset(m.offset, &ops);
+    }
+
+    tm.prev_action = TextModeAction::BufferModification;
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+}
+
+/// Indent all the lines under the marks/selections by one level.
+pub fn indent_lines(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    indent_or_outdent(editor, env, view, true);
+}
+
+/// Remove one level of indentation on all the lines under the marks/selections.
+pub fn outdent_lines(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    indent_or_outdent(editor, env, view, false);
+}
+
+/// Returns true if one of the selections spans several lines.
+pub fn has_multi_line_selection(tm: &TextModeContext, buffer: &Buffer) -> bool {
+    if tm.marks.len() != tm.select_point.len() {
+        return false;
+    }
+
+    let codec = tm.text_codec.as_ref();
+    tm.marks.iter().zip(tm.select_point.iter()).any(|(m, s)| {
+        let (min, max) = sort_pair((m.offset, s.offset));
+        max > min && next_line_start(buffer, codec, min).is_some_and(|l| l < max)
+    })
+}
+
+#[test]
+fn test_indent_policy() {
+    assert_eq!(IndentPolicy::parse("tabs"), Some(IndentPolicy::tabs(8)));
+    assert_eq!(
+        IndentPolicy::parse("spaces:2"),
+        Some(IndentPolicy::spaces(2))
+    );
+    assert_eq!(IndentPolicy::parse("spaces:0"), None);
+    assert_eq!(IndentPolicy::parse("foo"), None);
+
+    assert_eq!(
+        default_indent_policy_for_file("/tmp/Makefile").1,
+        Some(IndentPolicy::tabs(8))
+    );
+    assert_eq!(
+        default_indent_policy_for_file("main.rs"),
+        ("rs".to_owned(), Some(IndentPolicy::spaces(4)))
+    );
+    assert_eq!(
+        default_indent_policy_for_file("a.json").1,
+        Some(IndentPolicy::spaces(2))
+    );
+    assert_eq!(
+        default_indent_policy_for_file("notes.txt"),
+        ("txt".to_owned(), None)
+    );
+    assert!(IndentPolicy::default().use_tabs);
+
+    assert_eq!(IndentPolicy::tabs(8).indent_string(), b"\t".to_vec());
+    assert_eq!(IndentPolicy::spaces(2).indent_string(), b"  ".to_vec());
+
+    // remove 2 bytes at 4, insert 4 bytes at 10
+    let ops = vec![(4, 2, 0), (10, 0, 4)];
+    assert_eq!(shift_offset(2, &ops), 2);
+    assert_eq!(shift_offset(5, &ops), 4);
+    assert_eq!(shift_offset(6, &ops), 4);
+    assert_eq!(shift_offset(10, &ops), 12);
+    assert_eq!(shift_offset(11, &ops), 13);
+}
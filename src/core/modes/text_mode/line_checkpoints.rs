@@ -0,0 +1,428 @@
+/*
+    Line checkpoints: bounded layouts in very long lines.
+
+    In a line longer than LINE_CHECKPOINT_INTERVAL (8 KiB), the screen line is always broken at
+    the first codepoint of each multiple of 8 KiB (the checkpoint) when there is no '\n' in the
+    8 KiB before it, even if word wrap is disabled or the screen line is not full:
+    a screen line of a very long line is never longer than 8 KiB and a "short" screen line
+    can be displayed before a checkpoint.
+
+    The screen lines after a checkpoint do not depend on the layout start offset,
+    so a layout (scrolling, mark movements) inside the line starts from the previous checkpoint
+    instead of the start of the line (see get_layout_start_offset).
+    A checkpoint inside a multi-byte codepoint is moved to the start of the next codepoint.
+*/
+
+use parking_lot::RwLock;
+use std::rc::Rc;
+
+use super::*;
+
+use crate::core::codec::text::u32_to_char;
+use crate::core::codec::text::TextCodec;
+use crate::core::view::ContentFilter;
+use crate::core::view::FilterData;
+use crate::core::view::FilterIo;
+use crate::core::view::LayoutEnv;
+use crate::core::view::View;
+use crate::core::Editor;
+use crate::core::EditorEnv;
+
+use crate::core::buffer::Buffer;
+use crate::core::codepointinfo::TextStyle;
+
+/// The distance between two line checkpoints.<br/>
+/// In a line longer than LINE_CHECKPOINT_INTERVAL bytes, a screen line always starts at the
+/// first codepoint of each multiple of LINE_CHECKPOINT_INTERVAL if there is no '\n' in the
+/// LINE_CHECKPOINT_INTERVAL bytes before it.<br/>
+/// The soft-wrap positions after a checkpoint do not depend on the layout start offset,
+/// so a layout inside a very long line can restart from the previous checkpoint.
+pub const LINE_CHECKPOINT_INTERVAL: u64 = 8 * 1024;
+
+fn encode_new_line(codec: &dyn TextCodec) -> ([u8; 4], usize) {
+    let mut encode = [0; 4];
+    let sz = codec.encode('\n' as u32, &mut encode);
+    (encode, sz)
+}
+
+/// Returns true if there is no '\n' in the LINE_CHECKPOINT_INTERVAL bytes before 'checkpoint'.
+fn is_line_checkpoint(buffer: &Buffer, codec: &dyn TextCodec, checkpoint: u64) -> bool {
+    if checkpoint < LINE_CHECKPOINT_INTERVAL {
+        return false;
+    }
+    let (encode, sz) = encode_new_line(codec);
+    let min_offset = checkpoint - LINE_CHECKPOINT_INTERVAL;
+    buffer
+        .find_reverse(&encode[..sz], checkpoint, Some(min_offset))
+        .is_none()
+}
+
+/// Returns the offset of the first codepoint at or after 'offset'.
+fn sync_forward(buffer: &Buffer, codec: &dyn TextCodec, offset: u64) -> u64 {
+    let mut data = Vec::with_capacity(codec.encode_max_size());
+    buffer.read(offset, codec.encode_max_size(), &mut data);
+
+    let n = data.iter().take_while(|b| !codec.is_sync(**b)).count();
+    offset + n as u64
+}
+
+/// Returns the offset of a screen line start <= 'offset' to start a layout from:<br/>
+/// the start of the line if it is near, otherwise the previous line checkpoint.<br/>
+/// The returned offset is at most 2 * LINE_CHECKPOINT_INTERVAL bytes before 'offset',
+/// unless it is hidden by a fold: the layout then starts from the line of the fold.
+pub fn get_layout_start_offset(view: &Rc<RwLock<View>>, offset: u64) -> u64 {
+    let start = get_line_layout_start_offset(view, offset);
+
+    let fold = {
+        let v = view.read();
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        let folds = tm.folds.read();
+        folds.find_at(start).filter(|f| f.start < start)
+    };
+
+    match fold {
+        Some(f) => get_layout_start_offset(view, f.start),
+        None => start,
+    }
+}
+
+fn get_line_layout_start_offset(view: &Rc<RwLock<View>>, offset: u64) -> u64 {
+    let v = view.read();
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let tm = v.mode_ctx::<TextModeContext>("text-mode");
+    line_layout_start_offset(&buffer, tm.text_codec.as_ref(), offset)
+}
+
+/// Returns the start of the line of 'offset' if it is near, otherwise the previous line checkpoint.
+pub fn line_layout_start_offset(buffer: &Buffer, codec: &dyn TextCodec, offset: u64) -> u64 {
+    let offset = std::cmp::min(offset, buffer.size() as u64);
+
+    // look for the start of line in the checkpoints range only
+    let min_offset = offset.saturating_sub(2 * LINE_CHECKPOINT_INTERVAL);
+    let (encode, sz) = encode_new_line(codec);
+    if let Some(nl) = buffer.find_reverse(&encode[..sz], offset, Some(min_offset)) {
+        return nl + sz as u64;
+    }
+    if min_offset == 0 {
+        return 0;
+    }
+
+    // very long line: there is no '\n' before the previous checkpoints
+    let mut checkpoint = offset - offset % LINE_CHECKPOINT_INTERVAL;
+    if sync_forward(buffer, codec, checkpoint) > offset {
+        checkpoint -= LINE_CHECKPOINT_INTERVAL;
+    }
+    let checkpoint = sync_forward(buffer, codec, checkpoint);
+
+    dbg_println!(
+        "long line: layout of offset {} starts at checkpoint {}",
+        offset,
+        checkpoint
+    );
+
+    checkpoint
+}
+
+fn build_checkpoint_new_line(offset: Option<u64>) -> FilterIo {
+    FilterIo {
+        metadata: true,
+        style: TextStyle::new(),
+        offset,
+        size: 0,
+        data: FilterData::TextInfo {
+            real_cp: '\n' as u32,
+            displayed_cp: ' ' as u32,
+        },
+        bidi_level: 0,
+    }
+}
+
+/// The LineCheckpointFilter starts a new screen line at the checkpoints of very long lines.<br/>
+/// Must be placed before the word wrapping.
+pub struct LineCheckpointFilter {
+    /// the layout start offset
+    base_offset: u64,
+    /// the next checkpoint to check
+    next_checkpoint: u64,
+    /// the offset of the last '\n' seen since the layout start
+    last_new_line: Option<u64>,
+    /// the offset of the previous codepoint
+    prev_offset: Option<u64>,
+}
+
+impl Default for LineCheckpointFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineCheckpointFilter {
+    pub fn new() -> Self {
+        LineCheckpointFilter {
+            base_offset: 0,
+            next_checkpoint: LINE_CHECKPOINT_INTERVAL,
+            last_new_line: None,
+            prev_offset: None,
+        }
+    }
+
+    /// 'is_line_checkpoint' is used if the layout started less than LINE_CHECKPOINT_INTERVAL bytes before 'checkpoint'.
+    fn is_checkpoint<F: Fn(u64) -> bool>(&self, checkpoint: u64, is_line_checkpoint: &F) -> bool {
+        let min_offset = checkpoint - LINE_CHECKPOINT_INTERVAL;
+        if let Some(nl) = self.last_new_line {
+            return nl < min_offset;
+        }
+        if self.base_offset <= min_offset {
+            return true;
+        }
+
+        // the layout started after min_offset
+        is_line_checkpoint(checkpoint)
+    }
+
+    fn filter<F: Fn(u64) -> bool>(
+        &mut self,
+        filter_in: &[FilterIo],
+        filter_out: &mut Vec<FilterIo>,
+        is_line_checkpoint: F,
+    ) {
+        for io in filter_in.iter() {
+            let (offset, real_cp) = match (io.metadata, io.offset, &io.data) {
+                (false, Some(offset), FilterData::TextInfo { real_cp, .. }) => (offset, *real_cp),
+                _ => {
+                    filter_out.push(io.clone());
+                    continue;
+                }
+            };
+
+            if offset >= self.next_checkpoint {
+                let checkpoint = self.next_checkpoint;
+                self.next_checkpoint =
+                    (offset / LINE_CHECKPOINT_INTERVAL + 1) * LINE_CHECKPOINT_INTERVAL;
+
+                if offset != self.base_offset && self.is_checkpoint(checkpoint, &is_line_checkpoint)
+                {
+                    filter_out.push(build_checkpoint_new_line(self.prev_offset));
+                }
+            }
+
+            if u32_to_char(real_cp) == '\n' {
+                self.last_new_line = Some(offset);
+            }
+            self.prev_offset = Some(offset);
+
+            filter_out.push(io.clone());
+        }
+    }
+
+    fn reset(&mut self, base_offset: u64) {
+        self.base_offset = base_offset;
+        self.next_checkpoint =
+            (base_offset / LINE_CHECKPOINT_INTERVAL + 1) * LINE_CHECKPOINT_INTERVAL;
+        self.last_new_line = None;
+        self.prev_offset = None;
+    }
+}
+
+impl ContentFilter<'_> for LineCheckpointFilter {
+    fn name(&self) -> &'static str {
+        "LineCheckpointFilter"
+    }
+
+    fn setup(
+        &mut self,
+        _editor: &mut Editor<'static>,
+        _editor_env: &mut EditorEnv<'static>,
+        env: &mut LayoutEnv,
+        _view: &Rc<RwLock<View>>,
+        _parent_view: Option<&View<'static>>,
+    ) {
+        self.reset(env.base_offset);
+    }
+
+    fn run(
+        &mut self,
+        view: &View,
+        _env: &mut LayoutEnv,
+        filter_in: &[FilterIo],
+        filter_out: &mut Vec<FilterIo>,
+    ) {
+        self.filter(filter_in, filter_out, |checkpoint| {
+            let buffer = match view.buffer() {
+                Some(buffer) => buffer,
+                None => return false,
+            };
+            let buffer = buffer.read();
+            let tm = view.mode_ctx::<TextModeContext>("text-mode");
+            is_line_checkpoint(&buffer, tm.text_codec.as_ref(), checkpoint)
+        });
+    }
+}
+
+#[cfg(test)]
+fn test_buffer(text: &str) -> std::sync::Arc<RwLock<Buffer<'static>>> {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("test-line-checkpoints")
+        .internal(true)
+        .finalize()
+        .unwrap();
+    buffer.write().append(text.as_bytes());
+    buffer
+}
+
+#[test]
+fn test_is_line_checkpoint() {
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+    let interval = LINE_CHECKPOINT_INTERVAL as usize;
+
+    // a '\n' at offset 100 then a line of 3 intervals
+    let text = format!("{}\n{}", "a".repeat(100), "b".repeat(3 * interval));
+    let buffer = test_buffer(&text);
+    let buffer = buffer.read();
+
+    assert!(!is_line_checkpoint(&buffer, codec, 0));
+    assert!(!is_line_checkpoint(&buffer, codec, 100));
+    assert!(!is_line_checkpoint(
+        &buffer,
+        codec,
+        LINE_CHECKPOINT_INTERVAL
+    ));
+    assert!(is_line_checkpoint(
+        &buffer,
+        codec,
+        2 * LINE_CHECKPOINT_INTERVAL
+    ));
+    assert!(is_line_checkpoint(
+        &buffer,
+        codec,
+        3 * LINE_CHECKPOINT_INTERVAL
+    ));
+}
+
+#[test]
+fn test_line_layout_start_offset() {
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+    let interval = LINE_CHECKPOINT_INTERVAL;
+
+    // short lines: the start of the line
+    let buffer = test_buffer("line 1\nline 2\nline 3\n");
+    let buffer = buffer.read();
+    assert_eq!(line_layout_start_offset(&buffer, codec, 0), 0);
+    assert_eq!(line_layout_start_offset(&buffer, codec, 10), 7);
+    assert_eq!(line_layout_start_offset(&buffer, codec, 14), 14);
+    assert_eq!(line_layout_start_offset(&buffer, codec, 1000), 21);
+
+    // a line longer than the checkpoint interval, made of 2 bytes codepoints at odd offsets:
+    // the checkpoints are inside the codepoints
+    let text = format!("a{}", "é".repeat(3 * interval as usize));
+    let buffer = test_buffer(&text);
+    let buffer = buffer.read();
+
+    // near the start of the line
+    assert_eq!(line_layout_start_offset(&buffer, codec, 101), 0);
+    assert_eq!(
+        line_layout_start_offset(&buffer, codec, 2 * interval - 1),
+        0
+    );
+
+    // the first codepoint after the previous checkpoint
+    assert_eq!(
+        line_layout_start_offset(&buffer, codec, 2 * interval + 1),
+        2 * interval + 1
+    );
+    assert_eq!(
+        line_layout_start_offset(&buffer, codec, 5 * interval / 2 + 1),
+        2 * interval + 1
+    );
+
+    // the codepoint at the checkpoint starts before the offset: use the previous checkpoint
+    assert_eq!(
+        line_layout_start_offset(&buffer, codec, 3 * interval),
+        2 * interval + 1
+    );
+}
+
+#[test]
+fn test_line_checkpoint_filter() {
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+    let interval = LINE_CHECKPOINT_INTERVAL;
+
+    let text_io = |c: char, offset: u64| FilterIo {
+        metadata: false,
+        style: TextStyle::new(),
+        offset: Some(offset),
+        size: c.len_utf8(),
+        data: FilterData::TextInfo {
+            real_cp: c as u32,
+            displayed_cp: c as u32,
+        },
+        bidi_level: 0,
+    };
+
+    // returns the offsets of the codepoints following the checkpoint new lines
+    let run = |text: &str, base_offset: u64| {
+        let buffer = test_buffer(text);
+        let buffer = buffer.read();
+
+        let filter_in: Vec<FilterIo> = text[base_offset as usize..]
+            .char_indices()
+            .map(|(i, c)| text_io(c, base_offset + i as u64))
+            .collect();
+
+        let mut filter = LineCheckpointFilter::new();
+        filter.reset(base_offset);
+        let mut filter_out = vec![];
+        filter.filter(&filter_in, &mut filter_out, |checkpoint| {
+            is_line_checkpoint(&buffer, codec, checkpoint)
+        });
+
+        assert_eq!(
+            filter_in.len(),
+            filter_out.iter().filter(|io| !io.metadata).count()
+        );
+
+        let mut breaks = vec![];
+        for (i, io) in filter_out.iter().enumerate() {
+            if io.metadata {
+                // the new line has the offset of the previous codepoint
+                assert_eq!(io.offset, filter_out[i - 1].offset);
+                breaks.push(filter_out[i + 1].offset.unwrap());
+            }
+        }
+        breaks
+    };
+
+    // the checkpoints are inside the 2 bytes codepoints: the line breaks at the next codepoint
+    let text = format!("a{}", "é".repeat(3 * interval as usize / 2));
+    assert_eq!(run(&text, 0), vec![interval + 1, 2 * interval + 1]);
+
+    // the layout starts at the first checkpoint
+    assert_eq!(run(&text, interval + 1), vec![2 * interval + 1]);
+
+    // a '\n' less than an interval before the second checkpoint
+    let text = format!(
+        "{}\n{}",
+        "a".repeat(interval as usize + 100),
+        "b".repeat(3 * interval as usize)
+    );
+    assert_eq!(run(&text, 0), vec![interval, 3 * interval, 4 * interval]);
+
+    // the layout starts after the '\n': the buffer is read
+    assert_eq!(run(&text, interval + 101), vec![3 * interval, 4 * interval]);
+
+    // the layout starts less than an interval before a checkpoint, there is no '\n' before it
+    let text = "c".repeat(3 * interval as usize);
+    assert_eq!(run(&text, interval + 10), vec![2 * interval]);
+}
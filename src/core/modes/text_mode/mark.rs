@@ -1,9 +1,15 @@
 //
+use crate::core::buffer::find_nth_byte_offset;
+use crate::core::buffer::get_byte_count_at_offset;
 use crate::core::buffer::Buffer;
 
 use crate::core::codec::text::SyncDirection;
 use crate::core::codec::text::TextCodec;
 
+use crate::core::codec::text::utf8::extends_grapheme_cluster;
+use crate::core::codec::text::utf8::grapheme_cluster_sizes;
+use crate::core::codec::text::utf8::MAX_GRAPHEME_CLUSTER_CODEPOINTS;
+
 const DEBUG: bool = false;
 
 //
@@ -173,6 +179,68 @@ pub fn read_char_backward(
     (ret.0, from_offset - ret.2 as u64, ret.2)
 }
 
+/// Returns the size in bytes of the grapheme cluster starting at 'from_offset'.
+pub fn read_grapheme_cluster_size_forward(
+    buffer: &Buffer,
+    from_offset: u64,
+    codec: &dyn TextCodec,
+) -> usize {
+    let max_offset = buffer.size() as u64;
+    let mut cluster = String::new();
+    let mut count = 0;
+    let mut offset = from_offset;
+
+    while offset < max_offset && count < MAX_GRAPHEME_CLUSTER_CODEPOINTS {
+        let (cp, _, size) = read_char_forward(buffer, offset, codec);
+        if size == 0 {
+            break;
+        }
+        if count > 0 && !extends_grapheme_cluster(&cluster, cp) {
+            break;
+        }
+        cluster.push(cp);
+        count += 1;
+        offset += size as u64;
+    }
+
+    (offset - from_offset) as usize
+}
+
+/// Returns the size in bytes of the grapheme cluster ending at 'from_offset'.
+pub fn read_grapheme_cluster_size_backward(
+    buffer: &Buffer,
+    from_offset: u64,
+    codec: &dyn TextCodec,
+) -> usize {
+    let (last_cp, last_offset, last_size) = read_char_backward(buffer, from_offset, codec);
+    if last_size == 0 {
+        return 0;
+    }
+
+    // fast path: there is always a boundary between two ascii codepoints (except cr lf)
+    let (prev_cp, _, prev_size) = read_char_backward(buffer, last_offset, codec);
+    if prev_size == 0 || (last_cp.is_ascii() && prev_cp.is_ascii() && prev_cp != '\r') {
+        return last_size;
+    }
+
+    // collect the previous codepoints, a boundary always follows a line feed
+    let mut cps = vec![(last_cp, last_size)];
+    let mut offset = last_offset;
+    while offset > 0 && cps.len() < MAX_GRAPHEME_CLUSTER_CODEPOINTS * 2 {
+        let (cp, prev_offset, size) = read_char_backward(buffer, offset, codec);
+        if size == 0 || cp == '\n' {
+            break;
+        }
+        cps.push((cp, size));
+        offset = prev_offset;
+    }
+    cps.reverse();
+
+    grapheme_cluster_sizes(&cps)
+        .last()
+        .map_or(last_size, |size| *size)
+}
+
 // TODO(ceg): codec...
 pub fn read_char(
     _direction: SyncDirection,
@@ -311,27 +379,25 @@ impl Mark {
         Mark { offset }
     }
 
+    /// Move the mark after the grapheme cluster at the mark offset.
     pub fn move_forward(&mut self, buffer: &Buffer, codec: &dyn TextCodec) -> &mut Mark {
         if self.offset < buffer.size() as u64 {
-            // TODO(ceg): if '\r\n' must move + 1 in codec
-            let (_, _, size) = read_char_forward(&buffer, self.offset, codec);
+            let size = read_grapheme_cluster_size_forward(buffer, self.offset, codec);
             self.offset += size as u64;
         }
 
         self
     }
 
-    // TODO(ceg): check multi-byte utf8 sequence
+    /// Move the mark to the start of the grapheme cluster preceding the mark offset.
     pub fn move_backward(&mut self, buffer: &Buffer, codec: &dyn TextCodec) -> &mut Mark {
-        let (c, offset, size) = read_char_backward(&buffer, self.offset, codec);
+        let size = read_grapheme_cluster_size_backward(buffer, self.offset, codec);
         dbg_println!(
-            "move_backward : char = '{:?}', self.offset({}) = offset({}), size({})",
-            c,
+            "move_backward : self.offset({}), cluster size({})",
             self.offset,
-            offset,
             size
         );
-        self.offset = offset;
+        self.offset -= size as u64;
 
         self
     }
@@ -346,6 +412,19 @@ impl Mark {
         let mut encode = [0; 4];
         let sz = codec.encode('\n' as u32, &mut encode);
 
+        // the byte index avoids scanning very long lines
+        if sz == 1 && buffer.indexed {
+            let (n, _) = get_byte_count_at_offset(buffer, encode[0] as usize, self.offset);
+            if n == 0 {
+                self.offset = 0;
+                return self;
+            }
+            if let Some(offset) = find_nth_byte_offset(buffer, encode[0], n) {
+                self.offset = offset + 1;
+                return self;
+            }
+        }
+
         let target_offset =
             if let Some(offset) = buffer.find_reverse(&encode[..sz], self.offset, None) {
                 dbg_println!("move_to_start_of_line: find at offset {}", offset);
@@ -366,6 +445,17 @@ impl Mark {
     pub fn move_to_end_of_line(&mut self, buffer: &Buffer, codec: &dyn TextCodec) -> &mut Mark {
         let mut encode = [0; 4];
         let sz = codec.encode('\n' as u32, &mut encode);
+
+        // the byte index avoids scanning very long lines
+        if sz == 1 && buffer.indexed {
+            let (n, _) = get_byte_count_at_offset(buffer, encode[0] as usize, self.offset);
+            self.offset = match find_nth_byte_offset(buffer, encode[0], n + 1) {
+                Some(offset) => offset,
+                None => buffer.size() as u64,
+            };
+            return self;
+        }
+
         self.offset = if let Some(offset) = buffer.find(&encode[..sz], self.offset, None) {
             offset
         } else {
@@ -836,3 +926,102 @@ fn test_marks() {
 
     println!("\n*************************************");
 }
+
+#[test]
+fn test_marks_grapheme_clusters() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("test-grapheme-clusters")
+        .internal(false)
+        .finalize()
+        .unwrap();
+
+    let mut bb = buffer.write();
+
+    // e + combining acute accent, family (zwj sequence), flag, cr lf, x
+    let clusters = [
+        "e\u{301}",
+        "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}",
+        "\u{1f1eb}\u{1f1f7}",
+        "\r\n",
+        "x",
+    ];
+    let text = clusters.concat();
+    bb.insert(0, text.len(), text.as_bytes());
+
+    let mut offsets = vec![0];
+    for c in &clusters {
+        offsets.push(offsets.last().unwrap() + c.len() as u64);
+    }
+
+    let mut m = Mark::new(0);
+    for off in offsets.iter().skip(1) {
+        m.move_forward(&bb, codec);
+        assert_eq!(m.offset, *off);
+    }
+    // end of buffer
+    m.move_forward(&bb, codec);
+    assert_eq!(m.offset, text.len() as u64);
+
+    for off in offsets.iter().rev().skip(1) {
+        m.move_backward(&bb, codec);
+        assert_eq!(m.offset, *off);
+    }
+    m.move_backward(&bb, codec);
+    assert_eq!(m.offset, 0);
+}
+
+#[test]
+fn test_marks_start_end_of_line_indexed() {
+    use crate::core::buffer::build_index;
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name("test-start-end-of-line")
+        .internal(false)
+        .finalize()
+        .unwrap();
+
+    let text = "ab\ncd\n\nef";
+    buffer.write().insert(0, text.len(), text.as_bytes());
+
+    // (offset, start of line, end of line)
+    let expected = [
+        (0, 0, 2),
+        (2, 0, 2),
+        (3, 3, 5),
+        (5, 3, 5),
+        (6, 6, 6),
+        (7, 7, 9),
+        (9, 7, 9),
+    ];
+
+    // scan, then byte index
+    for indexed in [false, true] {
+        if indexed {
+            assert!(build_index(&buffer));
+        }
+
+        let bb = buffer.read();
+        assert_eq!(bb.indexed, indexed);
+
+        for (offset, start, end) in expected {
+            let mut m = Mark { offset };
+            m.move_to_start_of_line(&bb, codec);
+            assert_eq!(m.offset, start, "start of line of {}", offset);
+
+            let mut m = Mark { offset };
+            m.move_to_end_of_line(&bb, codec);
+            assert_eq!(m.offset, end, "end of line of {}", offset);
+        }
+    }
+}
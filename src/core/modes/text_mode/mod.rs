@@ -1,9 +1,17 @@
+pub mod bidi;
+pub mod block_selection;
+pub mod bracket_matching;
 pub mod char_map;
 pub mod draw_mark;
+pub mod folding;
+pub mod follow;
 pub mod highlight_keywords;
 pub mod highlight_selection_overlay;
 
+pub mod indent;
+pub mod line_checkpoints;
 pub mod mark;
+pub mod occurrences;
 pub mod raw_data_filter;
 pub mod ruler;
 pub mod screen_filler;
@@ -19,10 +27,13 @@ pub mod show_trailing_spaces;
 //
 mod text_mode;
 
+pub use bidi::*;
 pub use char_map::*;
 pub use draw_mark::*;
+pub use folding::*;
 pub use highlight_keywords::*;
 pub use highlight_selection_overlay::*;
+pub use line_checkpoints::*;
 
 pub use raw_data_filter::*;
 pub use screen_filler::*;
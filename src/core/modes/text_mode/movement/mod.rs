@@ -9,6 +9,7 @@ use super::*;
 use super::mark::Mark;
 use crate::core::editor::Editor;
 use crate::core::editor::EditorEnv;
+use crate::core::screen::line_logical_first;
 use crate::core::screen::Screen;
 use crate::core::view::View;
 
@@ -115,6 +116,8 @@ pub fn move_marks_backward(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc
 
     buffer.set_cache(min, max);
 
+    let folds = tm.folds.read();
+
     let mut scroll_down = 0;
     for (idx, m) in tm.marks.iter_mut().enumerate() {
         if idx == midx && m.offset <= start_offset {
@@ -122,7 +125,9 @@ pub fn move_marks_backward(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc
         }
 
         m.move_backward(&buffer, codec);
+        m.offset = folds.skip(m.offset, false);
     }
+    drop(folds);
 
     if scroll_down > 0 {
         tm.pre_compose_action
@@ -172,9 +177,12 @@ pub fn move_marks_forward(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<
 
     /* TODO(ceg): check error */
     let codec = tm.text_codec.as_ref();
+    let folds = tm.folds.read();
     for m in tm.marks.iter_mut() {
         m.move_forward(&buffer, codec);
+        m.offset = folds.skip(m.offset, true);
     }
+    drop(folds);
 
     // mark move off_screen ? scroll down 1 line
     // TODO(ceg): end_offset is not set properly at startup
@@ -232,9 +240,16 @@ pub fn move_marks_to_start_of_line(
     //
     let midx = tm.mark_index;
 
+    let folds = tm.folds.read();
+
     let mut center = false;
     for (idx, m) in tm.marks.iter_mut().enumerate() {
         m.move_to_start_of_line(&buffer, codec);
+        // the line is the last one of a fold: go to the start of the fold's first line
+        while let Some(f) = folds.find_at(m.offset).filter(|f| f.start < m.offset) {
+            m.offset = f.start;
+            m.move_to_start_of_line(&buffer, codec);
+        }
 
         if idx == midx && !screen.contains_offset(m.offset) {
             center = true;
@@ -268,9 +283,16 @@ pub fn move_marks_to_end_of_line(
 
     let midx = tm.mark_index;
 
+    let folds = tm.folds.read();
+
     let mut center = false;
     for (idx, m) in tm.marks.iter_mut().enumerate() {
         m.move_to_end_of_line(&buffer, codec);
+        // the end of line is folded: go to the end of the fold's last line
+        while let Some(f) = folds.find_at(m.offset).filter(|f| f.end > m.offset) {
+            m.offset = f.end;
+            m.move_to_end_of_line(&buffer, codec);
+        }
 
         if idx == midx && !screen.contains_offset(m.offset) {
             center = true;
@@ -317,10 +339,12 @@ pub fn scroll_screen_up(
 
             rewind += (width * height / 2) as u64; // rewind max is width * height * 4, but this is slower
 
-            let start_offset = offset.saturating_sub(rewind);
-            (start_offset, width, height)
+            (offset.saturating_sub(rewind), width, height)
         };
 
+        // restart the layout at a line start (or a checkpoint in long lines)
+        let start_offset = get_layout_start_offset(view, start_offset);
+
         // TODO(ceg): codec.sync_forward(start_offset) -> start_offset
         // if we are in the middle of a utf8 sequence we move max 4 bytes until a starting point is reached
         // for idx in 0..4 { if codec.is_sync(new_start) { break; } start_offset += codec.encode_min_size() }
@@ -484,15 +508,6 @@ pub fn move_mark_to_previous_line(
             let rewind = (width * max_encode_size) as u64;
 
             let start_offset = m_offset.saturating_sub(rewind);
-            dbg_println!(
-                "MARK next position is offscreen ---------------- rewind offset = {}",
-                start_offset
-            );
-
-            if start_offset == m_offset {
-                return;
-            }
-
             let end_offset = std::cmp::min(buffer_size, m_offset + rewind);
 
             dbg_println!(
@@ -505,6 +520,17 @@ pub fn move_mark_to_previous_line(
             (start_offset, end_offset, width, height)
         };
 
+        // restart the layout at a line start (or a checkpoint in long lines)
+        let start_offset = get_layout_start_offset(view, start_offset);
+        dbg_println!(
+            "MARK next position is offscreen ---------------- rewind offset = {}",
+            start_offset
+        );
+
+        if start_offset == m_offset {
+            return;
+        }
+
         //let end_offset = m_offset + width as u64 * 4;
         //let end_offset = std::cmp::min(end_offset, buffer_size);
         //let end_offset = m_offset + width as u64 * 4;
@@ -949,19 +975,8 @@ pub fn move_mark_to_next_line(
             (screen.width(), screen.height())
         };
 
-        // get start_of_line(m.offset) -> u64
-        let start_offset = {
-            let v = &view.read();
-            let buffer = v.buffer().unwrap();
-            let buffer = buffer.read();
-
-            let tm = v.mode_ctx::<TextModeContext>("text-mode");
-            let codec = tm.text_codec.as_ref();
-
-            let mut tmp = Mark::new(m.offset);
-            tmp.move_to_start_of_line(&buffer, codec);
-            tmp.offset
-        };
+        // start of line, or the nearest checkpoint in long lines
+        let start_offset = get_layout_start_offset(view, m.offset);
 
         dbg_println!("MARK IS OFFSCREEN");
 
@@ -1100,7 +1115,7 @@ fn move_onscreen_single_mark_to_next_line(
     }
 
     // Go to next screen ?
-    let cpi = &last_line[0].cpi; // will panic if invariant is broken
+    let cpi = &line_logical_first(last_line).cpi; // will panic if invariant is broken
     let last_line_first_offset = cpi.offset.unwrap(); // update next screen start offset
     let has_eof = screen.has_eof();
 
@@ -1300,12 +1315,12 @@ pub fn move_marks_to_next_line(
                 if screen.has_eof() {
                     break;
                 }
-                let cpi = &last_line[0].cpi;
+                let cpi = &line_logical_first(last_line).cpi;
                 m.offset = cpi.offset.unwrap(); // update next screen start offset
                 continue;
             }
 
-            let next_screen_start_cpi = &last_line[0].cpi;
+            let next_screen_start_cpi = &line_logical_first(last_line).cpi;
             // idx is on screen
             // find next onscreen index
             let mut idx_end = idx + 1;
@@ -0,0 +1,339 @@
+use parking_lot::RwLock;
+use std::rc::Rc;
+
+use super::mark::read_char_backward;
+use super::mark::read_char_forward;
+use super::mark::Mark;
+use super::*;
+
+use crate::core::buffer::Buffer;
+use crate::core::codec::text::TextCodec;
+use crate::core::editor::Editor;
+use crate::core::editor::EditorEnv;
+use crate::core::view::View;
+use crate::sort_pair;
+
+/// The **OccurrenceSearch** remembers the pattern and the last match
+/// used by the add-mark-at-next-occurrence/skip-occurrence actions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OccurrenceSearch {
+    pub pattern: Vec<u8>,
+    pub last_match: u64,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Returns the range of the word around 'offset'.
+pub fn word_range_at(buffer: &Buffer, codec: &dyn TextCodec, offset: u64) -> Option<(u64, u64)> {
+    let mut start = offset;
+    loop {
+        let (c, prev, size) = read_char_backward(buffer, start, codec);
+        if size == 0 || !is_word_char(c) {
+            break;
+        }
+        start = prev;
+    }
+
+    let mut end = offset;
+    loop {
+        let (c, _, size) = read_char_forward(buffer, end, codec);
+        if size == 0 || !is_word_char(c) {
+            break;
+        }
+        end += size as u64;
+    }
+
+    if start == end {
+        None
+    } else {
+        Some((start, end))
+    }
+}
+
+/// Returns all the non-overlapping match offsets of 'pattern' in [min, max).
+pub fn find_all_occurrences(buffer: &Buffer, pattern: &[u8], min: u64, max: u64) -> Vec<u64> {
+    let mut matches = vec![];
+    if pattern.is_empty() {
+        return matches;
+    }
+
+    let mut offset = min;
+    while let Some(found) = buffer.find(pattern, offset, Some(max)) {
+        if found + pattern.len() as u64 > max {
+            break;
+        }
+        matches.push(found);
+        offset = found + pattern.len() as u64;
+    }
+
+    matches
+}
+
+/// Returns the next match of 'pattern' after 'from', wraps around the end of the buffer.
+fn find_next_occurrence(buffer: &Buffer, pattern: &[u8], from: u64) -> Option<u64> {
+    if let Some(offset) = buffer.find(pattern, from, None) {
+        return Some(offset);
+    }
+    let max = from.saturating_add(pattern.len() as u64);
+    buffer.find(pattern, 0, Some(std::cmp::min(max, buffer.size() as u64)))
+}
+
+/// Returns the (min, max) range of the selection of the main mark.
+fn main_selection(tm: &TextModeContext) -> Option<(u64, u64)> {
+    if tm.marks.len() != tm.select_point.len() || tm.marks.is_empty() {
+        return None;
+    }
+
+    let idx = tm.mark_index;
+    let range = sort_pair((tm.marks[idx].offset, tm.select_point[idx].offset));
+    if range.0 == range.1 {
+        None
+    } else {
+        Some(range)
+    }
+}
+
+fn selection_pairs(tm: &TextModeContext) -> Vec<(u64, u64)> {
+    if tm.marks.len() != tm.select_point.len() {
+        return vec![];
+    }
+
+    tm.marks
+        .iter()
+        .zip(tm.select_point.iter())
+        .map(|(m, s)| sort_pair((s.offset, m.offset)))
+        .collect()
+}
+
+/// Replaces the marks and selection points by 'ranges' (sorted, deduplicated).<br/>
+/// The marks are placed at the end of each range, the main mark is set on 'main'.
+pub fn set_selection_pairs(tm: &mut TextModeContext, mut ranges: Vec<(u64, u64)>, main: u64) {
+    ranges.sort();
+    ranges.dedup();
+
+    tm.marks.clear();
+    tm.select_point.clear();
+    tm.block_selection = None;
+    tm.mark_index = 0;
+
+    for (idx, (start, end)) in ranges.iter().enumerate() {
+        if *start == main {
+            tm.mark_index = idx;
+        }
+        tm.select_point.push(Mark::new(*start));
+        tm.marks.push(Mark::new(*end));
+    }
+}
+
+/// Select the word under the main mark and returns its content.
+fn select_word_at_main_mark(tm: &mut TextModeContext, buffer: &Buffer) -> Option<Vec<u8>> {
+    let offset = tm.marks[tm.mark_index].offset;
+    let (start, end) = word_range_at(buffer, tm.text_codec.as_ref(), offset)?;
+
+    let mut data = vec![];
+    buffer.read(start, (end - start) as usize, &mut data);
+
+    set_selection_pairs(tm, vec![(start, end)], start);
+    tm.occurrence = Some(OccurrenceSearch {
+        pattern: data.clone(),
+        last_match: start,
+    });
+
+    Some(data)
+}
+
+/// Returns the current occurrence search if its last match is still selected,
+/// else start a new search with the main selection (or the word under the main mark).
+/// The second value is set when a new search was started.
+fn occurrence_search(
+    tm: &mut TextModeContext,
+    buffer: &Buffer,
+) -> Option<(OccurrenceSearch, bool)> {
+    let pairs = selection_pairs(tm);
+
+    if let Some(search) = &tm.occurrence {
+        let range = (
+            search.last_match,
+            search.last_match + search.pattern.len() as u64,
+        );
+        if pairs.contains(&range) {
+            return Some((search.clone(), false));
+        }
+    }
+
+    if let Some((min, max)) = main_selection(tm) {
+        let mut data = vec![];
+        buffer.read(min, (max - min) as usize, &mut data);
+        let search = OccurrenceSearch {
+            pattern: data,
+            last_match: min,
+        };
+        tm.occurrence = Some(search.clone());
+        return Some((search, false));
+    }
+
+    // no selection: the first call only selects the word
+    select_word_at_main_mark(tm, buffer)?;
+    Some((tm.occurrence.clone().unwrap(), true))
+}
+
+fn add_next_occurrence(view: &Rc<RwLock<View>>, skip_current: bool) {
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+
+    let (search, new_search) = match occurrence_search(tm, &buffer) {
+        Some(s) => s,
+        None => return,
+    };
+
+    tm.prev_action = TextModeAction::MarksMove;
+
+    if new_search || search.pattern.is_empty() {
+        return;
+    }
+
+    let len = search.pattern.len() as u64;
+    let mut pairs = selection_pairs(tm);
+
+    let from = search.last_match + len;
+    let found = match find_next_occurrence(&buffer, &search.pattern, from) {
+        Some(found) => found,
+        None => return,
+    };
+
+    if skip_current && pairs.len() > 1 {
+        pairs.retain(|r| r.0 != search.last_match);
+    }
+
+    pairs.push((found, found + len));
+    set_selection_pairs(tm, pairs, found);
+
+    tm.occurrence = Some(OccurrenceSearch {
+        pattern: search.pattern,
+        last_match: found,
+    });
+
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAround { offset: found });
+}
+
+/// Add a mark (and its selection) at the next occurrence of the main selection.<br/>
+/// Without selection, the first call selects the word under the main mark.
+pub fn add_mark_at_next_occurrence(
+    _editor: &mut Editor,
+    _env: &mut EditorEnv,
+    view: &Rc<RwLock<View>>,
+) {
+    add_next_occurrence(view, false);
+}
+
+/// Move the last added mark to the next occurrence.
+pub fn skip_occurrence(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    add_next_occurrence(view, true);
+}
+
+/// Create a mark (and its selection) on every match of 'pattern' in [min, max).<br/>
+/// Returns the number of matches.
+pub fn mark_occurrences_in_range(
+    view: &Rc<RwLock<View>>,
+    pattern: &[u8],
+    range: Option<(u64, u64)>,
+) -> usize {
+    let v = &mut view.write();
+
+    let buffer = v.buffer().unwrap();
+    let buffer = buffer.read();
+
+    let (min, max) = range.unwrap_or((0, buffer.size() as u64));
+    let matches = find_all_occurrences(&buffer, pattern, min, max);
+    if matches.is_empty() {
+        return 0;
+    }
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+
+    let len = pattern.len() as u64;
+    let ranges = matches.iter().map(|m| (*m, *m + len)).collect();
+    set_selection_pairs(tm, ranges, matches[0]);
+
+    tm.occurrence = Some(OccurrenceSearch {
+        pattern: pattern.to_vec(),
+        last_match: *matches.last().unwrap(),
+    });
+
+    tm.prev_action = TextModeAction::MarksMove;
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+
+    matches.len()
+}
+
+/// Create a mark on every occurrence of the main selection (or the word under the main mark).
+pub fn mark_all_occurrences(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    let pattern = {
+        let v = &mut view.write();
+        let buffer = v.buffer().unwrap();
+        let buffer = buffer.read();
+        let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+
+        match main_selection(tm) {
+            Some((min, max)) => {
+                let mut data = vec![];
+                buffer.read(min, (max - min) as usize, &mut data);
+                data
+            }
+            None => match select_word_at_main_mark(tm, &buffer) {
+                Some(data) => data,
+                None => return,
+            },
+        }
+    };
+
+    mark_occurrences_in_range(view, &pattern, None);
+}
+
+#[test]
+fn test_occurrences() {
+    use crate::core::buffer::BufferBuilder;
+    use crate::core::buffer::BufferKind;
+    use crate::core::buffer::OpenMode;
+    use crate::core::codec::text::utf8::Utf8Codec;
+
+    let codec = &Utf8Codec::new();
+
+    let mut builder = BufferBuilder::new(BufferKind::File);
+    let buffer = builder
+        .buffer_name("test-occurrences")
+        .internal(false)
+        .mode(OpenMode::ReadWrite)
+        .finalize()
+        .unwrap();
+    let mut bb = buffer.write();
+
+    let data = "let foo = foo_bar(foo);\nfoo".as_bytes();
+    bb.insert(0, data.len(), data);
+
+    assert_eq!(word_range_at(&bb, codec, 5), Some((4, 7)));
+    assert_eq!(word_range_at(&bb, codec, 7), Some((4, 7)));
+    assert_eq!(word_range_at(&bb, codec, 12), Some((10, 17)));
+    assert_eq!(word_range_at(&bb, codec, 8), None);
+
+    let size = bb.size() as u64;
+    assert_eq!(
+        find_all_occurrences(&bb, b"foo", 0, size),
+        vec![4, 10, 18, 24]
+    );
+    assert_eq!(find_all_occurrences(&bb, b"foo", 5, 21), vec![10, 18]);
+    assert_eq!(find_all_occurrences(&bb, b"foo", 5, 20), vec![10]);
+    assert!(find_all_occurrences(&bb, b"", 0, size).is_empty());
+
+    assert_eq!(find_next_occurrence(&bb, b"foo", 19), Some(24));
+    assert_eq!(find_next_occurrence(&bb, b"foo", 25), Some(4));
+    assert_eq!(find_next_occurrence(&bb, b"let", 1), Some(0));
+}
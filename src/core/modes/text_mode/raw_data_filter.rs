@@ -14,6 +14,11 @@ use crate::core::codepointinfo::TextStyle;
 use crate::core::view::View;
 
 use crate::core::bench_to_eof;
+use crate::core::editor::config_var_is_set;
+
+use super::build_fold_io;
+use super::layout_folds;
+use super::Fold;
 
 pub struct RawDataFilter {
     // data
@@ -23,6 +28,9 @@ pub struct RawDataFilter {
     read_max: usize,
     read_size: usize,
     read_count: usize,
+    /// the folds after pos: the hidden bytes are not read
+    folds: Vec<Fold>,
+    fold_index: usize,
 }
 
 impl RawDataFilter {
@@ -34,6 +42,8 @@ impl RawDataFilter {
             read_max: 0,
             read_size: 0,
             read_count: 0,
+            folds: vec![],
+            fold_index: 0,
         }
     }
 }
@@ -45,7 +55,7 @@ impl ContentFilter<'_> for RawDataFilter {
 
     fn setup(
         &mut self,
-        _editor: &mut Editor<'static>,
+        editor: &mut Editor<'static>,
         _editor_env: &mut EditorEnv<'static>,
 
         env: &mut LayoutEnv,
@@ -71,6 +81,15 @@ impl ContentFilter<'_> for RawDataFilter {
         // EOF
         self.max_pos = view.read().buffer().unwrap().read().size() as u64;
 
+        self.fold_index = 0;
+        self.folds = if !crate::core::raw_data_filter_to_screen()
+            && config_var_is_set(editor, "text-mode:folding", true)
+        {
+            layout_folds(&view.read(), self.pos)
+        } else {
+            vec![]
+        };
+
         if bench_to_eof() {
             let bench_size = match std::env::var("UNLIMITED_BENCH_FILE_FETCH_CHUNK_SIZE") {
                 Ok(val) => val.parse::<usize>().unwrap(),
@@ -143,7 +162,26 @@ impl ContentFilter<'_> for RawDataFilter {
             //
             let buffer = buffer.read();
 
-            let rd = buffer.read(self.pos, self.read_size, &mut raw_data);
+            // jump over the folded bytes
+            while self.fold_index < self.folds.len() && self.folds[self.fold_index].end <= self.pos
+            {
+                self.fold_index += 1;
+            }
+            let (fold, read_size) = match self.folds.get(self.fold_index) {
+                Some(f) if f.start <= self.pos => (Some(*f), 0),
+                Some(f) => (
+                    None,
+                    std::cmp::min(self.read_size, (f.start - self.pos) as usize),
+                ),
+                None => (None, self.read_size),
+            };
+
+            if let Some(fold) = fold {
+                (*filter_out).push(build_fold_io(&fold, self.pos));
+                self.pos = fold.end;
+            }
+
+            let rd = buffer.read(self.pos, read_size, &mut raw_data);
 
             if self.debug {
                 dbg_println!(
@@ -192,6 +230,7 @@ impl ContentFilter<'_> for RawDataFilter {
                     offset: Some(self.pos),
                     size: rd,
                     data: FilterData::ByteArray { vec: raw_data },
+                    bidi_level: 0,
                 });
             }
 
@@ -218,6 +257,7 @@ impl ContentFilter<'_> for RawDataFilter {
                         offset: Some(self.pos),
                         size: 0,
                         data: FilterData::EndOfStream,
+                        bidi_level: 0,
                     });
                 }
                 if self.debug {
@@ -238,6 +278,7 @@ impl ContentFilter<'_> for RawDataFilter {
                         offset: Some(self.pos),
                         size: 0,
                         data: FilterData::CustomLimitReached,
+                        bidi_level: 0,
                     });
                 }
             }
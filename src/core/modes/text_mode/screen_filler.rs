@@ -171,6 +171,7 @@ impl ContentFilter<'_> for ScreenFilter {
                         size: 0,
                         skip_render: false,
                         style,
+                        bidi_level: 0,
                     };
                     dbg_println!("add EOF to stream {:?}", io.offset);
                     let ret = env.screen.push(&eof_cpi);
@@ -201,6 +202,7 @@ impl ContentFilter<'_> for ScreenFilter {
                         size: io.size,
                         skip_render: false,
                         style: io.style,
+                        bidi_level: io.bidi_level,
                     };
 
                     let ret = self.add_text_to_screen(env, cpi, io.offset);
@@ -243,6 +245,7 @@ impl ContentFilter<'_> for ScreenFilter {
                             size: 1,
                             skip_render: false,
                             style: default_style.clone(),
+                            bidi_level: 0,
                         };
 
                         let ret = self.add_text_to_screen(env, cpi, Some(cur_offset));
@@ -283,6 +286,7 @@ impl ContentFilter<'_> for ScreenFilter {
                             size: 1,
                             skip_render: false,
                             style: default_style.clone(),
+                            bidi_level: 0,
                         };
 
                         let ret = self.add_text_to_screen(env, cpi, Some(cur_offset));
@@ -303,7 +307,7 @@ impl ContentFilter<'_> for ScreenFilter {
     }
 
     fn finish(&mut self, _view: &View, env: &mut LayoutEnv) {
-        //env.screen.finalize();
+        env.screen.finalize();
 
         env.screen.check_invariants();
         env.screen.buffer_max_offset = env.max_offset;
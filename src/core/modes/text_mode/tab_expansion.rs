@@ -7,6 +7,11 @@ use crate::core::view::{ContentFilter, FilterData, FilterIo, LayoutEnv, View};
 use crate::core::Editor;
 use crate::core::EditorEnv;
 
+use super::TextModeContext;
+
+/// number of columns between two tab stops
+pub const TAB_SIZE: u64 = 8;
+
 pub struct TabFilter {
     prev_cp: char,
     column_count: u64,
@@ -51,11 +56,19 @@ impl ContentFilter<'_> for TabFilter {
 
     fn run(
         &mut self,
-        _view: &View,
+        view: &View,
         _env: &mut LayoutEnv,
         filter_in: &[FilterIo],
         filter_out: &mut Vec<FilterIo>,
     ) {
+        let tab_size = if view.check_mode_ctx::<TextModeContext>("text-mode") {
+            view.mode_ctx::<TextModeContext>("text-mode")
+                .indent
+                .tab_width
+        } else {
+            TAB_SIZE
+        };
+
         for io in filter_in.iter() {
             if let FilterIo {
                 data: FilterData::TextInfo { real_cp, .. },
@@ -65,8 +78,7 @@ impl ContentFilter<'_> for TabFilter {
                 let codepoint = u32_to_char(*real_cp);
                 match codepoint {
                     '\t' => {
-                        self.prev_cp = '\t'; // TODO(ceg): user configuration/file hints
-                        let tab_size = 8;
+                        self.prev_cp = '\t';
                         let padding = tab_size - (self.column_count % tab_size);
 
                         for (idx, _) in (0..padding).enumerate() {
@@ -92,6 +92,7 @@ use crate::core::editor::EditorEnv;
 use crate::dbg_println;
 
 use crate::core::screen::Screen;
+use crate::core::screen::{line_logical_first, line_logical_last};
 
 use super::mark::Mark;
 
@@ -122,13 +123,32 @@ use crate::core::view::View;
 use crate::core::event::input_map::build_input_event_map;
 use crate::core::event::input_map::DEFAULT_INPUT_MAP;
 
+use crate::core::modes::line_changes::revert_hunk;
 use crate::core::modes::text_mode::mark::read_char_raw_backward;
 use crate::core::modes::text_mode::mark::read_char_raw_forward;
+use crate::core::modes::text_mode::mark::read_grapheme_cluster_size_forward;
 
 use super::movement::*;
 
+use super::block_selection::*;
+use super::bracket_matching::*;
+use super::folding::*;
+use super::follow::*;
+use super::indent::*;
+use super::line_checkpoints::line_layout_start_offset;
+use super::occurrences::*;
+
 use crate::core::editor::config_var_is_set;
 
+use crate::core::editor::file_history::FileHistoryEntry;
+use crate::core::editor::kill_ring::KillRingEntry;
+use crate::core::editor::kill_ring::KillRingSignature;
+use crate::core::editor::KillRing;
+
+use crate::core::buffer::Buffer;
+
+use crate::core::modes::core_mode::toggle_text_popup;
+
 #[derive(Debug, Clone, Copy)]
 pub enum PostInputAction {
     ScrollUp { n: usize },
@@ -163,8 +183,11 @@ pub enum TextModeAction {
 use super::super::Mode;
 
 // Text mode content filters
+use crate::core::modes::text_mode::BidiFilter;
 use crate::core::modes::text_mode::CharMapFilter;
+use crate::core::modes::text_mode::FoldFilter;
 use crate::core::modes::text_mode::HighlightKeywords;
+use crate::core::modes::text_mode::LineCheckpointFilter;
 use crate::core::modes::text_mode::RawDataFilter;
 use crate::core::modes::text_mode::ScreenFilter;
 use crate::core::modes::text_mode::TabFilter;
@@ -179,6 +202,8 @@ use crate::core::modes::text_mode::DrawMarks;
 
 use crate::core::modes::text_mode::HighlightSelectionOverlay;
 
+use crate::core::modes::text_mode::bracket_matching::HighlightMatchingBracketOverlay;
+
 use crate::core::modes::text_mode::ShowTrailingSpaces;
 
 use crate::core::view::ContentFilter;
@@ -192,10 +217,11 @@ struct ScreenOverlayFilterInfo<'a> {
     allocator: fn() -> Box<dyn ScreenOverlayFilter<'a>>,
 }
 
-/// CopyData is used to implement the selection/cut/paste buffer
-pub enum CopyData {
-    BufferLogIndex(usize), // the data is in the buffer buffer log index see BufferLog
-    InnerBuffer(Vec<u8>),  // a standalone copy
+/// RegisterOp is the pending register operation, the next inserted char is the register name
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegisterOp {
+    CopyToRegister,
+    InsertRegister,
 }
 
 pub struct TextModeContext {
@@ -212,7 +238,20 @@ pub struct TextModeContext {
     pub marks: Vec<Mark>,
     //
     pub select_point: Vec<Mark>,
-    pub copy_buffer: Vec<CopyData>,
+    pub block_selection: Option<BlockSelection>,
+    pub occurrence: Option<OccurrenceSearch>,
+    /// the folded regions, updated on buffer modifications (see FoldTracker)
+    pub folds: Arc<RwLock<FoldSet>>,
+    /// the id of the FoldTracker registered in the buffer
+    pub fold_tracker: usize,
+    pub indent: IndentPolicy,
+    pub auto_indent: bool,
+    pub auto_close_pairs: bool,
+    /// follow mode: the view stays at the end of the growing buffer
+    pub follow: bool,
+    /// size of the buffer at the previous composition (follow mode)
+    pub follow_size: u64,
+    pub register_op: Option<RegisterOp>,
     pub button_state: [u32; 8],
 
     // TODO ? char_map_and_color HashMap<char, String, Option<(u8,u8,u8)>>,
@@ -314,12 +353,30 @@ fn build_text_mode_content_filters_map() -> HashMap<&'static str, ContentFilterI
             allocator: || Box::new(ShowTrailingSpaces::new()),
         });
 
+    content_filter_map
+        .entry("text/line-checkpoints")
+        .or_insert(ContentFilterInfo {
+            allocator: || Box::new(LineCheckpointFilter::new()),
+        });
+
+    content_filter_map
+        .entry("text/folding")
+        .or_insert(ContentFilterInfo {
+            allocator: || Box::new(FoldFilter::new()),
+        });
+
     content_filter_map
         .entry("text/word-wrap")
         .or_insert(ContentFilterInfo {
             allocator: || Box::new(WordWrapFilter::new()),
         });
 
+    content_filter_map
+        .entry("text/bidi")
+        .or_insert(ContentFilterInfo {
+            allocator: || Box::new(BidiFilter::new()),
+        });
+
     content_filter_map
         .entry("text/screen")
         .or_insert(ContentFilterInfo {
@@ -345,6 +402,12 @@ fn build_text_mode_screen_overlay_filters_map(
             allocator: || Box::new(HighlightSelectionOverlay::new()),
         });
 
+    screen_overlay_filter_map
+        .entry("text/highlight-matching-bracket")
+        .or_insert(ScreenOverlayFilterInfo {
+            allocator: || Box::new(HighlightMatchingBracketOverlay::new()),
+        });
+
     screen_overlay_filter_map
         .entry("text/ruler")
         .or_insert(ScreenOverlayFilterInfo {
@@ -467,9 +530,18 @@ impl<'a> Mode for TextMode {
             prev_mark_revision: 0,
             mark_revision: 0,
             marks: vec![Mark::new(0)],
-            copy_buffer: vec![],
+            register_op: None,
             mark_index: 0,
             select_point: vec![],
+            block_selection: None,
+            occurrence: None,
+            folds: Arc::new(RwLock::new(FoldSet::new())),
+            fold_tracker: 0,
+            indent: IndentPolicy::default(),
+            auto_indent: true,
+            auto_close_pairs: true,
+            follow: false,
+            follow_size: 0,
             button_state: [0; 8],
             char_map: Some(char_map),
             color_map: Some(color_map),
@@ -494,7 +566,7 @@ impl<'a> Mode for TextMode {
         view.compose_priority = 256; // TODO: move to caller
 
         //
-        let start_offset = {
+        let (start_offset, follow) = {
             let buffer = view.buffer().unwrap();
             let mut buffer = buffer.write();
 
@@ -506,20 +578,45 @@ impl<'a> Mode for TextMode {
             let start_offset = buffer.start_position.offset;
             buffer.start_position.offset = None; // do this once (1st view)
 
-            if let Some(offset) = start_offset {
+            let start_offset = if let Some(offset) = start_offset {
                 std::cmp::min(buffer.size() as u64, offset)
             } else {
                 0
-            }
+            };
+
+            // a stream without file (stdin) is followed while the main mark is at its end
+            let follow = buffer.is_streaming && buffer.file_name().is_empty();
+
+            (start_offset, follow)
+        };
+
+        let indent = {
+            let buffer = view.buffer().unwrap();
+            let buffer = buffer.read();
+            indent_policy_for_file(editor, &buffer.name)
         };
 
         let tm = view.mode_ctx_mut::<TextModeContext>("text-mode");
         // refresh view offset after user input
         tm.marks[0].offset = start_offset;
+        tm.follow = follow;
+
+        tm.indent = indent;
+        tm.auto_indent = config_var_is_set(editor, "text-mode:auto-indent", true);
+        tm.auto_close_pairs = config_var_is_set(editor, "text-mode:auto-close-pairs", true);
+
+        // reopened file: restore its last position and undo tag before the first undo tag
+        let undo_tag = restore_view_position(editor, view);
+        let tm = view.mode_ctx::<TextModeContext>("text-mode");
 
         // create first mark
-        let marks_offsets: Vec<u64> = tm.marks.iter().map(|m| m.offset).collect();
-        let selections_offsets: Vec<u64> = tm.select_point.iter().map(|m| m.offset).collect();
+        let (marks_offsets, selections_offsets) = match undo_tag {
+            Some(tag) => tag,
+            None => (
+                tm.marks.iter().map(|m| m.offset).collect(),
+                tm.select_point.iter().map(|m| m.offset).collect(),
+            ),
+        };
 
         view.buffer.as_ref().unwrap().write().tag(
             Instant::now(),
@@ -528,6 +625,17 @@ impl<'a> Mode for TextMode {
             selections_offsets,
         );
 
+        // keep the folds in sync with the buffer modifications
+        let fold_tracker = Box::new(FoldTracker::new(&tm.folds));
+        let fold_tracker = view
+            .buffer
+            .as_ref()
+            .unwrap()
+            .write()
+            .register_subscriber(fold_tracker);
+        view.mode_ctx_mut::<TextModeContext>("text-mode")
+            .fold_tracker = fold_tracker;
+
         // Config input map
         // dbg_println!("DEFAULT_INPUT_MAP\n{}", DEFAULT_INPUT_MAP);
         // TODO(ceg): user define
@@ -593,10 +701,21 @@ impl<'a> Mode for TextMode {
                 v.push("text/show-trailing-spaces");
             }
 
+            // very long lines
+            v.push("text/line-checkpoints"); // mandatory
+
+            if config_var_is_set(editor, "text-mode:folding", true) {
+                v.push("text/folding");
+            }
+
             if config_var_is_set(&editor, "text-mode:word-wrap", true) {
                 v.push("text/word-wrap");
             }
 
+            if config_var_is_set(&editor, "text-mode:bidi", true) {
+                v.push("text/bidi");
+            }
+
             //
             v.push("text/screen"); // mandatory
 
@@ -619,6 +738,9 @@ impl<'a> Mode for TextMode {
             if config_var_is_set(&editor, "text-mode:ruler", true) {
                 v.push("text/ruler");
             }
+            if config_var_is_set(&editor, "text-mode:highlight-matching-bracket", true) {
+                v.push("text/highlight-matching-bracket");
+            }
             v
         } else {
             vec![]
@@ -698,6 +820,35 @@ impl TextMode {
             ("text-mode:copy-selection", copy_selection),
             ("text-mode:cut-selection", cut_selection),
             ("text-mode:exchange-point-and-mark", exchange_point_and_mark),
+            // block selection
+            ("text-mode:block-select-up", block_select_up),
+            ("text-mode:block-select-down", block_select_down),
+            ("text-mode:block-select-left", block_select_left),
+            ("text-mode:block-select-right", block_select_right),
+            (
+                "text-mode:block-select-button-press",
+                block_select_button_press,
+            ),
+            ("text-mode:yank-block", yank_block),
+            // indentation
+            ("text-mode:indent", indent_lines),
+            ("text-mode:outdent", outdent_lines),
+            // brackets
+            ("text-mode:goto-matching-bracket", goto_matching_bracket),
+            ("text-mode:select-inside-brackets", select_inside_brackets),
+            // folding
+            ("text-mode:fold-indentation", fold_indentation),
+            ("text-mode:fold-braces", fold_braces),
+            ("text-mode:fold-selection", fold_selection),
+            ("text-mode:unfold", unfold),
+            ("text-mode:unfold-all", unfold_all),
+            // multi-cursor from search
+            (
+                "text-mode:add-mark-at-next-occurrence",
+                add_mark_at_next_occurrence,
+            ),
+            ("text-mode:skip-occurrence", skip_occurrence),
+            ("text-mode:mark-all-occurrences", mark_all_occurrences),
             // screen
             ("text-mode:page-up", scroll_to_previous_screen),
             ("text-mode:page-down", scroll_to_next_screen),
@@ -705,6 +856,7 @@ impl TextMode {
             ("text-mode:scroll-down", scroll_down),
             //
             ("text-mode:center-around-mark", center_around_mark),
+            ("text-mode:toggle-follow", toggle_follow),
             ("text-mode:move-mark-to-clicked-area", button_press),
             // edition
             ("text-mode:self-insert", insert_codepoint_array),
@@ -714,6 +866,10 @@ impl TextMode {
                 remove_previous_codepoint,
             ),
             ("text-mode:paste", paste),
+            ("text-mode:yank-pop", yank_pop),
+            ("text-mode:copy-to-register", copy_to_register),
+            ("text-mode:insert-register", insert_register),
+            ("text-mode:show-kill-ring", show_kill_ring),
             ("text-mode:cut-to-end-of-line", cut_to_end_of_line),
             ("text-mode:transpose-char", transpose_char),
             ("text-mode:move-line-up", move_line_up),
@@ -729,6 +885,7 @@ impl TextMode {
             ("text-mode:join-lines", join_lines),
             ("text-mode:move-selection-forward", move_selection_forward),
             ("text-mode:move-selection-backward", move_selection_backward),
+            ("text-mode:revert-hunk", revert_hunk),
             // undo/redo
             ("text-mode:undo", undo),
             ("text-mode:redo", redo),
@@ -747,6 +904,120 @@ impl TextMode {
     }
 }
 
+/// Returns the absolute path of the file edited in 'view' (None for internal buffers).
+fn view_file_path(view: &View<'static>) -> Option<String> {
+    let buffer = view.buffer()?;
+    let buffer = buffer.read();
+    if !buffer.use_buffer_log || buffer.file_name().is_empty() {
+        return None;
+    }
+    let path = std::fs::canonicalize(buffer.file_name()).ok()?;
+    Some(path.to_string_lossy().to_string())
+}
+
+/// Remember the position of 'view' in the editor's file history.
+pub fn record_view_position(editor: &mut Editor<'static>, view: &View<'static>) {
+    let path = match view_file_path(view) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let (undo_marks, undo_selections) = view
+        .buffer()
+        .and_then(|b| b.write().get_tag_offsets())
+        .unwrap_or_default();
+
+    let tm = view.mode_ctx::<TextModeContext>("text-mode");
+    editor.file_history.update(FileHistoryEntry {
+        path,
+        start_offset: view.start_offset,
+        marks: tm.marks.iter().map(|m| m.offset).collect(),
+        selections: tm.select_point.iter().map(|m| m.offset).collect(),
+        undo_marks,
+        undo_selections,
+        last_access: 0,
+    });
+}
+
+/// Start the screen of 'view' at an offset remembered in a previous run (file history, session).<br/>
+/// The file may have changed since: the screen starts at the start of the line of 'offset'.
+pub fn set_remembered_start_offset(view: &mut View<'static>, offset: u64) {
+    let start = match view.buffer() {
+        Some(buffer) => {
+            let buffer = buffer.read();
+            let offset = std::cmp::min(offset, buffer.size() as u64);
+            let tm = view.mode_ctx::<TextModeContext>("text-mode");
+            line_layout_start_offset(&buffer, tm.text_codec.as_ref(), offset)
+        }
+        None => return,
+    };
+
+    view.start_offset = start;
+}
+
+/// Restore the last known position of the file edited in 'view'.<br/>
+/// Only done for the first view of the buffer (not tagged yet) and
+/// if no position is given on the command line.<br/>
+/// Returns the marks and selections of the remembered undo tag.
+fn restore_view_position(
+    editor: &mut Editor<'static>,
+    view: &mut View<'static>,
+) -> Option<(Vec<u64>, Vec<u64>)> {
+    let path = view_file_path(view)?;
+
+    let max_offset = {
+        let buffer = view.buffer().unwrap();
+        let buffer = buffer.read();
+        let p = buffer.start_position;
+        if buffer.buffer_log_count() != 0 || p.line.is_some() || p.column.is_some() {
+            return None;
+        }
+        buffer.size() as u64
+    };
+
+    let entry = editor.file_history.get(&path).cloned();
+    editor.file_history.touch(&path);
+
+    let entry = entry?;
+
+    // keep the offset given on the command line
+    let tm = view.mode_ctx::<TextModeContext>("text-mode");
+    if tm.marks.len() != 1 || tm.marks[0].offset != 0 {
+        return None;
+    }
+
+    // the file may have changed
+    let clamp = |offsets: &[u64]| -> Vec<Mark> {
+        offsets
+            .iter()
+            .map(|o| Mark::new(std::cmp::min(*o, max_offset)))
+            .collect()
+    };
+
+    let marks = clamp(&entry.marks);
+    if marks.is_empty() {
+        return None;
+    }
+
+    set_remembered_start_offset(view, entry.start_offset);
+
+    let undo_tag = clamp(&entry.undo_marks);
+    let undo_tag = if undo_tag.is_empty() {
+        None
+    } else {
+        let offsets = |marks: Vec<Mark>| marks.iter().map(|m| m.offset).collect();
+        Some((offsets(undo_tag), offsets(clamp(&entry.undo_selections))))
+    };
+
+    let tm = view.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.marks = marks;
+    tm.select_point = clamp(&entry.selections);
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+
+    undo_tag
+}
+
 pub fn run_text_mode_actions_vec(
     editor: &mut Editor<'static>,
     env: &mut EditorEnv<'static>,
@@ -813,6 +1084,13 @@ pub fn run_text_mode_actions_vec(
             PostInputAction::CheckMarks => {
                 let v = &mut view.write();
                 let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+                // the marks can not stay in the folded regions
+                {
+                    let folds = tm.folds.read();
+                    for m in tm.marks.iter_mut() {
+                        m.offset = folds.skip(m.offset, false);
+                    }
+                }
                 tm.marks.dedup();
                 tm.mark_index = tm.marks.len().saturating_sub(1);
 
@@ -908,6 +1186,7 @@ pub fn run_text_mode_actions_vec(
                 let v = &mut view.write();
                 let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
                 tm.select_point.clear();
+                tm.block_selection = None;
             }
         }
     }
@@ -1071,6 +1350,7 @@ fn run_text_mode_actions(
             (editor::Stage::Compositing, editor::StagePosition::Pre) => {
                 // clear
                 let mut v = view.write();
+                follow_buffer_end(&mut v);
                 let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
                 tm.pre_compose_action.drain(..).collect()
             }
@@ -1124,6 +1404,8 @@ pub fn cancel_selection(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<Rw
     let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
 
     tm.select_point.clear();
+    tm.block_selection = None;
+    tm.register_op = None;
 }
 
 pub fn editor_cancel(editor: &mut Editor, env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
@@ -1194,6 +1476,19 @@ pub fn insert_codepoint_array(
         }
     };
 
+    // pending register operation ? the char is the register name
+    let register_op = {
+        let mut v = view.write();
+        let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+        tm.register_op.take()
+    };
+    if let Some(op) = register_op {
+        if array.len() == 1 {
+            run_register_op(editor, env, view, op, array[0]);
+        }
+        return;
+    }
+
     // buffer read only ?
     {
         let v = view.read();
@@ -1205,6 +1500,21 @@ pub fn insert_codepoint_array(
         }
     }
 
+    // tab with a multi-line selection: indent the selected lines
+    if array.len() == 1 && array[0] == '\t' {
+        let indent = {
+            let v = view.read();
+            let buffer = v.buffer().unwrap();
+            let buffer = buffer.read();
+            let tm = v.mode_ctx::<TextModeContext>("text-mode");
+            has_multi_line_selection(tm, &buffer)
+        };
+        if indent {
+            indent_lines(editor, env, view);
+            return;
+        }
+    }
+
     // check previous action: if previous action was a mark move -> tag new positions
     let save_marks = {
         let v = view.read();
@@ -1254,29 +1564,69 @@ pub fn insert_codepoint_array(
 
             let mut insert_ops = vec![];
 
+            let auto_indent = tm.auto_indent && array.len() == 1 && array[0] == '\n';
+            let expand_tab = !tm.indent.use_tabs && array.len() == 1 && array[0] == '\t';
+            let auto_close = tm.auto_close_pairs && array.len() == 1;
+
             // build operations vector
             // while updating marks
 
             for m in tm.marks.iter_mut() {
-                if m.offset < view_start {
-                    view_growth += utf8.len() as u64;
-                }
+                let before_view_start = m.offset < view_start;
 
                 m.offset += grow;
-                buffer.insert(m.offset, utf8.len(), &utf8);
+
+                // the inserted data can depend on the mark position
+                let pair = if auto_close {
+                    auto_close_pair_data(&buffer, codec, m.offset, array[0])
+                } else {
+                    None
+                };
+
+                let (data, advance) = if let Some(pair) = pair {
+                    pair
+                } else if auto_indent {
+                    // copy the leading blanks of the current line
+                    let line = line_start(&buffer, codec, m.offset);
+                    let mut data = utf8.clone();
+                    data.extend(line_indentation(&buffer, line, m.offset));
+                    let advance = data.len() as u64;
+                    (data, advance)
+                } else if expand_tab {
+                    let data = tab_insert_data(&buffer, codec, &tm.indent, m.offset);
+                    let advance = data.len() as u64;
+                    (data, advance)
+                } else {
+                    (utf8.clone(), utf8.len() as u64)
+                };
+
+                if data.is_empty() {
+                    // typed over an auto-inserted closing bracket
+                    m.offset += advance;
+                    offset = m.offset;
+                    continue;
+                }
+
+                if before_view_start {
+                    view_growth += data.len() as u64;
+                }
+
+                buffer.insert(m.offset, data.len(), &data);
+
+                let data_len = data.len() as u64;
 
                 // track insert operation
                 insert_ops.push(BufferOperation {
                     op_type: BufferOperationType::Insert,
-                    data: Some(Arc::new(utf8.clone())),
+                    data: Some(Arc::new(data)),
                     offset: m.offset,
                 });
 
-                m.offset += utf8.len() as u64;
+                m.offset += advance;
 
                 offset = m.offset; // TODO(ceg): remove this merge
 
-                grow += utf8.len() as u64;
+                grow += data_len;
             }
 
             // notify buffer subscriberss of insert ops
@@ -1363,13 +1713,13 @@ pub fn remove_previous_codepoint(
                 continue;
             }
 
+            // remove the whole grapheme cluster
+            let end_offset = m.offset;
             m.move_backward(&buffer, codec);
             dbg_println!("after move.backward m.offset= {}", m.offset);
 
-            let mut data = vec![];
-            buffer.read(m.offset, 4, &mut data);
-            let (_, _, size) = codec.decode(SyncDirection::Forward, &data, 0);
-            dbg_println!("read {} bytes", size);
+            let size = (end_offset - m.offset) as usize;
+            dbg_println!("remove {} bytes", size);
 
             let nr_removed = buffer.remove(m.offset, size, None);
             dbg_println!("nr_removed {} bytes", nr_removed);
@@ -1629,9 +1979,8 @@ pub fn remove_codepoint(
                 m.offset -= shrink;
             }
 
-            let mut data = Vec::with_capacity(4);
-            buffer.read(m.offset, data.capacity(), &mut data);
-            let (_, _, size) = codec.decode(SyncDirection::Forward, &data, 0);
+            // remove the whole grapheme cluster
+            let size = read_grapheme_cluster_size_forward(&buffer, m.offset, codec);
 
             if m.offset < view_start {
                 view_shrink += size as u64;
@@ -1965,7 +2314,7 @@ pub fn move_line_down(
 /// Skip blanks (if any) and remove until end of the word.
 /// TODO(ceg): handle ',' | ';' | '(' | ')' | '{' | '}'
 pub fn remove_until_end_of_word(
-    _editor: &mut Editor,
+    editor: &mut Editor,
     _env: &mut EditorEnv,
 
     view: &Rc<RwLock<View>>,
@@ -1986,10 +2335,15 @@ pub fn remove_until_end_of_word(
         return;
     }
 
+    // contiguous kills are appended to the same kill ring entry
+    let contiguous = editor.kill_ring.last_kill == Some(kill_ring_signature(&buffer, &tm.marks));
+    let mut chunks = Vec::with_capacity(tm.marks.len());
+
     let mut shrink: u64 = 0;
 
     for m in tm.marks.iter_mut() {
         if m.offset == max_size {
+            chunks.push(vec![]);
             continue;
         }
 
@@ -2047,13 +2401,22 @@ pub fn remove_until_end_of_word(
         }
 
         // remove [start, m[
-        let nr_removed = buffer.remove(start.offset, (m.offset - start.offset) as usize, None);
+        let mut removed = vec![];
+        let nr_removed = buffer.remove(
+            start.offset,
+            (m.offset - start.offset) as usize,
+            Some(&mut removed),
+        );
+        chunks.push(removed);
 
         shrink += nr_removed as u64;
 
         m.offset = start.offset;
     }
 
+    kill_ring_save(&mut editor.kill_ring, chunks, contiguous, false);
+    editor.kill_ring.last_kill = Some(kill_ring_signature(&buffer, &tm.marks));
+
     tm.pre_compose_action.push(PostInputAction::CheckMarks);
     tm.pre_compose_action.push(PostInputAction::CancelSelection); //TODO register last optype
                                                                   // if buffer changes cancel selection ?
@@ -2438,8 +2801,10 @@ pub fn cut_to_end_of_line(
 
     let codec = tm.text_codec.as_ref();
 
-    tm.copy_buffer.clear();
+    // contiguous kills are appended to the same kill ring entry
+    let contiguous = editor.kill_ring.last_kill == Some(kill_ring_signature(&buffer, &tm.marks));
 
+    let mut chunks = Vec::with_capacity(tm.marks.len());
     let mut remove_size = Vec::with_capacity(tm.marks.len());
     let single_mark = tm.marks.len() == 1;
 
@@ -2463,12 +2828,10 @@ pub fn cut_to_end_of_line(
 
         // remove data
         let size = (end.offset - m.offset) as usize;
-        buffer.remove(m.offset, size, None);
+        let mut data = Vec::with_capacity(size);
+        buffer.remove(m.offset, size, Some(&mut data));
         remove_size.insert(0, size);
-
-        // save transaction's index
-        tm.copy_buffer
-            .insert(0, CopyData::BufferLogIndex(buffer.buffer_log.pos - 1));
+        chunks.insert(0, data);
     }
 
     // update marks offsets
@@ -2480,7 +2843,10 @@ pub fn cut_to_end_of_line(
 
     // invariants
     let mlen = tm.marks.len();
-    assert!(tm.copy_buffer.len() == mlen);
+    assert!(chunks.len() == mlen);
+
+    kill_ring_save(&mut editor.kill_ring, chunks, contiguous, false);
+    editor.kill_ring.last_kill = Some(kill_ring_signature(&buffer, &tm.marks));
 
     tm.pre_compose_action.push(PostInputAction::CheckMarks);
     tm.pre_compose_action.push(PostInputAction::CancelSelection);
@@ -2561,8 +2927,6 @@ pub fn transpose_char(
 
     let codec = tm.text_codec.as_ref();
 
-    tm.copy_buffer.clear();
-
     let single_mark = tm.marks.len() == 1;
     if !single_mark {
         // no multi-char transpose now
@@ -2606,93 +2970,261 @@ pub fn transpose_char(
     tm.pre_compose_action.push(PostInputAction::CancelSelection);
 }
 
-pub fn paste(
+/// Returns the kill ring signature of the current buffer/marks state.
+pub fn kill_ring_signature(buffer: &Buffer, marks: &[Mark]) -> KillRingSignature {
+    KillRingSignature {
+        buffer_id: buffer.id,
+        nr_changes: buffer.nr_changes(),
+        marks_offsets: marks.iter().map(|m| m.offset).collect(),
+    }
+}
+
+/// Save the killed chunks (one per mark) in the kill ring.<br/>
+/// If 'append' is set the chunks are merged with the most recent entry.
+pub fn kill_ring_save(kill_ring: &mut KillRing, chunks: Vec<Vec<u8>>, append: bool, before: bool) {
+    if append {
+        kill_ring.append(chunks, before);
+    } else {
+        kill_ring.push(KillRingEntry::new(chunks));
+    }
+}
+
+/// Insert 'entry' at each mark and record the inserted ranges (used by yank-pop).
+fn insert_kill_ring_entry(
     mut editor: &mut Editor<'static>,
     mut env: &mut EditorEnv<'static>,
     view: &Rc<RwLock<View<'static>>>,
+    entry: &KillRingEntry,
 ) {
+    // buffer read only ?
     {
-        let v = &mut view.write();
-
-        // buffer read only ?
-        {
-            let buffer = v.buffer().unwrap();
-            let buffer = buffer.read();
-            if buffer.is_syncing {
-                return;
-            }
+        let v = view.read();
+        let buffer = v.buffer().unwrap();
+        let buffer = buffer.read();
+        if buffer.is_syncing {
+            return;
         }
+    }
 
-        let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    if entry.is_empty() {
+        return;
+    }
+
+    // save marks
+    run_text_mode_actions_vec(
+        &mut editor,
+        &mut env,
+        &view,
+        &vec![PostInputAction::SaveMarks { caller: &"paste" }],
+    );
+
+    let v = &mut view.write();
+
+    let mut buffer = v.buffer.clone();
+    let buffer = buffer.as_mut().unwrap();
+    let mut buffer = buffer.write();
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+
+    let marks = &mut tm.marks;
+    let marks_len = marks.len();
+
+    let mut ranges = Vec::with_capacity(marks_len);
+    let mut grow = 0;
+    for (midx, m) in marks.iter_mut().enumerate() {
+        m.offset += grow;
+
+        let data = entry.data_for_mark(midx, marks_len);
 
-        let marks = &mut tm.marks;
-        let marks_len = marks.len();
+        dbg_println!("paste @ offset {} data.len {}", m.offset, data.len());
 
-        dbg_println!("mark_len {}", marks_len);
+        let nr_in = buffer.insert(m.offset, data.len(), data.as_slice());
+        assert_eq!(nr_in, data.len());
+        ranges.push((m.offset, m.offset + nr_in as u64));
+        grow += nr_in as u64;
+        m.offset += nr_in as u64;
+    }
+
+    editor.kill_ring.last_kill = None;
+    editor.kill_ring.last_yank = Some((kill_ring_signature(&buffer, &tm.marks), ranges));
+
+    tm.prev_action = TextModeAction::BufferModification;
+
+    tm.pre_compose_action.push(PostInputAction::CancelSelection);
+    tm.pre_compose_action.push(PostInputAction::CheckMarks);
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+}
 
-        dbg_println!("copy_buffer.len() {}", tm.copy_buffer.len());
-        if tm.copy_buffer.is_empty() {
+/// Insert the current kill ring entry at each mark.
+pub fn paste(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let entry = {
+        editor.kill_ring.yank_index = 0;
+        match editor.kill_ring.current() {
+            Some(entry) => entry.clone(),
+            None => return,
+        }
+    };
+
+    insert_kill_ring_entry(editor, env, view, &entry);
+}
+
+/// Replace the previously yanked text by the next kill ring entry.<br/>
+/// Must be called just after paste/yank-pop.
+pub fn yank_pop(
+    mut editor: &mut Editor<'static>,
+    mut env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let ranges = {
+        let v = view.read();
+        let buffer = v.buffer().unwrap();
+        let buffer = buffer.read();
+        if buffer.is_syncing {
             return;
         }
-    }
+
+        let tm = v.mode_ctx::<TextModeContext>("text-mode");
+        match &editor.kill_ring.last_yank {
+            Some((sig, ranges)) if *sig == kill_ring_signature(&buffer, &tm.marks) => {
+                ranges.clone()
+            }
+            _ => return,
+        }
+    };
+
+    let entry = match editor.kill_ring.rotate(1) {
+        Some(entry) => entry.clone(),
+        None => return,
+    };
 
     // save marks
     run_text_mode_actions_vec(
         &mut editor,
         &mut env,
         &view,
-        &vec![PostInputAction::SaveMarks { caller: &"paste" }],
+        &vec![PostInputAction::SaveMarks {
+            caller: &"yank_pop",
+        }],
     );
 
-    {
-        let v = &mut view.write();
+    let v = &mut view.write();
 
-        let mut buffer = v.buffer.clone();
-        let buffer = buffer.as_mut().unwrap();
-        let mut buffer = buffer.write();
+    let mut buffer = v.buffer.clone();
+    let buffer = buffer.as_mut().unwrap();
+    let mut buffer = buffer.write();
 
-        let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    let marks_len = tm.marks.len();
 
-        let marks = &mut tm.marks;
-        let marks_len = marks.len();
+    let mut new_ranges = Vec::with_capacity(ranges.len());
+    let mut grow: i64 = 0;
+    for (midx, (start, end)) in ranges.iter().enumerate() {
+        // ranges are sorted: shift with previous size changes
+        let offset = (*start as i64 + grow) as u64;
 
-        let mut grow = 0;
-        for (midx, m) in marks.iter_mut().enumerate() {
-            m.offset += grow;
+        let nr_removed = buffer.remove(offset, (end - start) as usize, None);
 
-            if tm.copy_buffer.len() != marks_len {
-                // TODO(ceg): insert each tm.copy_buffer transaction + '\n'
-                // grow += each tr
-            } else {
-                let copy = &tm.copy_buffer[midx];
-                let data = match copy {
-                    CopyData::BufferLogIndex(tridx) => {
-                        let tr = buffer.buffer_log.data[*tridx].clone();
-                        if let Some(ref data) = tr.data {
-                            data.as_ref().clone()
-                        } else {
-                            panic!("wrong transaction index");
-                        }
-                    }
-                    CopyData::InnerBuffer(data) => data.clone(),
-                };
+        let data = entry.data_for_mark(midx, marks_len);
+        let nr_in = buffer.insert(offset, data.len(), data.as_slice());
 
-                dbg_println!("paste @ offset {} data.len {}", m.offset, data.len());
+        tm.marks[midx].offset = offset + nr_in as u64;
+        new_ranges.push((offset, offset + nr_in as u64));
 
-                let nr_in = buffer.insert(m.offset, data.len(), data.as_slice());
-                assert_eq!(nr_in, data.len());
-                grow += nr_in as u64;
-                m.offset += nr_in as u64;
-            }
+        grow += nr_in as i64 - nr_removed as i64;
+    }
+
+    editor.kill_ring.last_yank = Some((kill_ring_signature(&buffer, &tm.marks), new_ranges));
+
+    tm.pre_compose_action.push(PostInputAction::CheckMarks);
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+}
+
+/// The next inserted char will be the name of the register to copy the selection into.
+pub fn copy_to_register(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let v = &mut view.write();
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.register_op = Some(RegisterOp::CopyToRegister);
+}
+
+/// The next inserted char will be the name of the register to insert at each mark.
+pub fn insert_register(
+    _editor: &mut Editor<'static>,
+    _env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+) {
+    let v = &mut view.write();
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.register_op = Some(RegisterOp::InsertRegister);
+}
+
+fn run_register_op(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    view: &Rc<RwLock<View<'static>>>,
+    op: RegisterOp,
+    name: char,
+) {
+    match op {
+        RegisterOp::CopyToRegister => {
+            let chunks = {
+                let v = view.read();
+                let buffer = v.buffer().unwrap();
+                let buffer = buffer.read();
+
+                let tm = v.mode_ctx::<TextModeContext>("text-mode");
+                if tm.marks.len() != tm.select_point.len() {
+                    return;
+                }
+
+                let mut chunks = Vec::with_capacity(tm.marks.len());
+                for (m, s) in tm.marks.iter().zip(tm.select_point.iter()) {
+                    let (min, max) = sort_pair((m.offset, s.offset));
+                    let mut data = Vec::with_capacity((max - min) as usize);
+                    buffer.read(min, (max - min) as usize, &mut data);
+                    chunks.push(data);
+                }
+                chunks
+            };
+
+            editor
+                .kill_ring
+                .set_register(name, KillRingEntry::new(chunks));
+
+            let v = &mut view.write();
+            let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+            tm.select_point.clear();
         }
 
-        tm.pre_compose_action.push(PostInputAction::CancelSelection);
-        tm.pre_compose_action.push(PostInputAction::CheckMarks);
-        tm.pre_compose_action
-            .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+        RegisterOp::InsertRegister => {
+            let entry = match editor.kill_ring.get_register(name) {
+                Some(entry) => entry.clone(),
+                None => return,
+            };
+            insert_kill_ring_entry(editor, env, view, &entry);
+        }
     }
 }
 
+/// Toggle a pop-up listing the kill ring entries and the named registers.
+pub fn show_kill_ring(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    _view: &Rc<RwLock<View<'static>>>,
+) {
+    let text = editor.kill_ring.describe(64);
+    toggle_text_popup(editor, env, "kill-ring-pop-up", &text);
+}
+
 pub fn set_selection_points_at_marks(
     _editor: &mut Editor,
     _env: &mut EditorEnv,
@@ -2708,6 +3240,7 @@ pub fn set_selection_points_at_marks(
 
         // update selection point
         tm.select_point.clear();
+        tm.block_selection = None;
         for m in tm.marks.iter() {
             dbg_println!("{:?} set point @ offset {}", vid, m.offset);
             tm.select_point.push(m.clone());
@@ -2729,7 +3262,7 @@ pub fn set_selection_points_at_marks(
 }
 
 pub fn copy_maybe_remove_selection_symmetric(
-    _editor: &mut Editor,
+    editor: &mut Editor,
     _env: &mut EditorEnv,
 
     view: &Rc<RwLock<View>>,
@@ -2748,9 +3281,7 @@ pub fn copy_maybe_remove_selection_symmetric(
     let mut nr_bytes_copied = 0;
     let mut nr_bytes_removed = 0;
 
-    if copy {
-        tm.copy_buffer.clear();
-    }
+    let mut chunks = Vec::with_capacity(tm.marks.len());
 
     let mut shrink = 0;
     for (idx, m) in tm.marks.iter_mut().enumerate() {
@@ -2777,7 +3308,7 @@ pub fn copy_maybe_remove_selection_symmetric(
             );
 
             assert_eq!(nr_read, data.len());
-            tm.copy_buffer.push(CopyData::InnerBuffer(data));
+            chunks.push(data);
 
             nr_bytes_copied += nr_read;
         }
@@ -2787,14 +3318,18 @@ pub fn copy_maybe_remove_selection_symmetric(
             shrink += data_size as u64;
             nr_bytes_removed += data_size;
 
-            if m.offset > tm.select_point[idx].offset {
-                m.offset = m.offset.saturating_sub(nr_bytes_removed as u64);
-            }
+            m.offset = min;
         }
     }
 
+    if copy {
+        editor.kill_ring.push(KillRingEntry::new(chunks));
+        editor.kill_ring.last_kill = None;
+    }
+
     if nr_bytes_copied + nr_bytes_removed > 0 {
         tm.select_point.clear();
+        tm.block_selection = None;
     }
 
     (nr_bytes_copied, nr_bytes_removed)
@@ -2979,6 +3514,7 @@ pub fn button_press(
         let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
 
         tm.select_point.clear();
+        tm.block_selection = None;
 
         for i in (0..=x).rev() {
             if let Some(cpi) = screen.get_cpinfo(i, y) {
@@ -3060,7 +3596,7 @@ pub fn button_release(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLo
         tm.button_state[button as usize] = 0;
 
         // swap selection point and mark and button release
-        if tm.marks.len() == 1 && tm.select_point.len() == 1 {
+        if tm.block_selection.is_none() && tm.marks.len() == 1 && tm.select_point.len() == 1 {
             std::mem::swap(&mut tm.marks[0].offset, &mut tm.select_point[0].offset);
         }
     }
@@ -3068,6 +3604,15 @@ pub fn button_release(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLo
 
 // TODO(ceg): add enter /leave clipped region detection
 pub fn pointer_motion(_editor: &mut Editor, _env: &mut EditorEnv, view: &Rc<RwLock<View>>) {
+    if !view.read().check_mode_ctx::<TextModeContext>("text-mode") {
+        return;
+    }
+
+    // alt + drag
+    if block_select_pointer_motion(view) {
+        return;
+    }
+
     let v = &mut view.write();
     let screen = v.screen.clone();
     let screen = screen.read();
@@ -3300,7 +3845,7 @@ pub fn get_lines_offsets_direct(
 
         // the next screen start is the offset past the last line last offset
         let l = screen.get_last_used_line().unwrap();
-        tmp.offset = 1 + l[l.len() - 1].cpi.offset.unwrap();
+        tmp.offset = 1 + line_logical_last(l).cpi.offset.unwrap();
 
         screen.clear(); // prepare next screen
     } // END LOOP
@@ -3391,7 +3936,7 @@ pub fn scroll_view_down(
         for idx in 0..=(h - nb_lines) {
             // just read the current screen
             if let Some(l) = v.screen.write().get_line(nb_lines + idx) {
-                let cpi = &l[0].cpi;
+                let cpi = &line_logical_first(l).cpi;
                 // set first offset of screen.line[nb_lines] as next screen start
                 if let Some(offset) = cpi.offset {
                     if offset > v.start_offset {
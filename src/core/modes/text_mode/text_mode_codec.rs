@@ -60,10 +60,14 @@ impl ContentFilter<'_> for TextCodecFilter {
                         offset: Some(base_offset),
                         size: 0, // count(data) ?
                         data: FilterData::UnicodeArray { vec: decoded },
+                        bidi_level: 0,
                     });
                 }
 
-                FilterData::EndOfStream | FilterData::CustomLimitReached => {
+                // already decoded: folded bytes (see build_fold_io)
+                FilterData::EndOfStream
+                | FilterData::CustomLimitReached
+                | FilterData::TextInfo { .. } => {
                     filter_out.push(d.clone());
                 }
 
@@ -240,7 +244,8 @@ pub fn filter_utf8_bytearray(
         data: FilterData::UnicodeArray {
             vec: ctx.out.clone(),
         },
-        // TODO(ceg): add style infos ?
+        // TODO(ceg): add style infos ?,
+        bidi_level: 0,
     };
 
     filter_out.push(new_io);
@@ -314,6 +319,11 @@ impl ContentFilter<'_> for Utf8Filter {
                     filter_out.push(d.clone());
                 }
 
+                // already decoded: folded bytes (see build_fold_io)
+                FilterData::TextInfo { .. } => {
+                    filter_out.push(d.clone());
+                }
+
                 _ => {
                     /* unexpected */
                     dbg_println!("receive unexpected io {:?}", d.data);
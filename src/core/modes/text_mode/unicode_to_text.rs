@@ -65,7 +65,8 @@ impl ContentFilter<'_> for UnicodeToTextFilter {
                                 real_cp: e.cp,
                                 displayed_cp: e.cp,
                             },
-                            // TODO(ceg): add style infos ?
+                            // TODO(ceg): add style infos ?,
+                            bidi_level: 0,
                         };
 
                         filter_out.push(new_io);
@@ -82,6 +83,17 @@ impl ContentFilter<'_> for UnicodeToTextFilter {
                     filter_out.push(io.clone());
                 }
 
+                // the folded bytes are not decoded (see build_fold_io)
+                FilterIo {
+                    data: FilterData::TextInfo { .. },
+                    offset: Some(offset),
+                    size,
+                    ..
+                } => {
+                    filter_out.push(io.clone());
+                    self.cur_offset = offset + *size as u64;
+                }
+
                 _ => {
                     // unhandled input type -> forward
                     filter_out.push(io.clone());
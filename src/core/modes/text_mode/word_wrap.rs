@@ -27,6 +27,8 @@ use crate::core::EditorEnv;
 use crate::core::view::View;
 
 use crate::core::codec::text::u32_to_char;
+use crate::core::codec::text::utf8::extends_grapheme_cluster;
+use crate::core::screen::grapheme_cluster_extra_width;
 
 use crate::core::codepointinfo::TextStyle;
 
@@ -78,6 +80,9 @@ pub struct WordWrapFilter {
 
     prev_offset: Option<u64>,
 
+    /// the codepoints of the current grapheme cluster
+    cluster: String,
+
     lines: Vec<Vec<WordWrapElement>>,
     line_index: usize,
 }
@@ -93,6 +98,7 @@ impl WordWrapFilter {
             column_count: 0,
             accum: vec![],
             prev_offset: None,
+            cluster: String::new(),
             lines: vec![Vec::new()],
             line_index: 0,
         }
@@ -108,6 +114,7 @@ impl WordWrapFilter {
         self.accum = Vec::new();
 
         self.prev_offset = None;
+        self.cluster.clear();
 
         self.lines = vec![Vec::new()];
         self.line_index = 0;
@@ -127,6 +134,7 @@ fn build_wrap_point_new_line(blank_offset: Option<u64>) -> FilterIo {
             real_cp: '\n' as u32,
             displayed_cp: ' ' as u32,
         },
+        bidi_level: 0,
     }
 }
 
@@ -140,6 +148,7 @@ fn build_wrap_point_delimiter(blank_offset: Option<u64>) -> FilterIo {
             real_cp: '\\' as u32,
             displayed_cp: '\\' as u32,
         },
+        bidi_level: 0,
     };
 
     io.style.color = (255, 255, 0);
@@ -215,7 +224,22 @@ impl ContentFilter<'_> for WordWrapFilter {
                     // prepare element info
                     let c = u32_to_char(*displayed_cp);
                     let real_c = u32_to_char(*real_cp);
-                    let c_width = env.screen.char_width(c) as u64;
+                    // the screen displays a grapheme cluster in its first codepoint cells
+                    let c_width = if !io.metadata
+                        && c == real_c
+                        && !real_c.is_control()
+                        && extends_grapheme_cluster(&self.cluster, real_c)
+                    {
+                        let width = grapheme_cluster_extra_width(&self.cluster, real_c);
+                        self.cluster.push(real_c);
+                        width as u64
+                    } else {
+                        self.cluster.clear();
+                        if !io.metadata {
+                            self.cluster.push(real_c);
+                        }
+                        env.screen.char_width(c) as u64
+                    };
                     let io = io.clone();
 
                     let e = WordWrapElement::new(io, c, c_width as u32, offset);
@@ -7,6 +7,7 @@ use super::Mode;
 
 use crate::core::codepointinfo::CodepointInfo;
 
+use crate::core::editor::config_var_is_set;
 use crate::core::editor::get_view_by_id;
 use crate::core::editor::register_input_stage_action;
 use crate::core::editor::set_focus_on_view_id;
@@ -30,6 +31,13 @@ use crate::core::view::ViewEventSource;
 
 use crate::core::event::*;
 
+use crate::core::modes::find_mode::FindModeContext;
+use crate::core::modes::overview::*;
+use crate::core::modes::text_mode::get_layout_start_offset;
+use crate::core::modes::text_mode::run_text_mode_actions_vec;
+use crate::core::modes::text_mode::PostInputAction;
+use crate::core::modes::text_mode::TextModeContext;
+
 static VSCROLLBAR_INPUT_MAP: &str = r#"
 [
   {
@@ -44,6 +52,15 @@ static VSCROLLBAR_INPUT_MAP: &str = r#"
 pub struct VscrollbarMode {
     // add common fields
 }
+
+/// The ticks of the overview, by increasing priority.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum OverviewTick {
+    Modified,
+    Match,
+    Mark,
+}
+
 pub struct VscrollbarModeContext {
     pub target_view_id: view::Id,
 
@@ -54,6 +71,19 @@ pub struct VscrollbarModeContext {
     pub selected: bool,
     pub pointer_over: bool,
     pub t: std::time::Instant,
+    /// draw the overview of the whole buffer instead of an empty column
+    pub overview: bool,
+    pub overview_density: Vec<Option<u64>>,
+    pub overview_ticks: Vec<Option<OverviewTick>>,
+    pub overview_matches: Option<OverviewMatches>,
+}
+
+/// The matches of the search drawn by the overview.
+pub struct OverviewMatches {
+    /// the buffer revision, the pattern and the height of the overview
+    key: (usize, Vec<u8>, usize),
+    /// None if the buffer is too large to be scanned
+    ranges: Option<Vec<(u64, u64)>>,
 }
 
 impl<'a> Mode for VscrollbarMode {
@@ -78,24 +108,34 @@ impl<'a> Mode for VscrollbarMode {
             selected: false,
             pointer_over: false,
             t: std::time::Instant::now(),
+            overview: false,
+            overview_density: vec![],
+            overview_ticks: vec![],
+            overview_matches: None,
         };
         Box::new(ctx)
     }
 
     fn configure_view(
         &mut self,
-        _editor: &mut Editor<'static>,
+        editor: &mut Editor<'static>,
         _env: &mut EditorEnv<'static>,
         view: &mut View<'static>,
     ) {
-        // setup input map for core actions
-        let input_map = build_input_event_map(VSCROLLBAR_INPUT_MAP).unwrap();
-        let mut input_map_stack = view.input_ctx.input_map.as_ref().borrow_mut();
-        input_map_stack.push((self.name(), input_map));
+        {
+            // setup input map for core actions
+            let input_map = build_input_event_map(VSCROLLBAR_INPUT_MAP).unwrap();
+            let mut input_map_stack = view.input_ctx.input_map.as_ref().borrow_mut();
+            input_map_stack.push((self.name(), input_map));
+        }
 
         view.compose_content_filters
             .borrow_mut()
             .push(Box::new(VscrollbarModeComposeFilter::new()));
+
+        let overview = config_var_is_set(editor, "vscrollbar-mode:overview", false);
+        let mode_ctx = view.mode_ctx_mut::<VscrollbarModeContext>("vscrollbar-mode");
+        mode_ctx.overview = overview;
     }
 
     fn on_view_event(
@@ -148,9 +188,7 @@ impl<'a> Mode for VscrollbarMode {
 
                 let mode_ctx = dst.mode_ctx_mut::<VscrollbarModeContext>("vscrollbar-mode");
 
-                let buffer = src.buffer.as_ref().unwrap();
-                let buffer = buffer.read();
-                let buffer_size = buffer.size();
+                let buffer_size = src.buffer.as_ref().unwrap().read().size();
 
                 let off = src.start_offset as f64 / buffer_size as f64;
                 let off2 = src.end_offset as f64 / buffer_size as f64;
@@ -170,6 +208,13 @@ impl<'a> Mode for VscrollbarMode {
                 };
                 mode_ctx.scroll_end = std::cmp::min(dim.1, mode_ctx.scroll_end);
 
+                if mode_ctx.overview && src.check_mode_ctx::<TextModeContext>("text-mode") {
+                    let (density, ticks) =
+                        build_overview(src, dim.1, &mut mode_ctx.overview_matches);
+                    mode_ctx.overview_density = density;
+                    mode_ctx.overview_ticks = ticks;
+                }
+
                 dbg_println!("SCROLLBAR: mode_ctx.percent {}", mode_ctx.percent);
                 dbg_println!("SCROLLBAR: mode_ctx.percent_end {}", mode_ctx.percent_end);
             }
@@ -228,15 +273,27 @@ pub fn vscrollbar_input_event(
                 dbg_println!("VSCROLLBAR btn press evt {} {}x{}", button, x, y);
 
                 if *button == 0 {
-                    let y = *y as usize;
+                    let y = std::cmp::max(0, *y) as usize;
+                    let height = v.screen.read().dimension().1;
                     let mode_ctx = v.mode_ctx_mut::<VscrollbarModeContext>("vscrollbar-mode");
                     if y >= mode_ctx.scroll_start && y < mode_ctx.scroll_end {
                         mode_ctx.selected = true;
                         env.focus_locked_on_view_id = Some(v.id);
+                    } else if mode_ctx.overview && mode_ctx.target_view_id != view::Id(0) {
+                        // click-to-jump
+                        let target_view_id = mode_ctx.target_view_id;
+                        overview_jump(editor, env, target_view_id, y, height);
                     }
 
                     return;
                 }
+
+                if *button == 1 {
+                    // toggle the overview
+                    let mode_ctx = v.mode_ctx_mut::<VscrollbarModeContext>("vscrollbar-mode");
+                    mode_ctx.overview = !mode_ctx.overview;
+                    return;
+                }
             }
         },
 
@@ -343,6 +400,42 @@ pub fn vscrollbar_input_event(
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
+static OVERVIEW_DENSITY_CHARS: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// Draws the lines density of each row and the ticks over it.
+fn draw_overview(mode_ctx: &VscrollbarModeContext, env: &mut LayoutEnv) {
+    let max = mode_ctx
+        .overview_density
+        .iter()
+        .map(|d| d.unwrap_or(0))
+        .max()
+        .unwrap_or(0);
+
+    for (i, d) in mode_ctx.overview_density.iter().enumerate() {
+        let level = match (d, max) {
+            (Some(d), max) if max > 0 => (4 * d).div_ceil(max) as usize,
+            _ => 0,
+        };
+        if let Some(cpi) = env.screen.get_cpinfo_mut(0, i) {
+            cpi.displayed_cp = OVERVIEW_DENSITY_CHARS[std::cmp::min(level, 4)];
+            cpi.style.color = (70, 80, 110);
+        }
+    }
+
+    for (i, tick) in mode_ctx.overview_ticks.iter().enumerate() {
+        let color = match tick {
+            Some(OverviewTick::Modified) => (230, 160, 60),
+            Some(OverviewTick::Match) => (90, 210, 120),
+            Some(OverviewTick::Mark) => (235, 235, 235),
+            None => continue,
+        };
+        if let Some(cpi) = env.screen.get_cpinfo_mut(0, i) {
+            cpi.displayed_cp = '━';
+            cpi.style.color = color;
+        }
+    }
+}
+
 pub struct VscrollbarModeComposeFilter {}
 
 impl VscrollbarModeComposeFilter {
@@ -393,13 +486,19 @@ impl ContentFilter<'_> for VscrollbarModeComposeFilter {
         //     }
         // }
 
+        if mode_ctx.overview {
+            draw_overview(mode_ctx, env);
+        }
+
         dbg_println!("SCROLLBAR height {}", env.screen.height());
         dbg_println!("SCROLLBAR start {}", mode_ctx.scroll_start);
         dbg_println!("SCROLLBAR end {}", mode_ctx.scroll_end);
 
         for i in mode_ctx.scroll_start..mode_ctx.scroll_end {
             if let Some(cpi) = env.screen.get_cpinfo_mut(0, i) {
-                cpi.displayed_cp = ' ';
+                if !mode_ctx.overview {
+                    cpi.displayed_cp = ' ';
+                }
 
                 if mode_ctx.selected {
                     cpi.style.bg_color = (34, 167, 242);
@@ -418,3 +517,115 @@ impl ContentFilter<'_> for VscrollbarModeComposeFilter {
 
     fn finish(&mut self, _view: &View, _env: &mut LayoutEnv) {}
 }
+
+/// Returns the density and the ticks of each row of the overview of the 'src' text view.
+fn build_overview(
+    src: &View<'static>,
+    height: usize,
+    matches: &mut Option<OverviewMatches>,
+) -> (Vec<Option<u64>>, Vec<Option<OverviewTick>>) {
+    let buffer = src.buffer().unwrap();
+    let overview = get_buffer_overview(&buffer);
+
+    let buffer = buffer.read();
+    let mut overview = overview.write();
+    if !buffer.changed {
+        overview.clear_modified();
+    }
+    overview.sync(&buffer);
+
+    let density = overview.rows(height).to_vec();
+
+    let total = buffer.size() as u64;
+    let mut ticks = vec![None; height];
+    let mut set_tick = |min: u64, max: u64, tick: OverviewTick| {
+        if height == 0 {
+            return;
+        }
+        let first = overview_offset_to_row(min, total, height);
+        let last = overview_offset_to_row(std::cmp::max(min, max.saturating_sub(1)), total, height);
+        for t in ticks.iter_mut().take(last + 1).skip(first) {
+            if *t < Some(tick) {
+                *t = Some(tick);
+            }
+        }
+    };
+
+    for (min, max) in overview.modified() {
+        set_tick(*min, *max, OverviewTick::Modified);
+    }
+
+    if src.check_mode_ctx::<FindModeContext>("find-mode") {
+        let fm = src.mode_ctx::<FindModeContext>("find-mode");
+        if let (true, Some(min)) = (fm.active, fm.match_start) {
+            let max = fm.match_end.unwrap_or(min);
+
+            // the matched data is the encoded pattern
+            let mut pattern = vec![];
+            buffer.read(min, (max - min) as usize, &mut pattern);
+
+            let key = (buffer.nr_changes(), pattern, height);
+            if matches.as_ref().map(|m| &m.key) != Some(&key) {
+                let ranges = find_overview_matches(&buffer, &key.1, height);
+                *matches = Some(OverviewMatches { key, ranges });
+            }
+
+            // the current match only if the buffer is too large
+            match matches.as_ref().and_then(|m| m.ranges.as_ref()) {
+                Some(ranges) => {
+                    for (min, max) in ranges {
+                        set_tick(*min, *max, OverviewTick::Match);
+                    }
+                }
+                None => set_tick(min, max, OverviewTick::Match),
+            }
+        }
+    }
+
+    let tm = src.mode_ctx::<TextModeContext>("text-mode");
+    if let Some(occurrence) = &tm.occurrence {
+        let max = occurrence.last_match + occurrence.pattern.len() as u64;
+        set_tick(occurrence.last_match, max, OverviewTick::Match);
+    }
+    for m in tm.marks.iter() {
+        set_tick(m.offset, m.offset, OverviewTick::Mark);
+    }
+
+    (density, ticks)
+}
+
+/// Moves the main mark of the target view to the start of the screen line covered by 'row',
+/// the other marks are kept.
+fn overview_jump(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    target_view_id: view::Id,
+    row: usize,
+    height: usize,
+) {
+    let text_view = get_view_by_id(editor, target_view_id);
+    let offset = {
+        let v = text_view.read();
+        let buffer = v.buffer().unwrap();
+        let total = buffer.read().size() as u64;
+        overview_row_to_offset(row, total, height)
+    };
+    let offset = get_layout_start_offset(&text_view, offset);
+
+    dbg_println!("OVERVIEW jump to row {} offset {}", row, offset);
+
+    {
+        let mut v = text_view.write();
+        v.start_offset = offset;
+
+        let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+        tm.marks[tm.mark_index].offset = offset;
+    }
+
+    run_text_mode_actions_vec(
+        editor,
+        env,
+        &text_view,
+        &vec![PostInputAction::CenterAroundMainMark],
+    );
+}
@@ -1,6 +1,11 @@
 extern crate unicode_width;
 
 use unicode_width::UnicodeWidthChar;
+use unicode_width::UnicodeWidthStr;
+
+use crate::core::bidi::reorder_line;
+use crate::core::codec::text::utf8::extends_grapheme_cluster;
+use crate::core::codec::text::utf8::MAX_GRAPHEME_CLUSTER_CODEPOINTS;
 
 use crate::core::codepointinfo::CodepointInfo;
 use crate::core::codepointinfo::TextStyle;
@@ -20,17 +25,54 @@ pub fn toggle_screen_checks() {
     SCREEN_CHECK_FLAG.store(!v, Ordering::Relaxed);
 }
 
+/// Returns the number of columns added by the codepoint 'c' to the grapheme cluster 'cluster'.
+pub fn grapheme_cluster_extra_width(cluster: &str, c: char) -> usize {
+    let width = UnicodeWidthStr::width(cluster).clamp(1, 2);
+    let mut s = String::with_capacity(cluster.len() + c.len_utf8());
+    s.push_str(cluster);
+    s.push(c);
+    UnicodeWidthStr::width(s.as_str())
+        .clamp(1, 2)
+        .saturating_sub(width)
+}
+
+/// Returns the logical first cell of a line (the lowest offset).<br/>
+/// The cells of a line containing right-to-left text are stored in visual order.
+pub fn line_logical_first(line: &[ScreenCell]) -> &ScreenCell {
+    if !line.iter().any(|c| c.cpi.bidi_level > 0) {
+        return &line[0];
+    }
+    line.iter()
+        .filter(|c| c.cpi.offset.is_some())
+        .min_by_key(|c| c.cpi.offset)
+        .unwrap_or(&line[0])
+}
+
+/// Returns the logical last cell of a line (the highest offset).
+pub fn line_logical_last(line: &[ScreenCell]) -> &ScreenCell {
+    if !line.iter().any(|c| c.cpi.bidi_level > 0) {
+        return &line[line.len() - 1];
+    }
+    line.iter()
+        .filter(|c| c.cpi.offset.is_some())
+        .max_by_key(|c| c.cpi.offset)
+        .unwrap_or(&line[line.len() - 1])
+}
+
 /// A LineCell encapsulates code point information (CodepoinInfo).<br/>
 /// The displayed Lines are composed of LineCell
 #[derive(Hash, Default, Debug, Clone, Eq, PartialEq)]
 pub struct ScreenCell {
     pub cpi: CodepointInfo,
+    /// the codepoints following cpi.cp in its grapheme cluster (combining marks, joiners, ...)
+    pub cluster: String,
 }
 
 impl ScreenCell {
     pub fn new() -> Self {
         ScreenCell {
             cpi: CodepointInfo::new(),
+            cluster: String::new(),
         }
     }
 }
@@ -68,6 +110,16 @@ pub struct Screen {
     pub line_index: Vec<(usize, usize)>,
 
     pub style: TextStyle,
+
+    /// the codepoints of the last pushed grapheme cluster
+    cluster: String,
+    /// index of the first cell of the last pushed grapheme cluster
+    cluster_index: usize,
+    /// offset following the last pushed grapheme cluster
+    cluster_end: Option<u64>,
+
+    /// the current line contains right-to-left cells
+    line_has_bidi: bool,
 }
 
 impl Screen {
@@ -100,6 +152,10 @@ impl Screen {
             line_offset: Vec::with_capacity(height),
             line_index: Vec::with_capacity(height),
             style: TextStyle::new(),
+            cluster: String::new(),
+            cluster_index: 0,
+            cluster_end: None,
+            line_has_bidi: false,
         }
     }
 
@@ -134,6 +190,11 @@ impl Screen {
                 continue;
             }
 
+            // reordered (right-to-left) cells
+            if cell.cpi.bidi_level > 0 {
+                continue;
+            }
+
             if let (Some(cur), Some(cpi_offset)) = (cur_offset, cell.cpi.offset) {
                 if cpi_offset < cur {
                     panic!("cpi_offset {} < cur {}", cpi_offset, cur);
@@ -146,9 +207,13 @@ impl Screen {
     pub fn copy_screen_at_xy(&mut self, src: &Screen, x: usize, y: usize) -> bool {
         for src_y in 0..src.height() {
             for src_x in 0..src.width() {
-                if let Some(cpi_src) = src.get_cpinfo(src_x, src_y) {
-                    if let Some(cpi_dst) = self.get_cpinfo_mut(x + src_x, y + src_y) {
-                        *cpi_dst = *cpi_src;
+                let cell_src = match src.get_line(src_y) {
+                    Some(line) => &line[src_x],
+                    None => continue,
+                };
+                if let Some(line) = self.get_line_mut(y + src_y) {
+                    if let Some(cell_dst) = line.get_mut(x + src_x) {
+                        cell_dst.clone_from(cell_src);
                     }
                 }
             }
@@ -189,6 +254,10 @@ impl Screen {
 
         self.line_offset.clear();
         self.line_index.clear();
+
+        self.cluster.clear();
+        self.cluster_end = None;
+        self.line_has_bidi = false;
     }
 
     pub fn push_available(&self) -> usize {
@@ -216,6 +285,10 @@ impl Screen {
             return self.current_line_index;
         }
 
+        if self.line_has_bidi {
+            self.reorder_current_line();
+        }
+
         let used = self.width - self.current_line_remain;
         let skip = self.width - used;
 
@@ -253,6 +326,11 @@ impl Screen {
         // internal cpi
         let mut cpi = *cpi;
 
+        // continuation of the previous grapheme cluster ?
+        if self.extend_cluster(&cpi) {
+            return (true, self.current_line_index);
+        }
+
         // TODO(ceg):  cpi.check_invariants();
         if false {
             if !cpi.metadata && cpi.size == 0 {
@@ -384,6 +462,7 @@ impl Screen {
             //self.buffer[self.push_count].cpi = cpi;
             let cell = self.buffer.get_unchecked_mut(self.push_count);
             cell.cpi = cpi;
+            cell.cluster.clear();
 
             for i in 1..unicode_width {
                 //self.buffer[self.push_count + i].cpi = cpi;
@@ -392,9 +471,23 @@ impl Screen {
                 let cell = self.buffer.get_unchecked_mut(self.push_count + i);
                 cell.cpi = cpi;
                 cell.cpi.skip_render = true;
+                cell.cluster.clear();
             }
         }
 
+        if cpi.bidi_level > 0 {
+            self.line_has_bidi = true;
+        }
+
+        // start a new grapheme cluster
+        self.cluster.clear();
+        self.cluster_end = None;
+        if let (Some(offset), false) = (cpi.offset, cpi.metadata) {
+            self.cluster.push(cpi.cp);
+            self.cluster_index = self.push_count;
+            self.cluster_end = Some(offset + cpi.size as u64);
+        }
+
         self.last_offset = cpi.offset;
 
         self.push_count += unicode_width;
@@ -406,9 +499,102 @@ impl Screen {
         (true, self.current_line_index)
     }
 
+    /// Attach 'cpi' to the cells of the last pushed grapheme cluster if it belongs to it.<br/>
+    /// The cells of the cluster then cover the cluster bytes, the cluster is widened if
+    /// its display width grows (emoji presentation, flags) and if the line has room for it.
+    fn extend_cluster(&mut self, cpi: &CodepointInfo) -> bool {
+        let offset = match (cpi.offset, self.cluster_end) {
+            (Some(offset), Some(end)) if offset == end => offset,
+            _ => return false,
+        };
+
+        // controls are displayed apart (cr lf)
+        if cpi.metadata || cpi.cp.is_control() || cpi.cp != cpi.displayed_cp {
+            return false;
+        }
+
+        if self.cluster.chars().count() >= MAX_GRAPHEME_CLUSTER_CODEPOINTS
+            || !extends_grapheme_cluster(&self.cluster, cpi.cp)
+        {
+            return false;
+        }
+
+        self.cluster.push(cpi.cp);
+        self.cluster_end = Some(offset + cpi.size as u64);
+
+        let base = self.cluster_index;
+        let mut width = 1;
+        while base + width < self.push_count && self.buffer[base + width].cpi.skip_render {
+            width += 1;
+        }
+
+        let cluster_width = UnicodeWidthStr::width(self.cluster.as_str()).clamp(1, 2);
+        if cluster_width > width
+            && base + width == self.push_count
+            && base / self.width == self.current_line_index
+            && cluster_width - width <= self.current_line_remain
+        {
+            let extra = cluster_width - width;
+            for i in 0..extra {
+                let mut skip = self.buffer[base].cpi;
+                skip.skip_render = true;
+                let cell = &mut self.buffer[self.push_count + i];
+                cell.cpi = skip;
+                cell.cluster.clear();
+            }
+            width = cluster_width;
+            self.push_count += extra;
+            self.current_line_remain -= extra;
+            if self.current_line_remain == 0 {
+                self.select_next_line_index();
+            }
+        }
+
+        for cell in self.buffer[base..base + width].iter_mut() {
+            cell.cpi.size += cpi.size;
+        }
+        self.buffer[base].cluster.push(cpi.cp);
+
+        true
+    }
+
+    /// Display the cells of the current line in visual order (bidi rule L2).<br/>
+    /// A cell and the cells it covers (wide characters) are moved together.
+    fn reorder_current_line(&mut self) {
+        self.line_has_bidi = false;
+
+        let start = self.current_line_index * self.width;
+        let used = self.width - self.current_line_remain;
+        let cells = &mut self.buffer[start..start + used];
+
+        let mut units = vec![];
+        let mut levels = vec![];
+        let mut k = 0;
+        while k < cells.len() {
+            let unit_start = k;
+            k += 1;
+            while k < cells.len() && cells[k].cpi.skip_render {
+                k += 1;
+            }
+            units.push(unit_start..k);
+            levels.push(cells[unit_start].cpi.bidi_level);
+        }
+
+        let mut reordered = Vec::with_capacity(used);
+        for u in reorder_line(&levels) {
+            reordered.extend_from_slice(&cells[units[u].clone()]);
+        }
+        cells.clone_from_slice(&reordered);
+    }
+
     // call this to compute line start/end offsets, line_indexes
     // TODO: move all push(..) code that deal with metadata
-    pub fn finalize(&mut self) {}
+    pub fn finalize(&mut self) {
+        // the last line is not complete
+        if self.line_has_bidi && self.current_line_index < self.height {
+            self.reorder_current_line();
+        }
+    }
 
     pub fn append(&mut self, cpi_vec: &Vec<CodepointInfo>) -> (usize, usize, Option<u64>) {
         for (idx, cpi) in cpi_vec.iter().enumerate() {
@@ -442,6 +628,10 @@ impl Screen {
 
         self.line_offset.clear();
         self.line_index.clear();
+
+        self.cluster.clear();
+        self.cluster_end = None;
+        self.line_has_bidi = false;
     }
 
     pub fn has_eof(&self) -> bool {
@@ -616,3 +806,36 @@ pub fn screen_line_apply<F: FnMut(usize, &mut [ScreenCell]) -> bool>(
 // TODO(ceg): test are broken
 #[test]
 fn test_screen() {}
+
+#[test]
+fn test_screen_grapheme_clusters() {
+    let mut screen = Screen::new(10, 2);
+
+    let mut offset = 0;
+    for c in "e\u{301}\u{1f1eb}\u{1f1f7}a".chars() {
+        let mut cpi = CodepointInfo::new();
+        cpi.used = true;
+        cpi.metadata = false;
+        cpi.cp = c;
+        cpi.displayed_cp = c;
+        cpi.offset = Some(offset);
+        cpi.size = c.len_utf8();
+        offset += cpi.size as u64;
+        assert!(screen.push(&cpi).0);
+    }
+
+    let line = screen.get_line(0).unwrap();
+    // e + accent: one cell
+    assert_eq!(line[0].cpi.cp, 'e');
+    assert_eq!(line[0].cluster, "\u{301}");
+    assert_eq!(line[0].cpi.size, 3);
+    // flag: two cells
+    assert_eq!(line[1].cpi.cp, '\u{1f1eb}');
+    assert_eq!(line[1].cluster, "\u{1f1f7}");
+    assert!(line[2].cpi.skip_render);
+    assert_eq!(line[1].cpi.size, 8);
+    // next cluster
+    assert_eq!(line[3].cpi.cp, 'a');
+    assert_eq!(line[3].cpi.offset, Some(11));
+    assert_eq!(screen.push_count(), 4);
+}
@@ -0,0 +1,782 @@
+//! Save and restore of the editing session: the open files, the split layout
+//! of the current file, the views offsets and marks and the focused view.
+//!
+//! The sessions are stored as json in user_data_dir()/sessions/NAME.json.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::json;
+use serde_json::Value;
+
+use crate::core::buffer::BufferKind;
+use crate::core::config::user_data_dir;
+use crate::core::config::Config;
+use crate::core::editor::check_view_by_id;
+use crate::core::editor::get_view_by_id;
+use crate::core::editor::set_focus_on_view_id;
+use crate::core::editor::update_view_and_send_draw_event;
+use crate::core::editor::Editor;
+use crate::core::editor::EditorEnv;
+use crate::core::modes::core_mode::split_view_with_direction;
+use crate::core::modes::tab_bar_mode::current_file_view;
+use crate::core::modes::tab_bar_mode::find_tagged_child;
+use crate::core::modes::tab_bar_mode::show_file_view;
+use crate::core::modes::text_mode::mark::Mark;
+use crate::core::modes::text_mode::set_remembered_start_offset;
+use crate::core::modes::text_mode::PostInputAction;
+use crate::core::modes::text_mode::TextModeContext;
+use crate::core::view;
+use crate::core::view::LayoutDirection;
+use crate::core::view::LayoutSize;
+
+/// name of the session used when no --session is given
+pub const DEFAULT_SESSION_NAME: &str = "default";
+
+const SESSION_VERSION: u64 = 1;
+
+/// The first visible offset and the marks of a view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionPosition {
+    pub start_offset: u64,
+    pub marks: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionFile {
+    /// absolute path
+    pub path: String,
+    pub position: SessionPosition,
+}
+
+/// The split tree of the current file view, as built by split_view_with_direction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionLayout {
+    View {
+        position: SessionPosition,
+        /// the view had the focus
+        active: bool,
+    },
+    Split {
+        direction: LayoutDirection,
+        /// size of the first child
+        percent: f32,
+        children: Vec<SessionLayout>,
+    },
+}
+
+/// The open files in tab order, the index of the displayed one and its split layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub files: Vec<SessionFile>,
+    pub current: Option<usize>,
+    pub layout: Option<SessionLayout>,
+}
+
+/// Returns the name of the session to use:<br/>
+/// the --session NAME, or the default session if no file is given on the command line
+/// and the "session:auto" variable is not disabled.
+pub fn session_name(config: &Config) -> Option<String> {
+    if let Some(name) = &config.session {
+        return Some(name.clone());
+    }
+
+    let auto = config
+        .vars
+        .get("session:auto")
+        .is_none_or(|v| v == "true" || v == "1");
+
+    if auto && config.files_list.is_empty() {
+        Some(DEFAULT_SESSION_NAME.to_owned())
+    } else {
+        None
+    }
+}
+
+/// user_data_dir()/sessions/NAME.json, None if the name is not a plain file name.
+pub fn session_path(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return None;
+    }
+    Some(
+        user_data_dir()?
+            .join("sessions")
+            .join(format!("{}.json", name)),
+    )
+}
+
+/// Compare two paths after symlink resolution.
+pub fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn direction_to_str(dir: LayoutDirection) -> &'static str {
+    match dir {
+        LayoutDirection::Vertical => "vertical",
+        LayoutDirection::Horizontal => "horizontal",
+        LayoutDirection::NotSet => "not-set",
+    }
+}
+
+fn direction_from_str(s: &str) -> Option<LayoutDirection> {
+    match s {
+        "vertical" => Some(LayoutDirection::Vertical),
+        "horizontal" => Some(LayoutDirection::Horizontal),
+        _ => None,
+    }
+}
+
+impl SessionPosition {
+    fn to_json(&self) -> Value {
+        json!({
+            "start_offset": self.start_offset,
+            "marks": self.marks,
+        })
+    }
+
+    fn from_json(v: &Value) -> Option<Self> {
+        let start_offset = v.get("start_offset")?.as_u64()?;
+        let marks = v
+            .get("marks")?
+            .as_array()?
+            .iter()
+            .filter_map(|m| m.as_u64())
+            .collect();
+
+        Some(SessionPosition {
+            start_offset,
+            marks,
+        })
+    }
+}
+
+impl SessionLayout {
+    fn to_json(&self) -> Value {
+        match self {
+            SessionLayout::View { position, active } => {
+                let mut v = position.to_json();
+                v["active"] = json!(active);
+                v
+            }
+            SessionLayout::Split {
+                direction,
+                percent,
+                children,
+            } => {
+                json!({
+                    "split": direction_to_str(*direction),
+                    "percent": percent,
+                    "children": children.iter().map(|c| c.to_json()).collect::<Vec<_>>(),
+                })
+            }
+        }
+    }
+
+    fn from_json(v: &Value) -> Option<Self> {
+        if let Some(dir) = v.get("split") {
+            let direction = direction_from_str(dir.as_str()?)?;
+            let percent = v.get("percent").and_then(|p| p.as_f64()).unwrap_or(50.0) as f32;
+            let children = v
+                .get("children")?
+                .as_array()?
+                .iter()
+                .map(SessionLayout::from_json)
+                .collect::<Option<Vec<_>>>()?;
+            if children.len() != 2 {
+                return None;
+            }
+
+            return Some(SessionLayout::Split {
+                direction,
+                percent,
+                children,
+            });
+        }
+
+        Some(SessionLayout::View {
+            position: SessionPosition::from_json(v)?,
+            active: v.get("active").and_then(|a| a.as_bool()).unwrap_or(false),
+        })
+    }
+}
+
+impl Session {
+    pub fn to_json(&self) -> Value {
+        let files: Vec<Value> = self
+            .files
+            .iter()
+            .map(|f| {
+                let mut v = f.position.to_json();
+                v["path"] = json!(f.path);
+                v
+            })
+            .collect();
+
+        json!({
+            "version": SESSION_VERSION,
+            "files": files,
+            "current": self.current,
+            "layout": self.layout.as_ref().map(|l| l.to_json()),
+        })
+    }
+
+    pub fn from_json(v: &Value) -> Option<Self> {
+        if v.get("version")?.as_u64()? != SESSION_VERSION {
+            return None;
+        }
+
+        let files: Vec<SessionFile> = v
+            .get("files")?
+            .as_array()?
+            .iter()
+            .filter_map(|f| {
+                Some(SessionFile {
+                    path: f.get("path")?.as_str()?.to_owned(),
+                    position: SessionPosition::from_json(f)?,
+                })
+            })
+            .collect();
+
+        let current = v
+            .get("current")
+            .and_then(|c| c.as_u64())
+            .map(|c| c as usize)
+            .filter(|c| *c < files.len());
+
+        let layout = v.get("layout").and_then(SessionLayout::from_json);
+
+        Some(Session {
+            files,
+            current,
+            layout,
+        })
+    }
+}
+
+pub fn load_session(name: &str) -> Option<Session> {
+    let path = session_path(name)?;
+    let s = fs::read_to_string(&path).ok()?;
+    let v: Value = serde_json::from_str(&s).ok()?;
+    let session = Session::from_json(&v);
+    dbg_println!("session: load {:?} -> {:?}", path, session);
+    session
+}
+
+pub fn save_session(name: &str, session: &Session) -> std::io::Result<()> {
+    let path = match session_path(name) {
+        Some(path) => path,
+        None => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid session name '{}'", name),
+            ))
+        }
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // do not leave a truncated file on error
+    let tmp = path.with_extension("json.tmp");
+    let s = serde_json::to_string_pretty(&session.to_json())?;
+    fs::write(&tmp, s)?;
+    fs::rename(&tmp, &path)
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the absolute path of the file displayed by the (file) view 'id'.
+fn view_file_path(editor: &Editor<'static>, id: view::Id) -> Option<String> {
+    let v = check_view_by_id(editor, id)?;
+    let buffer = v.read().buffer()?;
+    let buffer = buffer.read();
+    if buffer.kind != BufferKind::File {
+        return None;
+    }
+
+    let path = fs::canonicalize(buffer.file_name()).ok()?;
+    if !path.is_file() {
+        return None;
+    }
+    Some(path.to_string_lossy().to_string())
+}
+
+/// Returns the position of the target view of the (file) view 'id'.
+fn view_position(editor: &Editor<'static>, id: view::Id) -> Option<SessionPosition> {
+    let target = find_tagged_child(editor, id, "target-view")?;
+    let v = get_view_by_id(editor, target);
+    let v = v.read();
+    let tm = v.mode_ctx::<TextModeContext>("text-mode");
+
+    Some(SessionPosition {
+        start_offset: v.start_offset,
+        marks: tm.marks.iter().map(|m| m.offset).collect(),
+    })
+}
+
+fn build_session_layout(
+    editor: &Editor<'static>,
+    env: &EditorEnv<'static>,
+    id: view::Id,
+) -> Option<SessionLayout> {
+    let v = check_view_by_id(editor, id)?;
+    let v = v.read();
+
+    if v.tags.contains("file-view") {
+        let target = find_tagged_child(editor, id, "target-view");
+        return Some(SessionLayout::View {
+            position: view_position(editor, id)?,
+            active: target.is_some() && target == env.active_view,
+        });
+    }
+
+    // [ view_to_split ] [ splitter ] [ view_clone ]
+    if !v.is_group || v.children.len() != 3 {
+        return None;
+    }
+
+    let percent = match v.children[0].layout_op {
+        LayoutSize::Percent { p } => p,
+        _ => 50.0,
+    };
+
+    let first = build_session_layout(editor, env, v.children[0].id);
+    let second = build_session_layout(editor, env, v.children[2].id);
+    match (first, second) {
+        (Some(first), Some(second)) => Some(SessionLayout::Split {
+            direction: v.layout_direction,
+            percent,
+            children: vec![first, second],
+        }),
+        (first, second) => first.or(second),
+    }
+}
+
+/// Returns the leftmost file view of the split tree 'id'.
+fn first_file_view(editor: &Editor<'static>, id: view::Id) -> Option<view::Id> {
+    let v = check_view_by_id(editor, id)?;
+    let v = v.read();
+    if v.tags.contains("file-view") {
+        return Some(id);
+    }
+    first_file_view(editor, v.children.first()?.id)
+}
+
+/// Build the session from the editor's tabs.
+pub fn build_session(editor: &Editor<'static>, env: &EditorEnv<'static>) -> Session {
+    let current_view = current_file_view(editor);
+    let current_file = current_view.and_then(|id| first_file_view(editor, id));
+
+    let mut session = Session {
+        files: vec![],
+        current: None,
+        layout: None,
+    };
+
+    for id in &editor.active_views {
+        let path = match view_file_path(editor, *id) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        let position = match view_position(editor, *id) {
+            Some(position) => position,
+            None => continue,
+        };
+
+        if Some(*id) == current_file {
+            session.current = Some(session.files.len());
+        }
+
+        session.files.push(SessionFile { path, position });
+    }
+
+    if session.current.is_some() {
+        session.layout = match build_session_layout(editor, env, current_view.unwrap()) {
+            Some(layout @ SessionLayout::Split { .. }) => Some(layout),
+            _ => None,
+        };
+    }
+
+    session
+}
+
+/// Save the editor's session (if any) before exiting.
+pub fn save_editor_session(editor: &Editor<'static>, env: &EditorEnv<'static>) {
+    let name = match session_name(&editor.config) {
+        Some(name) => name,
+        None => return,
+    };
+
+    let session = build_session(editor, env);
+    if let Err(e) = save_session(&name, &session) {
+        dbg_println!("session: cannot save '{}': {}", name, e);
+    }
+}
+
+/// Load the session selected by the configuration.
+pub fn load_editor_session(config: &Config) -> Option<Session> {
+    load_session(&session_name(config)?)
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn apply_position(editor: &Editor<'static>, id: view::Id, position: &SessionPosition) {
+    let target = match find_tagged_child(editor, id, "target-view") {
+        Some(target) => target,
+        None => return,
+    };
+
+    let v = get_view_by_id(editor, target);
+    let mut v = v.write();
+
+    let max_offset = match v.buffer() {
+        Some(buffer) => buffer.read().size() as u64,
+        None => return,
+    };
+
+    set_remembered_start_offset(&mut v, position.start_offset);
+
+    let tm = v.mode_ctx_mut::<TextModeContext>("text-mode");
+    tm.marks = position
+        .marks
+        .iter()
+        .map(|offset| Mark::new(std::cmp::min(*offset, max_offset)))
+        .collect();
+    if tm.marks.is_empty() {
+        tm.marks.push(Mark::new(0));
+    }
+    tm.pre_compose_action
+        .push(PostInputAction::CenterAroundMainMarkIfOffScreen);
+}
+
+/// Reorder the tabs, restore the positions and display the current file.<br/>
+/// The positions of the files opened with a line/column/offset on the command line are not restored.<br/>
+/// The split layout is applied later by restore_session_layout (the views must have a size).
+pub fn restore_session(
+    editor: &mut Editor<'static>,
+    env: &mut EditorEnv<'static>,
+    session: Session,
+) {
+    let positioned: Vec<String> = super::build_buffer_options(editor)
+        .into_iter()
+        .filter(|arg| {
+            arg.start_position.offset.is_some()
+                || arg.start_position.line.is_some()
+                || arg.start_position.column.is_some()
+        })
+        .map(|arg| arg.path)
+        .collect();
+
+    // session file index -> view
+    let mut file_views: Vec<Option<view::Id>> = vec![None; session.files.len()];
+    for id in &editor.active_views {
+        let path = match view_file_path(editor, *id) {
+            Some(path) => path,
+            None => continue,
+        };
+        if let Some(idx) = session.files.iter().position(|f| same_file(&f.path, &path)) {
+            if file_views[idx].is_none() {
+                file_views[idx] = Some(*id);
+            }
+        }
+    }
+
+    // tabs order: the session's files first
+    let mut active_views: Vec<view::Id> = file_views.iter().flatten().copied().collect();
+    for id in &editor.active_views {
+        if !active_views.contains(id) {
+            active_views.push(*id);
+        }
+    }
+    editor.active_views = active_views;
+
+    for (idx, id) in file_views.iter().enumerate() {
+        let id = match id {
+            Some(id) => *id,
+            None => continue,
+        };
+
+        let file = &session.files[idx];
+        if positioned.iter().any(|p| same_file(p, &file.path)) {
+            continue;
+        }
+        apply_position(editor, id, &file.position);
+    }
+
+    // files given on the command line are displayed first
+    if !editor.config.files_list.is_empty() {
+        return;
+    }
+
+    let current = match session.current.and_then(|idx| file_views[idx]) {
+        Some(id) => id,
+        None => return,
+    };
+
+    show_file_view(editor, env, current);
+
+    if let Some(layout) = session.layout {
+        editor.pending_session_layout = Some((current, layout));
+    }
+}
+
+/// Replay the splits of the restored session.<br/>
+/// Called once the views have been laid out: split_view_with_direction needs the views dimensions.
+pub fn restore_session_layout(editor: &mut Editor<'static>, env: &mut EditorEnv<'static>) {
+    let (id, layout) = match editor.pending_session_layout.take() {
+        Some(pending) => pending,
+        None => return,
+    };
+
+    // the tab may have been closed
+    if current_file_view(editor) != Some(id) {
+        return;
+    }
+
+    let mut active = None;
+    let mut level = vec![(id, layout)];
+
+    while !level.is_empty() {
+        let mut next = vec![];
+
+        for (id, node) in level {
+            match node {
+                SessionLayout::View {
+                    position,
+                    active: is_active,
+                } => {
+                    apply_position(editor, id, &position);
+                    if is_active {
+                        active = find_tagged_child(editor, id, "target-view");
+                    }
+                }
+
+                SessionLayout::Split {
+                    direction,
+                    percent,
+                    mut children,
+                } => {
+                    let target = match find_tagged_child(editor, id, "target-view") {
+                        Some(target) => target,
+                        None => continue,
+                    };
+
+                    let target = get_view_by_id(editor, target);
+                    if split_view_with_direction(editor, env, &target, direction).is_none() {
+                        // keep the first view
+                        next.push((id, children.swap_remove(0)));
+                        continue;
+                    }
+
+                    // [ id ] [ splitter ] [ clone ]
+                    let parent_id = get_view_by_id(editor, id).read().parent_id.unwrap();
+                    let parent = get_view_by_id(editor, parent_id);
+                    let clone_id = {
+                        let mut parent = parent.write();
+                        parent.children[0].layout_op = LayoutSize::Percent { p: percent };
+                        parent.children[2].id
+                    };
+
+                    let second = children.pop().unwrap();
+                    let first = children.pop().unwrap();
+                    next.push((id, first));
+                    next.push((clone_id, second));
+                }
+            }
+        }
+
+        // compute the new views dimensions before splitting them
+        update_view_and_send_draw_event(editor, env);
+
+        level = next;
+    }
+
+    if let Some(active) = active {
+        set_focus_on_view_id(editor, env, active);
+        update_view_and_send_draw_event(editor, env);
+    }
+}
+
+#[test]
+fn test_session_json() {
+    let session = Session {
+        files: vec![
+            SessionFile {
+                path: "/tmp/a.rs".to_owned(),
+                position: SessionPosition {
+                    start_offset: 120,
+                    marks: vec![130, 200],
+                },
+            },
+            SessionFile {
+                path: "/tmp/b.rs".to_owned(),
+                position: SessionPosition {
+                    start_offset: 0,
+                    marks: vec![0],
+                },
+            },
+        ],
+        current: Some(1),
+        layout: Some(SessionLayout::Split {
+            direction: LayoutDirection::Horizontal,
+            percent: 30.0,
+            children: vec![
+                SessionLayout::View {
+                    position: SessionPosition {
+                        start_offset: 0,
+                        marks: vec![0],
+                    },
+                    active: false,
+                },
+                SessionLayout::Split {
+                    direction: LayoutDirection::Vertical,
+                    percent: 50.0,
+                    children: vec![
+                        SessionLayout::View {
+                            position: SessionPosition {
+                                start_offset: 10,
+                                marks: vec![12],
+                            },
+                            active: true,
+                        },
+                        SessionLayout::View {
+                            position: SessionPosition {
+                                start_offset: 20,
+                                marks: vec![],
+                            },
+                            active: false,
+                        },
+                    ],
+                },
+            ],
+        }),
+    };
+
+    let s = serde_json::to_string(&session.to_json()).unwrap();
+    let v: Value = serde_json::from_str(&s).unwrap();
+    assert_eq!(Session::from_json(&v), Some(session));
+
+    // unknown version
+    let v = json!({ "version": 0, "files": [] });
+    assert_eq!(Session::from_json(&v), None);
+
+    // out of range current file
+    let v = json!({ "version": SESSION_VERSION, "files": [], "current": 3 });
+    assert_eq!(Session::from_json(&v).unwrap().current, None);
+
+    assert!(session_path("work").is_some() || user_data_dir().is_none());
+    assert_eq!(session_path("../work"), None);
+    assert_eq!(session_path(""), None);
+}
+
+#[test]
+fn test_session_restore_layout() {
+    use std::sync::mpsc::channel;
+
+    let dir = std::env::temp_dir().join(format!("unlimited-session-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("a.txt");
+    let text: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+    fs::write(&path, &text).unwrap();
+    let path = fs::canonicalize(&path)
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+
+    // the start offsets are in the middle of a line
+    let position = |start_offset: u64| SessionPosition {
+        start_offset,
+        marks: vec![start_offset],
+    };
+    let session = Session {
+        files: vec![SessionFile {
+            path: path.clone(),
+            position: position(3),
+        }],
+        current: Some(0),
+        layout: Some(SessionLayout::Split {
+            direction: LayoutDirection::Vertical,
+            percent: 30.0,
+            children: vec![
+                SessionLayout::View {
+                    position: position(65),
+                    active: false,
+                },
+                SessionLayout::View {
+                    position: position(131),
+                    active: true,
+                },
+            ],
+        }),
+    };
+
+    let (core_tx, _core_rx) = channel();
+    let (ui_tx, _ui_rx) = channel();
+    let (worker_tx, _worker_rx) = channel();
+    let (indexer_tx, _indexer_rx) = channel();
+    let (executor_tx, _executor_rx) = channel();
+
+    let config = Config {
+        files_list: vec![],
+        ui_frontend: String::new(),
+        vars: Default::default(),
+        session: None,
+    };
+    let mut editor = Editor::new(config, core_tx, ui_tx, worker_tx, indexer_tx, executor_tx);
+    let mut env = EditorEnv::new();
+
+    super::load_buffers(&mut editor, &mut env, Some(&session));
+    super::load_modes(&mut editor, &mut env);
+    super::configure_modes(&mut editor, &mut env);
+    super::create_layout(&mut editor, &mut env);
+
+    restore_session(&mut editor, &mut env, session.clone());
+
+    // the splits wait for the first layout
+    let (id, _) = editor.pending_session_layout.clone().unwrap();
+    let first = find_tagged_child(&editor, id, "target-view").unwrap();
+    assert_eq!(get_view_by_id(&editor, first).read().start_offset, 0);
+
+    env.width = 120;
+    env.height = 40;
+    update_view_and_send_draw_event(&mut editor, &mut env);
+    restore_session_layout(&mut editor, &mut env);
+    assert!(editor.pending_session_layout.is_none());
+
+    // the restored screens start at the start of the lines
+    let restored = build_session(&editor, &env);
+    assert_eq!(restored.files.len(), 1);
+    assert_eq!(restored.files[0].path, path);
+    assert_eq!(
+        restored.layout,
+        Some(SessionLayout::Split {
+            direction: LayoutDirection::Vertical,
+            percent: 30.0,
+            children: vec![
+                SessionLayout::View {
+                    position: SessionPosition {
+                        start_offset: 63,
+                        marks: vec![65],
+                    },
+                    active: false,
+                },
+                SessionLayout::View {
+                    position: SessionPosition {
+                        start_offset: 126,
+                        marks: vec![131],
+                    },
+                    active: true,
+                },
+            ],
+        })
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
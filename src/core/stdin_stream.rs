@@ -0,0 +1,185 @@
+//! Reading of the standard input into an in-memory buffer: `some_command | unlimited -`.
+//!
+//! The pipe is moved away from fd 0 before the ui starts and /dev/tty takes its place,
+//! so the terminal input thread keeps reading the keyboard.<br/>
+//! A dedicated thread appends the received data to the buffer until the producer closes the pipe.<br/>
+//! The buffer has no file: the views follow its end (see follow_buffer_end) and saving it asks for a file name.
+
+use std::fs::File;
+use std::io::Read;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+
+use crate::core::buffer::Buffer;
+use crate::core::buffer::BufferBuilder;
+use crate::core::buffer::BufferKind;
+use crate::core::event::pending_input_event_inc;
+use crate::core::event::Event;
+use crate::core::event::Message;
+use crate::core::Editor;
+
+/// the command line argument that designates the standard input
+pub const STDIN_ARG: &str = "-";
+
+/// the name of the buffer displaying the standard input
+pub const STDIN_BUFFER_NAME: &str = "*stdin*";
+
+/// size of a single read
+const STDIN_READ_SIZE: usize = 64 * 1024;
+
+/// minimum delay between two screen refreshes requested by the reader
+const STDIN_REFRESH_MS: u64 = 100;
+
+/// the pipe detached from fd 0 by detach_stdin()
+static STDIN_PIPE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Move the standard input pipe away from fd 0 and reopen /dev/tty as fd 0.<br/>
+/// Must be called before the ui thread reads the terminal.
+#[cfg(unix)]
+pub fn detach_stdin() -> std::io::Result<()> {
+    use std::io::Error;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::io::FromRawFd;
+
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 {
+        return Err(Error::other("stdin is a terminal"));
+    }
+
+    // keep the pipe out of the children's file descriptors
+    let fd = unsafe { libc::fcntl(libc::STDIN_FILENO, libc::F_DUPFD_CLOEXEC, 3) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    let pipe = unsafe { File::from_raw_fd(fd) };
+
+    let tty = File::options().read(true).write(true).open("/dev/tty")?;
+    if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDIN_FILENO) } < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    *STDIN_PIPE.lock().unwrap() = Some(pipe);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn detach_stdin() -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "reading stdin is not supported on this platform",
+    ))
+}
+
+/// Create the buffer of the standard input and start filling it.<br/>
+/// Returns None if detach_stdin() did not succeed or if the pipe is already used.
+pub fn open_stdin_buffer(editor: &Editor<'static>) -> Option<Arc<RwLock<Buffer<'static>>>> {
+    let pipe = STDIN_PIPE.lock().unwrap().take()?;
+
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name(STDIN_BUFFER_NAME)
+        .internal(false)
+        .use_buffer_log(true)
+        .finalize()?;
+
+    buffer.write().is_streaming = true;
+
+    let core_tx = editor.core_tx.clone();
+    let stream_buffer = Arc::clone(&buffer);
+
+    // the reader notifies each append, the refresher coalesces the notifications:
+    // the data received during the throttle delay is drawn by a trailing refresh
+    let (data_tx, data_rx) = channel::<()>();
+
+    std::thread::spawn(move || {
+        while data_rx.recv().is_ok() {
+            while data_rx.try_recv().is_ok() {}
+
+            let ts = crate::core::BOOT_TIME.elapsed().unwrap().as_millis();
+            let msg = Message::new(0, 0, ts, Event::RefreshView);
+            pending_input_event_inc(1);
+            if core_tx.send(msg).is_err() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(STDIN_REFRESH_MS));
+        }
+    });
+
+    // a blocking read: do not use the executor thread
+    std::thread::spawn(move || {
+        stream_to_buffer(pipe, &stream_buffer, |_| data_tx.send(()).is_ok());
+
+        stream_buffer.write().is_streaming = false;
+        let _ = data_tx.send(());
+    });
+
+    Some(buffer)
+}
+
+/// Append the data read from 'input' to 'buffer' until the end of 'input'.<br/>
+/// 'on_data' is called with the new size of the buffer after each append, the reading stops if it returns false.<br/>
+/// The appended data is not recorded in the buffer log and does not mark the buffer as changed.<br/>
+/// Returns the number of appended bytes.
+pub fn stream_to_buffer<R: Read, F: FnMut(usize) -> bool>(
+    mut input: R,
+    buffer: &Arc<RwLock<Buffer<'static>>>,
+    mut on_data: F,
+) -> u64 {
+    let mut data = vec![0; STDIN_READ_SIZE];
+    let mut total = 0;
+    loop {
+        let n = match input.read(&mut data) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+
+        let size = {
+            let mut b = buffer.write();
+            let (use_buffer_log, changed) = (b.use_buffer_log, b.changed);
+            b.use_buffer_log = false;
+            b.append(&data[..n]);
+            b.use_buffer_log = use_buffer_log;
+            b.changed = changed;
+            b.size()
+        };
+        total += n as u64;
+
+        if !on_data(size) {
+            break;
+        }
+    }
+    total
+}
+
+#[test]
+fn test_stream_to_buffer() {
+    let buffer = BufferBuilder::new(BufferKind::File)
+        .buffer_name(STDIN_BUFFER_NAME)
+        .use_buffer_log(true)
+        .finalize()
+        .unwrap();
+
+    let input: Vec<u8> = (0..STDIN_READ_SIZE * 2 + 10)
+        .map(|i| b'a' + (i % 26) as u8)
+        .collect();
+
+    let mut sizes = vec![];
+    let n = stream_to_buffer(&input[..], &buffer, |size| {
+        sizes.push(size);
+        true
+    });
+    assert_eq!(n, input.len() as u64);
+    assert_eq!(sizes.last(), Some(&input.len()));
+
+    let b = buffer.read();
+    assert!(!b.changed);
+    assert_eq!(b.buffer_log_count(), 0);
+
+    let mut data = vec![];
+    b.read(0, b.size(), &mut data);
+    assert_eq!(data, input);
+}
@@ -150,6 +150,8 @@ pub struct FilterIo {
     //
     pub data: FilterData,
     // TODO(ceg): add style infos ?
+    /// The bidi embedding level (see BidiFilter).
+    pub bidi_level: u8,
 }
 
 impl FilterIo {
@@ -161,6 +163,7 @@ impl FilterIo {
             offset: from_offset,
             size: cp_size,
             data: FilterData::TextInfo { real_cp, .. },
+            bidi_level,
         } = io
         {
             return FilterIo {
@@ -173,6 +176,7 @@ impl FilterIo {
                     real_cp,
                     displayed_cp: disp_cp as u32,
                 },
+                bidi_level,
             };
         }
 
@@ -35,6 +35,18 @@ fn main() {
 
     let config = parse_command_line();
 
+    // the terminal input thread needs fd 0
+    if config
+        .files_list
+        .iter()
+        .any(|f| f == core::stdin_stream::STDIN_ARG)
+    {
+        if let Err(e) = core::stdin_stream::detach_stdin() {
+            eprintln!("error: cannot read stdin: {e}");
+            std::process::exit(1);
+        }
+    }
+
     let config_vars = config.vars.clone();
 
     // build core/ui communication channels
@@ -134,7 +146,13 @@ fn parse_command_line() -> Config {
                 .help("configuration variables"),
         )
         .arg(
-            arg!(<FILES> ... "file to edit")
+            Arg::new("SESSION")
+                .value_name("NAME")
+                .long("session")
+                .help("restore the session NAME and save it on exit"),
+        )
+        .arg(
+            arg!(<FILES> ... "file to edit, - reads stdin")
                 .required(false)
                 .trailing_var_arg(true),
         )
@@ -152,6 +170,8 @@ fn parse_command_line() -> Config {
         .get_many::<String>("FILES")
         .map_or(vec![], |v| v.map(|e| e.clone()).collect());
 
+    let session = matches.get_one::<String>("SESSION").cloned();
+
     if let Some(debug) = matches.get_one::<bool>("debug") {
         if *debug {
             core::enable_dbg_println();
@@ -213,6 +233,7 @@ fn parse_command_line() -> Config {
     dbg_println!("config vars  = \n{:?}", vars);
     dbg_println!("ui_frontend  = \n{:?}", ui_frontend);
     dbg_println!("files_list   = \n{:?}", files_list);
+    dbg_println!("session      = \n{:?}", session);
     dbg_println!("LOG_FILENAME = \n{:?}", log_filename);
 
     if fatal_error {
@@ -223,5 +244,6 @@ fn parse_command_line() -> Config {
         files_list,
         ui_frontend,
         vars,
+        session,
     }
 }
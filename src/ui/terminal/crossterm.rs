@@ -41,6 +41,7 @@ use crate::core::event::PointerEvent;
 use crate::core::event::InputEvent;
 use crate::core::event::Message;
 use crate::core::screen::Screen;
+use crate::core::screen::ScreenCell;
 
 //use crate::core::event::InputEvent::*;
 use crate::core::event::Key;
@@ -406,6 +407,7 @@ fn draw_screen_dumb(
             is_bold = cpi.style.is_bold;
 
             ops.push(ScreenOp::PrintText(cpi.displayed_cp));
+            push_cluster_ops(&mut ops, line, c, li);
         }
     }
 
@@ -458,6 +460,24 @@ fn cpis_have_same_style(a: &CodepointInfo, b: &CodepointInfo) -> bool {
     *a == *b
 }
 
+/// Print the rest of the grapheme cluster of line[c].<br/>
+/// The terminals do not agree on the width of the clusters: the cursor is moved after the cluster cells.
+fn push_cluster_ops(ops: &mut Vec<ScreenOp>, line: &[ScreenCell], c: usize, l: usize) {
+    if line[c].cluster.is_empty() {
+        return;
+    }
+
+    for cp in line[c].cluster.chars() {
+        ops.push(ScreenOp::PrintText(cp));
+    }
+
+    let mut next = c + 1;
+    while next < line.len() && line[next].cpi.skip_render {
+        next += 1;
+    }
+    ops.push(ScreenOp::MoveTo(next as u16, l as u16));
+}
+
 /*
    TODO(ceg):
      if width || height change -> clear redraw all
@@ -516,7 +536,9 @@ fn draw_screen(
                     let prev_screen_cpi = &prev_line[c].cpi;
                     let cpi = &line[c].cpi;
 
-                    if !cpis_have_same_style(cpi, prev_screen_cpi) {
+                    if !cpis_have_same_style(cpi, prev_screen_cpi)
+                        || line[c].cluster != prev_line[c].cluster
+                    {
                         // diff found stop @ c
                         // debug_cpi.style.is_bold = true;
                         //debug_cpi.style.bg_color.0 = 255;
@@ -593,6 +615,7 @@ fn draw_screen(
             prev_bg = cpi.style.bg_color;
 
             ops.push(ScreenOp::PrintText(cpi.displayed_cp));
+            push_cluster_ops(&mut ops, line, c, l);
 
             c += 1;
         }
@@ -743,7 +766,10 @@ fn translate_crossterm_event(evt: ::crossterm::event::Event) -> InputEvent {
             }
 
             ::crossterm::event::KeyCode::BackTab => {
-                return InputEvent::DummyInputEvent;
+                return build_key_press_event!(
+                    Key::Unicode('\t'),
+                    ke.modifiers | event::KeyModifiers::SHIFT
+                );
             }
 
             ::crossterm::event::KeyCode::Delete => {
@@ -0,0 +1,226 @@
+extern crate unlimited;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use unlimited::core;
+use unlimited::core::config::Config;
+use unlimited::core::event::Event;
+use unlimited::core::event::InputEvent;
+use unlimited::core::event::Key;
+use unlimited::core::event::KeyModifiers;
+use unlimited::core::event::Message;
+
+/// Measures the time spent by the core to handle the mark movements and scrolls
+/// in files made of a single very long line.<br/>
+/// usage: cargo bench --bench long_lines -- [size in MiB]...
+/// (default sizes: 16 64 256)
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut sizes: Vec<u64> = args.iter().filter_map(|a| a.parse::<u64>().ok()).collect();
+    if sizes.is_empty() {
+        sizes = vec![16, 64, 256];
+    }
+
+    // the core can run once per process
+    if !args.iter().any(|a| a == "--child") {
+        println!("{:>10} {:>16} {:>10}", "file size", "action", "time");
+        let exe = env::current_exe().unwrap();
+        for size in sizes {
+            let status = Command::new(&exe)
+                .arg(size.to_string())
+                .arg("--child")
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+        return;
+    }
+
+    // do not touch the user's sessions and file history
+    let data_dir = env::temp_dir().join(format!("unlimited-bench-{}", std::process::id()));
+    env::set_var("XDG_DATA_HOME", &data_dir);
+
+    let actions = [
+        ("end-of-line", key(Key::End, false)),
+        ("previous-line", key(Key::Up, false)),
+        ("page-up", key(Key::PageUp, false)),
+        ("page-up", key(Key::PageUp, false)),
+        ("next-line", key(Key::Down, false)),
+        ("page-down", key(Key::PageDown, false)),
+        ("start-of-line", key(Key::Home, false)),
+        ("end-of-file", key(Key::End, true)),
+        ("page-up", key(Key::PageUp, false)),
+        ("scroll-up", wheel_up()),
+        ("scroll-up", wheel_up()),
+        // the mark is off-screen
+        ("next-line", key(Key::Down, false)),
+        ("start-of-file", key(Key::Home, true)),
+    ];
+
+    let size = sizes[0];
+    let path = env::temp_dir().join(format!("unlimited-long-line-{}M.txt", size));
+    gen_long_line(&path, size * 1024 * 1024);
+
+    let times = run_actions(&path, &actions);
+    for ((name, _), t) in actions.iter().zip(times) {
+        println!(
+            "{:>9}M {:>16} {:>8.2}ms",
+            size,
+            name,
+            t.as_secs_f64() * 1000.0
+        );
+    }
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
+
+fn key(key: Key, ctrl: bool) -> InputEvent {
+    InputEvent::KeyPress {
+        key,
+        mods: KeyModifiers {
+            ctrl,
+            alt: false,
+            shift: false,
+        },
+    }
+}
+
+fn wheel_up() -> InputEvent {
+    InputEvent::WheelUp {
+        mods: KeyModifiers {
+            ctrl: false,
+            alt: false,
+            shift: false,
+        },
+        x: 60,
+        y: 20,
+    }
+}
+
+/// Write a file of 'size' bytes: words of random letters and a final '\n'.
+fn gen_long_line(path: &Path, size: u64) {
+    let mut out = BufWriter::new(File::create(path).unwrap());
+
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut chunk = Vec::with_capacity(1024 * 1024);
+    let mut written = 0;
+    while written + 1 < size {
+        chunk.clear();
+        while chunk.len() < chunk.capacity() && written + (chunk.len() as u64) + 1 < size {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let b = if seed % 8 == 0 {
+                b' '
+            } else {
+                b'a' + ((seed >> 8) % 26) as u8
+            };
+            chunk.push(b);
+        }
+        out.write_all(&chunk).unwrap();
+        written += chunk.len() as u64;
+    }
+    out.write_all(b"\n").unwrap();
+}
+
+fn send(core_tx: &Sender<Message<'static>>, event: Event<'static>, count: usize) {
+    let ts = core::BOOT_TIME.elapsed().unwrap().as_millis();
+    core::event::pending_input_event_inc(count);
+    core_tx.send(Message::new(0, ts, ts, event)).unwrap();
+}
+
+/// Wait for the next screen, the core's view updates are forwarded like the ui does.
+fn wait_draw(
+    ui_rx: &Receiver<Message<'static>>,
+    core_tx: &Sender<Message<'static>>,
+    timeout: Duration,
+) -> bool {
+    let end = Instant::now() + timeout;
+    loop {
+        let remain = end.saturating_duration_since(Instant::now());
+        match ui_rx.recv_timeout(remain) {
+            Ok(msg) => match msg.event {
+                Event::Draw { .. } => {
+                    core::event::pending_render_event_dec(1);
+                    return true;
+                }
+                Event::UpdateView { width, height } => {
+                    send(core_tx, Event::UpdateView { width, height }, 1);
+                }
+                Event::ApplicationQuit => return false,
+                _ => {}
+            },
+            Err(_) => return false,
+        }
+    }
+}
+
+fn run_actions(path: &PathBuf, actions: &[(&str, InputEvent)]) -> Vec<Duration> {
+    let config = Config {
+        files_list: vec![path.to_str().unwrap().to_owned()],
+        ui_frontend: "bench".to_owned(),
+        vars: HashMap::new(),
+        session: None,
+    };
+
+    let (ui_tx, ui_rx) = channel();
+    let (core_tx, core_rx) = channel();
+
+    let core_th = {
+        let core_tx = core_tx.clone();
+        thread::spawn(move || core::run(config, &core_rx, &core_tx, &ui_tx))
+    };
+
+    // first screen, then the screen refreshed when the byte index is built
+    send(
+        &core_tx,
+        Event::UpdateView {
+            width: 120,
+            height: 40,
+        },
+        1,
+    );
+    wait_draw(&ui_rx, &core_tx, Duration::from_secs(60));
+    wait_draw(&ui_rx, &core_tx, Duration::from_secs(60));
+
+    let mut times = vec![];
+    for (_, ev) in actions {
+        let start = Instant::now();
+        send(
+            &core_tx,
+            Event::Input {
+                events: vec![ev.clone()],
+            },
+            1,
+        );
+        wait_draw(&ui_rx, &core_tx, Duration::from_secs(600));
+        times.push(start.elapsed());
+
+        // skip the extra screens
+        while wait_draw(&ui_rx, &core_tx, Duration::from_millis(50)) {}
+    }
+
+    // ctrl+x ctrl+q
+    send(
+        &core_tx,
+        Event::Input {
+            events: vec![key(Key::Unicode('x'), true), key(Key::Unicode('q'), true)],
+        },
+        2,
+    );
+    while wait_draw(&ui_rx, &core_tx, Duration::from_secs(5)) {}
+    core_th.join().unwrap();
+
+    times
+}